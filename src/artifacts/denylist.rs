@@ -4,8 +4,20 @@
 //! push nothing denied leaves the machine, and on pull a poisoned sync repo
 //! cannot plant a denied file (or escape `~/.claude`). No user configuration
 //! is consulted here — there is deliberately no way to override this list.
+//!
+//! Deny patterns from the configuration (`deny_patterns`) and from the sync
+//! repository's policy file ([`RepoPolicy`]) are additive: checked by
+//! [`is_denied_with`] alongside the built-in rules, they can only deny more.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::encryption::REPO_CONFIG_DIR;
 
-use std::path::Path;
+/// Policy file shipped inside the sync repository's `.claude-code-sync/`
+/// directory, so every machine syncing the repository gets its rules
+pub const POLICY_FILE: &str = "policy.toml";
 
 /// Exact file names that must never sync, regardless of location.
 const DENIED_FILE_NAMES: &[&str] = &[
@@ -83,6 +95,131 @@ pub fn is_unsafe_rel_path(rel_path: &Path) -> bool {
     })
 }
 
+/// Returns true when a `~/.claude`-relative path is denied by the built-in
+/// rules or by one of the additive `patterns` (see [`matches_pattern`]).
+pub fn is_denied_with(rel_path: &Path, patterns: &[String]) -> bool {
+    is_denied(rel_path) || patterns.iter().any(|p| matches_pattern(p, rel_path))
+}
+
+/// Whether a deny pattern matches a `~/.claude`-relative path.
+///
+/// Patterns work like `.gitignore` entries: `*` and `?` match within one
+/// path component and `**` matches any number of components. A pattern
+/// without a `/` matches a file or directory name anywhere; one with a `/`
+/// is anchored at `~/.claude`. A pattern matching a directory denies
+/// everything below it.
+pub fn matches_pattern(pattern: &str, rel_path: &Path) -> bool {
+    let Some(components) = rel_path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return true;
+    };
+    let pattern = pattern.trim().trim_matches('/');
+    if pattern.is_empty() {
+        return false;
+    }
+    if !pattern.contains('/') {
+        return components.iter().any(|c| glob_component(pattern, c));
+    }
+    let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+    match_components(&parts, &components)
+}
+
+/// Match pattern parts against path components; running out of pattern
+/// first means the path lies below a matched directory
+fn match_components(parts: &[&str], components: &[&str]) -> bool {
+    match parts.split_first() {
+        None => true,
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|i| match_components(rest, &components[i..]))
+        }
+        Some((part, rest)) => components
+            .split_first()
+            .is_some_and(|(c, tail)| glob_component(part, c) && match_components(rest, tail)),
+    }
+}
+
+/// `*` and `?` wildcard match of one path component
+fn glob_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Check deny patterns for mistakes
+pub fn validate_patterns(patterns: &[String]) -> Result<()> {
+    for pattern in patterns {
+        if pattern.trim().trim_matches('/').is_empty() {
+            anyhow::bail!("deny pattern '{}' matches nothing", pattern);
+        }
+        if Path::new(pattern.trim())
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            anyhow::bail!("deny pattern '{}' cannot contain '..'", pattern);
+        }
+    }
+    Ok(())
+}
+
+/// Deny rules a sync repository ships to every machine syncing it, read
+/// from `.claude-code-sync/policy.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RepoPolicy {
+    /// Extra never-sync patterns, added to each machine's own
+    pub deny_patterns: Vec<String>,
+}
+
+impl RepoPolicy {
+    /// Where the policy file of the repository at `repo_root` lives
+    pub fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(REPO_CONFIG_DIR).join(POLICY_FILE)
+    }
+
+    /// Load the policy of the repository at `repo_root`; none if it has no
+    /// policy file. A policy that cannot be read is an error rather than
+    /// ignored, so its rules never silently stop applying.
+    pub fn load(repo_root: &Path) -> Result<Self> {
+        let path = Self::path(repo_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read policy file {}", path.display()))?;
+        let policy: RepoPolicy = toml::from_str(&content)
+            .with_context(|| format!("Invalid policy file {}", path.display()))?;
+        validate_patterns(&policy.deny_patterns)
+            .with_context(|| format!("Invalid policy file {}", path.display()))?;
+        Ok(policy)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,4 +301,57 @@ mod tests {
         assert!(!is_unsafe_rel_path(Path::new("skills/ok/file.md")));
         assert!(!is_unsafe_rel_path(Path::new("settings.json")));
     }
+
+    #[test]
+    fn test_deny_patterns() {
+        let patterns = vec![
+            ".npmrc".to_string(),
+            "skills/acme-*/".to_string(),
+            "projects/**/*customer*.md".to_string(),
+        ];
+        let denied = |p: &str| is_denied_with(Path::new(p), &patterns);
+        // Built-in rules still apply
+        assert!(denied(".credentials.json"));
+        // A bare name matches anywhere
+        assert!(denied(".npmrc"));
+        assert!(denied("skills/x/.npmrc"));
+        assert!(!denied("skills/x/.npmrc.md"));
+        // An anchored directory denies everything below it
+        assert!(denied("skills/acme-billing/SKILL.md"));
+        assert!(!denied("agents/skills/acme-billing/SKILL.md"));
+        // `**` spans any number of components
+        assert!(denied("projects/-home-u-app/notes/customer-x.md"));
+        assert!(denied("projects/a-customer.md"));
+        assert!(!denied("projects/-home-u-app/customer.txt"));
+        assert!(!denied("skills/acme/SKILL.md"));
+    }
+
+    #[test]
+    fn test_glob_component() {
+        assert!(glob_component("*", ""));
+        assert!(glob_component("a*b*c", "aXbYbc"));
+        assert!(glob_component("?.md", "a.md"));
+        assert!(!glob_component("?.md", "ab.md"));
+        assert!(!glob_component("a*b", "acd"));
+    }
+
+    #[test]
+    fn test_repo_policy() {
+        let temp = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            RepoPolicy::load(temp.path()).unwrap(),
+            RepoPolicy::default()
+        );
+
+        let path = RepoPolicy::path(temp.path());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "deny_patterns = [\"projects/*nda*\"]\n").unwrap();
+        assert_eq!(
+            RepoPolicy::load(temp.path()).unwrap().deny_patterns,
+            vec!["projects/*nda*"]
+        );
+
+        std::fs::write(&path, "deny_patterns = [\"../x\"]\n").unwrap();
+        assert!(RepoPolicy::load(temp.path()).is_err());
+    }
 }
@@ -124,7 +124,7 @@ fn collect(
         SourceSpec::Files(list) => {
             for entry in list {
                 let claude_rel = Path::new(entry);
-                if is_denied(claude_rel) {
+                if filter.is_denied(claude_rel) {
                     *skipped += 1;
                     continue;
                 }
@@ -156,7 +156,7 @@ fn collect(
                 // Deny rules match against the ~/.claude-relative path so a
                 // component like `cache/` is caught wherever it appears.
                 let claude_rel = abs.strip_prefix(claude_dir).unwrap_or(abs);
-                if is_denied(claude_rel) {
+                if filter.is_denied(claude_rel) {
                     *skipped += 1;
                     continue;
                 }
@@ -404,6 +404,28 @@ fn local_destination(
     }
 }
 
+/// The `~/.claude`-relative path a repository-relative path syncs with,
+/// ignoring the project-name-only layout. None for paths outside the
+/// sessions and artifact categories.
+pub fn claude_path_of_repo_file(repo_rel: &Path, filter: &FilterConfig) -> Option<PathBuf> {
+    if let Ok(rest) = repo_rel.strip_prefix(&filter.sync_subdirectory) {
+        return Some(Path::new("projects").join(rest));
+    }
+    let mut parts = repo_rel.strip_prefix(ARTIFACTS_SUBDIR).ok()?.components();
+    let subdir = parts.next()?.as_os_str();
+    let rest = parts.as_path();
+    let desc = REGISTRY
+        .iter()
+        .find(|d| d.dest == DestRoot::Artifacts && subdir == d.repo_subdir)?;
+    match desc.source {
+        SourceSpec::Files(list) => list
+            .iter()
+            .find(|entry| Path::new(entry).file_name() == rest.file_name())
+            .map(PathBuf::from),
+        SourceSpec::Dir(dir) => Some(Path::new(dir).join(rest)),
+    }
+}
+
 /// Classify what a pull would write, without writing. Remote (repo) bytes win
 /// for raw categories; union targets are compared against local ∪ remote.
 pub fn plan_pull(claude_dir: &Path, repo_root: &Path, filter: &FilterConfig) -> Result<PullPlan> {
@@ -419,6 +441,17 @@ pub fn plan_pull(claude_dir: &Path, repo_root: &Path, filter: &FilterConfig) ->
                 plan.skipped += 1;
                 continue;
             };
+            // Deny patterns are written against ~/.claude, which only the
+            // local destination is relative to
+            let claude_rel = local_path.strip_prefix(claude_dir).unwrap_or(&local_path);
+            if filter.is_denied(claude_rel) {
+                log::warn!(
+                    "Refusing denied artifact from sync repo: {}",
+                    repo_path.display()
+                );
+                plan.skipped += 1;
+                continue;
+            }
             let write = PlannedWrite {
                 category: desc.id,
                 local_path: local_path.clone(),
@@ -0,0 +1,170 @@
+//! Canonical JSON serialization for content hashing.
+//!
+//! Two copies of a transcript can carry the same data yet serialize
+//! differently: keys in another order, different whitespace, or numbers
+//! formatted differently (`1.0` vs `1`, `1e2` vs `100`) after a round trip
+//! through another machine or tool version. Hashing the canonical form makes
+//! those copies compare equal, so they are neither reported as conflicts nor
+//! pushed as modifications.
+//!
+//! The canonical form is compact JSON with object keys sorted by byte order,
+//! integral numbers written as integers, and all other numbers in Rust's
+//! shortest round-trip notation. Hashes are SHA-256, so they are stable across
+//! machines, platforms and compiler versions.
+
+use serde_json::{Number, Value};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+
+/// Largest magnitude at which an integral `f64` is still written as an integer
+/// (2^53, beyond which `f64` no longer represents every integer exactly)
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Append the canonical serialization of `value` to `out`
+pub fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Canonical serialization of `value`
+pub fn to_canonical_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_number(n: &Number, out: &mut String) {
+    if let Some(i) = n.as_i64() {
+        let _ = write!(out, "{i}");
+    } else if let Some(u) = n.as_u64() {
+        let _ = write!(out, "{u}");
+    } else if let Some(f) = n.as_f64() {
+        if f.fract() == 0.0 && f.abs() <= MAX_SAFE_INTEGER {
+            // Also folds -0.0 into 0
+            let _ = write!(out, "{}", f as i64);
+        } else {
+            let _ = write!(out, "{f}");
+        }
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    // serde_json's escaping is deterministic; reuse it rather than re-deriving
+    // the JSON string grammar.
+    out.push_str(&serde_json::to_string(s).unwrap_or_default());
+}
+
+/// Streaming SHA-256 over canonical JSON values
+pub struct CanonicalHasher {
+    hasher: Sha256,
+    buf: String,
+}
+
+impl CanonicalHasher {
+    pub fn new() -> Self {
+        CanonicalHasher {
+            hasher: Sha256::new(),
+            buf: String::new(),
+        }
+    }
+
+    /// Feed one value. Values are newline-separated, so a sequence hashes
+    /// like its canonical JSONL rendering.
+    pub fn update(&mut self, value: &Value) {
+        self.buf.clear();
+        write_canonical(value, &mut self.buf);
+        self.buf.push('\n');
+        self.hasher.update(self.buf.as_bytes());
+    }
+
+    /// Lowercase hex digest
+    pub fn finish(self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
+
+impl Default for CanonicalHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(json: &str) -> String {
+        to_canonical_string(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_key_order_and_whitespace_are_normalized() {
+        assert_eq!(
+            canonical(r#"{ "b": 1, "a": { "d": [1, 2], "c": null } }"#),
+            r#"{"a":{"c":null,"d":[1,2]},"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_numbers_are_normalized() {
+        assert_eq!(canonical("[1.0, 1e2, -0.0, 2.5, 0.1]"), "[1,100,0,2.5,0.1]");
+        assert_eq!(canonical("18446744073709551615"), "18446744073709551615");
+        assert_eq!(canonical("-42"), "-42");
+    }
+
+    #[test]
+    fn test_strings_are_escaped() {
+        assert_eq!(canonical(r#""a\"b\né""#), "\"a\\\"b\\né\"");
+    }
+
+    #[test]
+    fn test_hash_is_stable_and_order_independent() {
+        let mut a = CanonicalHasher::new();
+        a.update(&serde_json::from_str(r#"{"x":1.0,"y":"z"}"#).unwrap());
+        let mut b = CanonicalHasher::new();
+        b.update(&serde_json::from_str(r#"{"y":"z","x":1}"#).unwrap());
+
+        let digest = a.finish();
+        assert_eq!(digest, b.finish());
+        // Pinned: sha256 of `{"x":1,"y":"z"}\n`. The hash must not change
+        // between releases or platforms.
+        assert_eq!(
+            digest,
+            "72b5d6bf6c074186e3df12599f55e74945cf5646963ea51cc32aa10783b0fd94"
+        );
+    }
+}
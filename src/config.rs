@@ -75,6 +75,26 @@ impl ConfigManager {
         Ok(Self::config_dir()?.join("operation-history.json"))
     }
 
+    /// Get the discovery index path (cached session summaries)
+    pub fn discovery_index_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("discovery-index.json"))
+    }
+
+    /// Get the search index directory (full-text index over message text)
+    pub fn search_index_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("search-index"))
+    }
+
+    /// Get the copy ledger path (repository copies rewritten on push)
+    pub fn copy_ledger_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("repo-copies.json"))
+    }
+
+    /// Get the default key file for an encrypted sync repository
+    pub fn default_key_file_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("encryption-key.txt"))
+    }
+
     /// Get the snapshots directory path
     pub fn snapshots_dir() -> Result<PathBuf> {
         Ok(Self::config_dir()?.join("snapshots"))
@@ -91,6 +111,54 @@ impl ConfigManager {
         Ok(Self::config_dir()?.join("latest-conflict-report.json"))
     }
 
+    /// Get this machine's quarantine directory for unparsable transcripts.
+    ///
+    /// Entries are kept per machine so a config directory shared between hosts
+    /// (e.g. a synced dotfiles folder) never mixes up their source paths.
+    pub fn quarantine_dir() -> Result<PathBuf> {
+        Ok(Self::config_dir()?
+            .join("quarantine")
+            .join(Self::machine_name()))
+    }
+
+    /// Name identifying this machine, safe to use as a path component.
+    ///
+    /// Uses the hostname from the environment, `/etc/hostname`, or the
+    /// `hostname` command, falling back to `unknown-machine`.
+    pub fn machine_name() -> String {
+        let raw = ["HOSTNAME", "COMPUTERNAME"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|name| !name.trim().is_empty())
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .or_else(|| {
+                std::process::Command::new("hostname")
+                    .output()
+                    .ok()
+                    .filter(|output| output.status.success())
+                    .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            })
+            .unwrap_or_default();
+
+        let name: String = raw
+            .trim()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        if name.is_empty() || name.chars().all(|c| c == '.') {
+            "unknown-machine".to_string()
+        } else {
+            name
+        }
+    }
+
     /// Get the log file path
     pub fn log_file_path() -> Result<PathBuf> {
         Ok(Self::config_dir()?.join("claude-code-sync.log"))
@@ -149,6 +217,12 @@ mod tests {
         let repo = ConfigManager::default_repo_dir().unwrap();
         assert!(repo.to_string_lossy().contains("repo"));
 
+        let quarantine = ConfigManager::quarantine_dir().unwrap();
+        assert!(quarantine.starts_with(config_dir.join("quarantine")));
+
+        let search_index = ConfigManager::search_index_path().unwrap();
+        assert_eq!(search_index, config_dir.join("search-index"));
+
         let conflict = ConfigManager::conflict_report_path().unwrap();
         assert!(conflict
             .to_string_lossy()
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
 use crate::merge;
-use crate::parser::ConversationSession;
+use crate::parser::{ConversationSession, SessionInfo};
+use crate::sync::copies::CopyLedger;
 
 /// Represents a conflict between local and remote versions of the same conversation session.
 ///
@@ -35,6 +36,13 @@ pub struct Conflict {
     /// different states of the same conversation.
     pub session_id: String,
 
+    /// The session this conversation is a subagent sidechain of, if any.
+    ///
+    /// Sidechains are resolved together with their parent: whatever is decided
+    /// for the parent's conflict applies to theirs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+
     /// The file path to the local version of the conversation.
     ///
     /// This points to the conversation file in the local filesystem, typically in the
@@ -202,11 +210,12 @@ impl Conflict {
     /// println!("Local messages: {}", conflict.local_message_count);
     /// println!("Remote messages: {}", conflict.remote_message_count);
     /// ```
-    pub fn new(local: &ConversationSession, remote: &ConversationSession) -> Self {
+    pub fn new(local: &impl SessionInfo, remote: &impl SessionInfo) -> Self {
         Conflict {
-            session_id: local.session_id.clone(),
-            local_file: PathBuf::from(&local.file_path),
-            remote_file: PathBuf::from(&remote.file_path),
+            session_id: local.session_id().to_string(),
+            parent_session_id: local.parent_session_id().map(String::from),
+            local_file: PathBuf::from(local.file_path()),
+            remote_file: PathBuf::from(remote.file_path()),
             local_timestamp: local.latest_timestamp(),
             remote_timestamp: remote.latest_timestamp(),
             local_message_count: local.message_count(),
@@ -288,6 +297,38 @@ impl Conflict {
     pub fn is_real_conflict(&self) -> bool {
         self.local_hash != self.remote_hash
     }
+
+    /// The session id of the sync unit this conflict belongs to
+    pub fn unit_id(&self) -> &str {
+        self.parent_session_id
+            .as_deref()
+            .unwrap_or(&self.session_id)
+    }
+}
+
+/// Group conflicts into sync units, returning indices into `conflicts`.
+///
+/// Each group holds one session and its conflicting subagent sidechains, with
+/// the parent session's conflict first when the parent itself conflicts.
+/// Groups are ordered by their first conflict, so the grouping is as
+/// deterministic as the input.
+pub fn group_by_unit(conflicts: &[Conflict]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_unit: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+
+    for (i, conflict) in conflicts.iter().enumerate() {
+        let group = *by_unit.entry(conflict.unit_id()).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        if conflict.parent_session_id.is_none() {
+            groups[group].insert(0, i);
+        } else {
+            groups[group].push(i);
+        }
+    }
+
+    groups
 }
 
 /// Conflict detector for conversation sessions
@@ -345,23 +386,36 @@ impl ConflictDetector {
     }
 
     /// Compare local and remote sessions and detect conflicts
-    pub fn detect(
+    ///
+    /// Accepts fully-parsed sessions or streamed [`SessionSummary`] values;
+    /// only identity and hashes are compared.
+    ///
+    /// [`SessionSummary`]: crate::parser::SessionSummary
+    pub fn detect<S: SessionInfo>(&mut self, local_sessions: &[S], remote_sessions: &[S]) {
+        self.detect_with_ledger(local_sessions, remote_sessions, &CopyLedger::in_memory());
+    }
+
+    /// [`detect`](Self::detect), treating a remote copy that push rewrote
+    /// from the local content (see [`CopyLedger`]) as unchanged
+    pub fn detect_with_ledger<S: SessionInfo>(
         &mut self,
-        local_sessions: &[ConversationSession],
-        remote_sessions: &[ConversationSession],
+        local_sessions: &[S],
+        remote_sessions: &[S],
+        ledger: &CopyLedger,
     ) {
         // Build a map of session_id -> local session
-        let local_map: std::collections::HashMap<_, _> = local_sessions
-            .iter()
-            .map(|s| (s.session_id.clone(), s))
-            .collect();
+        let local_map: std::collections::HashMap<_, _> =
+            local_sessions.iter().map(|s| (s.session_id(), s)).collect();
 
         // Check each remote session against local
         for remote in remote_sessions {
-            if let Some(local) = local_map.get(&remote.session_id) {
+            if let Some(local) = local_map.get(remote.session_id()) {
                 // Session exists in both - check for conflicts
-                if local.content_hash() != remote.content_hash() {
-                    let conflict = Conflict::new(local, remote);
+                let (local_hash, remote_hash) = (local.content_hash(), remote.content_hash());
+                if local_hash != remote_hash
+                    && !ledger.is_copy_of(remote.session_id(), &local_hash, &remote_hash)
+                {
+                    let conflict = Conflict::new(*local, remote);
                     if conflict.is_real_conflict() {
                         self.conflicts.push(conflict);
                     }
@@ -474,4 +528,34 @@ mod tests {
 
         assert!(!detector.has_conflicts());
     }
+
+    #[test]
+    fn test_sidechain_conflicts_group_with_parent() {
+        let sidechain = |id: &str, parent: &str, count: usize| {
+            let mut session = create_test_session(id, count);
+            for entry in &mut session.entries {
+                entry.session_id = Some(parent.to_string());
+            }
+            session
+        };
+        let local = vec![
+            sidechain("agent-1", "parent", 2),
+            create_test_session("other", 2),
+            create_test_session("parent", 2),
+        ];
+        let remote = vec![
+            sidechain("agent-1", "parent", 3),
+            create_test_session("other", 3),
+            create_test_session("parent", 3),
+        ];
+
+        let mut detector = ConflictDetector::new();
+        detector.detect(&local, &remote);
+        let conflicts = detector.conflicts();
+        assert_eq!(conflicts[0].parent_session_id.as_deref(), Some("parent"));
+        assert_eq!(conflicts[0].unit_id(), "parent");
+
+        // The parent leads its unit even though its sidechain was found first
+        assert_eq!(group_by_unit(conflicts), vec![vec![2, 0], vec![1]]);
+    }
 }
@@ -0,0 +1,716 @@
+//! End-to-end encryption of the sync repository.
+//!
+//! An encrypted repository keeps its settings in
+//! `.claude-code-sync/encryption.toml`: the public keys (age X25519
+//! recipients) files are encrypted to, and for projects only some may read,
+//! that project's own list. Session transcripts, attachments and artifacts
+//! are stored as `<name>.age`; file names, the directory layout, `machines/`
+//! and the ignore files stay readable.
+//!
+//! A machine unlocks the repository with either
+//! - a key file holding an age X25519 identity (`AGE-SECRET-KEY-1...`), or
+//! - a passphrase, which opens `.claude-code-sync/identity.age`: the
+//!   repository's identity, encrypted with that passphrase and shared through
+//!   the repository itself.
+//!
+//! Sync logic never sees ciphertext: it runs against a decrypted copy of the
+//! repository kept in the config directory (see [`view`]), so hashing,
+//! conflict detection and smart merge work on plaintext exactly as they do
+//! for an unencrypted repository.
+//!
+//! The settings file is plain text that anyone who can push to the
+//! repository, or whoever hosts it, could edit. Each machine keeps the
+//! recipients it has accepted in its own configuration, and a changed list
+//! is only encrypted to once it is accepted there (see [`unlock`]).
+
+pub mod view;
+
+use age::secrecy::{ExposeSecret, SecretString};
+use age::x25519;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::filter::FilterConfig;
+
+/// Directory at the sync repository root holding repository-wide settings
+pub const REPO_CONFIG_DIR: &str = ".claude-code-sync";
+
+/// Extension appended to the name of every encrypted file
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+/// Environment variable read for the passphrase before prompting
+pub const PASSPHRASE_ENV: &str = "CLAUDE_CODE_SYNC_PASSPHRASE";
+
+const ENCRYPTION_FILE: &str = "encryption.toml";
+const WRAPPED_IDENTITY_FILE: &str = "identity.age";
+
+/// Encryption settings shared through the repository
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RepoEncryption {
+    /// Public keys (`age1...`) files are encrypted to
+    pub recipients: Vec<String>,
+    /// Projects readable only by some, by directory name in the sync
+    /// subdirectory: their files are encrypted to these keys instead.
+    /// Their keys should also be repository recipients, as a machine must be
+    /// one to open the repository at all.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub projects: BTreeMap<String, Vec<String>>,
+}
+
+impl RepoEncryption {
+    /// Settings path of the repository at `repo_root`
+    pub fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(REPO_CONFIG_DIR).join(ENCRYPTION_FILE)
+    }
+
+    /// Whether the repository at `repo_root` is encrypted
+    pub fn is_enabled(repo_root: &Path) -> bool {
+        Self::path(repo_root).exists()
+    }
+
+    /// Load the settings; `None` if the repository is not encrypted
+    pub fn load(repo_root: &Path) -> Result<Option<Self>> {
+        let path = Self::path(repo_root);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config.parsed_recipients()?;
+        config.parsed_projects()?;
+        Ok(Some(config))
+    }
+
+    /// Write the settings into the repository
+    pub fn save(&self, repo_root: &Path) -> Result<()> {
+        let path = Self::path(repo_root);
+        fs::create_dir_all(repo_root.join(REPO_CONFIG_DIR))
+            .with_context(|| format!("Failed to create {}", REPO_CONFIG_DIR))?;
+        let content =
+            toml::to_string_pretty(self).context("Failed to serialize encryption settings")?;
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Public keys the files of `project` (or, for `None`, files outside
+    /// any project) are encrypted to
+    pub fn recipients_for(&self, project: Option<&str>) -> &[String] {
+        project
+            .and_then(|name| self.projects.get(name))
+            .unwrap_or(&self.recipients)
+    }
+
+    /// Add `key` to the recipients of `project`, or of the repository. A
+    /// project without its own list gets one holding only `key`. Returns
+    /// false if the key was already there.
+    pub fn add_recipient(&mut self, key: &str, project: Option<&str>) -> Result<bool> {
+        let key = parse_recipient(key)?.to_string();
+        let list = match project {
+            Some(name) => self.projects.entry(name.to_string()).or_default(),
+            None => &mut self.recipients,
+        };
+        if list.contains(&key) {
+            return Ok(false);
+        }
+        list.push(key);
+        Ok(true)
+    }
+
+    /// Remove `key` from the recipients of `project`, or of the repository.
+    /// Returns false if it was not there; refuses to leave a list empty.
+    pub fn remove_recipient(&mut self, key: &str, project: Option<&str>) -> Result<bool> {
+        let key = parse_recipient(key)?.to_string();
+        let list = match project {
+            Some(name) => match self.projects.get_mut(name) {
+                Some(list) => list,
+                None => return Ok(false),
+            },
+            None => &mut self.recipients,
+        };
+        if !list.contains(&key) {
+            return Ok(false);
+        }
+        if list.len() == 1 {
+            match project {
+                Some(name) => bail!(
+                    "{key} is the last recipient of project {name}. To let every repository \
+                     recipient read it again, remove its entry from {ENCRYPTION_FILE}"
+                ),
+                None => bail!("{key} is the repository's last recipient"),
+            }
+        }
+        list.retain(|k| *k != key);
+        Ok(true)
+    }
+
+    /// How the recipient lists differ from `trusted`. A project is compared
+    /// by the keys its files are encrypted to, so one that gains or loses
+    /// its own list counts as changed too.
+    pub fn changes_from(&self, trusted: &RepoEncryption) -> Vec<RecipientChange> {
+        let projects: BTreeSet<&String> = trusted
+            .projects
+            .keys()
+            .chain(self.projects.keys())
+            .collect();
+        let mut changes: Vec<RecipientChange> =
+            list_change(None, &trusted.recipients, &self.recipients)
+                .into_iter()
+                .collect();
+        for name in projects {
+            changes.extend(list_change(
+                Some(name),
+                trusted.recipients_for(Some(name)),
+                self.recipients_for(Some(name)),
+            ));
+        }
+        changes
+    }
+
+    fn parsed_recipients(&self) -> Result<Vec<x25519::Recipient>> {
+        parse_list(&self.recipients, "The repository")
+    }
+
+    fn parsed_projects(&self) -> Result<BTreeMap<String, Vec<x25519::Recipient>>> {
+        self.projects
+            .iter()
+            .map(|(name, keys)| Ok((name.clone(), parse_list(keys, &format!("Project {name}"))?)))
+            .collect()
+    }
+}
+
+/// A recipient list that differs from the one this machine trusts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipientChange {
+    /// Project the list covers; `None` for the repository's own list
+    pub project: Option<String>,
+    /// Keys the list gained
+    pub added: Vec<String>,
+    /// Keys the list lost
+    pub removed: Vec<String>,
+}
+
+fn list_change(
+    project: Option<&str>,
+    before: &[String],
+    after: &[String],
+) -> Option<RecipientChange> {
+    let added: Vec<String> = after
+        .iter()
+        .filter(|k| !before.contains(k))
+        .cloned()
+        .collect();
+    let removed: Vec<String> = before
+        .iter()
+        .filter(|k| !after.contains(k))
+        .cloned()
+        .collect();
+    if added.is_empty() && removed.is_empty() {
+        return None;
+    }
+    Some(RecipientChange {
+        project: project.map(str::to_string),
+        added,
+        removed,
+    })
+}
+
+/// Show recipient changes to the user, before they are accepted
+pub fn print_changes(changes: &[RecipientChange]) {
+    for change in changes {
+        let target = match &change.project {
+            Some(name) => format!("project {name}"),
+            None => "the sync repository".to_string(),
+        };
+        println!(
+            "  {} Recipients of {} changed:",
+            "!".yellow(),
+            target.cyan()
+        );
+        for key in &change.added {
+            println!("    + {}", key.yellow());
+        }
+        for key in &change.removed {
+            println!("    - {key}");
+        }
+    }
+}
+
+/// Record the recipients of `config` as the ones this machine trusts
+pub fn trust(config: &RepoEncryption) -> Result<()> {
+    let mut filter = FilterConfig::load()?;
+    filter.trusted_recipients = Some(config.clone());
+    filter.save().context("Failed to save filter configuration")
+}
+
+/// Check the repository's recipients against those this machine trusts. A
+/// change is shown and must be accepted, at a prompt or with `encryption
+/// trust`, before anything is encrypted to it. The first unlock trusts the
+/// settings as found.
+fn check_trusted(config: &RepoEncryption, filter: &FilterConfig) -> Result<()> {
+    let Some(trusted) = &filter.trusted_recipients else {
+        return trust(config);
+    };
+    let changes = config.changes_from(trusted);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    print_changes(&changes);
+    if !crate::interactive_conflict::is_interactive() {
+        bail!(
+            "The sync repository's recipients changed since this machine accepted them. \
+             Check the keys above, then run: claude-code-sync encryption trust"
+        );
+    }
+    let accepted = inquire::Confirm::new("Encrypt to these recipients from now on?")
+        .with_default(false)
+        .prompt()
+        .context("Failed to read confirmation")?;
+    if !accepted {
+        bail!("Recipient change not accepted");
+    }
+    trust(config)
+}
+
+fn parse_list(keys: &[String], owner: &str) -> Result<Vec<x25519::Recipient>> {
+    if keys.is_empty() {
+        bail!("{owner} has no recipients in {ENCRYPTION_FILE}");
+    }
+    keys.iter().map(|key| parse_recipient(key)).collect()
+}
+
+/// How this machine unlocks an encrypted repository
+#[derive(Debug)]
+pub enum KeySource {
+    /// An age identity file, created if missing when encryption is set up
+    KeyFile(PathBuf),
+    /// The passphrase protecting the repository's shared identity
+    Passphrase(SecretString),
+}
+
+/// Result of [`setup`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupOutcome {
+    /// The repository was not encrypted; it is now, to this public key
+    Created { public_key: String },
+    /// The repository was already encrypted and the key opens it
+    Joined { public_key: String },
+}
+
+/// Keys to read and write an encrypted repository
+#[derive(Clone)]
+pub struct Cipher {
+    identity: x25519::Identity,
+    recipients: Vec<x25519::Recipient>,
+    projects: BTreeMap<String, Vec<x25519::Recipient>>,
+}
+
+impl Cipher {
+    fn new(identity: x25519::Identity, config: &RepoEncryption) -> Result<Self> {
+        Ok(Cipher {
+            identity,
+            recipients: config.parsed_recipients()?,
+            projects: config.parsed_projects()?,
+        })
+    }
+
+    /// The same identity with the recipients of `config`
+    pub fn with_config(&self, config: &RepoEncryption) -> Result<Self> {
+        Self::new(self.identity.clone(), config)
+    }
+
+    /// Public key of this machine's identity
+    pub fn public_key(&self) -> String {
+        self.identity.to_public().to_string()
+    }
+
+    fn recipients_for(&self, project: Option<&str>) -> &[x25519::Recipient] {
+        project
+            .and_then(|name| self.projects.get(name))
+            .unwrap_or(&self.recipients)
+    }
+
+    /// Whether this machine's key is a recipient of `project`'s files (or,
+    /// for `None`, of files outside any project)
+    pub fn can_open(&self, project: Option<&str>) -> bool {
+        self.recipients_for(project)
+            .contains(&self.identity.to_public())
+    }
+
+    /// Projects with their own recipient list that leaves this machine out
+    pub fn locked_projects(&self) -> BTreeSet<String> {
+        self.projects
+            .keys()
+            .filter(|name| !self.can_open(Some(name)))
+            .cloned()
+            .collect()
+    }
+
+    /// Public keys `project`'s files (or, for `None`, files outside any
+    /// project) are encrypted to
+    pub fn recipient_keys(&self, project: Option<&str>) -> Vec<String> {
+        self.recipients_for(project)
+            .iter()
+            .map(|r| r.to_string())
+            .collect()
+    }
+
+    /// Stable digest of the recipients `project`'s files are encrypted to,
+    /// so files can be re-encrypted when the list changes
+    pub fn recipients_digest(&self, project: Option<&str>) -> String {
+        let mut keys = self.recipient_keys(project);
+        keys.sort();
+        let digest = Sha256::digest(keys.join("\n").as_bytes());
+        digest[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Encrypt `plaintext` to the repository's recipients
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_for(None, plaintext)
+    }
+
+    /// Encrypt `plaintext` to the recipients of `project`
+    pub fn encrypt_for(&self, project: Option<&str>, plaintext: &[u8]) -> Result<Vec<u8>> {
+        encrypt_to(self.recipients_for(project), plaintext)
+    }
+
+    /// Decrypt `ciphertext` with this machine's identity
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        age::decrypt(&self.identity, ciphertext).map_err(|e| anyhow::anyhow!("{e}"))
+    }
+}
+
+/// The keys for the repository at `repo_root`, or `None` if it is not
+/// encrypted. The passphrase, when needed, comes from
+/// [`PASSPHRASE_ENV`] or an interactive prompt, and recipients this machine
+/// has not accepted must be first.
+pub fn unlock(repo_root: &Path, filter: &FilterConfig) -> Result<Option<Cipher>> {
+    let Some(config) = RepoEncryption::load(repo_root)? else {
+        return Ok(None);
+    };
+    check_trusted(&config, filter)?;
+    let identity = match &filter.encryption_key_file {
+        Some(path) => read_key_file(path)?,
+        None => {
+            if !repo_root
+                .join(REPO_CONFIG_DIR)
+                .join(WRAPPED_IDENTITY_FILE)
+                .exists()
+            {
+                bail!(
+                    "The sync repository is encrypted with key files. Set encryption_key_file \
+                     in the configuration, or run: claude-code-sync encryption enable --key-file <path>"
+                );
+            }
+            unwrap_identity(repo_root, &passphrase(false)?)?
+        }
+    };
+    let cipher = Cipher::new(identity, &config)?;
+    if !cipher.can_open(None) {
+        bail!(
+            "This machine's key ({}) is not a recipient of the encrypted sync repository",
+            cipher.public_key()
+        );
+    }
+    Ok(Some(cipher))
+}
+
+/// Encrypt the repository at `repo_root`, or check that `source` opens it if
+/// it already is. A missing key file is generated.
+pub fn setup(repo_root: &Path, source: &KeySource) -> Result<SetupOutcome> {
+    if let Some(config) = RepoEncryption::load(repo_root)? {
+        let identity = match source {
+            KeySource::KeyFile(path) => read_key_file(path)?,
+            KeySource::Passphrase(passphrase) => {
+                if !repo_root
+                    .join(REPO_CONFIG_DIR)
+                    .join(WRAPPED_IDENTITY_FILE)
+                    .exists()
+                {
+                    bail!("The sync repository is encrypted with key files, not a passphrase");
+                }
+                unwrap_identity(repo_root, passphrase)?
+            }
+        };
+        let public_key = identity.to_public().to_string();
+        if !config.recipients.contains(&public_key) {
+            bail!("Key {public_key} is not a recipient of the encrypted sync repository");
+        }
+        return Ok(SetupOutcome::Joined { public_key });
+    }
+
+    let identity = match source {
+        KeySource::KeyFile(path) if path.exists() => read_key_file(path)?,
+        KeySource::KeyFile(path) => {
+            let identity = x25519::Identity::generate();
+            write_key_file(path, &identity)?;
+            identity
+        }
+        KeySource::Passphrase(passphrase) => {
+            let identity = x25519::Identity::generate();
+            wrap_identity(repo_root, &identity, passphrase)?;
+            identity
+        }
+    };
+    let public_key = identity.to_public().to_string();
+    RepoEncryption {
+        recipients: vec![public_key.clone()],
+        ..Default::default()
+    }
+    .save(repo_root)?;
+    Ok(SetupOutcome::Created { public_key })
+}
+
+/// The passphrase from [`PASSPHRASE_ENV`], else asked for interactively
+/// (twice when `confirm` is set, for a new passphrase)
+pub fn passphrase(confirm: bool) -> Result<SecretString> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(SecretString::from(passphrase));
+        }
+    }
+    if !crate::interactive_conflict::is_interactive() {
+        bail!("The sync repository passphrase is needed: set {PASSPHRASE_ENV}");
+    }
+    let mut prompt = inquire::Password::new("Sync repository passphrase:")
+        .with_display_mode(inquire::PasswordDisplayMode::Masked);
+    if !confirm {
+        prompt = prompt.without_confirmation();
+    }
+    let passphrase = prompt.prompt().context("Failed to read passphrase")?;
+    if passphrase.is_empty() {
+        bail!("The passphrase cannot be empty");
+    }
+    Ok(SecretString::from(passphrase))
+}
+
+/// Encrypt `plaintext` so any of `recipients` can open it
+pub fn encrypt_to(recipients: &[x25519::Recipient], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let mut ciphertext = Vec::with_capacity(plaintext.len() + 256);
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .context("Failed to start encryption")?;
+    writer.write_all(plaintext).context("Failed to encrypt")?;
+    writer.finish().context("Failed to encrypt")?;
+    Ok(ciphertext)
+}
+
+/// Parse an `age1...` public key
+pub fn parse_recipient(key: &str) -> Result<x25519::Recipient> {
+    x25519::Recipient::from_str(key.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid public key '{}': {}", key.trim(), e))
+}
+
+/// Read the first identity of an age key file
+pub fn read_key_file(path: &Path) -> Result<x25519::Identity> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read key file {}", path.display()))?;
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .with_context(|| format!("No key found in {}", path.display()))?;
+    x25519::Identity::from_str(line)
+        .map_err(|e| anyhow::anyhow!("Invalid key in {}: {}", path.display(), e))
+}
+
+/// Write `identity` as an age key file readable only by the owner
+fn write_key_file(path: &Path, identity: &x25519::Identity) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let content = format!(
+        "# created: {}\n# public key: {}\n{}\n",
+        chrono::Utc::now().to_rfc3339(),
+        identity.to_public(),
+        identity.to_string().expose_secret()
+    );
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create key file {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write key file {}", path.display()))
+}
+
+/// Store `identity` in the repository, encrypted with `passphrase`
+fn wrap_identity(
+    repo_root: &Path,
+    identity: &x25519::Identity,
+    passphrase: &SecretString,
+) -> Result<()> {
+    let encryptor = age::Encryptor::with_user_passphrase(passphrase.clone());
+    let mut wrapped = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut wrapped)
+        .context("Failed to start encryption")?;
+    writer
+        .write_all(identity.to_string().expose_secret().as_bytes())
+        .context("Failed to encrypt key")?;
+    writer.finish().context("Failed to encrypt key")?;
+
+    let dir = repo_root.join(REPO_CONFIG_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(WRAPPED_IDENTITY_FILE);
+    fs::write(&path, wrapped).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Open the repository's passphrase-protected identity
+fn unwrap_identity(repo_root: &Path, passphrase: &SecretString) -> Result<x25519::Identity> {
+    let path = repo_root.join(REPO_CONFIG_DIR).join(WRAPPED_IDENTITY_FILE);
+    let wrapped = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let decryptor = age::Decryptor::new(&wrapped[..])
+        .with_context(|| format!("{} is not an age file", path.display()))?;
+    let identity = age::scrypt::Identity::new(passphrase.clone());
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase for the sync repository"))?;
+    let mut key = String::new();
+    reader
+        .read_to_string(&mut key)
+        .context("Failed to decrypt the repository key")?;
+    x25519::Identity::from_str(key.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid repository key in {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_key_file_setup_and_unlock() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let key = dir.path().join("keys/key.txt");
+
+        let SetupOutcome::Created { public_key } =
+            setup(&repo, &KeySource::KeyFile(key.clone())).unwrap()
+        else {
+            panic!("expected a new setup");
+        };
+        assert!(public_key.starts_with("age1"));
+        assert_eq!(
+            setup(&repo, &KeySource::KeyFile(key.clone())).unwrap(),
+            SetupOutcome::Joined {
+                public_key: public_key.clone()
+            }
+        );
+
+        let filter = FilterConfig {
+            encryption_key_file: Some(key),
+            trusted_recipients: RepoEncryption::load(&repo).unwrap(),
+            ..Default::default()
+        };
+        let cipher = unlock(&repo, &filter).unwrap().unwrap();
+        let ciphertext = cipher.encrypt(b"{\"type\":\"user\"}\n").unwrap();
+        assert!(!ciphertext.windows(4).any(|w| w == b"user"));
+        assert_eq!(
+            cipher.decrypt(&ciphertext).unwrap(),
+            b"{\"type\":\"user\"}\n"
+        );
+
+        // A key that is not a recipient is refused
+        let other = dir.path().join("other.txt");
+        write_key_file(&other, &x25519::Identity::generate()).unwrap();
+        assert!(setup(&repo, &KeySource::KeyFile(other)).is_err());
+
+        // Unencrypted repositories need no keys
+        assert!(unlock(dir.path(), &FilterConfig::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_passphrase_setup() {
+        let dir = TempDir::new().unwrap();
+        let secret = SecretString::from("correct horse".to_string());
+
+        let SetupOutcome::Created { public_key } =
+            setup(dir.path(), &KeySource::Passphrase(secret.clone())).unwrap()
+        else {
+            panic!("expected a new setup");
+        };
+        assert_eq!(
+            unwrap_identity(dir.path(), &secret)
+                .unwrap()
+                .to_public()
+                .to_string(),
+            public_key
+        );
+        let wrong = SecretString::from("wrong".to_string());
+        assert!(setup(dir.path(), &KeySource::Passphrase(wrong)).is_err());
+    }
+
+    #[test]
+    fn test_changed_recipients_are_not_trusted() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let key = dir.path().join("key.txt");
+        setup(&repo, &KeySource::KeyFile(key.clone())).unwrap();
+        let trusted = RepoEncryption::load(&repo).unwrap().unwrap();
+
+        // Someone with push access adds their own key to the settings
+        let intruder = x25519::Identity::generate().to_public().to_string();
+        let mut config = trusted.clone();
+        config.add_recipient(&intruder, None).unwrap();
+        config.save(&repo).unwrap();
+
+        assert_eq!(
+            config.changes_from(&trusted),
+            vec![RecipientChange {
+                project: None,
+                added: vec![intruder],
+                removed: Vec::new(),
+            }]
+        );
+        assert!(trusted.changes_from(&trusted).is_empty());
+
+        // Projects compare by who reads them: an own list holding the same
+        // keys is no change, one key more on it is
+        let mut restricted = trusted.clone();
+        let own = trusted.recipients[0].clone();
+        restricted.add_recipient(&own, Some("nda")).unwrap();
+        assert!(restricted.changes_from(&trusted).is_empty());
+        let mut widened = restricted.clone();
+        let outsider = x25519::Identity::generate().to_public().to_string();
+        widened.add_recipient(&outsider, Some("nda")).unwrap();
+        assert_eq!(
+            widened.changes_from(&restricted),
+            vec![RecipientChange {
+                project: Some("nda".to_string()),
+                added: vec![outsider],
+                removed: Vec::new(),
+            }]
+        );
+
+        // Tests run without a terminal, so the change is refused
+        let filter = FilterConfig {
+            encryption_key_file: Some(key),
+            trusted_recipients: Some(trusted),
+            ..Default::default()
+        };
+        let err = unlock(&repo, &filter).err().unwrap();
+        assert!(err.to_string().contains("encryption trust"));
+    }
+}
@@ -0,0 +1,632 @@
+//! Decrypted view of an encrypted sync repository.
+//!
+//! The view is a plaintext copy of the repository's encrypted areas (the
+//! projects subdirectory and `artifacts/`) kept under the config directory.
+//! Push and pull run against it instead of the repository: [`RepoView::open`]
+//! decrypts what changed in the repository since the last run, and
+//! [`RepoView::seal`] encrypts what sync changed in the view back into it.
+//!
+//! A manifest records, per file, the hash of its ciphertext and of its
+//! plaintext, and the recipients it was encrypted to. Encryption is
+//! randomized, so without it every push would re-encrypt, and rewrite, every
+//! file; with it only files whose plaintext or recipients changed are
+//! written.
+//!
+//! Projects whose recipient list leaves this machine out are locked: they
+//! are neither decrypted into the view nor written back, so the repository
+//! copies other recipients pushed are never overwritten by a machine that
+//! cannot read them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::ConfigManager;
+use crate::filter::FilterConfig;
+
+use super::{unlock, Cipher, RepoEncryption, ENCRYPTED_EXTENSION};
+
+const MANIFEST_FILE: &str = ".view-manifest.json";
+const MANIFEST_VERSION: u32 = 2;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// Plaintext path relative to the root -> hashes
+    files: BTreeMap<String, FileHashes>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileHashes {
+    /// SHA-256 of the `.age` file in the repository
+    encrypted: String,
+    /// SHA-256 of the decrypted content
+    plain: String,
+    /// [`Cipher::recipients_digest`] of the keys it is encrypted to
+    recipients: String,
+}
+
+/// Outcome counts of [`RepoView::open`] and [`RepoView::seal`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ViewReport {
+    /// Files written (decrypted into the view, or encrypted into the repo)
+    pub written: usize,
+    /// Files removed (from the view, or from the repo: plaintext left there
+    /// and files the view no longer has)
+    pub removed: usize,
+}
+
+/// The sync repository as plaintext
+pub struct RepoView {
+    repo_root: PathBuf,
+    root: PathBuf,
+    areas: Vec<String>,
+    /// The area holding sessions, by project directory
+    projects_area: String,
+    cipher: Option<Cipher>,
+    locked: BTreeSet<String>,
+    /// Recipient lists files were re-encrypted to because the list changed:
+    /// by project, `None` for the repository's own list
+    rekeyed: BTreeSet<Option<String>>,
+    manifest: Manifest,
+}
+
+impl RepoView {
+    /// Open the repository at `repo_root`. An unencrypted repository is its
+    /// own view; an encrypted one is unlocked and its view brought up to date.
+    pub fn open(repo_root: &Path, filter: &FilterConfig) -> Result<Self> {
+        let Some(cipher) = unlock(repo_root, filter)? else {
+            return Ok(Self::plain(repo_root));
+        };
+        let mut view = Self::encrypted(repo_root, &view_dir(repo_root)?, cipher, filter);
+        let report = view.refresh()?;
+        if report.written + report.removed > 0 {
+            log::info!(
+                "Decrypted {} file(s), removed {} from the plaintext view",
+                report.written,
+                report.removed
+            );
+        }
+        Ok(view)
+    }
+
+    /// Plaintext root for reading without unlocking: the repository itself,
+    /// or the view of an encrypted one as of its last push or pull
+    pub fn plaintext_root(repo_root: &Path) -> Result<PathBuf> {
+        if RepoEncryption::is_enabled(repo_root) {
+            view_dir(repo_root)
+        } else {
+            Ok(repo_root.to_path_buf())
+        }
+    }
+
+    fn plain(repo_root: &Path) -> Self {
+        RepoView {
+            repo_root: repo_root.to_path_buf(),
+            root: repo_root.to_path_buf(),
+            areas: Vec::new(),
+            projects_area: String::new(),
+            cipher: None,
+            locked: BTreeSet::new(),
+            rekeyed: BTreeSet::new(),
+            manifest: Manifest::default(),
+        }
+    }
+
+    fn encrypted(repo_root: &Path, root: &Path, cipher: Cipher, filter: &FilterConfig) -> Self {
+        let manifest = fs::read_to_string(root.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Manifest>(&json).ok())
+            .filter(|m| m.version == MANIFEST_VERSION)
+            .unwrap_or(Manifest {
+                version: MANIFEST_VERSION,
+                files: BTreeMap::new(),
+            });
+        RepoView {
+            repo_root: repo_root.to_path_buf(),
+            root: root.to_path_buf(),
+            areas: vec![
+                filter.sync_subdirectory.clone(),
+                crate::artifacts::registry::ARTIFACTS_SUBDIR.to_string(),
+            ],
+            projects_area: filter.sync_subdirectory.clone(),
+            locked: cipher.locked_projects(),
+            cipher: Some(cipher),
+            rekeyed: BTreeSet::new(),
+            manifest,
+        }
+    }
+
+    /// Directory to read and write plaintext in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the repository is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Project directories this machine's key cannot open, left out of the
+    /// view
+    pub fn locked_projects(&self) -> &BTreeSet<String> {
+        &self.locked
+    }
+
+    /// Recipient lists files were re-encrypted to since the view was opened
+    /// because the list changed, with their keys: by project, `None` for the
+    /// repository's own list
+    pub fn rekeyed_lists(&self) -> Vec<(Option<String>, Vec<String>)> {
+        let Some(cipher) = &self.cipher else {
+            return Vec::new();
+        };
+        self.rekeyed
+            .iter()
+            .map(|project| (project.clone(), cipher.recipient_keys(project.as_deref())))
+            .collect()
+    }
+
+    /// Project directory `rel` belongs to, if it is a session file
+    fn project_of<'a>(&self, rel: &'a str) -> Option<&'a str> {
+        let within = rel.strip_prefix(&self.projects_area)?.strip_prefix('/')?;
+        within.split_once('/').map(|(project, _)| project)
+    }
+
+    fn is_locked(&self, rel: &str) -> bool {
+        self.project_of(rel)
+            .is_some_and(|project| self.locked.contains(project))
+    }
+
+    /// Decrypt files that changed in the repository into the view and drop
+    /// view files the repository no longer has
+    fn refresh(&mut self) -> Result<ViewReport> {
+        let Some(cipher) = self.cipher.clone() else {
+            return Ok(ViewReport::default());
+        };
+        let mut report = ViewReport::default();
+        let mut seen = HashSet::new();
+
+        for (rel, path) in area_files(&self.repo_root, &self.areas) {
+            if self.is_locked(&rel) {
+                continue;
+            }
+            let Some(plain_rel) = rel.strip_suffix(&format!(".{ENCRYPTED_EXTENSION}")) else {
+                // Plaintext left from before encryption: take it as is, and
+                // let the next seal encrypt it
+                if seen.insert(rel.clone()) {
+                    report.written += copy_if_changed(&path, &self.root.join(&rel))? as usize;
+                }
+                continue;
+            };
+            seen.insert(plain_rel.to_string());
+            let bytes =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let encrypted = sha256_hex(&bytes);
+            let dest = self.root.join(plain_rel);
+            if dest.exists()
+                && self
+                    .manifest
+                    .files
+                    .get(plain_rel)
+                    .is_some_and(|h| h.encrypted == encrypted)
+            {
+                continue;
+            }
+            let project = self.project_of(plain_rel);
+            let plain = match (cipher.decrypt(&bytes), project) {
+                (Ok(plain), _) => plain,
+                // Listed as a recipient, but the files are still encrypted to
+                // the old list: wait for a recipient to push them again
+                (Err(e), Some(project)) => {
+                    log::warn!("Cannot decrypt {rel}: {e:#}");
+                    self.locked.insert(project.to_string());
+                    continue;
+                }
+                (Err(e), None) => return Err(e.context(format!("Failed to decrypt {rel}"))),
+            };
+            write_file(&dest, &plain)?;
+            self.manifest.files.insert(
+                plain_rel.to_string(),
+                FileHashes {
+                    encrypted,
+                    plain: sha256_hex(&plain),
+                    recipients: cipher.recipients_digest(project),
+                },
+            );
+            report.written += 1;
+        }
+        seen.retain(|rel| !self.is_locked(rel));
+
+        for (rel, path) in area_files(&self.root, &self.areas) {
+            if !seen.contains(&rel) {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+                self.manifest.files.remove(&rel);
+                report.removed += 1;
+            }
+        }
+        self.manifest.files.retain(|rel, _| seen.contains(rel));
+        self.save_manifest()?;
+        Ok(report)
+    }
+
+    /// Encrypt view files whose plaintext or recipients changed into the
+    /// repository, and remove any plaintext copies left there along with
+    /// files removed from the view. Locked projects are left alone. A no-op
+    /// when unencrypted.
+    pub fn seal(&mut self) -> Result<ViewReport> {
+        let Some(cipher) = self.cipher.clone() else {
+            return Ok(ViewReport::default());
+        };
+        let mut report = ViewReport::default();
+
+        for (rel, path) in area_files(&self.root, &self.areas) {
+            if self.is_locked(&rel) {
+                continue;
+            }
+            let plain =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let plain_hash = sha256_hex(&plain);
+            let project = self.project_of(&rel);
+            let recipients = cipher.recipients_digest(project);
+            let dest = self.repo_root.join(format!("{rel}.{ENCRYPTED_EXTENSION}"));
+            let previous = self.manifest.files.get(&rel);
+            let current = dest.exists()
+                && previous.is_some_and(|h| h.plain == plain_hash && h.recipients == recipients);
+            if previous.is_some_and(|h| h.recipients != recipients) {
+                self.rekeyed.insert(project.map(str::to_string));
+            }
+            if !current {
+                let ciphertext = cipher
+                    .encrypt_for(project, &plain)
+                    .with_context(|| format!("Failed to encrypt {rel}"))?;
+                write_file(&dest, &ciphertext)?;
+                self.manifest.files.insert(
+                    rel.clone(),
+                    FileHashes {
+                        encrypted: sha256_hex(&ciphertext),
+                        plain: plain_hash,
+                        recipients,
+                    },
+                );
+                report.written += 1;
+            }
+            let leftover = self.repo_root.join(&rel);
+            if leftover.is_file() {
+                fs::remove_file(&leftover)
+                    .with_context(|| format!("Failed to remove {}", leftover.display()))?;
+                report.removed += 1;
+            }
+        }
+
+        // A file removed from the view goes from the repository too, or the
+        // next refresh would bring it back
+        for (rel, path) in area_files(&self.repo_root, &self.areas) {
+            let plain_rel = rel
+                .strip_suffix(&format!(".{ENCRYPTED_EXTENSION}"))
+                .unwrap_or(&rel);
+            if self.is_locked(plain_rel) || self.root.join(plain_rel).is_file() {
+                continue;
+            }
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            self.manifest.files.remove(plain_rel);
+            report.removed += 1;
+        }
+        self.save_manifest()?;
+        Ok(report)
+    }
+
+    /// Re-encrypt the repository to the recipients of `config`, before it
+    /// replaces the repository's settings. Projects readable here are
+    /// re-encrypted even if `config` locks this machine out of them.
+    pub fn rekey(&mut self, config: &RepoEncryption) -> Result<ViewReport> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(ViewReport::default());
+        };
+        let cipher = cipher.with_config(config)?;
+        if !cipher.can_open(None) {
+            anyhow::bail!("This would leave this machine's key unable to open the repository");
+        }
+        let locked = cipher.locked_projects();
+        self.cipher = Some(cipher);
+        let report = self.seal()?;
+
+        // Drop the plaintext of projects this machine can no longer read
+        for project in locked.difference(&self.locked) {
+            let dir = self.root.join(&self.projects_area).join(project);
+            if dir.exists() {
+                fs::remove_dir_all(&dir)
+                    .with_context(|| format!("Failed to remove {}", dir.display()))?;
+            }
+        }
+        self.locked = locked;
+        let locked_files: Vec<String> = self
+            .manifest
+            .files
+            .keys()
+            .filter(|rel| self.is_locked(rel))
+            .cloned()
+            .collect();
+        for rel in locked_files {
+            self.manifest.files.remove(&rel);
+        }
+        self.save_manifest()?;
+        Ok(report)
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("Failed to create {}", self.root.display()))?;
+        let path = self.root.join(MANIFEST_FILE);
+        let json = serde_json::to_string(&self.manifest)
+            .context("Failed to serialize the plaintext view manifest")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Plaintext view directory of the repository at `repo_root`, one per
+/// repository so switching between repositories never mixes their files
+fn view_dir(repo_root: &Path) -> Result<PathBuf> {
+    let canonical = repo_root
+        .canonicalize()
+        .unwrap_or_else(|_| repo_root.to_path_buf());
+    let hash = sha256_hex(canonical.to_string_lossy().as_bytes());
+    Ok(ConfigManager::config_dir()?
+        .join("plaintext")
+        .join(&hash[..16]))
+}
+
+/// Files under the given areas of `root`, as (`/`-separated relative path, path)
+fn area_files(root: &Path, areas: &[String]) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    for area in areas {
+        for entry in WalkDir::new(root.join(area))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(rel) = entry.path().strip_prefix(root) else {
+                continue;
+            };
+            let rel = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((rel, entry.into_path()));
+        }
+    }
+    files
+}
+
+fn copy_if_changed(src: &Path, dest: &Path) -> Result<bool> {
+    let bytes = fs::read(src).with_context(|| format!("Failed to read {}", src.display()))?;
+    if fs::read(dest).is_ok_and(|existing| existing == bytes) {
+        return Ok(false);
+    }
+    write_file(dest, &bytes)?;
+    Ok(true)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{setup, KeySource};
+    use tempfile::TempDir;
+
+    fn cipher_for(repo: &Path, dir: &Path) -> Cipher {
+        let key = dir.join("key.txt");
+        setup(repo, &KeySource::KeyFile(key.clone())).unwrap();
+        let filter = FilterConfig {
+            encryption_key_file: Some(key),
+            trusted_recipients: RepoEncryption::load(repo).unwrap(),
+            ..Default::default()
+        };
+        unlock(repo, &filter).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_refresh_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let filter = FilterConfig::default();
+        let cipher = cipher_for(&repo, dir.path());
+
+        // A plaintext session from before encryption was enabled
+        write_file(&repo.join("projects/p/old.jsonl"), b"old\n").unwrap();
+
+        let view_a = dir.path().join("view-a");
+        let mut a = RepoView::encrypted(&repo, &view_a, cipher.clone(), &filter);
+        assert_eq!(a.refresh().unwrap().written, 1);
+        write_file(&view_a.join("projects/p/new.jsonl"), b"new\n").unwrap();
+        assert_eq!(
+            a.seal().unwrap(),
+            ViewReport {
+                written: 2,
+                removed: 1
+            }
+        );
+        assert!(!repo.join("projects/p/old.jsonl").exists());
+        assert!(repo.join("projects/p/new.jsonl.age").exists());
+
+        // Nothing changed: nothing is re-encrypted
+        let sealed = fs::read(repo.join("projects/p/new.jsonl.age")).unwrap();
+        assert_eq!(a.seal().unwrap(), ViewReport::default());
+        assert_eq!(
+            fs::read(repo.join("projects/p/new.jsonl.age")).unwrap(),
+            sealed
+        );
+
+        // A second machine sees the plaintext, and deletions propagate
+        let view_b = dir.path().join("view-b");
+        let mut b = RepoView::encrypted(&repo, &view_b, cipher, &filter);
+        assert_eq!(b.refresh().unwrap().written, 2);
+        assert_eq!(
+            fs::read(view_b.join("projects/p/new.jsonl")).unwrap(),
+            b"new\n"
+        );
+        fs::remove_file(repo.join("projects/p/old.jsonl.age")).unwrap();
+        assert_eq!(b.refresh().unwrap().removed, 1);
+        assert!(!view_b.join("projects/p/old.jsonl").exists());
+    }
+
+    #[test]
+    fn test_seal_removes_files_deleted_from_view() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let filter = FilterConfig::default();
+        let cipher = cipher_for(&repo, dir.path());
+
+        let view_a = dir.path().join("view-a");
+        let mut a = RepoView::encrypted(&repo, &view_a, cipher.clone(), &filter);
+        write_file(
+            &view_a.join("projects/p/keep.jsonl"),
+            b"keep
+",
+        )
+        .unwrap();
+        write_file(
+            &view_a.join("projects/p/drop.jsonl"),
+            b"drop
+",
+        )
+        .unwrap();
+        write_file(
+            &view_a.join("artifacts/skills/x.md"),
+            b"x
+",
+        )
+        .unwrap();
+        assert_eq!(a.seal().unwrap().written, 3);
+
+        fs::remove_file(view_a.join("projects/p/drop.jsonl")).unwrap();
+        fs::remove_file(view_a.join("artifacts/skills/x.md")).unwrap();
+        assert_eq!(
+            a.seal().unwrap(),
+            ViewReport {
+                written: 0,
+                removed: 2
+            }
+        );
+        assert!(repo.join("projects/p/keep.jsonl.age").exists());
+        assert!(!repo.join("projects/p/drop.jsonl.age").exists());
+        assert!(!repo.join("artifacts/skills/x.md.age").exists());
+
+        // Nothing comes back on the next refresh
+        assert_eq!(a.refresh().unwrap(), ViewReport::default());
+        assert!(!view_a.join("projects/p/drop.jsonl").exists());
+        let mut b = RepoView::encrypted(&repo, &dir.path().join("view-b"), cipher, &filter);
+        assert_eq!(b.refresh().unwrap().written, 1);
+    }
+
+    #[test]
+    fn test_restricted_project_is_locked_for_other_recipients() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let filter = FilterConfig::default();
+        let alice = cipher_for(&repo, dir.path());
+
+        // Bob can read the repository, but not project "secret"
+        let bob_key = dir.path().join("bob.txt");
+        setup(
+            &dir.path().join("scratch"),
+            &KeySource::KeyFile(bob_key.clone()),
+        )
+        .unwrap();
+        let bob_public = crate::encryption::read_key_file(&bob_key)
+            .unwrap()
+            .to_public()
+            .to_string();
+        let mut config = RepoEncryption::load(&repo).unwrap().unwrap();
+        config.add_recipient(&bob_public, None).unwrap();
+        config
+            .add_recipient(&alice.public_key(), Some("secret"))
+            .unwrap();
+        config.save(&repo).unwrap();
+        let unlock_with = |key: &Path| {
+            let filter = FilterConfig {
+                encryption_key_file: Some(key.to_path_buf()),
+                trusted_recipients: RepoEncryption::load(&repo).unwrap(),
+                ..Default::default()
+            };
+            unlock(&repo, &filter).unwrap().unwrap()
+        };
+        let alice = unlock_with(&dir.path().join("key.txt"));
+        let alice_public = alice.public_key();
+        let bob = unlock_with(&bob_key);
+
+        let view_a = dir.path().join("view-a");
+        let mut a = RepoView::encrypted(&repo, &view_a, alice, &filter);
+        write_file(&view_a.join("projects/open/o.jsonl"), b"open\n").unwrap();
+        write_file(&view_a.join("projects/secret/s.jsonl"), b"secret\n").unwrap();
+        assert_eq!(a.seal().unwrap().written, 2);
+
+        let view_b = dir.path().join("view-b");
+        let mut b = RepoView::encrypted(&repo, &view_b, bob.clone(), &filter);
+        assert_eq!(b.refresh().unwrap().written, 1);
+        assert!(b.locked_projects().contains("secret"));
+        assert!(!view_b.join("projects/secret/s.jsonl").exists());
+
+        // Bob's own copy of a locked project never overwrites Alice's
+        let sealed = fs::read(repo.join("projects/secret/s.jsonl.age")).unwrap();
+        write_file(&view_b.join("projects/secret/s.jsonl"), b"bob\n").unwrap();
+        assert_eq!(b.seal().unwrap(), ViewReport::default());
+        assert_eq!(
+            fs::read(repo.join("projects/secret/s.jsonl.age")).unwrap(),
+            sealed
+        );
+
+        // Adding Bob re-encrypts only the project's files, and he can read them
+        let mut config = RepoEncryption::load(&repo).unwrap().unwrap();
+        config.add_recipient(&bob_public, Some("secret")).unwrap();
+        assert_eq!(a.rekey(&config).unwrap().written, 1);
+        assert_eq!(
+            a.rekeyed_lists(),
+            vec![(
+                Some("secret".to_string()),
+                vec![alice_public, bob_public.clone()]
+            )]
+        );
+        config.save(&repo).unwrap();
+        fs::remove_file(view_b.join("projects/secret/s.jsonl")).unwrap();
+        let mut b = RepoView::encrypted(&repo, &view_b, unlock_with(&bob_key), &filter);
+        assert_eq!(b.refresh().unwrap().written, 1);
+        assert!(b.locked_projects().is_empty());
+        assert_eq!(
+            fs::read(view_b.join("projects/secret/s.jsonl")).unwrap(),
+            b"secret\n"
+        );
+
+        // Alice leaving the project re-encrypts it without her and drops her
+        // plaintext; a list is never emptied
+        config
+            .remove_recipient(&a.cipher.as_ref().unwrap().public_key(), Some("secret"))
+            .unwrap();
+        assert_eq!(a.rekey(&config).unwrap().written, 1);
+        assert!(a.locked_projects().contains("secret"));
+        assert!(!view_a.join("projects/secret").exists());
+        assert!(config
+            .remove_recipient(&bob_public, Some("secret"))
+            .is_err());
+    }
+}
@@ -21,6 +21,17 @@ pub struct FilterConfig {
     #[serde(default)]
     pub exclude_patterns: Vec<String>,
 
+    /// Never sync `~/.claude`-relative paths matching these patterns, in
+    /// addition to the built-in denylist (see
+    /// [`crate::artifacts::denylist::matches_pattern`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_patterns: Vec<String>,
+
+    /// Leave sessions out of push and pull by their content, as
+    /// `[[exclude_rules]]` tables (default: none)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_rules: Vec<crate::session_rules::ExcludeRule>,
+
     /// Maximum file size in bytes (default: 10MB)
     #[serde(default = "default_max_file_size")]
     pub max_file_size_bytes: u64,
@@ -60,6 +71,62 @@ pub struct FilterConfig {
     /// false so configs from older versions keep their exact behavior.
     #[serde(default)]
     pub sync_artifacts: crate::artifacts::registry::ArtifactToggles,
+
+    /// Skip malformed JSONL lines instead of dropping the whole session
+    /// (default: true). Skipped lines are reported by status, push and pull;
+    /// set to false to fail on any unparsable line.
+    #[serde(default = "default_lenient_parsing")]
+    pub lenient_parsing: bool,
+
+    /// Worker threads used to parse and hash transcripts during discovery
+    /// (default: 0, one per CPU). Set to 1 to discover sequentially.
+    #[serde(default)]
+    pub discovery_threads: usize,
+
+    /// What push does with secrets found in sessions: `redact` (default)
+    /// replaces them in the repository copy, `strict` refuses to push, `off`
+    /// pushes sessions as they are
+    #[serde(default)]
+    pub redaction: crate::redact::RedactionMode,
+
+    /// Extra secret patterns for redaction, as `[[redaction_rules]]` tables
+    /// (default: none, only the built-in detectors)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redaction_rules: Vec<crate::redact::RedactionRule>,
+
+    /// Also redact random-looking tokens no specific detector matches
+    /// (default: false)
+    #[serde(default)]
+    pub redact_high_entropy: bool,
+
+    /// Lite push: trim bulky tool results and drop file history snapshots
+    /// from repository copies, as a `[lite_push]` table (default: off)
+    #[serde(default)]
+    pub lite_push: crate::lite::LitePush,
+
+    /// Write this machine's home directory as `${HOME}` in the `cwd`, tool
+    /// inputs and tool outputs of pushed sessions, and back as the local home
+    /// directory on pull (default: false). Project directory names still
+    /// hold the home path; see `use_project_name_only`.
+    #[serde(default)]
+    pub anonymize_home_paths: bool,
+
+    /// Key file that unlocks an encrypted sync repository (default: none, so
+    /// the repository passphrase is used)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key_file: Option<PathBuf>,
+
+    /// Recipients of the encrypted sync repository this machine has
+    /// accepted, kept by the encryption commands (default: none, so the
+    /// first unlock accepts the repository's settings as found). Files are
+    /// not encrypted to a changed list until it is accepted here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_recipients: Option<crate::encryption::RepoEncryption>,
+
+    /// Token prices per model for the cost estimates of `stats`, as
+    /// `[[pricing]]` tables (default: none, so no cost is shown)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pricing: Vec<crate::stats::pricing::ModelPrice>,
 }
 
 fn default_lfs_patterns() -> Vec<String> {
@@ -78,12 +145,18 @@ fn default_sync_subdirectory() -> String {
     "projects".to_string()
 }
 
+fn default_lenient_parsing() -> bool {
+    true
+}
+
 impl Default for FilterConfig {
     fn default() -> Self {
         FilterConfig {
             exclude_older_than_days: None,
             include_patterns: Vec::new(),
             exclude_patterns: Vec::new(),
+            deny_patterns: Vec::new(),
+            exclude_rules: Vec::new(),
             max_file_size_bytes: default_max_file_size(),
             exclude_attachments: false,
             enable_lfs: false,
@@ -92,11 +165,30 @@ impl Default for FilterConfig {
             sync_subdirectory: default_sync_subdirectory(),
             use_project_name_only: false,
             sync_artifacts: Default::default(),
+            lenient_parsing: default_lenient_parsing(),
+            discovery_threads: 0,
+            redaction: Default::default(),
+            redaction_rules: Vec::new(),
+            redact_high_entropy: false,
+            lite_push: Default::default(),
+            anonymize_home_paths: false,
+            encryption_key_file: None,
+            trusted_recipients: None,
+            pricing: Vec::new(),
         }
     }
 }
 
 impl FilterConfig {
+    /// Parse mode for reading transcripts under this configuration
+    pub fn parse_mode(&self) -> crate::parser::ParseMode {
+        if self.lenient_parsing {
+            crate::parser::ParseMode::Lenient
+        } else {
+            crate::parser::ParseMode::Strict
+        }
+    }
+
     /// Load configuration from file
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
@@ -223,6 +315,34 @@ impl FilterConfig {
         true
     }
 
+    /// Whether a `~/.claude`-relative path must never sync: denied by the
+    /// built-in rules or by one of the deny patterns
+    pub fn is_denied(&self, claude_rel: &Path) -> bool {
+        crate::artifacts::denylist::is_denied_with(claude_rel, &self.deny_patterns)
+    }
+
+    /// Whether a session transcript, given by its path relative to the
+    /// projects directory, matches one of the deny patterns
+    pub fn is_session_denied(&self, projects_rel: &Path) -> bool {
+        let claude_rel = Path::new("projects").join(projects_rel);
+        self.deny_patterns
+            .iter()
+            .any(|p| crate::artifacts::denylist::matches_pattern(p, &claude_rel))
+    }
+
+    /// Add the deny patterns of the sync repository's policy file to this
+    /// configuration's own. Call on the configuration a sync runs with, never
+    /// on one that is saved back.
+    pub fn apply_repo_policy(&mut self, repo_root: &Path) -> Result<()> {
+        let policy = crate::artifacts::denylist::RepoPolicy::load(repo_root)?;
+        for pattern in policy.deny_patterns {
+            if !self.deny_patterns.contains(&pattern) {
+                self.deny_patterns.push(pattern);
+            }
+        }
+        Ok(())
+    }
+
     /// Get the configured SCM backend.
     pub fn backend(&self) -> Result<Backend> {
         match self.scm_backend.to_lowercase().as_str() {
@@ -253,12 +373,36 @@ impl FilterConfig {
                 crate::artifacts::registry::ARTIFACTS_SUBDIR
             );
         }
+        if self.sync_subdirectory == crate::encryption::REPO_CONFIG_DIR {
+            bail!(
+                "sync_subdirectory cannot be '{}': that directory is reserved \
+                 for repository settings",
+                crate::encryption::REPO_CONFIG_DIR
+            );
+        }
+        if self.sync_subdirectory == crate::sync::machines::MACHINES_SUBDIR {
+            bail!(
+                "sync_subdirectory cannot be '{}': that directory is reserved \
+                 for per-machine push records",
+                crate::sync::machines::MACHINES_SUBDIR
+            );
+        }
+        crate::stats::pricing::PriceTable::validate(&self.pricing)?;
+        crate::redact::Redactor::validate(&self.redaction_rules)?;
         if self.max_file_size_bytes == 0 {
             bail!(
                 "max_file_size_bytes cannot be 0: every file would be filtered out \
                  and nothing would ever sync"
             );
         }
+        if self.lite_push.enabled && self.lite_push.max_tool_result_bytes == 0 {
+            bail!(
+                "lite_push.max_tool_result_bytes cannot be 0: set a size above which \
+                 tool results are trimmed"
+            );
+        }
+        crate::artifacts::denylist::validate_patterns(&self.deny_patterns)?;
+        crate::session_rules::SessionRules::validate(&self.exclude_rules)?;
         Ok(())
     }
 }
@@ -499,6 +643,43 @@ pub fn show_config() -> Result<()> {
             config.exclude_patterns.join(", ")
         }
     );
+    println!(
+        "  {}: {}",
+        "Deny patterns".cyan(),
+        if config.deny_patterns.is_empty() {
+            "None (built-in denylist only)".to_string()
+        } else {
+            config.deny_patterns.join(", ")
+        }
+    );
+    println!(
+        "  {}: {}",
+        "Exclude rules".cyan(),
+        if config.exclude_rules.is_empty() {
+            "None".to_string()
+        } else {
+            config
+                .exclude_rules
+                .iter()
+                .map(|rule| rule.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    );
+    if let Ok(state) = crate::sync::SyncState::load() {
+        let policy = crate::artifacts::denylist::RepoPolicy::load(&state.sync_repo_path);
+        if let Ok(policy) = &policy {
+            if !policy.deny_patterns.is_empty() {
+                println!(
+                    "  {}: {}",
+                    "Repository deny patterns".cyan(),
+                    policy.deny_patterns.join(", ")
+                );
+            }
+        } else if let Err(e) = policy {
+            println!("  {}: {:#}", "Repository deny patterns".cyan(), e);
+        }
+    }
     println!(
         "  {}: {} bytes ({:.2} MB)",
         "Max file size".cyan(),
@@ -538,6 +719,104 @@ pub fn show_config() -> Result<()> {
             "No (full path mode)".yellow()
         }
     );
+    println!(
+        "  {}: {}",
+        "Lenient parsing".cyan(),
+        if config.lenient_parsing {
+            "Yes (skip malformed lines)".green()
+        } else {
+            "No (fail on malformed lines)".yellow()
+        }
+    );
+    println!(
+        "  {}: {}",
+        "Discovery threads".cyan(),
+        if config.discovery_threads == 0 {
+            "Auto (one per CPU)".to_string()
+        } else {
+            config.discovery_threads.to_string()
+        }
+    );
+
+    println!(
+        "  {}: {}{}{}",
+        "Secret redaction".cyan(),
+        match config.redaction {
+            crate::redact::RedactionMode::Off => "Off".yellow(),
+            crate::redact::RedactionMode::Redact => "Redact".green(),
+            crate::redact::RedactionMode::Strict => "Strict (block push)".green(),
+        },
+        if config.redaction_rules.is_empty() {
+            String::new()
+        } else {
+            format!(", {} custom rule(s)", config.redaction_rules.len())
+        },
+        if config.redact_high_entropy {
+            ", high-entropy strings"
+        } else {
+            ""
+        }
+    );
+
+    println!(
+        "  {}: {}",
+        "Lite push".cyan(),
+        if config.lite_push.enabled {
+            format!(
+                "On (tool results over {} bytes {}{})",
+                config.lite_push.max_tool_result_bytes,
+                match config.lite_push.tool_results {
+                    crate::lite::ToolResultTrim::Truncate => "truncated",
+                    crate::lite::ToolResultTrim::Elide => "elided",
+                },
+                if config.lite_push.drop_file_history_snapshots {
+                    ", file history snapshots dropped"
+                } else {
+                    ""
+                }
+            )
+            .green()
+        } else {
+            "Off".normal()
+        }
+    );
+
+    println!(
+        "  {}: {}",
+        "Home path anonymization".cyan(),
+        if config.anonymize_home_paths {
+            "On (home directory synced as ${HOME})".green()
+        } else {
+            "Off".normal()
+        }
+    );
+
+    println!(
+        "  {}: {}",
+        "Encryption key".cyan(),
+        match &config.encryption_key_file {
+            Some(path) => path.display().to_string().green(),
+            None => "Passphrase (if the repository is encrypted)".normal(),
+        }
+    );
+
+    if let Some(trusted) = &config.trusted_recipients {
+        println!(
+            "  {}: {} key(s)",
+            "Trusted recipients".cyan(),
+            trusted.recipients.len()
+        );
+    }
+
+    println!(
+        "  {}: {}",
+        "Pricing".cyan(),
+        if config.pricing.is_empty() {
+            "None (no cost estimates)".yellow()
+        } else {
+            format!("{} model price(s)", config.pricing.len()).green()
+        }
+    );
 
     println!("  {}:", "Artifact sync".cyan());
     for desc in crate::artifacts::registry::toggleable() {
@@ -0,0 +1,176 @@
+//! Audit command handler
+//!
+//! Checks the sync repository and its history for denied paths, unsafe paths
+//! and secrets, and writes a purge plan for rewriting history
+//! (see [`crate::audit`]).
+
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::audit::{write_purge_plan, Auditor, PathAudit};
+use crate::filter::FilterConfig;
+use crate::redact::{Findings, Redactor};
+use crate::scm::{self, Backend};
+use crate::sync::SyncState;
+
+/// Handle `audit`
+///
+/// # Arguments
+/// * `history` - Check every past commit, not only the working tree
+/// * `purge_plan` - File to write the paths to remove from history to
+pub fn handle_audit(history: bool, purge_plan: Option<&Path>) -> Result<()> {
+    let state = SyncState::load()?;
+    let mut filter = FilterConfig::load()?;
+    let repo_root = &state.sync_repo_path;
+    filter.apply_repo_policy(repo_root)?;
+    let redactor =
+        Redactor::new(&filter.redaction_rules)?.with_high_entropy(filter.redact_high_entropy);
+    let repo = scm::open(repo_root)?;
+    let backend = scm::detect_backend(repo_root).unwrap_or(Backend::Git);
+
+    println!("{}", "=== Sync Repository Audit ===".bold().cyan());
+    println!("  Repository: {}", repo_root.display());
+    let report = Auditor::new(&redactor)
+        .with_deny_patterns(&filter)
+        .audit(repo_root, history.then_some(repo.as_ref()))?;
+    if report.history_checked {
+        println!(
+            "  Checked {} file(s) in the working tree and {} file change(s) in history",
+            report.files, report.changes
+        );
+    } else {
+        println!(
+            "  Checked {} file(s) in the working tree (history skipped)",
+            report.files
+        );
+    }
+
+    for (path, audit) in &report.paths {
+        println!();
+        println!("{} {}", "✗".red(), path.bold());
+        describe(audit);
+    }
+    if report.encrypted > 0 {
+        println!();
+        println!(
+            "{} {} encrypted file(s): only their names were checked",
+            "!".yellow(),
+            report.encrypted
+        );
+    }
+
+    println!();
+    if report.is_clean() {
+        println!("{}", "✓ No problems found".green());
+        return Ok(());
+    }
+
+    if let Some(plan) = purge_plan {
+        let count = write_purge_plan(&report, backend, plan)?;
+        if count == 0 {
+            println!(
+                "{}",
+                "Nothing to purge from history: remove the flagged files and push.".dimmed()
+            );
+        } else {
+            print_purge_steps(count, plan, repo_root, backend);
+        }
+    } else if !report.purge_paths().is_empty() {
+        println!(
+            "{}",
+            "Write a purge plan for rewriting history with: claude-code-sync audit --purge-plan <FILE>"
+                .dimmed()
+        );
+    }
+
+    anyhow::bail!(
+        "Audit flagged {} path(s) in the sync repository",
+        report.paths.len()
+    )
+}
+
+/// Print what is wrong with one path and where it is
+fn describe(audit: &PathAudit) {
+    if audit.denied {
+        println!("    Denied path: never synced");
+    }
+    if audit.unsafe_path {
+        println!("    Unsafe path: would escape the directory it is restored into");
+    }
+    if !audit.secrets.is_empty() {
+        println!(
+            "    Secrets in the working tree: {}",
+            describe_findings(&audit.secrets)
+        );
+    }
+    if !audit.history_secrets.is_empty() {
+        println!(
+            "    Secrets added by commits: {}",
+            describe_findings(&audit.history_secrets)
+        );
+    }
+
+    let commits = match audit.revisions.first() {
+        Some(first) => format!(
+            "{} commit(s), first {}",
+            audit.revisions.len(),
+            &first[..first.len().min(10)]
+        ),
+        None => String::new(),
+    };
+    let location = match (audit.in_tree, commits.is_empty()) {
+        (true, true) => "In the working tree, not committed".to_string(),
+        (true, false) => format!("In the working tree and history ({commits})"),
+        (false, _) => format!("Removed from the working tree, still in history ({commits})"),
+    };
+    println!("    {}", location.dimmed());
+}
+
+fn describe_findings(findings: &Findings) -> String {
+    findings
+        .iter()
+        .map(|(rule, count)| format!("{count} {rule}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// How to rewrite history with the purge plan
+fn print_purge_steps(count: usize, plan: &Path, repo_root: &Path, backend: Backend) {
+    let plan = plan.canonicalize().unwrap_or_else(|_| plan.to_path_buf());
+    println!(
+        "{} Wrote {} path(s) to remove from history to {}",
+        "✓".green(),
+        count,
+        plan.display()
+    );
+    println!();
+    println!("{}", "To rewrite history:".bold());
+    println!("  1. Back up the repository and make sure no machine syncs meanwhile");
+    match backend {
+        Backend::Git => {
+            println!(
+                "  2. git -C {} filter-repo --force --invert-paths --paths-from-file {}",
+                repo_root.display(),
+                plan.display()
+            );
+            println!(
+                "     (git filter-repo is installed separately; it removes the remote, so add it back)"
+            );
+            println!("  3. Force-push every branch: git push --force --all <remote>");
+        }
+        Backend::Mercurial => {
+            println!(
+                "  2. hg convert --filemap {} {} <new repository>",
+                plan.display(),
+                repo_root.display()
+            );
+            println!("  3. Replace the remote repository with the converted one");
+        }
+    }
+    println!("  4. Clone the sync repository again on every other machine");
+    println!(
+        "{}",
+        "Sessions still on a machine are pushed again, redacted, on its next push.".dimmed()
+    );
+}
@@ -5,6 +5,7 @@ use anyhow::{Context, Result};
 use colored::Colorize;
 use std::path::PathBuf;
 
+use crate::encryption::RepoEncryption;
 use crate::filter::FilterConfig;
 use crate::onboarding::InitConfig;
 use crate::sync::{MultiRepoState, SyncState};
@@ -64,6 +65,15 @@ pub fn handle_config_export() -> Result<()> {
         );
     }
 
+    // The key file itself stays put; another machine needs a copy of it
+    let encryption = if filter.encryption_key_file.is_some() {
+        Some("key-file".to_string())
+    } else if RepoEncryption::is_enabled(std::path::Path::new(&repo_path)) {
+        Some("passphrase".to_string())
+    } else {
+        None
+    };
+
     let init_config = InitConfig {
         repo_path,
         remote_url: remote_url.clone(),
@@ -75,6 +85,11 @@ pub fn handle_config_export() -> Result<()> {
         sync_subdirectory: filter.sync_subdirectory,
         use_project_name_only: filter.use_project_name_only,
         sync_artifacts: filter.sync_artifacts.clone(),
+        encryption,
+        encryption_key_file: filter
+            .encryption_key_file
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string()),
     };
 
     let content =
@@ -0,0 +1,175 @@
+//! Dataset command handler
+//!
+//! Exports selected sessions, found locally or in the sync repository, as
+//! ShareGPT or OpenAI chat JSONL: one record per session, on stdout or into
+//! a file.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::filter::FilterConfig;
+use crate::parser::SessionSummary;
+use crate::search::session_project;
+use crate::search::sessions::KnownSessions;
+use crate::sync::parallel::{parallel_map, resolve_threads};
+use crate::transcript::dataset::{self, DatasetFormat, DatasetOptions};
+
+/// Which sessions go into a dataset
+#[derive(Debug, Clone, Default)]
+pub struct DatasetSelection {
+    /// Session ids or unique id prefixes; every session when empty
+    pub session_ids: Vec<String>,
+    /// Only projects whose name contains this (case-insensitive)
+    pub project: Option<String>,
+    /// Only sessions last active on or after this day
+    pub since: Option<NaiveDate>,
+    /// Only sessions last active on or before this day
+    pub until: Option<NaiveDate>,
+    /// Include subagent transcripts when selecting by filter; sessions named
+    /// by id are always included
+    pub subagents: bool,
+}
+
+/// Handle dataset command
+///
+/// # Arguments
+/// * `selection` - Sessions to export
+/// * `format` - ShareGPT or OpenAI chat records
+/// * `options` - What to leave out of each record
+/// * `output` - File to write; stdout when not given
+pub fn handle_dataset(
+    selection: &DatasetSelection,
+    format: DatasetFormat,
+    options: DatasetOptions,
+    output: Option<&Path>,
+) -> Result<()> {
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let selected = select(&known, selection)?;
+
+    let records = parallel_map(
+        &selected,
+        resolve_threads(filter.discovery_threads),
+        |summary| -> Result<Option<String>> {
+            let session = summary.load()?;
+            Ok(dataset::record(&session, format, options).map(|record| record.to_string()))
+        },
+    );
+
+    let mut lines = Vec::new();
+    let mut empty = 0;
+    for (summary, record) in selected.iter().zip(records) {
+        match record {
+            Ok(Some(line)) => lines.push(line),
+            Ok(None) => empty += 1,
+            Err(e) => eprintln!(
+                "{} Skipping session {}: {:#}",
+                "⚠".yellow(),
+                summary.session_id,
+                e
+            ),
+        }
+    }
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    match output {
+        Some(path) => {
+            std::fs::write(path, text)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!(
+                "{} Wrote {} record(s) to {}",
+                "✓".green(),
+                lines.len(),
+                path.display()
+            );
+        }
+        None => {
+            std::io::stdout()
+                .write_all(text.as_bytes())
+                .context("Failed to write to stdout")?;
+        }
+    }
+    // On stderr, so stdout stays valid JSONL
+    if empty > 0 {
+        eprintln!(
+            "{} {} of {} session(s) had no assistant messages to export",
+            "ℹ".cyan(),
+            empty,
+            selected.len()
+        );
+    }
+    Ok(())
+}
+
+/// The sessions of `selection`, each once (preferring the local copy),
+/// oldest first
+fn select<'a>(
+    known: &'a KnownSessions,
+    selection: &DatasetSelection,
+) -> Result<Vec<&'a SessionSummary>> {
+    let candidates: Vec<(&SessionSummary, String)> = if selection.session_ids.is_empty() {
+        let mut seen = HashSet::new();
+        known
+            .sides()
+            .into_iter()
+            .flat_map(|(_, base_path, sessions)| {
+                sessions
+                    .iter()
+                    .map(move |summary| (summary, session_project(summary, base_path)))
+            })
+            .filter(|(summary, _)| selection.subagents || summary.parent_session_id.is_none())
+            .filter(|(summary, _)| seen.insert(summary.session_id.clone()))
+            .collect()
+    } else {
+        let mut found = Vec::new();
+        for id in &selection.session_ids {
+            let session = known.find(id)?;
+            if !found.iter().any(|(s, _): &(&SessionSummary, String)| {
+                s.session_id == session.summary.session_id
+            }) {
+                found.push((session.summary, session.project));
+            }
+        }
+        found
+    };
+
+    let mut selected: Vec<&SessionSummary> = candidates
+        .into_iter()
+        .filter(|(_, project)| {
+            selection
+                .project
+                .as_ref()
+                .is_none_or(|wanted| project.to_lowercase().contains(&wanted.to_lowercase()))
+        })
+        .filter(|(summary, _)| matches_day(selection, summary))
+        .map(|(summary, _)| summary)
+        .collect();
+    selected.sort_by(|a, b| {
+        (&a.latest_timestamp, &a.session_id).cmp(&(&b.latest_timestamp, &b.session_id))
+    });
+    Ok(selected)
+}
+
+/// Whether a session was last active in the selected range; sessions
+/// without timestamps only match when no range is set
+fn matches_day(selection: &DatasetSelection, summary: &SessionSummary) -> bool {
+    if selection.since.is_none() && selection.until.is_none() {
+        return true;
+    }
+    let Some(day) = summary
+        .latest_timestamp
+        .as_deref()
+        .and_then(|ts| ts.get(..10))
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+    else {
+        return false;
+    };
+    selection.since.is_none_or(|since| day >= since)
+        && selection.until.is_none_or(|until| day <= until)
+}
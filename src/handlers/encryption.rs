@@ -0,0 +1,270 @@
+//! Encryption command handlers
+//!
+//! Sets up end-to-end encryption of the sync repository, or joins a machine
+//! to a repository that is already encrypted, and reports how it is set up
+//! (see [`crate::encryption`]).
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigManager;
+use crate::encryption::view::RepoView;
+use crate::encryption::{self, KeySource, RepoEncryption, SetupOutcome};
+use crate::filter::FilterConfig;
+use crate::sync::SyncState;
+
+/// Handle `encryption enable`
+///
+/// # Arguments
+/// * `key_file` - Key file to use (created if missing); the default key
+///   path when neither this nor `passphrase` is given
+/// * `passphrase` - Use a passphrase instead of a key file
+pub fn handle_encryption_enable(key_file: Option<&Path>, passphrase: bool) -> Result<()> {
+    let state = SyncState::load()?;
+    let repo_root = &state.sync_repo_path;
+    let source = if passphrase {
+        // A new passphrase is typed twice; an existing one only once
+        KeySource::Passphrase(encryption::passphrase(!RepoEncryption::is_enabled(
+            repo_root,
+        ))?)
+    } else {
+        KeySource::KeyFile(match key_file {
+            Some(path) => path.to_path_buf(),
+            None => ConfigManager::default_key_file_path()?,
+        })
+    };
+
+    let mut filter = FilterConfig::load()?;
+    let outcome = apply_key_setup(repo_root, &source, &mut filter)?;
+    filter
+        .save()
+        .context("Failed to save filter configuration")?;
+    report_setup(&outcome, &source);
+    Ok(())
+}
+
+/// Handle `encryption status`
+pub fn handle_encryption_status() -> Result<()> {
+    let state = SyncState::load()?;
+    let filter = FilterConfig::load()?;
+    let Some(config) = RepoEncryption::load(&state.sync_repo_path)? else {
+        println!(
+            "{} The sync repository is not encrypted. Enable with: claude-code-sync encryption enable",
+            "ℹ".cyan()
+        );
+        return Ok(());
+    };
+
+    println!("{}", "Encryption:".bold());
+    println!("  {}: {}", "Repository".cyan(), "encrypted".green());
+    println!("  {}:", "Recipients".cyan());
+    for recipient in &config.recipients {
+        println!("    {recipient}");
+    }
+    // The passphrase is not asked for just to show which key it opens
+    let own_key = match &filter.encryption_key_file {
+        Some(path) => {
+            let key =
+                encryption::read_key_file(path).map(|identity| identity.to_public().to_string());
+            match key {
+                Ok(key) if config.recipients.contains(&key) => {
+                    println!("  {}: key file {}", "This machine".cyan(), path.display());
+                    Some(key)
+                }
+                Ok(key) => {
+                    println!(
+                        "  {}: key file {} ({} is not a recipient)",
+                        "This machine".cyan(),
+                        path.display(),
+                        key.yellow()
+                    );
+                    Some(key)
+                }
+                Err(e) => {
+                    println!("  {}: {:#}", "This machine".cyan(), e);
+                    None
+                }
+            }
+        }
+        None => {
+            println!(
+                "  {}: passphrase (from {} or a prompt)",
+                "This machine".cyan(),
+                encryption::PASSPHRASE_ENV
+            );
+            None
+        }
+    };
+
+    // Without a trusted list yet, the next unlock accepts the settings
+    let changes = match &filter.trusted_recipients {
+        Some(trusted) => config.changes_from(trusted),
+        None => Vec::new(),
+    };
+    if !changes.is_empty() {
+        println!(
+            "  {}: not yet accepted on this machine (see: claude-code-sync encryption trust)",
+            "Recipient changes".cyan()
+        );
+        encryption::print_changes(&changes);
+    }
+
+    if !config.projects.is_empty() {
+        println!("  {}:", "Restricted projects".cyan());
+        for (project, recipients) in &config.projects {
+            let access = match &own_key {
+                Some(key) if recipients.contains(key) => " (readable here)".green(),
+                Some(_) => " (locked here)".yellow(),
+                None => "".normal(),
+            };
+            println!("    {}{}", project, access);
+            for recipient in recipients {
+                println!("      {recipient}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle `encryption add-recipient` and `encryption remove-recipient`
+///
+/// # Arguments
+/// * `key` - Public key (`age1...`) to add or remove
+/// * `project` - Project directory whose own recipient list to change,
+///   instead of the repository's
+/// * `add` - Add the key rather than remove it
+pub fn handle_encryption_recipient(key: &str, project: Option<&str>, add: bool) -> Result<()> {
+    let state = SyncState::load()?;
+    let repo_root = &state.sync_repo_path;
+    let Some(mut config) = RepoEncryption::load(repo_root)? else {
+        anyhow::bail!(
+            "The sync repository is not encrypted. Enable with: claude-code-sync encryption enable"
+        );
+    };
+    let newly_restricted = project.is_some_and(|name| !config.projects.contains_key(name));
+
+    let changed = if add {
+        config.add_recipient(key, project)?
+    } else {
+        config.remove_recipient(key, project)?
+    };
+    let target = match project {
+        Some(name) => format!("project {name}"),
+        None => "the repository".to_string(),
+    };
+    if !changed {
+        let state = if add { "already" } else { "not" };
+        println!(
+            "{} {} is {} a recipient of {}",
+            "ℹ".cyan(),
+            key,
+            state,
+            target
+        );
+        return Ok(());
+    }
+    // Files and settings change together, so every machine can trust the
+    // settings to say who the files are encrypted to
+    let filter = FilterConfig::load()?;
+    let mut view = RepoView::open(repo_root, &filter)?;
+    let rekeyed = view.rekey(&config)?;
+    config.save(repo_root)?;
+    // A change made here needs no accepting
+    encryption::trust(&config)?;
+
+    let verb = if add { "Added" } else { "Removed" };
+    println!("{} {} {} for {}", "✓".green(), verb, key.cyan(), target);
+    if newly_restricted {
+        println!(
+            "  {} Only keys listed for {} can read it now; add every other key that should.",
+            "Note:".yellow(),
+            target
+        );
+    }
+    println!(
+        "  Re-encrypted {} file(s); the next push commits them. Files committed before \
+         then remain readable to the previous recipients in the repository's history.",
+        rekeyed.written
+    );
+    Ok(())
+}
+
+/// Handle `encryption trust`: accept the recipients the repository's
+/// settings list now, after a change made on another machine
+pub fn handle_encryption_trust() -> Result<()> {
+    let state = SyncState::load()?;
+    let Some(config) = RepoEncryption::load(&state.sync_repo_path)? else {
+        anyhow::bail!(
+            "The sync repository is not encrypted. Enable with: claude-code-sync encryption enable"
+        );
+    };
+    let filter = FilterConfig::load()?;
+    let changes = config.changes_from(&filter.trusted_recipients.unwrap_or_default());
+    if changes.is_empty() {
+        println!(
+            "{} This machine already trusts the repository's recipients",
+            "ℹ".cyan()
+        );
+        return Ok(());
+    }
+    encryption::print_changes(&changes);
+    encryption::trust(&config)?;
+    println!(
+        "{} Trusted these recipients: push encrypts to them from now on",
+        "✓".green()
+    );
+    Ok(())
+}
+
+/// Set up encryption of `repo_root` with `source`, and record in `filter`
+/// how this machine unlocks it and the recipients it trusts
+pub(crate) fn apply_key_setup(
+    repo_root: &Path,
+    source: &KeySource,
+    filter: &mut FilterConfig,
+) -> Result<SetupOutcome> {
+    let outcome = encryption::setup(repo_root, source)?;
+    filter.trusted_recipients = RepoEncryption::load(repo_root)?;
+    filter.encryption_key_file = match source {
+        KeySource::KeyFile(path) => Some(absolute(path)),
+        KeySource::Passphrase(_) => None,
+    };
+    Ok(outcome)
+}
+
+/// Tell the user what [`apply_key_setup`] did
+pub(crate) fn report_setup(outcome: &SetupOutcome, source: &KeySource) {
+    match outcome {
+        SetupOutcome::Created { public_key } => {
+            println!(
+                "{} Sync repository encrypted to {}",
+                "✓".green(),
+                public_key.cyan()
+            );
+            println!(
+                "  The next push encrypts every session and artifact. Files committed \
+                 before now remain readable in the repository's history."
+            );
+            if let KeySource::KeyFile(path) = source {
+                println!(
+                    "  {} Keep {} safe and copy it to your other machines: without it \
+                     the repository cannot be read.",
+                    "Note:".yellow(),
+                    path.display()
+                );
+            }
+        }
+        SetupOutcome::Joined { public_key } => {
+            println!(
+                "{} This machine can open the encrypted sync repository ({})",
+                "✓".green(),
+                public_key.cyan()
+            );
+        }
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
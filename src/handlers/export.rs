@@ -0,0 +1,60 @@
+//! Export command handler
+//!
+//! Renders one session, found locally or in the sync repository, as a
+//! Markdown or HTML transcript on stdout or into a file.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+
+use crate::filter::FilterConfig;
+use crate::search::sessions::KnownSessions;
+use crate::transcript::{self, Format, TranscriptOptions};
+
+/// Handle export command
+///
+/// # Arguments
+/// * `session_id` - Session id or unique id prefix
+/// * `format` - Output format; inferred from `output`'s extension when not
+///   given, Markdown otherwise
+/// * `output` - File to write; stdout when not given
+/// * `options` - Which branches to include
+pub fn handle_export(
+    session_id: &str,
+    format: Option<Format>,
+    output: Option<&Path>,
+    options: TranscriptOptions,
+) -> Result<()> {
+    let format = format
+        .or_else(|| {
+            output
+                .and_then(|path| path.extension())
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| ext.parse().ok())
+        })
+        .unwrap_or(Format::Markdown);
+
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let found = known.find(session_id)?;
+    let session = found.summary.load()?;
+
+    let rendered = transcript::render(&session, &found.project, format, options);
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!(
+                "{} Exported session {} ({}) to {}",
+                "✓".green(),
+                found.summary.session_id.cyan(),
+                found.source,
+                path.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
+
+    Ok(())
+}
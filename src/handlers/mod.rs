@@ -3,21 +3,45 @@
 //! This module contains all command handler functions extracted from main.rs,
 //! organized by functionality area.
 
+pub mod audit;
 pub mod cleanup;
 pub mod config;
+pub mod dataset;
+pub mod encryption;
+pub mod export;
 pub mod history;
 pub mod onboarding;
+pub mod quarantine;
+pub mod search;
+pub mod show;
+pub mod site;
+pub mod stats;
 pub mod undo;
 
 // Re-export all public handler functions for convenient use
+pub use audit::handle_audit;
 pub use cleanup::handle_cleanup_snapshots;
 pub use config::{
     handle_config_export, handle_config_interactive, handle_config_wizard, handle_repo_selector,
 };
+pub use dataset::{handle_dataset, DatasetSelection};
+pub use encryption::{
+    handle_encryption_enable, handle_encryption_recipient, handle_encryption_status,
+    handle_encryption_trust,
+};
+pub use export::handle_export;
 pub use history::{
     handle_history_clear, handle_history_last, handle_history_list, handle_history_review,
 };
 pub use onboarding::{
     is_initialized, run_init_from_config, run_onboarding_flow, try_init_from_config,
 };
+pub use quarantine::{
+    handle_quarantine_discard, handle_quarantine_list, handle_quarantine_retry,
+    handle_quarantine_show,
+};
+pub use search::handle_search;
+pub use show::handle_show;
+pub use site::handle_site_build;
+pub use stats::{handle_stats, handle_tool_stats};
 pub use undo::{handle_undo_pull, handle_undo_push};
@@ -7,6 +7,7 @@ use anyhow::{Context, Result};
 use colored::Colorize;
 use std::path::Path;
 
+use super::encryption;
 use crate::config;
 use crate::filter;
 use crate::onboarding::{self, InitConfig};
@@ -47,12 +48,17 @@ pub fn run_onboarding_flow() -> Result<()> {
     .context("Failed to initialize sync state")?;
 
     // Save filter configuration
-    let filter_config = filter::FilterConfig {
+    let mut filter_config = filter::FilterConfig {
         exclude_attachments: onboarding_config.exclude_attachments,
         exclude_older_than_days: onboarding_config.exclude_older_than_days,
         sync_artifacts: onboarding_config.sync_artifacts.clone(),
         ..Default::default()
     };
+    if let Some(ref source) = onboarding_config.encryption {
+        let outcome =
+            encryption::apply_key_setup(&onboarding_config.repo_path, source, &mut filter_config)?;
+        encryption::report_setup(&outcome, source);
+    }
     filter_config
         .save()
         .context("Failed to save filter configuration")?;
@@ -104,7 +110,7 @@ pub fn run_init_from_config<P: AsRef<Path>>(config_path: Option<P>) -> Result<()
     .context("Failed to initialize sync state")?;
 
     // Save filter configuration with all settings from init config
-    let filter_config = filter::FilterConfig {
+    let mut filter_config = filter::FilterConfig {
         exclude_attachments: init_config.exclude_attachments,
         exclude_older_than_days: init_config.exclude_older_than_days,
         enable_lfs: init_config.enable_lfs,
@@ -113,6 +119,9 @@ pub fn run_init_from_config<P: AsRef<Path>>(config_path: Option<P>) -> Result<()
         sync_artifacts: init_config.sync_artifacts.clone(),
         ..Default::default()
     };
+    if let Some(ref source) = onboarding_config.encryption {
+        encryption::apply_key_setup(&onboarding_config.repo_path, source, &mut filter_config)?;
+    }
     filter_config
         .save()
         .context("Failed to save filter configuration")?;
@@ -130,6 +139,9 @@ pub fn run_init_from_config<P: AsRef<Path>>(config_path: Option<P>) -> Result<()
     if init_config.enable_lfs {
         println!("  {} enabled", "LFS:".cyan());
     }
+    if let Some(ref mode) = init_config.encryption {
+        println!("  {} {}", "Encryption:".cyan(), mode);
+    }
     println!();
 
     Ok(())
@@ -0,0 +1,180 @@
+//! Quarantine command handlers
+//!
+//! Handles listing, inspecting, retrying and discarding transcripts that
+//! were quarantined because they could not be parsed.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::filter::FilterConfig;
+use crate::quarantine::{Quarantine, RetryOutcome};
+
+/// Number of lines of the quarantined copy shown by `quarantine show`
+const PREVIEW_LINES: usize = 5;
+
+/// Longest preview line printed before truncation
+const PREVIEW_LINE_CHARS: usize = 160;
+
+/// Handle quarantine list command
+pub fn handle_quarantine_list() -> Result<()> {
+    let quarantine = Quarantine::open()?;
+    let entries = quarantine.list()?;
+
+    if entries.is_empty() {
+        println!("{}", "No quarantined transcripts.".green());
+        return Ok(());
+    }
+
+    println!("{}", "Quarantined Transcripts".cyan().bold());
+    println!("{}", "=".repeat(80).cyan());
+
+    for entry in &entries {
+        println!("\n{}", entry.id.bold());
+        println!("   {} {}", "Source:".dimmed(), entry.source_path.display());
+        println!(
+            "   {} {}",
+            "Since:".dimmed(),
+            entry.quarantined_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        println!("   {} {}", "Reason:".dimmed(), entry.reason);
+    }
+
+    println!(
+        "\n{} {} transcript(s) in {}",
+        "Total:".bold(),
+        entries.len(),
+        quarantine.dir().display()
+    );
+
+    Ok(())
+}
+
+/// Handle quarantine show command
+pub fn handle_quarantine_show(id: &str) -> Result<()> {
+    let quarantine = Quarantine::open()?;
+    let entry = quarantine.get(id)?;
+    let copy_path = quarantine.copy_path(id);
+
+    println!("{}", entry.id.cyan().bold());
+    println!("  {} {}", "Source:".dimmed(), entry.source_path.display());
+    println!(
+        "  {} {}",
+        "Source exists:".dimmed(),
+        if entry.source_path.exists() {
+            "Yes".green()
+        } else {
+            "No".yellow()
+        }
+    );
+    println!("  {} {}", "Quarantined copy:".dimmed(), copy_path.display());
+    println!(
+        "  {} {}",
+        "Since:".dimmed(),
+        entry.quarantined_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    println!("  {} {} bytes", "Size:".dimmed(), entry.source_size);
+    println!("  {} {}", "Reason:".dimmed(), entry.reason);
+
+    let file = File::open(&copy_path)
+        .with_context(|| format!("Failed to open {}", copy_path.display()))?;
+    println!("\n{}", format!("First {PREVIEW_LINES} lines:").bold());
+    for (idx, line) in BufReader::new(file).lines().take(PREVIEW_LINES).enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => format!("<unreadable: {e}>"),
+        };
+        let preview: String = line.chars().take(PREVIEW_LINE_CHARS).collect();
+        let ellipsis = if line.chars().count() > PREVIEW_LINE_CHARS {
+            "…"
+        } else {
+            ""
+        };
+        println!(
+            "  {} {}{}",
+            format!("{:>4}", idx + 1).dimmed(),
+            preview,
+            ellipsis
+        );
+    }
+
+    Ok(())
+}
+
+/// Handle quarantine retry command
+///
+/// Re-parses each entry's source with the configured parse mode and
+/// releases the ones that parse now.
+pub fn handle_quarantine_retry(id: Option<&str>) -> Result<()> {
+    let quarantine = Quarantine::open()?;
+    let mode = FilterConfig::load()?.parse_mode();
+
+    let ids: Vec<String> = match id {
+        Some(id) => vec![id.to_string()],
+        None => quarantine.list()?.into_iter().map(|e| e.id).collect(),
+    };
+
+    if ids.is_empty() {
+        println!("{}", "No quarantined transcripts.".green());
+        return Ok(());
+    }
+
+    let mut released = 0;
+    for id in &ids {
+        match quarantine.retry(id, mode)? {
+            RetryOutcome::Released => {
+                released += 1;
+                println!(
+                    "  {} {} parses now; it will be included in the next push",
+                    "✓".green(),
+                    id
+                );
+            }
+            RetryOutcome::StillFailing(reason) => {
+                println!("  {} {} still fails: {}", "✗".red(), id, reason);
+            }
+            RetryOutcome::SourceMissing => {
+                println!(
+                    "  {} {} source no longer exists (discard with: claude-code-sync quarantine discard {})",
+                    "!".yellow(),
+                    id,
+                    id
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} Released {} of {} transcript(s)",
+        "✓".green(),
+        released,
+        ids.len()
+    );
+
+    Ok(())
+}
+
+/// Handle quarantine discard command
+///
+/// Removes quarantined copies only; the original transcripts are untouched.
+pub fn handle_quarantine_discard(id: Option<&str>, all: bool) -> Result<()> {
+    let quarantine = Quarantine::open()?;
+
+    let ids: Vec<String> = match (id, all) {
+        (Some(id), _) => vec![id.to_string()],
+        (None, true) => quarantine.list()?.into_iter().map(|e| e.id).collect(),
+        (None, false) => anyhow::bail!("Specify a quarantine id or --all"),
+    };
+
+    for id in &ids {
+        quarantine.discard(id)?;
+        println!("  {} Discarded {}", "✓".green(), id);
+    }
+
+    if ids.is_empty() {
+        println!("{}", "No quarantined transcripts.".green());
+    }
+
+    Ok(())
+}
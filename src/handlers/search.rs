@@ -0,0 +1,116 @@
+//! Search command handler
+//!
+//! Finds messages across local history and the sync repository and prints
+//! each match with its session, project, time and a highlighted snippet.
+//! The full-text index is topped up with whatever push and pull have not
+//! indexed yet before the query runs.
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::filter::FilterConfig;
+use crate::search::index::SearchIndex;
+use crate::search::query::{highlight_ranges, QueryTerm};
+use crate::search::sessions::KnownSessions;
+use crate::search::{search_indexed, SearchHit, SearchQuery, Source};
+
+/// Handle search command
+///
+/// # Arguments
+/// * `query` - Query text and filters
+/// * `limit` - Maximum number of matches to print
+/// * `reindex` - Rebuild the search index from scratch first. With an empty
+///   query, only the index is rebuilt.
+pub fn handle_search(query: &SearchQuery, limit: usize, reindex: bool) -> Result<()> {
+    if query.terms().is_empty() && !reindex {
+        anyhow::bail!("Search query is empty");
+    }
+
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let sides = known.sides();
+
+    let mut search_index = SearchIndex::load()?;
+    if reindex {
+        search_index.clear();
+    }
+    let indexed: usize = sides
+        .iter()
+        .map(|(_, dir, sessions)| search_index.update(dir, sessions, filter.discovery_threads))
+        .sum();
+    search_index.save_or_warn();
+    if reindex {
+        println!(
+            "{} Indexed {} transcript(s)",
+            "✓".green(),
+            indexed.to_string().cyan()
+        );
+        if query.terms().is_empty() {
+            return Ok(());
+        }
+    }
+
+    let hits = search_indexed(query, &sides, filter.discovery_threads, &search_index);
+
+    if hits.is_empty() {
+        println!("{}", "No matches found.".yellow());
+        return Ok(());
+    }
+
+    let terms = query.terms();
+    for hit in hits.iter().take(limit) {
+        print_hit(hit, &terms);
+    }
+
+    let sessions: std::collections::HashSet<&str> =
+        hits.iter().map(|h| h.session_id.as_str()).collect();
+    println!(
+        "\n{} {} match(es) in {} session(s)",
+        "Total:".bold(),
+        hits.len(),
+        sessions.len()
+    );
+    if hits.len() > limit {
+        println!(
+            "  {}",
+            format!("Showing the newest {limit}; use --limit to see more").dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn print_hit(hit: &SearchHit, terms: &[QueryTerm]) {
+    let session = match &hit.parent_session_id {
+        Some(parent) => format!("{} (subagent of {})", hit.session_id, parent),
+        None => hit.session_id.clone(),
+    };
+    let mut details = vec![hit.project.cyan().to_string()];
+    if let Some(ts) = &hit.timestamp {
+        details.push(ts.clone());
+    }
+    details.push(hit.role.clone());
+    if let Some(branch) = &hit.git_branch {
+        details.push(format!("branch {branch}"));
+    }
+    if hit.source == Source::SyncRepo {
+        details.push(hit.source.to_string());
+    }
+
+    println!("\n{}", session.bold());
+    println!("  {}", details.join(&" · ".dimmed().to_string()));
+    println!("  {}", highlight(&hit.snippet, terms));
+}
+
+/// Render `text` with every occurrence of the query terms highlighted
+fn highlight(text: &str, terms: &[QueryTerm]) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for range in highlight_ranges(text, terms) {
+        out.push_str(&text[last..range.start]);
+        out.push_str(&text[range.clone()].yellow().bold().to_string());
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
@@ -0,0 +1,33 @@
+//! Show command handler
+//!
+//! Pages through one session in the terminal. When stdout is not a terminal
+//! (or paging is turned off) the transcript is printed in full instead.
+
+use anyhow::Result;
+
+use crate::filter::FilterConfig;
+use crate::search::sessions::KnownSessions;
+use crate::transcript::viewer::{self, Viewer};
+
+/// Handle show command
+///
+/// # Arguments
+/// * `session_id` - Session id or unique id prefix
+/// * `expand` - Start with tool output and thinking unfolded
+/// * `no_pager` - Print the whole transcript instead of paging
+pub fn handle_show(session_id: &str, expand: bool, no_pager: bool) -> Result<()> {
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let found = known.find(session_id)?;
+    let session = found.summary.load()?;
+
+    let interactive = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout);
+    let mut viewer = Viewer::new(&session, &found.project, expand, viewer::terminal_width());
+
+    if interactive && !no_pager {
+        viewer::page(&mut viewer)
+    } else {
+        viewer::print(&viewer);
+        Ok(())
+    }
+}
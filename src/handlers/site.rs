@@ -0,0 +1,64 @@
+//! Site command handler
+//!
+//! Builds or updates the static HTML site of the sync repository's sessions
+//! (see [`crate::site`]).
+
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::filter::FilterConfig;
+use crate::search::session_project;
+use crate::search::sessions::KnownSessions;
+use crate::search::Source;
+use crate::site::{self, BuildOptions, SiteSource};
+
+/// Handle `site build`
+///
+/// # Arguments
+/// * `out_dir` - Directory of the site; created if missing
+/// * `full` - Render every session again instead of only changed ones
+pub fn handle_site_build(out_dir: &Path, full: bool) -> Result<()> {
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let Some((_, base_path, sessions)) = known
+        .sides()
+        .into_iter()
+        .find(|(source, _, _)| *source == Source::SyncRepo)
+    else {
+        anyhow::bail!("No sync repository found. Run 'claude-code-sync init' first.");
+    };
+
+    let sources: Vec<SiteSource> = sessions
+        .iter()
+        .map(|summary| SiteSource {
+            summary,
+            project: session_project(summary, base_path),
+        })
+        .collect();
+    let options = BuildOptions {
+        full,
+        threads: filter.discovery_threads,
+    };
+    let report = site::build(&sources, out_dir, options)?;
+
+    for (session_id, error) in &report.failed {
+        eprintln!(
+            "{} Skipped session {}: {}",
+            "⚠".yellow(),
+            session_id.cyan(),
+            error
+        );
+    }
+    println!(
+        "{} Built site with {} session(s) in {} project(s): {} rendered, {} unchanged, {} removed",
+        "✓".green(),
+        report.sessions,
+        report.projects,
+        report.rendered,
+        report.sessions - report.rendered,
+        report.removed
+    );
+    println!("  Open {}", out_dir.join("index.html").display());
+    Ok(())
+}
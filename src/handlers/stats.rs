@@ -0,0 +1,128 @@
+//! Stats command handler
+//!
+//! Summarizes token usage, messages and sessions over local history or the
+//! whole sync repository. Local sessions are all attributed to this machine;
+//! sessions in the sync repository to the machine that last pushed changes
+//! to them (see [`crate::sync::machines`]). Costs are estimated from the
+//! `[[pricing]]` tables of the config file. With `--tools`, reports tool
+//! calls, failures and shell commands instead.
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::config::ConfigManager;
+use crate::filter::FilterConfig;
+use crate::search::session_project;
+use crate::search::sessions::KnownSessions;
+use crate::search::Source;
+use crate::stats::output::{self, OutputFormat};
+use crate::stats::pricing::PriceTable;
+use crate::stats::tools::{collect_tools, render_tools};
+use crate::stats::{collect, SessionSource, StatsQuery};
+use crate::sync::machines::MachineAttribution;
+use crate::sync::SyncState;
+
+/// Machine of sync repository sessions pushed before machines were recorded
+const UNKNOWN_MACHINE: &str = "unknown";
+
+/// Handle stats command
+///
+/// # Arguments
+/// * `query` - Grouping and filters; prices are taken from the config
+/// * `repo` - Summarize the sync repository instead of local history
+/// * `format` - Table, CSV or JSON
+pub fn handle_stats(query: &StatsQuery, repo: bool, format: OutputFormat) -> Result<()> {
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let sources = session_sources(&known, repo)?;
+
+    let query = StatsQuery {
+        prices: PriceTable::new(filter.pricing.clone()),
+        ..query.clone()
+    };
+    let report = collect(&query, &sources, filter.discovery_threads);
+    if format == OutputFormat::Table && report.total.messages == 0 {
+        println!("{}", "No messages found.".yellow());
+        return Ok(());
+    }
+    print!("{}", output::render(&report, format));
+
+    // On stderr, so CSV and JSON output stay machine-readable
+    if !report.unpriced.is_empty() {
+        let models: Vec<String> = report
+            .unpriced
+            .iter()
+            .map(|(model, responses)| format!("{model} ({responses})"))
+            .collect();
+        eprintln!(
+            "{} No price configured for: {}. Their cost is not included.",
+            "⚠".yellow(),
+            models.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Handle `stats --tools`
+///
+/// # Arguments
+/// * `query` - Project and date filters
+/// * `repo` - Analyze the sync repository instead of local history
+/// * `format` - Table, CSV or JSON
+/// * `limit` - Commands per project and sessions listed in a table
+pub fn handle_tool_stats(
+    query: &StatsQuery,
+    repo: bool,
+    format: OutputFormat,
+    limit: usize,
+) -> Result<()> {
+    let filter = FilterConfig::load()?;
+    let known = KnownSessions::discover(&filter)?;
+    let sources = session_sources(&known, repo)?;
+
+    let report = collect_tools(query, &sources, filter.discovery_threads);
+    if format == OutputFormat::Table && report.tools.is_empty() {
+        println!("{}", "No tool calls found.".yellow());
+        return Ok(());
+    }
+    print!("{}", render_tools(&report, format, limit));
+    Ok(())
+}
+
+/// Sessions of one side with their project and machine
+fn session_sources(known: &KnownSessions, repo: bool) -> Result<Vec<SessionSource<'_>>> {
+    let wanted = if repo {
+        Source::SyncRepo
+    } else {
+        Source::Local
+    };
+    let Some((_, base_path, sessions)) = known
+        .sides()
+        .into_iter()
+        .find(|(source, _, _)| *source == wanted)
+    else {
+        anyhow::bail!("No sync repository found. Run 'claude-code-sync init' first.");
+    };
+
+    let this_machine = ConfigManager::machine_name();
+    let attribution = if repo {
+        MachineAttribution::load(&SyncState::load()?.sync_repo_path)
+    } else {
+        MachineAttribution::default()
+    };
+    Ok(sessions
+        .iter()
+        .map(|summary| SessionSource {
+            summary,
+            project: session_project(summary, base_path),
+            machine: if repo {
+                attribution
+                    .machine_of(&summary.session_id, &summary.content_hash)
+                    .unwrap_or(UNKNOWN_MACHINE)
+                    .to_string()
+            } else {
+                this_machine.clone()
+            },
+        })
+        .collect())
+}
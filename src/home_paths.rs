@@ -0,0 +1,311 @@
+//! Home directory anonymization of repository copies.
+//!
+//! Every entry's `cwd` and most tool calls spell out the home directory
+//! (`/home/alice/...`, `/Users/alice/...`), which puts usernames into a shared
+//! repository and ties sessions to one machine's layout. With
+//! `anonymize_home_paths` enabled, push writes the local home directory as
+//! `${HOME}` in `cwd`, tool inputs and tool outputs, and pull writes it back as
+//! the home directory of the machine pulling. Message text and thinking are
+//! left as they are, and so are project directory names (`-home-alice-proj`),
+//! which only `use_project_name_only` keeps out of the repository. The copy
+//! ledger ([`crate::sync::copies`]) records both directions, so neither side
+//! sees the rewritten copy as a change.
+//!
+//! The rewrite is reversible: a `~` in the transcript is never touched, and a
+//! `${HOME}` that was already there is escaped as `$${HOME}` on push, so pull
+//! restores every string exactly.
+
+use serde_json::Value;
+
+use crate::parser::ConversationEntry;
+
+/// What the home directory is written as in the repository
+pub const HOME_TOKEN: &str = "${HOME}";
+
+/// Rewrites the home directory of this machine in transcript entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomePaths {
+    home: String,
+}
+
+impl HomePaths {
+    /// The rewrite for `home`, or None for a home directory that is not an
+    /// absolute path below the root (nothing useful to anonymize). Windows
+    /// homes (`C:\Users\alice`) count from below their drive.
+    pub fn new(home: &str) -> Option<Self> {
+        let home = home.trim_end_matches(['/', '\\']);
+        let below_root = match home.strip_prefix('/') {
+            Some(rest) => rest,
+            None => strip_drive(home)?,
+        };
+        let depth = below_root
+            .split(['/', '\\'])
+            .filter(|s| !s.is_empty())
+            .count();
+        (depth >= 2).then(|| HomePaths {
+            home: home.to_string(),
+        })
+    }
+
+    /// The rewrite for this machine's home directory
+    pub fn local() -> Option<Self> {
+        Self::new(&dirs::home_dir()?.to_string_lossy())
+    }
+
+    /// The rewrite `filter` asks for, if any
+    pub fn from_config(filter: &crate::filter::FilterConfig) -> Option<Self> {
+        if !filter.anonymize_home_paths {
+            return None;
+        }
+        let local = Self::local();
+        if local.is_none() {
+            log::warn!(
+                "anonymize_home_paths is set, but the home directory ({}) is not one \
+                 that can be anonymized; sessions sync with their paths as they are",
+                dirs::home_dir().map_or_else(|| "unknown".to_string(), |h| h.display().to_string())
+            );
+        }
+        local
+    }
+
+    /// Write the home directory as [`HOME_TOKEN`] (push)
+    pub fn collapse(&self, entry: &mut ConversationEntry) {
+        rewrite_entry(entry, &mut |text| collapse_text(text, &self.home));
+    }
+
+    /// Write [`HOME_TOKEN`] back as the home directory (pull)
+    pub fn expand(&self, entry: &mut ConversationEntry) {
+        rewrite_entry(entry, &mut |text| expand_text(text, &self.home));
+    }
+}
+
+/// Apply `rewrite` to the strings of `entry` that hold paths: `cwd`, tool
+/// call inputs, tool result contents and the structured `toolUseResult`
+fn rewrite_entry(entry: &mut ConversationEntry, rewrite: &mut dyn FnMut(&str) -> Option<String>) {
+    if let Some(cwd) = entry.cwd.as_mut() {
+        if let Some(new) = rewrite(cwd) {
+            *cwd = new;
+        }
+    }
+    if let Some(blocks) = entry
+        .message
+        .as_mut()
+        .and_then(|message| message.get_mut("content"))
+        .and_then(Value::as_array_mut)
+    {
+        for block in blocks {
+            let field = match block.get("type").and_then(Value::as_str) {
+                Some("tool_use") => "input",
+                Some("tool_result") => "content",
+                _ => continue,
+            };
+            if let Some(value) = block.get_mut(field) {
+                rewrite_strings(value, rewrite);
+            }
+        }
+    }
+    if let Some(result) = entry.extra.get_mut("toolUseResult") {
+        rewrite_strings(result, rewrite);
+    }
+}
+
+fn rewrite_strings(value: &mut Value, rewrite: &mut dyn FnMut(&str) -> Option<String>) {
+    match value {
+        Value::String(text) => {
+            if let Some(new) = rewrite(text) {
+                *text = new;
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| rewrite_strings(item, rewrite)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| rewrite_strings(item, rewrite)),
+        _ => {}
+    }
+}
+
+/// The part of a Windows path after its drive (`C:\` or `C:/`)
+fn strip_drive(path: &str) -> Option<&str> {
+    let mut chars = path.chars();
+    let (letter, colon, separator) = (chars.next()?, chars.next()?, chars.next()?);
+    (letter.is_ascii_alphabetic() && colon == ':' && matches!(separator, '\\' | '/'))
+        .then_some(chars.as_str())
+}
+
+/// Characters that continue a path component: the home directory is only
+/// replaced where neither neighbour is one, so `/home/al` never matches
+/// inside `/home/alice`
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '~' | '+' | '@' | '%')
+}
+
+/// `text` with the home directory written as [`HOME_TOKEN`] and each
+/// `${HOME}` already there escaped with one more `$`, or None when nothing
+/// changes
+fn collapse_text(text: &str, home: &str) -> Option<String> {
+    let escaped = text
+        .contains(HOME_TOKEN)
+        .then(|| text.replace(HOME_TOKEN, &format!("${HOME_TOKEN}")));
+    replace_bounded(escaped.as_deref().unwrap_or(text), home, HOME_TOKEN).or(escaped)
+}
+
+/// Inverse of [`collapse_text`]: [`HOME_TOKEN`] becomes `home`, and an
+/// escaped `${HOME}` loses one `$`
+fn expand_text(text: &str, home: &str) -> Option<String> {
+    let name = &HOME_TOKEN[1..];
+    let mut out: Option<String> = None;
+    let mut copied = 0;
+    for (start, _) in text.match_indices(name) {
+        let dollars = start - text[..start].trim_end_matches('$').len();
+        if dollars == 0 {
+            continue;
+        }
+        let out = out.get_or_insert_with(|| String::with_capacity(text.len()));
+        out.push_str(&text[copied..start - dollars]);
+        if dollars == 1 {
+            out.push_str(home);
+        } else {
+            out.push_str(&text[start - dollars + 1..start + name.len()]);
+        }
+        copied = start + name.len();
+    }
+    out.map(|mut out| {
+        out.push_str(&text[copied..]);
+        out
+    })
+}
+
+/// Replace each bounded occurrence of `from` in `text` with `to`, or None
+/// when there is none. An occurrence right after a `$` is left alone too, so
+/// the `$` never reads as an escape on the way back.
+fn replace_bounded(text: &str, from: &str, to: &str) -> Option<String> {
+    let mut out: Option<String> = None;
+    let mut copied = 0;
+    for (start, _) in text.match_indices(from) {
+        let end = start + from.len();
+        if start < copied {
+            continue;
+        }
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        if before.is_some_and(|c| is_name_char(c) || c == '$') || after.is_some_and(is_name_char) {
+            continue;
+        }
+        let out = out.get_or_insert_with(|| String::with_capacity(text.len()));
+        out.push_str(&text[copied..start]);
+        out.push_str(to);
+        copied = end;
+    }
+    out.map(|mut out| {
+        out.push_str(&text[copied..]);
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry() -> ConversationEntry {
+        serde_json::from_value(json!({
+            "type": "assistant",
+            "uuid": "a1",
+            "cwd": "/home/alice/proj",
+            "message": {"role": "assistant", "content": [
+                {"type": "text", "text": "Reading /home/alice/proj/main.rs"},
+                {"type": "tool_use", "id": "toolu_1", "name": "Read",
+                 "input": {"file_path": "/home/alice/proj/main.rs"}},
+                {"type": "tool_result", "tool_use_id": "toolu_1",
+                 "content": "cd /home/alice && ls /home/alice2 ~bob"}
+            ]},
+            "toolUseResult": {"filePath": "/home/alice/proj/main.rs"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collapse_then_expand_elsewhere() {
+        let mut e = entry();
+        HomePaths::new("/home/alice/").unwrap().collapse(&mut e);
+        let content = &e.message.as_ref().unwrap()["content"];
+        assert_eq!(e.cwd.as_deref(), Some("${HOME}/proj"));
+        assert_eq!(content[0]["text"], "Reading /home/alice/proj/main.rs");
+        assert_eq!(content[1]["input"]["file_path"], "${HOME}/proj/main.rs");
+        assert_eq!(content[2]["content"], "cd ${HOME} && ls /home/alice2 ~bob");
+        assert_eq!(e.extra["toolUseResult"]["filePath"], "${HOME}/proj/main.rs");
+
+        HomePaths::new("/Users/bob").unwrap().expand(&mut e);
+        let content = &e.message.as_ref().unwrap()["content"];
+        assert_eq!(e.cwd.as_deref(), Some("/Users/bob/proj"));
+        assert_eq!(
+            content[2]["content"],
+            "cd /Users/bob && ls /home/alice2 ~bob"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_restores_entry() {
+        let original = serde_json::to_value(entry()).unwrap();
+        let home = HomePaths::new("/home/alice").unwrap();
+        let mut e = entry();
+        home.collapse(&mut e);
+        assert_ne!(serde_json::to_value(&e).unwrap(), original);
+        home.expand(&mut e);
+        assert_eq!(serde_json::to_value(&e).unwrap(), original);
+    }
+
+    #[test]
+    fn test_text_round_trip_keeps_literal_tokens() {
+        let home = "/home/alice";
+        for text in [
+            "build took ~ 3s; run `cd ~` then ls ~ and /home/alice/p",
+            "cp ~/a ~user/b /home/alice",
+            "echo ${HOME} $${HOME} $$/home/alice ${HOME}/home/alice",
+        ] {
+            let collapsed = collapse_text(text, home).unwrap_or_else(|| text.to_string());
+            assert!(!collapsed.contains("alice/p"), "{collapsed}");
+            let expanded = expand_text(&collapsed, home).unwrap_or(collapsed);
+            assert_eq!(expanded, text);
+        }
+        assert_eq!(
+            collapse_text("cd ~ && cd /home/alice", home).as_deref(),
+            Some("cd ~ && cd ${HOME}")
+        );
+        assert_eq!(
+            expand_text("ls ~ ${HOME}/x $${HOME}", "/Users/bob").as_deref(),
+            Some("ls ~ /Users/bob/x ${HOME}")
+        );
+    }
+
+    #[test]
+    fn test_unusable_homes() {
+        assert!(HomePaths::new("/").is_none());
+        assert!(HomePaths::new("/root").is_none());
+        assert!(HomePaths::new("relative/home").is_none());
+        assert!(HomePaths::new("/home/alice").is_some());
+        assert!(HomePaths::new("C:\\").is_none());
+        assert!(HomePaths::new("C:\\Users").is_none());
+        assert!(HomePaths::new("C:alice\\x").is_none());
+    }
+
+    #[test]
+    fn test_windows_home() {
+        let home = HomePaths::new("C:\\Users\\alice\\").unwrap();
+        let mut e: ConversationEntry = serde_json::from_value(json!({
+            "type": "user", "uuid": "u1", "cwd": "C:\\Users\\alice\\proj",
+            "toolUseResult": {"filePath": "C:\\Users\\alice\\proj\\main.rs"}
+        }))
+        .unwrap();
+        home.collapse(&mut e);
+        assert_eq!(e.cwd.as_deref(), Some("${HOME}\\proj"));
+        assert_eq!(
+            e.extra["toolUseResult"]["filePath"],
+            "${HOME}\\proj\\main.rs"
+        );
+        home.expand(&mut e);
+        assert_eq!(e.cwd.as_deref(), Some("C:\\Users\\alice\\proj"));
+    }
+}
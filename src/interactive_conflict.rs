@@ -3,8 +3,8 @@ use colored::Colorize;
 use inquire::{Confirm, Select};
 use std::path::{Path, PathBuf};
 
-use crate::conflict::{Conflict, ConflictResolution};
-use crate::parser::ConversationSession;
+use crate::conflict::{group_by_unit, Conflict, ConflictResolution};
+use crate::parser::{ConversationSession, SessionSummary};
 
 /// Resolution action chosen by the user
 #[derive(Debug, Clone)]
@@ -205,81 +205,54 @@ pub fn resolve_conflicts_interactive_with_sessions(
 
     let mut result = ResolutionResult::new();
 
-    for (idx, conflict) in conflicts.iter_mut().enumerate() {
+    // Sidechains follow their parent session's decision, so each sync unit is
+    // asked about once.
+    let units = group_by_unit(conflicts);
+    let total_units = units.len();
+
+    for (idx, unit) in units.iter().enumerate() {
         println!(
             "\n{} Conflict {} of {}",
             ">>>".yellow().bold(),
             (idx + 1).to_string().cyan(),
-            total_conflicts.to_string().cyan()
+            total_units.to_string().cyan()
         );
 
-        let action = resolve_conflict_interactive(conflict)?;
+        let (&root, sidechains) = unit.split_first().expect("units are never empty");
+        if !sidechains.is_empty() {
+            println!(
+                "  {} {} conflicting subagent transcript(s) will follow this choice",
+                "→".cyan(),
+                sidechains.len()
+            );
+        }
 
-        match action {
-            ResolutionAction::SmartMerge => {
-                // Attempt smart merge
-                if let (Some(local_map), Some(remote_map)) = (local_sessions, remote_sessions) {
-                    if let (Some(&local_session), Some(&remote_session)) = (
-                        local_map.get(&conflict.session_id),
-                        remote_map.get(&conflict.session_id),
-                    ) {
-                        match conflict.try_smart_merge(local_session, remote_session) {
-                            Ok(()) => {
-                                if let ConflictResolution::SmartMerge { ref stats, .. } =
-                                    conflict.resolution
-                                {
-                                    println!(
-                                        "  {} Smart merged ({} local + {} remote = {} total, {} branches)",
-                                        "✓".green(),
-                                        stats.local_messages,
-                                        stats.remote_messages,
-                                        stats.merged_messages,
-                                        stats.branches_detected
-                                    );
-                                }
-                                result.smart_merge.push(conflict.clone());
-                            }
-                            Err(e) => {
-                                eprintln!("  {} Smart merge failed: {}", "✗".red(), e);
-                                eprintln!("  Please choose another resolution method...");
-                                // Don't add to result, user will be prompted again
-                                continue;
-                            }
-                        }
-                    } else {
-                        eprintln!("  {} Cannot find local or remote session", "✗".red());
-                        eprintln!("  Please choose another resolution method...");
-                        continue;
-                    }
-                } else {
-                    eprintln!("  {} Session maps not provided", "✗".red());
-                    eprintln!("  Please choose another resolution method...");
-                    continue;
-                }
-            }
-            ResolutionAction::KeepLocal => {
-                println!("  {} Keeping local version", "✓".green());
-                conflict.resolution = ConflictResolution::KeepLocal;
-                result.keep_local.push(conflict.clone());
-            }
-            ResolutionAction::KeepRemote => {
-                println!(
-                    "  {} Keeping remote version (will overwrite local)",
-                    "✓".yellow()
-                );
-                conflict.resolution = ConflictResolution::KeepRemote;
-                result.keep_remote.push(conflict.clone());
-            }
-            ResolutionAction::KeepBoth => {
+        let action = resolve_conflict_interactive(&conflicts[root])?;
+        if !apply_action(
+            &mut conflicts[root],
+            &action,
+            local_sessions,
+            remote_sessions,
+            &mut result,
+        ) {
+            continue;
+        }
+
+        for &i in sidechains {
+            if !apply_action(
+                &mut conflicts[i],
+                &action,
+                local_sessions,
+                remote_sessions,
+                &mut result,
+            ) {
+                // Nothing is lost by keeping both, and the parent is already resolved
                 println!(
-                    "  {} Keeping both versions (remote will be saved with conflict suffix)",
-                    "✓".cyan()
+                    "  {} Keeping both versions of {}",
+                    "✓".cyan(),
+                    conflicts[i].session_id
                 );
-                // Keep both is handled later with proper renaming
-                result.keep_both.push(conflict.clone());
-            }
-            ResolutionAction::ViewDetails => {
-                unreachable!("ViewDetails should be handled in the loop")
+                result.keep_both.push(conflicts[i].clone());
             }
         }
     }
@@ -320,6 +293,87 @@ pub fn resolve_conflicts_interactive_with_sessions(
     Ok(result)
 }
 
+/// Apply one resolution action to a conflict, recording it in `result`.
+///
+/// Returns false if the action could not be applied (a smart merge that failed
+/// or had no session data), leaving the conflict unresolved.
+fn apply_action(
+    conflict: &mut Conflict,
+    action: &ResolutionAction,
+    local_sessions: Option<&std::collections::HashMap<String, &ConversationSession>>,
+    remote_sessions: Option<&std::collections::HashMap<String, &ConversationSession>>,
+    result: &mut ResolutionResult,
+) -> bool {
+    match action {
+        ResolutionAction::SmartMerge => {
+            // Attempt smart merge
+            if let (Some(local_map), Some(remote_map)) = (local_sessions, remote_sessions) {
+                if let (Some(&local_session), Some(&remote_session)) = (
+                    local_map.get(&conflict.session_id),
+                    remote_map.get(&conflict.session_id),
+                ) {
+                    match conflict.try_smart_merge(local_session, remote_session) {
+                        Ok(()) => {
+                            if let ConflictResolution::SmartMerge { ref stats, .. } =
+                                conflict.resolution
+                            {
+                                println!(
+                                    "  {} Smart merged ({} local + {} remote = {} total, {} branches)",
+                                    "✓".green(),
+                                    stats.local_messages,
+                                    stats.remote_messages,
+                                    stats.merged_messages,
+                                    stats.branches_detected
+                                );
+                            }
+                            result.smart_merge.push(conflict.clone());
+                        }
+                        Err(e) => {
+                            eprintln!("  {} Smart merge failed: {}", "✗".red(), e);
+                            eprintln!("  Please choose another resolution method...");
+                            // Don't add to result, user will be prompted again
+                            return false;
+                        }
+                    }
+                } else {
+                    eprintln!("  {} Cannot find local or remote session", "✗".red());
+                    eprintln!("  Please choose another resolution method...");
+                    return false;
+                }
+            } else {
+                eprintln!("  {} Session maps not provided", "✗".red());
+                eprintln!("  Please choose another resolution method...");
+                return false;
+            }
+        }
+        ResolutionAction::KeepLocal => {
+            println!("  {} Keeping local version", "✓".green());
+            conflict.resolution = ConflictResolution::KeepLocal;
+            result.keep_local.push(conflict.clone());
+        }
+        ResolutionAction::KeepRemote => {
+            println!(
+                "  {} Keeping remote version (will overwrite local)",
+                "✓".yellow()
+            );
+            conflict.resolution = ConflictResolution::KeepRemote;
+            result.keep_remote.push(conflict.clone());
+        }
+        ResolutionAction::KeepBoth => {
+            println!(
+                "  {} Keeping both versions (remote will be saved with conflict suffix)",
+                "✓".cyan()
+            );
+            // Keep both is handled later with proper renaming
+            result.keep_both.push(conflict.clone());
+        }
+        ResolutionAction::ViewDetails => {
+            unreachable!("ViewDetails should be handled in the loop")
+        }
+    }
+    true
+}
+
 /// Backward-compatible version of resolve_conflicts_interactive without session maps
 ///
 /// This version doesn't support SmartMerge since it requires session data.
@@ -335,16 +389,24 @@ pub fn resolve_conflicts_interactive(conflicts: &mut [Conflict]) -> Result<Resol
 /// * `remote_sessions` - All remote sessions (to find the ones we need)
 /// * `claude_dir` - The Claude projects directory
 /// * `_remote_projects_dir` - The remote sync repository projects directory (unused)
+/// * `home_paths` - Home directory rewrite for remote transcripts written locally, if enabled
 ///
 /// # Returns
 /// List of (original_path, renamed_path) tuples for conflicts kept as both
 pub fn apply_resolutions(
     result: &ResolutionResult,
-    remote_sessions: &[ConversationSession],
+    remote_sessions: &[SessionSummary],
     claude_dir: &Path,
     _remote_projects_dir: &Path,
+    home_paths: Option<&crate::home_paths::HomePaths>,
 ) -> Result<Vec<(PathBuf, PathBuf)>> {
     let mut renames = Vec::new();
+    let localize = |e: &mut crate::parser::ConversationEntry| {
+        if let Some(home_paths) = home_paths {
+            home_paths.expand(e);
+        }
+        true
+    };
 
     // Handle "smart merge" - write merged entries to local file
     for conflict in &result.smart_merge {
@@ -386,7 +448,7 @@ pub fn apply_resolutions(
         {
             // Write remote session to local path (overwrite)
             remote_session
-                .write_to_file(&conflict.local_file)
+                .copy_to_with(&conflict.local_file, localize)
                 .with_context(|| {
                     format!(
                         "Failed to overwrite local file with remote: {}",
@@ -418,7 +480,7 @@ pub fn apply_resolutions(
             .find(|s| s.session_id == conflict.session_id)
         {
             remote_session
-                .write_to_file(&renamed_path)
+                .copy_to_with(&renamed_path, localize)
                 .with_context(|| {
                     format!(
                         "Failed to write remote conflict version: {}",
@@ -24,11 +24,14 @@
 //!
 //! - Configuration and state management ([`config`], [`filter`])
 //! - Source control operations, Git or Mercurial ([`scm`])
-//! - Conversation parsing and analysis ([`parser`])
+//! - Conversation parsing and analysis ([`parser`], [`message`], [`canonical`], [`quarantine`])
 //! - Conflict detection and resolution ([`conflict`], [`interactive_conflict`], [`merge`])
 //! - Operation tracking and undo ([`history`], [`undo`])
 //! - User interface and reporting ([`onboarding`], [`report`], [`logger`])
 //! - Core synchronization logic ([`sync`])
+//! - Querying, rendering and summarizing conversation history ([`search`], [`transcript`], [`stats`], [`site`])
+//! - Shaping what push writes to the sync repository ([`lite`], [`home_paths`], [`session_rules`])
+//! - Keeping secrets out of the sync repository ([`redact`], [`encryption`], [`audit`])
 //! - Syncing Claude Code state beyond conversations ([`artifacts`])
 //! - The command handlers behind the CLI ([`handlers`])
 
@@ -43,6 +46,13 @@ pub enum VerbosityLevel {
 // `artifacts` documents itself in artifacts/mod.rs.
 pub mod artifacts;
 
+/// Canonical JSON serialization and hashing.
+///
+/// Session content hashes are computed over a canonical form (sorted keys,
+/// normalized numbers, no whitespace) with SHA-256, so copies that differ only
+/// in formatting compare equal on every machine and release.
+pub mod canonical;
+
 /// Platform-agnostic configuration directory management for claude-code-sync.
 ///
 /// Provides utilities for locating and managing configuration files and directories
@@ -115,6 +125,93 @@ pub mod onboarding;
 /// file snapshots, etc.) with metadata like timestamps, UUIDs, and session IDs.
 pub mod parser;
 
+/// Typed view of conversation message content.
+///
+/// Lifts the opaque `message` JSON of an entry into text, thinking, tool_use,
+/// tool_result and image blocks plus model and token usage metadata, and
+/// converts back losslessly so rewritten sessions serialize identically.
+pub mod message;
+
+/// Quarantine for transcripts that cannot be parsed.
+///
+/// Copies unparsable transcripts into a per-machine directory under the config
+/// directory so they are noticed instead of silently skipped, and backs the
+/// `quarantine` command for listing, retrying and discarding them.
+pub mod quarantine;
+
+/// Secret redaction for sessions pushed to the sync repository.
+///
+/// Detects cloud keys, access tokens, JWTs, private key blocks, credentials in
+/// URLs, user-defined patterns and, when enabled, high-entropy strings in
+/// transcripts, and replaces them in the repository copy so they never reach
+/// a remote.
+pub mod redact;
+
+/// Lite push: smaller repository copies of sessions.
+///
+/// Truncates or elides tool results above a size threshold and leaves file
+/// history snapshots out of the copy pushed to the sync repository, keeping
+/// the local transcript whole.
+pub mod lite;
+
+/// Home directory anonymization of repository copies.
+///
+/// Writes this machine's home directory as `${HOME}` in the working directory,
+/// tool inputs and tool outputs of pushed sessions, and back as the local home
+/// directory on pull.
+pub mod home_paths;
+
+/// Content-based exclusion of sessions from push.
+///
+/// Leaves out sessions whose message text, git branch, working directory,
+/// model or message count match a configured `[[exclude_rules]]` table.
+pub mod session_rules;
+
+/// End-to-end encryption of the sync repository.
+///
+/// Encrypts transcripts, attachments and artifacts with age on their way into
+/// the sync repository and decrypts them into a local plaintext view on pull,
+/// unlocked by a key file or a passphrase.
+pub mod encryption;
+
+/// Audit of the sync repository and its history.
+///
+/// Flags denied paths, unsafe paths and secrets committed to the sync
+/// repository at any point, and writes a purge plan for rewriting history.
+pub mod audit;
+
+/// Search across conversation history.
+///
+/// Matches message text (not the surrounding JSON) in local and sync repository
+/// sessions, with project, date, git branch and role filters, and builds the
+/// highlighted snippets printed by the `search` command.
+pub mod search;
+
+/// Human-readable conversation transcripts.
+///
+/// Follows the `parentUuid` tree of a session (the active branch, or every
+/// branch) and renders its messages, tool calls and attachment references as
+/// Markdown or self-contained HTML for the `export` command, pages through it
+/// in the terminal for `show`, or turns it into ShareGPT or OpenAI chat
+/// records for `dataset`.
+pub mod transcript;
+
+/// Usage statistics over conversation history.
+///
+/// Totals tokens, messages and sessions from local or sync repository
+/// transcripts, grouped by project, day, month, machine or model, and renders
+/// them with estimated costs as a table, CSV or JSON for the `stats` command,
+/// along with tool call analytics.
+pub mod stats;
+
+/// Static HTML site of the sync repository.
+///
+/// Renders the repository's sessions into an offline-browsable site for the
+/// `site build` command: a project index, per-project session lists,
+/// transcript pages and a client-side search index. Rebuilds only render
+/// sessions that changed since the last build.
+pub mod site;
+
 /// Conflict report generation and formatting.
 ///
 /// Generates detailed reports of sync conflicts in multiple formats (JSON, Markdown, console).
@@ -0,0 +1,282 @@
+//! Lite push: smaller repository copies of sessions.
+//!
+//! Tool output (whole files read, build and test logs) makes up most of a
+//! transcript's bytes yet is rarely worth reading on another machine. With
+//! lite push enabled, the repository copy of a session has every tool result
+//! above a size threshold truncated or elided, the `toolUseResult` duplicate
+//! Claude Code keeps of large results dropped, and `file-history-snapshot`
+//! entries left out. The local transcript is never touched; the copy ledger
+//! ([`crate::sync::copies`]) keeps the smaller copy from looking modified.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::parser::ConversationEntry;
+
+/// Default size above which a tool result is trimmed (16 KiB)
+pub const DEFAULT_MAX_TOOL_RESULT_BYTES: usize = 16 * 1024;
+
+/// Ends every note lite push leaves in place of trimmed output, so a copy
+/// pushed again (say, from a machine that pulled it) is not trimmed twice
+const NOTE_SUFFIX: &str = " by lite push]";
+
+/// What happens to a tool result above the threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolResultTrim {
+    /// Keep the first `max_tool_result_bytes` of its text
+    #[default]
+    Truncate,
+    /// Replace it with a note of its size
+    Elide,
+}
+
+/// Lite push settings, the `[lite_push]` table of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LitePush {
+    /// Shrink repository copies at all (default: false)
+    pub enabled: bool,
+    /// Tool results larger than this many bytes are trimmed
+    pub max_tool_result_bytes: usize,
+    /// Truncate or elide tool results above the threshold
+    pub tool_results: ToolResultTrim,
+    /// Leave `file-history-snapshot` entries out of the copy (default: true)
+    pub drop_file_history_snapshots: bool,
+}
+
+impl Default for LitePush {
+    fn default() -> Self {
+        LitePush {
+            enabled: false,
+            max_tool_result_bytes: DEFAULT_MAX_TOOL_RESULT_BYTES,
+            tool_results: ToolResultTrim::default(),
+            drop_file_history_snapshots: true,
+        }
+    }
+}
+
+/// What lite push took out of the copies it wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiteStats {
+    /// Tool results truncated, elided or left out
+    pub trimmed_results: usize,
+    /// Bytes of tool output left out
+    pub trimmed_bytes: usize,
+    /// `file-history-snapshot` entries left out
+    pub dropped_snapshots: usize,
+}
+
+impl LiteStats {
+    /// Whether anything was left out
+    pub fn is_empty(&self) -> bool {
+        self.trimmed_results == 0 && self.dropped_snapshots == 0
+    }
+
+    /// Add `other` to these counts
+    pub fn add(&mut self, other: &LiteStats) {
+        self.trimmed_results += other.trimmed_results;
+        self.trimmed_bytes += other.trimmed_bytes;
+        self.dropped_snapshots += other.dropped_snapshots;
+    }
+}
+
+impl LitePush {
+    /// Shrink `entry` for the repository copy. Returns false when the entry
+    /// is left out of the copy altogether.
+    pub fn apply(&self, entry: &mut ConversationEntry, stats: &mut LiteStats) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        if self.drop_file_history_snapshots && entry.entry_type == "file-history-snapshot" {
+            stats.dropped_snapshots += 1;
+            return false;
+        }
+
+        let trimmed_before = stats.trimmed_results;
+        if let Some(blocks) = entry
+            .message
+            .as_mut()
+            .and_then(|message| message.get_mut("content"))
+            .and_then(Value::as_array_mut)
+        {
+            for block in blocks {
+                if block.get("type").and_then(Value::as_str) != Some("tool_result") {
+                    continue;
+                }
+                if let Some(content) = block.get_mut("content") {
+                    self.trim(content, stats);
+                }
+            }
+        }
+
+        // The structured copy of the result Claude Code keeps next to the
+        // message; the trimmed content above already stands in for it, and
+        // was counted if it had to be trimmed as well
+        if let Some(extra) = entry.extra.as_object_mut() {
+            let size = extra.get("toolUseResult").map(json_size).unwrap_or(0);
+            if size > self.max_tool_result_bytes {
+                extra.remove("toolUseResult");
+                stats.trimmed_bytes += size;
+                if stats.trimmed_results == trimmed_before {
+                    stats.trimmed_results += 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Trim one tool result's `content` (a string or a list of blocks)
+    fn trim(&self, content: &mut Value, stats: &mut LiteStats) {
+        let size = content_size(content);
+        if size <= self.max_tool_result_bytes || is_trimmed(content) {
+            return;
+        }
+        let kept = match self.tool_results {
+            ToolResultTrim::Elide => {
+                *content =
+                    Value::String(format!("[{size} bytes of tool output elided{NOTE_SUFFIX}"));
+                0
+            }
+            ToolResultTrim::Truncate => {
+                let head = head_text(content, self.max_tool_result_bytes);
+                let kept = head.len();
+                *content = Value::String(format!(
+                    "{head}\n[{} bytes of tool output truncated{NOTE_SUFFIX}",
+                    size - kept
+                ));
+                kept
+            }
+        };
+        stats.trimmed_results += 1;
+        stats.trimmed_bytes += size - kept;
+    }
+}
+
+/// Bytes of a tool result's content: text as is, anything else (images) as
+/// serialized JSON
+fn content_size(content: &Value) -> usize {
+    match content {
+        Value::String(text) => text.len(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .map(|block| match block.get("text").and_then(Value::as_str) {
+                Some(text) => text.len(),
+                None => json_size(block),
+            })
+            .sum(),
+        other => json_size(other),
+    }
+}
+
+fn json_size(value: &Value) -> usize {
+    serde_json::to_string(value).map_or(0, |json| json.len())
+}
+
+fn is_trimmed(content: &Value) -> bool {
+    content
+        .as_str()
+        .is_some_and(|text| text.ends_with(NOTE_SUFFIX))
+}
+
+/// The first `max` bytes of a content's text, cut at a character boundary
+fn head_text(content: &Value, max: usize) -> String {
+    let text = match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool_result_entry(content: Value) -> ConversationEntry {
+        serde_json::from_value(json!({
+            "type": "user",
+            "uuid": "u1",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": content}
+            ]},
+            "toolUseResult": {"stdout": content.to_string()}
+        }))
+        .unwrap()
+    }
+
+    fn result_content(entry: &ConversationEntry) -> &Value {
+        &entry.message.as_ref().unwrap()["content"][0]["content"]
+    }
+
+    #[test]
+    fn test_large_results_are_truncated_once() {
+        let lite = LitePush {
+            enabled: true,
+            max_tool_result_bytes: 40,
+            ..Default::default()
+        };
+        let mut stats = LiteStats::default();
+
+        let mut small = tool_result_entry(json!("short"));
+        assert!(lite.apply(&mut small, &mut stats));
+        assert_eq!(result_content(&small), "short");
+        assert!(small.extra.get("toolUseResult").is_some());
+
+        let mut large = tool_result_entry(json!([{"type": "text", "text": "é".repeat(40)}]));
+        assert!(lite.apply(&mut large, &mut stats));
+        let text = result_content(&large).as_str().unwrap().to_string();
+        assert!(text.starts_with(&"é".repeat(20)));
+        assert!(text.ends_with("40 bytes of tool output truncated by lite push]"));
+        assert!(large.extra.get("toolUseResult").is_none());
+        assert_eq!(stats.trimmed_results, 1);
+
+        // Pushing the trimmed copy again changes nothing
+        let before = large.clone();
+        assert!(lite.apply(&mut large, &mut stats));
+        assert_eq!(result_content(&large), result_content(&before));
+        assert_eq!(stats.trimmed_results, 1);
+
+        // A large structured result next to short content is left out, and
+        // counted
+        small.extra["toolUseResult"] = json!({"stdout": "x".repeat(40)});
+        assert!(lite.apply(&mut small, &mut stats));
+        assert_eq!(result_content(&small), "short");
+        assert!(small.extra.get("toolUseResult").is_none());
+        assert_eq!(stats.trimmed_results, 2);
+    }
+
+    #[test]
+    fn test_elide_and_drop_snapshots() {
+        let lite = LitePush {
+            enabled: true,
+            max_tool_result_bytes: 4,
+            tool_results: ToolResultTrim::Elide,
+            ..Default::default()
+        };
+        let mut stats = LiteStats::default();
+
+        let mut entry = tool_result_entry(json!("0123456789"));
+        assert!(lite.apply(&mut entry, &mut stats));
+        assert_eq!(
+            result_content(&entry),
+            "[10 bytes of tool output elided by lite push]"
+        );
+
+        let mut snapshot: ConversationEntry =
+            serde_json::from_value(json!({"type": "file-history-snapshot", "messageId": "m"}))
+                .unwrap();
+        assert!(!lite.apply(&mut snapshot, &mut stats));
+        assert!(LitePush::default().apply(&mut snapshot, &mut stats));
+        assert_eq!(stats.dropped_snapshots, 1);
+    }
+}
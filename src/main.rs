@@ -8,12 +8,18 @@ use std::path::PathBuf;
 // `handlers` — both export a `config`, and two globs supplying the same name
 // is an ambiguity error at every `config::` call site.
 use claude_code_sync::handlers::{
-    handle_cleanup_snapshots, handle_config_export, handle_config_interactive,
-    handle_config_wizard, handle_history_clear, handle_history_last, handle_history_list,
-    handle_history_review, handle_repo_selector, handle_undo_pull, handle_undo_push,
-    is_initialized, run_init_from_config, run_onboarding_flow, try_init_from_config,
+    handle_audit, handle_cleanup_snapshots, handle_config_export, handle_config_interactive,
+    handle_config_wizard, handle_dataset, handle_encryption_enable, handle_encryption_recipient,
+    handle_encryption_status, handle_encryption_trust, handle_export, handle_history_clear,
+    handle_history_last, handle_history_list, handle_history_review, handle_quarantine_discard,
+    handle_quarantine_list, handle_quarantine_retry, handle_quarantine_show, handle_repo_selector,
+    handle_search, handle_show, handle_site_build, handle_stats, handle_tool_stats,
+    handle_undo_pull, handle_undo_push, is_initialized, run_init_from_config, run_onboarding_flow,
+    try_init_from_config, DatasetSelection,
+};
+use claude_code_sync::{
+    config, filter, logger, report, scm, search, stats, sync, transcript, VerbosityLevel,
 };
-use claude_code_sync::{config, filter, logger, report, scm, sync, VerbosityLevel};
 
 #[derive(Parser)]
 #[command(name = "claude-code-sync")]
@@ -238,6 +244,187 @@ enum Commands {
         action: HistoryAction,
     },
 
+    /// Search message text across local history and the sync repository
+    Search {
+        /// Terms that must all appear in a message (case-insensitive):
+        /// words, `prefix*` and "quoted phrases"
+        #[arg(required_unless_present = "reindex")]
+        query: Vec<String>,
+
+        /// Only sessions whose project name contains this
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Only messages on or after this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only messages on or before this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only messages recorded on this git branch
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        /// Only messages by this author: user or assistant
+        #[arg(short, long)]
+        role: Option<String>,
+
+        /// Maximum number of matches to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Rebuild the search index from scratch before searching
+        #[arg(long)]
+        reindex: bool,
+    },
+
+    /// Export a conversation as a Markdown or HTML transcript
+    Export {
+        /// Session id (or a unique prefix of one)
+        session_id: String,
+
+        /// Output format: markdown or html (default: from the output file's
+        /// extension, else markdown)
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Include every branch of the conversation, not just the active one
+        #[arg(long)]
+        all_branches: bool,
+    },
+
+    /// Export sessions as a ShareGPT or OpenAI chat JSONL dataset
+    Dataset {
+        /// Session ids (or unique prefixes); every session matching the
+        /// filters when none are given
+        session_ids: Vec<String>,
+
+        /// Record format: sharegpt or openai
+        #[arg(short, long, default_value = "sharegpt")]
+        format: String,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Only sessions of projects whose name contains this
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Only sessions last active on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only sessions last active on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Include subagent transcripts when selecting by filter
+        #[arg(long)]
+        subagents: bool,
+
+        /// Leave out model reasoning
+        #[arg(long)]
+        no_thinking: bool,
+
+        /// Leave out tool output (and, in the openai format, the tool calls)
+        #[arg(long)]
+        no_tool_results: bool,
+
+        /// Leave out entries Claude Code writes for itself: system events,
+        /// file-history snapshots, summaries, meta prompts
+        #[arg(long)]
+        no_system: bool,
+    },
+
+    /// Read a conversation in the terminal
+    Show {
+        /// Session id (or a unique prefix of one)
+        session_id: String,
+
+        /// Start with tool output and thinking unfolded
+        #[arg(short, long)]
+        expand: bool,
+
+        /// Print the whole transcript instead of paging through it
+        #[arg(long)]
+        no_pager: bool,
+    },
+
+    /// Summarize token usage, messages and sessions
+    Stats {
+        /// Group by any of: project, day, month, machine, model
+        /// (comma-separated, in column order)
+        #[arg(short = 'b', long, value_delimiter = ',', default_value = "project")]
+        by: Vec<String>,
+
+        /// Summarize every session in the sync repository instead of local
+        /// history
+        #[arg(long)]
+        repo: bool,
+
+        /// Only sessions whose project name contains this
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Only messages on or after this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only messages on or before this date (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Output format: table, csv or json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+
+        /// Report tool calls instead: calls, error rates and time per tool,
+        /// the most common shell commands and the most tool-heavy sessions
+        #[arg(long, conflicts_with = "by")]
+        tools: bool,
+
+        /// With --tools, commands per project and sessions to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Inspect and manage transcripts quarantined because they could not be parsed
+    Quarantine {
+        #[command(subcommand)]
+        action: QuarantineAction,
+    },
+
+    /// Static HTML site of the sync repository
+    Site {
+        #[command(subcommand)]
+        action: SiteAction,
+    },
+
+    /// End-to-end encryption of the sync repository
+    Encryption {
+        #[command(subcommand)]
+        action: EncryptionAction,
+    },
+
+    /// Check the sync repository and its history for denied files and secrets
+    Audit {
+        /// Only check the working tree, not past commits
+        #[arg(long)]
+        no_history: bool,
+
+        /// Write the paths to remove from history to FILE, for git filter-repo
+        /// (or an hg convert filemap)
+        #[arg(long, value_name = "FILE")]
+        purge_plan: Option<PathBuf>,
+    },
+
     /// Clean up old snapshot files
     CleanupSnapshots {
         /// Show what would be deleted without actually deleting
@@ -333,6 +520,93 @@ enum HistoryAction {
     Clear,
 }
 
+#[derive(Subcommand)]
+enum SiteAction {
+    /// Build or update an offline-browsable site of every synced session
+    Build {
+        /// Directory to write the site to
+        out_dir: PathBuf,
+
+        /// Render every session again, not only those changed since the
+        /// last build
+        #[arg(long)]
+        full: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum EncryptionAction {
+    /// Encrypt the sync repository, or unlock one that is already encrypted
+    /// on this machine
+    Enable {
+        /// Key file to encrypt to (created if missing; default:
+        /// encryption-key.txt in the config directory)
+        #[arg(long, conflicts_with = "passphrase")]
+        key_file: Option<PathBuf>,
+
+        /// Use a passphrase instead of a key file
+        #[arg(long)]
+        passphrase: bool,
+    },
+
+    /// Show whether the sync repository is encrypted and how this machine
+    /// opens it
+    Status,
+
+    /// Let another public key read the repository, or only one project
+    AddRecipient {
+        /// Public key (age1...) to add
+        key: String,
+
+        /// Restrict the key to this project directory's own recipient list
+        #[arg(short, long, allow_hyphen_values = true)]
+        project: Option<String>,
+    },
+
+    /// Stop encrypting to a public key, for the repository or one project
+    RemoveRecipient {
+        /// Public key (age1...) to remove
+        key: String,
+
+        /// Remove the key from this project directory's own recipient list
+        #[arg(short, long, allow_hyphen_values = true)]
+        project: Option<String>,
+    },
+
+    /// Accept recipients changed on another machine, so push encrypts to
+    /// them
+    Trust,
+}
+
+#[derive(Subcommand)]
+enum QuarantineAction {
+    /// List quarantined transcripts
+    List,
+
+    /// Show details and the first lines of a quarantined transcript
+    Show {
+        /// Quarantine id (from `quarantine list`)
+        id: String,
+    },
+
+    /// Re-parse quarantined transcripts and release those that parse now
+    Retry {
+        /// Quarantine id (default: retry all)
+        id: Option<String>,
+    },
+
+    /// Delete quarantined copies (the original transcripts are not touched)
+    Discard {
+        /// Quarantine id
+        #[arg(required_unless_present = "all")]
+        id: Option<String>,
+
+        /// Discard every quarantined transcript
+        #[arg(long, conflicts_with = "id")]
+        all: bool,
+    },
+}
+
 fn main() -> Result<()> {
     // Initialize logging (rotate log if needed, then set up logger)
     logger::rotate_log_if_needed().ok(); // Ignore errors during log rotation
@@ -653,6 +927,140 @@ fn main() -> Result<()> {
                 handle_history_clear()?;
             }
         },
+        Commands::Search {
+            query,
+            project,
+            since,
+            until,
+            branch,
+            role,
+            limit,
+            reindex,
+        } => {
+            let query = search::SearchQuery {
+                text: search::query::join_args(&query),
+                project,
+                since: since.as_deref().map(search::parse_date).transpose()?,
+                until: until.as_deref().map(search::parse_date).transpose()?,
+                branch,
+                role: role.as_deref().map(str::parse).transpose()?,
+            };
+            handle_search(&query, limit, reindex)?;
+        }
+        Commands::Export {
+            session_id,
+            format,
+            output,
+            all_branches,
+        } => {
+            handle_export(
+                &session_id,
+                format.as_deref().map(str::parse).transpose()?,
+                output.as_deref(),
+                transcript::TranscriptOptions { all_branches },
+            )?;
+        }
+        Commands::Dataset {
+            session_ids,
+            format,
+            output,
+            project,
+            since,
+            until,
+            subagents,
+            no_thinking,
+            no_tool_results,
+            no_system,
+        } => {
+            let selection = DatasetSelection {
+                session_ids,
+                project,
+                since: since.as_deref().map(search::parse_date).transpose()?,
+                until: until.as_deref().map(search::parse_date).transpose()?,
+                subagents,
+            };
+            let options = transcript::dataset::DatasetOptions {
+                drop_thinking: no_thinking,
+                drop_tool_results: no_tool_results,
+                drop_system: no_system,
+            };
+            handle_dataset(&selection, format.parse()?, options, output.as_deref())?;
+        }
+        Commands::Show {
+            session_id,
+            expand,
+            no_pager,
+        } => {
+            handle_show(&session_id, expand, no_pager)?;
+        }
+        Commands::Stats {
+            by,
+            repo,
+            project,
+            since,
+            until,
+            format,
+            tools,
+            limit,
+        } => {
+            let query = stats::StatsQuery {
+                group_by: by.iter().map(|d| d.parse()).collect::<Result<_>>()?,
+                project,
+                since: since.as_deref().map(search::parse_date).transpose()?,
+                until: until.as_deref().map(search::parse_date).transpose()?,
+                ..Default::default()
+            };
+            if tools {
+                handle_tool_stats(&query, repo, format.parse()?, limit)?;
+            } else {
+                handle_stats(&query, repo, format.parse()?)?;
+            }
+        }
+        Commands::Site { action } => match action {
+            SiteAction::Build { out_dir, full } => {
+                handle_site_build(&out_dir, full)?;
+            }
+        },
+        Commands::Encryption { action } => match action {
+            EncryptionAction::Enable {
+                key_file,
+                passphrase,
+            } => {
+                handle_encryption_enable(key_file.as_deref(), passphrase)?;
+            }
+            EncryptionAction::Status => {
+                handle_encryption_status()?;
+            }
+            EncryptionAction::AddRecipient { key, project } => {
+                handle_encryption_recipient(&key, project.as_deref(), true)?;
+            }
+            EncryptionAction::RemoveRecipient { key, project } => {
+                handle_encryption_recipient(&key, project.as_deref(), false)?;
+            }
+            EncryptionAction::Trust => {
+                handle_encryption_trust()?;
+            }
+        },
+        Commands::Audit {
+            no_history,
+            purge_plan,
+        } => {
+            handle_audit(!no_history, purge_plan.as_deref())?;
+        }
+        Commands::Quarantine { action } => match action {
+            QuarantineAction::List => {
+                handle_quarantine_list()?;
+            }
+            QuarantineAction::Show { id } => {
+                handle_quarantine_show(&id)?;
+            }
+            QuarantineAction::Retry { id } => {
+                handle_quarantine_retry(id.as_deref())?;
+            }
+            QuarantineAction::Discard { id, all } => {
+                handle_quarantine_discard(id.as_deref(), all)?;
+            }
+        },
         Commands::CleanupSnapshots {
             dry_run,
             max_count,
@@ -0,0 +1,567 @@
+//! Typed view of `ConversationEntry.message`.
+//!
+//! Claude Code stores each message as an Anthropic API message object:
+//!
+//! ```json
+//! {"role":"assistant","model":"claude-...","content":[
+//!   {"type":"thinking","thinking":"...","signature":"..."},
+//!   {"type":"text","text":"..."},
+//!   {"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"ls"}}
+//! ],"usage":{"input_tokens":10,"output_tokens":5}}
+//! ```
+//!
+//! [`Message`] exposes the parts features need (text, thinking, tool calls and
+//! results, images, model and token usage) without giving up fidelity. The
+//! conversion is lossless by construction: a field is only lifted into a typed
+//! slot when it has exactly the expected shape, everything else stays in an
+//! `extra` map, and blocks of an unknown type or unexpected shape are kept
+//! verbatim as [`ContentBlock::Other`]. Converting back with
+//! [`Message::to_value`] therefore reproduces the original JSON, so a session
+//! rewritten through the typed model hashes and writes identically.
+
+use serde_json::{Map, Value};
+
+use crate::parser::ConversationEntry;
+
+/// A message object with typed access to its well-known fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Author role ("user" or "assistant")
+    pub role: Option<String>,
+
+    /// Model that produced an assistant message
+    pub model: Option<String>,
+
+    /// Message body: a plain string or a list of content blocks
+    pub content: Option<MessageContent>,
+
+    /// Token accounting reported for an assistant message
+    pub usage: Option<Usage>,
+
+    /// Every other field (id, stop_reason, ...), preserved as-is
+    pub extra: Map<String, Value>,
+}
+
+/// Message (or tool result) content
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageContent {
+    /// Plain string content, as typed user prompts are stored
+    Text(String),
+    /// Structured content blocks
+    Blocks(Vec<ContentBlock>),
+}
+
+/// One block of structured message content
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentBlock {
+    Text(TextBlock),
+    Thinking(ThinkingBlock),
+    ToolUse(ToolUseBlock),
+    ToolResult(ToolResultBlock),
+    Image(ImageBlock),
+    /// A block of unknown type or unexpected shape, kept verbatim
+    Other(Value),
+}
+
+/// `{"type":"text","text":...}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBlock {
+    pub text: String,
+    pub extra: Map<String, Value>,
+}
+
+/// `{"type":"thinking","thinking":...}`; the signature stays in `extra`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThinkingBlock {
+    pub thinking: String,
+    pub extra: Map<String, Value>,
+}
+
+/// `{"type":"tool_use","id":...,"name":...,"input":{...}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolUseBlock {
+    pub id: String,
+    pub name: String,
+    pub input: Value,
+    pub extra: Map<String, Value>,
+}
+
+/// `{"type":"tool_result","tool_use_id":...,"content":...,"is_error":...}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResultBlock {
+    pub tool_use_id: String,
+    pub content: Option<MessageContent>,
+    pub is_error: Option<bool>,
+    pub extra: Map<String, Value>,
+}
+
+/// `{"type":"image","source":{...}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageBlock {
+    /// Image source (base64 data or URL), left untyped
+    pub source: Value,
+    pub extra: Map<String, Value>,
+}
+
+/// Token usage of an assistant message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub cache_creation_input_tokens: Option<u64>,
+    pub cache_read_input_tokens: Option<u64>,
+    /// service_tier, server_tool_use, cache_creation breakdown, ...
+    pub extra: Map<String, Value>,
+}
+
+impl Message {
+    /// Build the typed view of a message value. Returns `None` if the value is
+    /// not a JSON object.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let mut map = value.as_object()?.clone();
+
+        let content = match map.get("content") {
+            Some(Value::String(_)) | Some(Value::Array(_)) => {
+                map.remove("content").and_then(MessageContent::from_value)
+            }
+            _ => None,
+        };
+        let usage = match map.get("usage") {
+            Some(Value::Object(_)) => map.remove("usage").and_then(Usage::from_value),
+            _ => None,
+        };
+
+        Some(Message {
+            role: take_string(&mut map, "role"),
+            model: take_string(&mut map, "model"),
+            content,
+            usage,
+            extra: map,
+        })
+    }
+
+    /// Convert back into the JSON value stored on the entry
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        put_string(&mut map, "role", &self.role);
+        put_string(&mut map, "model", &self.model);
+        if let Some(content) = &self.content {
+            map.insert("content".to_string(), content.to_value());
+        }
+        if let Some(usage) = &self.usage {
+            map.insert("usage".to_string(), usage.to_value());
+        }
+        Value::Object(map)
+    }
+
+    /// Content blocks, or an empty slice for plain-string content
+    pub fn blocks(&self) -> &[ContentBlock] {
+        match &self.content {
+            Some(MessageContent::Blocks(blocks)) => blocks,
+            _ => &[],
+        }
+    }
+
+    /// Visible text of the message: the string content, or all text blocks
+    /// joined by blank lines. Thinking and tool blocks are not included.
+    pub fn text(&self) -> String {
+        self.content
+            .as_ref()
+            .map(MessageContent::text)
+            .unwrap_or_default()
+    }
+
+    /// Tool calls made by this message
+    pub fn tool_uses(&self) -> impl Iterator<Item = &ToolUseBlock> {
+        self.blocks().iter().filter_map(|block| match block {
+            ContentBlock::ToolUse(tool_use) => Some(tool_use),
+            _ => None,
+        })
+    }
+
+    /// Tool results carried by this message
+    pub fn tool_results(&self) -> impl Iterator<Item = &ToolResultBlock> {
+        self.blocks().iter().filter_map(|block| match block {
+            ContentBlock::ToolResult(result) => Some(result),
+            _ => None,
+        })
+    }
+}
+
+impl MessageContent {
+    /// Type a string or array content value; `None` for any other shape
+    pub fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(text) => Some(MessageContent::Text(text)),
+            Value::Array(items) => Some(MessageContent::Blocks(
+                items.into_iter().map(ContentBlock::from_value).collect(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Convert back into the content's JSON value
+    pub fn to_value(&self) -> Value {
+        match self {
+            MessageContent::Text(text) => Value::String(text.clone()),
+            MessageContent::Blocks(blocks) => {
+                Value::Array(blocks.iter().map(ContentBlock::to_value).collect())
+            }
+        }
+    }
+
+    /// Plain text of the content: the string itself, or the text blocks
+    /// joined by blank lines
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }
+    }
+}
+
+impl ContentBlock {
+    /// Type the block if it has a known `type` and the expected fields;
+    /// otherwise keep it as [`ContentBlock::Other`].
+    pub fn from_value(value: Value) -> Self {
+        let Value::Object(mut map) = value else {
+            return ContentBlock::Other(value);
+        };
+        let block_type = map.get("type").and_then(Value::as_str).map(str::to_string);
+
+        let typed = match block_type.as_deref() {
+            Some("text") if map.get("text").is_some_and(Value::is_string) => {
+                map.remove("type");
+                take_string(&mut map, "text").map(|text| {
+                    ContentBlock::Text(TextBlock {
+                        text,
+                        extra: std::mem::take(&mut map),
+                    })
+                })
+            }
+            Some("thinking") if map.get("thinking").is_some_and(Value::is_string) => {
+                map.remove("type");
+                take_string(&mut map, "thinking").map(|thinking| {
+                    ContentBlock::Thinking(ThinkingBlock {
+                        thinking,
+                        extra: std::mem::take(&mut map),
+                    })
+                })
+            }
+            Some("tool_use")
+                if map.get("id").is_some_and(Value::is_string)
+                    && map.get("name").is_some_and(Value::is_string)
+                    && map.contains_key("input") =>
+            {
+                map.remove("type");
+                let id = take_string(&mut map, "id").unwrap_or_default();
+                let name = take_string(&mut map, "name").unwrap_or_default();
+                let input = map.remove("input").unwrap_or_default();
+                Some(ContentBlock::ToolUse(ToolUseBlock {
+                    id,
+                    name,
+                    input,
+                    extra: std::mem::take(&mut map),
+                }))
+            }
+            Some("tool_result") if map.get("tool_use_id").is_some_and(Value::is_string) => {
+                map.remove("type");
+                let tool_use_id = take_string(&mut map, "tool_use_id").unwrap_or_default();
+                let content = match map.get("content") {
+                    Some(Value::String(_)) | Some(Value::Array(_)) => {
+                        map.remove("content").and_then(MessageContent::from_value)
+                    }
+                    _ => None,
+                };
+                let is_error = match map.get("is_error") {
+                    Some(Value::Bool(b)) => {
+                        let b = *b;
+                        map.remove("is_error");
+                        Some(b)
+                    }
+                    _ => None,
+                };
+                Some(ContentBlock::ToolResult(ToolResultBlock {
+                    tool_use_id,
+                    content,
+                    is_error,
+                    extra: std::mem::take(&mut map),
+                }))
+            }
+            Some("image") if map.contains_key("source") => {
+                map.remove("type");
+                let source = map.remove("source").unwrap_or_default();
+                Some(ContentBlock::Image(ImageBlock {
+                    source,
+                    extra: std::mem::take(&mut map),
+                }))
+            }
+            _ => None,
+        };
+
+        typed.unwrap_or(ContentBlock::Other(Value::Object(map)))
+    }
+
+    /// Convert back into the block's JSON value
+    pub fn to_value(&self) -> Value {
+        let (block_type, mut map) = match self {
+            ContentBlock::Other(value) => return value.clone(),
+            ContentBlock::Text(block) => {
+                let mut map = block.extra.clone();
+                map.insert("text".to_string(), Value::String(block.text.clone()));
+                ("text", map)
+            }
+            ContentBlock::Thinking(block) => {
+                let mut map = block.extra.clone();
+                map.insert(
+                    "thinking".to_string(),
+                    Value::String(block.thinking.clone()),
+                );
+                ("thinking", map)
+            }
+            ContentBlock::ToolUse(block) => {
+                let mut map = block.extra.clone();
+                map.insert("id".to_string(), Value::String(block.id.clone()));
+                map.insert("name".to_string(), Value::String(block.name.clone()));
+                map.insert("input".to_string(), block.input.clone());
+                ("tool_use", map)
+            }
+            ContentBlock::ToolResult(block) => {
+                let mut map = block.extra.clone();
+                map.insert(
+                    "tool_use_id".to_string(),
+                    Value::String(block.tool_use_id.clone()),
+                );
+                if let Some(content) = &block.content {
+                    map.insert("content".to_string(), content.to_value());
+                }
+                if let Some(is_error) = block.is_error {
+                    map.insert("is_error".to_string(), Value::Bool(is_error));
+                }
+                ("tool_result", map)
+            }
+            ContentBlock::Image(block) => {
+                let mut map = block.extra.clone();
+                map.insert("source".to_string(), block.source.clone());
+                ("image", map)
+            }
+        };
+        map.insert("type".to_string(), Value::String(block_type.to_string()));
+        Value::Object(map)
+    }
+}
+
+impl ToolResultBlock {
+    /// Text of the result (string content or its text blocks)
+    pub fn text(&self) -> String {
+        self.content
+            .as_ref()
+            .map(MessageContent::text)
+            .unwrap_or_default()
+    }
+}
+
+impl Usage {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Object(mut map) = value else {
+            return None;
+        };
+        Some(Usage {
+            input_tokens: take_u64(&mut map, "input_tokens"),
+            output_tokens: take_u64(&mut map, "output_tokens"),
+            cache_creation_input_tokens: take_u64(&mut map, "cache_creation_input_tokens"),
+            cache_read_input_tokens: take_u64(&mut map, "cache_read_input_tokens"),
+            extra: map,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        for (key, tokens) in [
+            ("input_tokens", self.input_tokens),
+            ("output_tokens", self.output_tokens),
+            (
+                "cache_creation_input_tokens",
+                self.cache_creation_input_tokens,
+            ),
+            ("cache_read_input_tokens", self.cache_read_input_tokens),
+        ] {
+            if let Some(tokens) = tokens {
+                map.insert(key.to_string(), Value::from(tokens));
+            }
+        }
+        Value::Object(map)
+    }
+}
+
+impl ConversationEntry {
+    /// Typed view of this entry's `message`, if it is a JSON object
+    pub fn typed_message(&self) -> Option<Message> {
+        self.message.as_ref().and_then(Message::from_value)
+    }
+
+    /// Replace this entry's `message` with a typed message
+    pub fn set_typed_message(&mut self, message: &Message) {
+        self.message = Some(message.to_value());
+    }
+}
+
+/// Remove `key` from `map` if it holds a string
+fn take_string(map: &mut Map<String, Value>, key: &str) -> Option<String> {
+    match map.get(key) {
+        Some(Value::String(_)) => match map.remove(key) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Remove `key` from `map` if it holds an unsigned integer
+fn take_u64(map: &mut Map<String, Value>, key: &str) -> Option<u64> {
+    let n = map.get(key).filter(|v| v.is_u64())?.as_u64();
+    map.remove(key);
+    n
+}
+
+fn put_string(map: &mut Map<String, Value>, key: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        map.insert(key.to_string(), Value::String(value.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ConversationSession;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn assistant_message() -> Value {
+        json!({
+            "id": "msg_01",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "stop_reason": null,
+            "content": [
+                {"type": "thinking", "thinking": "Let me look.", "signature": "sig"},
+                {"type": "text", "text": "Listing files."},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}},
+                {"type": "server_tool_use", "id": "srv_1", "name": "web_search"},
+                {"type": "text", "text": 42}
+            ],
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 5,
+                "cache_read_input_tokens": 100,
+                "cache_creation": {"ephemeral_5m_input_tokens": 0},
+                "service_tier": "standard"
+            }
+        })
+    }
+
+    #[test]
+    fn test_typed_view_of_assistant_message() {
+        let message = Message::from_value(&assistant_message()).unwrap();
+
+        assert_eq!(message.role.as_deref(), Some("assistant"));
+        assert_eq!(message.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(message.text(), "Listing files.");
+        assert_eq!(message.blocks().len(), 5);
+        assert!(matches!(message.blocks()[0], ContentBlock::Thinking(_)));
+        // Unknown block types and malformed known ones are kept verbatim
+        assert!(matches!(message.blocks()[3], ContentBlock::Other(_)));
+        assert!(matches!(message.blocks()[4], ContentBlock::Other(_)));
+
+        let tool_use = message.tool_uses().next().unwrap();
+        assert_eq!(tool_use.name, "Bash");
+        assert_eq!(tool_use.input["command"], "ls");
+
+        let usage = message.usage.as_ref().unwrap();
+        assert_eq!(usage.input_tokens, Some(10));
+        assert_eq!(usage.output_tokens, Some(5));
+        assert_eq!(usage.cache_read_input_tokens, Some(100));
+        assert_eq!(usage.cache_creation_input_tokens, None);
+        assert!(message.extra.contains_key("stop_reason"));
+    }
+
+    #[test]
+    fn test_tool_result_and_image_blocks() {
+        let value = json!({
+            "role": "user",
+            "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "is_error": true,
+                 "content": [{"type": "text", "text": "permission denied"}]},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+            ]
+        });
+        let message = Message::from_value(&value).unwrap();
+
+        let result = message.tool_results().next().unwrap();
+        assert_eq!(result.tool_use_id, "toolu_1");
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.text(), "permission denied");
+        assert!(matches!(message.blocks()[1], ContentBlock::Image(_)));
+        assert_eq!(message.to_value(), value);
+    }
+
+    #[test]
+    fn test_plain_string_content() {
+        let value = json!({"role": "user", "content": "hello"});
+        let message = Message::from_value(&value).unwrap();
+        assert_eq!(message.text(), "hello");
+        assert!(message.blocks().is_empty());
+        assert_eq!(message.to_value(), value);
+
+        assert!(Message::from_value(&json!("not an object")).is_none());
+    }
+
+    #[test]
+    fn test_round_trip_through_write_to_file_is_lossless() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("session.jsonl");
+        let mut file = std::fs::File::create(&source).unwrap();
+        for (uuid, message) in [
+            ("1", json!({"role": "user", "content": "hi"})),
+            ("2", assistant_message()),
+            (
+                "3",
+                json!({"role": "user", "content": [{"type": "tool_result", "tool_use_id": "toolu_1", "content": "a\nb"}]}),
+            ),
+            (
+                "4",
+                json!({"role": null, "content": {"odd": true}, "usage": 7}),
+            ),
+        ] {
+            let entry = json!({"type": "user", "uuid": uuid, "message": message});
+            writeln!(file, "{entry}").unwrap();
+        }
+        drop(file);
+
+        let original = ConversationSession::from_file(&source).unwrap();
+        let mut rewritten = original.clone();
+        for entry in &mut rewritten.entries {
+            let typed = entry.typed_message().unwrap();
+            entry.set_typed_message(&typed);
+        }
+
+        let original_path = temp_dir.path().join("original.jsonl");
+        let rewritten_path = temp_dir.path().join("rewritten.jsonl");
+        original.write_to_file(&original_path).unwrap();
+        rewritten.write_to_file(&rewritten_path).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&original_path).unwrap(),
+            std::fs::read_to_string(&rewritten_path).unwrap()
+        );
+        assert_eq!(original.content_hash(), rewritten.content_hash());
+    }
+}
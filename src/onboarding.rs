@@ -6,6 +6,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::config::ConfigManager;
+use crate::encryption::KeySource;
 
 /// Configuration file for non-interactive initialization.
 ///
@@ -44,6 +45,14 @@ use crate::config::ConfigManager;
 /// # Optional: Use only project name for multi-device sync (default: false)
 /// use_project_name_only = true
 ///
+/// # Optional: Encrypt the sync repository - "key-file" or "passphrase".
+/// # Passphrase mode reads CLAUDE_CODE_SYNC_PASSPHRASE.
+/// encryption = "key-file"
+///
+/// # Optional: Key file for encryption = "key-file"
+/// # (default: encryption-key.txt in the config directory; created if missing)
+/// encryption_key_file = "~/.config/claude-code-sync/encryption-key.txt"
+///
 /// # Optional: Artifact categories to sync (all default to false)
 /// [sync_artifacts]
 /// settings = true
@@ -92,6 +101,14 @@ pub struct InitConfig {
     /// Missing table means every category stays off.
     #[serde(default)]
     pub sync_artifacts: crate::artifacts::registry::ArtifactToggles,
+
+    /// Encrypt the sync repository: "key-file" or "passphrase".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<String>,
+
+    /// Key file for `encryption = "key-file"` (created if missing).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key_file: Option<String>,
 }
 
 fn default_scm_backend() -> String {
@@ -192,12 +209,44 @@ impl InitConfig {
             ));
         }
 
+        // Validate encryption mode
+        match self.encryption.as_deref() {
+            None | Some("key-file") => {}
+            Some("passphrase") if self.encryption_key_file.is_none() => {}
+            Some("passphrase") => {
+                return Err(anyhow::anyhow!(
+                    "encryption_key_file cannot be used with encryption = 'passphrase'"
+                ));
+            }
+            Some(other) => {
+                return Err(anyhow::anyhow!(
+                    "Invalid encryption '{}'. Use 'key-file' or 'passphrase'.",
+                    other
+                ));
+            }
+        }
+        if self.encryption.is_none() && self.encryption_key_file.is_some() {
+            return Err(anyhow::anyhow!(
+                "encryption_key_file requires encryption = 'key-file'"
+            ));
+        }
+
         Ok(())
     }
 
     /// Convert to OnboardingConfig for use with existing initialization flow.
     pub fn to_onboarding_config(&self) -> Result<OnboardingConfig> {
         let repo_path = expand_tilde(&self.repo_path)?;
+        let encryption = match self.encryption.as_deref() {
+            None => None,
+            Some("passphrase") => {
+                Some(KeySource::Passphrase(crate::encryption::passphrase(false)?))
+            }
+            Some(_) => Some(KeySource::KeyFile(match &self.encryption_key_file {
+                Some(path) => expand_tilde(path)?,
+                None => ConfigManager::default_key_file_path()?,
+            })),
+        };
 
         Ok(OnboardingConfig {
             repo_path,
@@ -206,6 +255,7 @@ impl InitConfig {
             exclude_attachments: self.exclude_attachments,
             exclude_older_than_days: self.exclude_older_than_days,
             sync_artifacts: self.sync_artifacts.clone(),
+            encryption,
         })
     }
 }
@@ -301,6 +351,13 @@ pub struct OnboardingConfig {
     /// Which artifact categories (settings, skills, agents, ...) to sync
     /// alongside conversation history.
     pub sync_artifacts: crate::artifacts::registry::ArtifactToggles,
+
+    /// How this machine encrypts the sync repository, or unlocks one that
+    /// is already encrypted.
+    ///
+    /// - `Some(_)`: Set up encryption once the repository is in place
+    /// - `None`: Keep the repository in plaintext
+    pub encryption: Option<KeySource>,
 }
 
 /// Run the interactive onboarding flow
@@ -428,6 +485,9 @@ pub fn run_onboarding() -> Result<OnboardingConfig> {
     // Step 3: Artifact sync (settings, skills, agents, ...)
     let sync_artifacts = prompt_artifact_categories()?;
 
+    // Step 4: Encryption
+    let encryption = prompt_encryption()?;
+
     println!();
     println!("{}", "✓ Configuration complete!".green().bold());
 
@@ -438,6 +498,7 @@ pub fn run_onboarding() -> Result<OnboardingConfig> {
         exclude_attachments,
         exclude_older_than_days,
         sync_artifacts,
+        encryption,
     })
 }
 
@@ -474,6 +535,64 @@ fn prompt_artifact_categories() -> Result<crate::artifacts::registry::ArtifactTo
     Ok(toggles)
 }
 
+/// Encryption option for onboarding
+#[derive(Debug, Clone)]
+enum EncryptionChoice {
+    None,
+    Passphrase,
+    KeyFile,
+}
+
+impl std::fmt::Display for EncryptionChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncryptionChoice::None => write!(f, "No encryption"),
+            EncryptionChoice::Passphrase => {
+                write!(f, "Passphrase (asked for on push and pull)")
+            }
+            EncryptionChoice::KeyFile => {
+                write!(f, "Key file (kept on this machine, copied to the others)")
+            }
+        }
+    }
+}
+
+/// Ask whether to encrypt the sync repository, or how to unlock one that
+/// is already encrypted
+fn prompt_encryption() -> Result<Option<KeySource>> {
+    let choice = Select::new(
+        "Encrypt conversations in the sync repository?",
+        vec![
+            EncryptionChoice::None,
+            EncryptionChoice::Passphrase,
+            EncryptionChoice::KeyFile,
+        ],
+    )
+    .without_filtering()
+    .with_help_message(
+        "Encrypted repositories can live on a host you don't trust with your \
+         conversations. Pick the same method to unlock an already encrypted one.",
+    )
+    .prompt()
+    .context("Failed to get encryption preference")?;
+
+    match choice {
+        EncryptionChoice::None => Ok(None),
+        EncryptionChoice::Passphrase => Ok(Some(KeySource::Passphrase(
+            crate::encryption::passphrase(true)?,
+        ))),
+        EncryptionChoice::KeyFile => {
+            let default_path = ConfigManager::default_key_file_path()?;
+            let path = Text::new("Key file path:")
+                .with_default(&default_path.display().to_string())
+                .with_help_message("Created if missing; copy an existing key here to join")
+                .prompt()
+                .context("Failed to get key file path")?;
+            Ok(Some(KeySource::KeyFile(expand_tilde(&path)?)))
+        }
+    }
+}
+
 /// Validate git URL format
 fn is_valid_git_url(url: &str) -> bool {
     url.starts_with("https://")
@@ -581,6 +700,8 @@ mod tests {
             sync_subdirectory: "projects".to_string(),
             use_project_name_only: false,
             sync_artifacts: Default::default(),
+            encryption: None,
+            encryption_key_file: None,
         };
         assert!(config.validate().is_err());
     }
@@ -598,6 +719,8 @@ mod tests {
             sync_subdirectory: "projects".to_string(),
             use_project_name_only: false,
             sync_artifacts: Default::default(),
+            encryption: None,
+            encryption_key_file: None,
         };
         assert!(config.validate().is_err());
     }
@@ -615,6 +738,8 @@ mod tests {
             sync_subdirectory: "projects".to_string(),
             use_project_name_only: false,
             sync_artifacts: Default::default(),
+            encryption: None,
+            encryption_key_file: None,
         };
         assert!(config.validate().is_err());
     }
@@ -632,6 +757,8 @@ mod tests {
             sync_subdirectory: "projects".to_string(),
             use_project_name_only: false,
             sync_artifacts: Default::default(),
+            encryption: None,
+            encryption_key_file: None,
         };
         let onboarding = config.to_onboarding_config().unwrap();
         assert_eq!(onboarding.repo_path, PathBuf::from("/tmp/test"));
@@ -5,6 +5,8 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 
+use crate::canonical::CanonicalHasher;
+
 /// Represents a single line/entry in the JSONL conversation file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationEntry {
@@ -111,33 +113,460 @@ pub struct ConversationSession {
     pub file_path: String,
 }
 
-impl ConversationSession {
-    /// Parse a JSONL file into a ConversationSession
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+/// How malformed lines in a JSONL transcript are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseMode {
+    /// Any malformed line fails the whole file
+    Strict,
+    /// Malformed lines are skipped and recorded; every valid line is kept.
+    ///
+    /// Claude Code appends to transcripts while it runs, so a sync that races a
+    /// write routinely sees a truncated last line.
+    Lenient,
+}
+
+/// A line dropped while parsing a transcript in [`ParseMode::Lenient`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLine {
+    /// 1-based line number in the file
+    pub line: usize,
+
+    /// Why the line could not be used (parser error message)
+    pub reason: String,
+}
+
+/// Streaming reader over the entries of a JSONL conversation file.
+///
+/// Yields one parsed [`ConversationEntry`] per non-empty line without holding
+/// the rest of the file in memory, so callers that only need aggregates
+/// (counts, timestamps, hashes) never materialize the whole transcript.
+///
+/// In [`ParseMode::Lenient`] malformed lines are not yielded; they are
+/// collected and available from [`EntryStream::skipped_lines`].
+pub struct EntryStream {
+    lines: std::io::Lines<BufReader<File>>,
+    line_num: usize,
+    path: std::path::PathBuf,
+    mode: ParseMode,
+    skipped: Vec<SkippedLine>,
+}
+
+impl EntryStream {
+    /// Open a JSONL file for strict streaming
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_mode(path, ParseMode::Strict)
+    }
+
+    /// Open a JSONL file for streaming in the given parse mode
+    pub fn open_with_mode<P: AsRef<Path>>(path: P, mode: ParseMode) -> Result<Self> {
         let path = path.as_ref();
         let file =
             File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
 
-        let reader = BufReader::new(file);
-        let mut entries = Vec::new();
-        let mut entry_session_id = None;
+        Ok(EntryStream {
+            lines: BufReader::new(file).lines(),
+            line_num: 0,
+            path: path.to_path_buf(),
+            mode,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Lines skipped so far (always empty in strict mode)
+    pub fn skipped_lines(&self) -> &[SkippedLine] {
+        &self.skipped
+    }
 
-        for (line_num, line) in reader.lines().enumerate() {
-            let line = line.with_context(|| {
-                format!("Failed to read line {} in {}", line_num + 1, path.display())
-            })?;
+    /// Consume the stream, returning the lines it skipped
+    pub fn into_skipped_lines(self) -> Vec<SkippedLine> {
+        self.skipped
+    }
+
+    fn skip(&mut self, reason: String) {
+        log::debug!(
+            "Skipping line {} in {}: {}",
+            self.line_num,
+            self.path.display(),
+            reason
+        );
+        self.skipped.push(SkippedLine {
+            line: self.line_num,
+            reason,
+        });
+    }
+}
+
+impl Iterator for EntryStream {
+    type Item = Result<ConversationEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            self.line_num += 1;
+
+            let line = match line {
+                Ok(line) => line,
+                // Invalid UTF-8 only spoils this line; the reader has moved past it.
+                Err(e)
+                    if self.mode == ParseMode::Lenient
+                        && e.kind() == std::io::ErrorKind::InvalidData =>
+                {
+                    self.skip(e.to_string());
+                    continue;
+                }
+                Err(e) => {
+                    return Some(Err(anyhow::Error::new(e).context(format!(
+                        "Failed to read line {} in {}",
+                        self.line_num,
+                        self.path.display()
+                    ))))
+                }
+            };
 
             if line.trim().is_empty() {
                 continue;
             }
 
-            let entry: ConversationEntry = serde_json::from_str(&line).with_context(|| {
-                format!(
-                    "Failed to parse JSON at line {} in {}",
-                    line_num + 1,
-                    path.display()
-                )
-            })?;
+            match serde_json::from_str(&line) {
+                Ok(entry) => return Some(Ok(entry)),
+                Err(e) if self.mode == ParseMode::Lenient => {
+                    self.skip(e.to_string());
+                    continue;
+                }
+                Err(e) => {
+                    return Some(Err(anyhow::Error::new(e).context(format!(
+                        "Failed to parse JSON at line {} in {}",
+                        self.line_num,
+                        self.path.display()
+                    ))))
+                }
+            }
+        }
+    }
+}
+
+/// Incremental content hasher shared by [`ConversationSession::content_hash`]
+/// and [`SessionSummary`], so a streamed hash always equals the in-memory one.
+///
+/// Entries are hashed in canonical JSON form (see [`crate::canonical`]), so
+/// copies that differ only in key order, whitespace or number formatting hash
+/// the same.
+struct EntryHasher(CanonicalHasher);
+
+impl EntryHasher {
+    fn new() -> Self {
+        EntryHasher(CanonicalHasher::new())
+    }
+
+    fn update(&mut self, entry: &ConversationEntry) {
+        if let Ok(value) = serde_json::to_value(entry) {
+            self.0.update(&value);
+        }
+    }
+
+    fn finish(self) -> String {
+        self.0.finish()
+    }
+}
+
+/// Resolve a transcript's identity from its path, falling back to the first
+/// interior `sessionId` only when the path has no usable stem.
+fn session_identity(path: &Path, entry_session_id: Option<String>) -> Result<String> {
+    // Identity is the filename stem, which Claude Code guarantees is unique per
+    // transcript: `<uuid>.jsonl` for a session and `agent-<hash>.jsonl` for each
+    // of its subagents. The interior `sessionId` field is NOT unique — subagent
+    // sidechain transcripts carry their *parent* session's id, so keying identity
+    // on it collapses every subagent onto the parent and makes sync drop the
+    // parent's main transcript. Fall back to the interior id only if the path has
+    // no usable stem.
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string())
+        .or(entry_session_id)
+        .with_context(|| {
+            format!(
+                "No session ID found in file or filename: {}",
+                path.display()
+            )
+        })
+}
+
+/// True if a transcript identity names a subagent sidechain (`agent-<hash>`)
+pub fn is_sidechain_id(session_id: &str) -> bool {
+    session_id.starts_with("agent-")
+}
+
+/// Parent session of a subagent sidechain transcript.
+///
+/// Every entry of a sidechain carries its parent's `sessionId`, which is what
+/// ties the two into one sync unit. Regular sessions have no parent.
+fn sidechain_parent<'a>(session_id: &str, entry_session_id: Option<&'a str>) -> Option<&'a str> {
+    if !is_sidechain_id(session_id) {
+        return None;
+    }
+    entry_session_id.filter(|parent| *parent != session_id)
+}
+
+/// A lenient parse that kept nothing is not a recovery, it is an unreadable file
+fn check_recovered(path: &Path, kept: usize, skipped: &[SkippedLine]) -> Result<()> {
+    if kept == 0 {
+        if let Some(first) = skipped.first() {
+            anyhow::bail!(
+                "No valid lines in {} ({} skipped, first at line {}: {})",
+                path.display(),
+                skipped.len(),
+                first.line,
+                first.reason
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns true for entry types counted as conversation messages
+fn is_message_entry(entry: &ConversationEntry) -> bool {
+    entry.entry_type == "user" || entry.entry_type == "assistant"
+}
+
+/// Identity, change-detection and display metadata common to fully-parsed
+/// sessions and streamed [`SessionSummary`] values.
+///
+/// Planning and conflict detection only need these fields, so they accept
+/// either representation and leave full parsing to the sessions that merge.
+pub trait SessionInfo {
+    /// Identity of the transcript (the filename stem)
+    fn session_id(&self) -> &str;
+
+    /// Path of the JSONL file the session was read from
+    fn file_path(&self) -> &str;
+
+    /// Hash of the conversation content
+    fn content_hash(&self) -> String;
+
+    /// Number of user + assistant messages
+    fn message_count(&self) -> usize;
+
+    /// Latest entry timestamp, if any entry carries one
+    fn latest_timestamp(&self) -> Option<String>;
+
+    /// Project name derived from the first `cwd` in the transcript
+    fn project_name(&self) -> Option<&str>;
+
+    /// Session this transcript is a subagent sidechain of, if any
+    fn parent_session_id(&self) -> Option<&str>;
+}
+
+/// Metadata of a conversation file computed in a single streaming pass.
+///
+/// Holds everything push planning, conflict detection and status need — but
+/// none of the entries, so discovering multi-hundred-MB transcripts costs one
+/// line of memory at a time. Call [`SessionSummary::load`] for the sessions
+/// that actually need the full model (smart merge).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// Identity of the transcript, resolved exactly like [`ConversationSession`]
+    pub session_id: String,
+
+    /// Path to the JSONL file this summary was computed from
+    pub file_path: String,
+
+    /// Number of user + assistant entries
+    pub message_count: usize,
+
+    /// Latest entry timestamp
+    pub latest_timestamp: Option<String>,
+
+    /// Content hash, identical to [`ConversationSession::content_hash`]
+    pub content_hash: String,
+
+    /// First `cwd` found in the transcript
+    pub cwd: Option<String>,
+
+    /// Mode the file was parsed in; [`load`](Self::load) and
+    /// [`copy_to`](Self::copy_to) re-read it the same way
+    pub parse_mode: ParseMode,
+
+    /// Malformed lines dropped by a lenient parse
+    pub skipped_lines: Vec<SkippedLine>,
+
+    /// Parent session, if this transcript is a subagent sidechain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+}
+
+impl SessionSummary {
+    /// Stream a JSONL file and summarize it without retaining its entries
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_file_with_mode(path, ParseMode::Strict)
+    }
+
+    /// Summarize a JSONL file in the given parse mode.
+    ///
+    /// A lenient parse that finds no valid line at all still fails: there is
+    /// nothing to recover, and syncing an empty transcript would hide the damage.
+    pub fn from_file_with_mode<P: AsRef<Path>>(path: P, mode: ParseMode) -> Result<Self> {
+        let path = path.as_ref();
+        let mut hasher = EntryHasher::new();
+        let mut entry_session_id = None;
+        let mut entry_count = 0;
+        let mut message_count = 0;
+        let mut latest_timestamp: Option<String> = None;
+        let mut cwd = None;
+
+        let mut stream = EntryStream::open_with_mode(path, mode)?;
+        for entry in stream.by_ref() {
+            let entry = entry?;
+            entry_count += 1;
+
+            if entry_session_id.is_none() {
+                entry_session_id = entry.session_id.clone();
+            }
+            if cwd.is_none() {
+                cwd = entry.cwd.clone();
+            }
+            if is_message_entry(&entry) {
+                message_count += 1;
+            }
+            if let Some(ts) = &entry.timestamp {
+                if latest_timestamp.as_deref() < Some(ts.as_str()) {
+                    latest_timestamp = Some(ts.clone());
+                }
+            }
+            hasher.update(&entry);
+        }
+
+        let skipped_lines = stream.into_skipped_lines();
+        check_recovered(path, entry_count, &skipped_lines)?;
+
+        let session_id = session_identity(path, entry_session_id.clone())?;
+        let parent_session_id =
+            sidechain_parent(&session_id, entry_session_id.as_deref()).map(String::from);
+
+        Ok(SessionSummary {
+            session_id,
+            file_path: path.to_string_lossy().to_string(),
+            message_count,
+            latest_timestamp,
+            content_hash: hasher.finish(),
+            cwd,
+            parse_mode: mode,
+            skipped_lines,
+            parent_session_id,
+        })
+    }
+
+    /// Materialize the full in-memory session this summary describes
+    pub fn load(&self) -> Result<ConversationSession> {
+        ConversationSession::from_file_with_mode(&self.file_path, self.parse_mode)
+            .map(|(session, _)| session)
+    }
+
+    /// Write this session to `dest` exactly as [`ConversationSession::write_to_file`]
+    /// would, re-serializing one entry at a time instead of loading the file.
+    ///
+    /// The output goes through a temp file in the destination directory, so a
+    /// failed copy never leaves a truncated transcript behind.
+    pub fn copy_to<P: AsRef<Path>>(&self, dest: P) -> Result<()> {
+        self.copy_to_with(dest, |_| true).map(|_| ())
+    }
+
+    /// [`copy_to`](Self::copy_to), passing each entry through `transform`
+    /// before it is written; entries it returns false for are left out.
+    /// Returns the content hash of what was written, which differs from
+    /// [`content_hash`](Self::content_hash) when the transform changed
+    /// anything.
+    pub fn copy_to_with<P, F>(&self, dest: P, mut transform: F) -> Result<String>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&mut ConversationEntry) -> bool,
+    {
+        let dest = dest.as_ref();
+        let parent = dest
+            .parent()
+            .with_context(|| format!("No parent directory for {}", dest.display()))?;
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+
+        let tmp = tempfile::NamedTempFile::new_in(parent)
+            .with_context(|| format!("Failed to create temp file in {}", parent.display()))?;
+        let mut hasher = EntryHasher::new();
+        {
+            let mut writer = std::io::BufWriter::new(tmp.as_file());
+            for entry in EntryStream::open_with_mode(&self.file_path, self.parse_mode)? {
+                let mut entry = entry?;
+                if !transform(&mut entry) {
+                    continue;
+                }
+                hasher.update(&entry);
+                let json = serde_json::to_string(&entry)
+                    .context("Failed to serialize conversation entry")?;
+                writeln!(writer, "{json}")
+                    .with_context(|| format!("Failed to write to file: {}", dest.display()))?;
+            }
+            writer
+                .flush()
+                .with_context(|| format!("Failed to write to file: {}", dest.display()))?;
+        }
+        tmp.persist(dest)
+            .with_context(|| format!("Failed to persist {}", dest.display()))?;
+
+        Ok(hasher.finish())
+    }
+}
+
+impl SessionInfo for SessionSummary {
+    fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    fn content_hash(&self) -> String {
+        self.content_hash.clone()
+    }
+
+    fn message_count(&self) -> usize {
+        self.message_count
+    }
+
+    fn latest_timestamp(&self) -> Option<String> {
+        self.latest_timestamp.clone()
+    }
+
+    fn project_name(&self) -> Option<&str> {
+        self.cwd
+            .as_ref()
+            .and_then(|cwd| std::path::Path::new(cwd).file_name())
+            .and_then(|name| name.to_str())
+    }
+
+    fn parent_session_id(&self) -> Option<&str> {
+        self.parent_session_id.as_deref()
+    }
+}
+
+impl ConversationSession {
+    /// Parse a JSONL file into a ConversationSession
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_file_with_mode(path, ParseMode::Strict).map(|(session, _)| session)
+    }
+
+    /// Parse a JSONL file in the given mode, returning the session together
+    /// with any lines a lenient parse had to drop
+    pub fn from_file_with_mode<P: AsRef<Path>>(
+        path: P,
+        mode: ParseMode,
+    ) -> Result<(Self, Vec<SkippedLine>)> {
+        let path = path.as_ref();
+        let mut entries = Vec::new();
+        let mut entry_session_id = None;
+
+        let mut stream = EntryStream::open_with_mode(path, mode)?;
+        for entry in stream.by_ref() {
+            let entry = entry?;
 
             // Remember the first interior sessionId, used only as a fallback below.
             if entry_session_id.is_none() {
@@ -149,30 +578,15 @@ impl ConversationSession {
             entries.push(entry);
         }
 
-        // Identity is the filename stem, which Claude Code guarantees is unique per
-        // transcript: `<uuid>.jsonl` for a session and `agent-<hash>.jsonl` for each
-        // of its subagents. The interior `sessionId` field is NOT unique — subagent
-        // sidechain transcripts carry their *parent* session's id, so keying identity
-        // on it collapses every subagent onto the parent and makes sync drop the
-        // parent's main transcript. Fall back to the interior id only if the path has
-        // no usable stem.
-        let session_id = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string())
-            .or(entry_session_id)
-            .with_context(|| {
-                format!(
-                    "No session ID found in file or filename: {}",
-                    path.display()
-                )
-            })?;
-
-        Ok(ConversationSession {
-            session_id,
+        let skipped_lines = stream.into_skipped_lines();
+        check_recovered(path, entries.len(), &skipped_lines)?;
+
+        let session = ConversationSession {
+            session_id: session_identity(path, entry_session_id)?,
             entries,
             file_path: path.to_string_lossy().to_string(),
-        })
+        };
+        Ok((session, skipped_lines))
     }
 
     /// Write the conversation session to a JSONL file
@@ -208,10 +622,7 @@ impl ConversationSession {
 
     /// Get the number of messages (user + assistant) in the conversation
     pub fn message_count(&self) -> usize {
-        self.entries
-            .iter()
-            .filter(|e| e.entry_type == "user" || e.entry_type == "assistant")
-            .count()
+        self.entries.iter().filter(|e| is_message_entry(e)).count()
     }
 
     /// Get the project name from the first entry's `cwd` path
@@ -223,18 +634,49 @@ impl ConversationSession {
             .and_then(|name| name.to_str())
     }
 
+    /// Get the parent session if this is a subagent sidechain transcript
+    pub fn parent_session_id(&self) -> Option<&str> {
+        let entry_session_id = self.entries.iter().find_map(|e| e.session_id.as_deref());
+        sidechain_parent(&self.session_id, entry_session_id)
+    }
+
     /// Calculate a simple hash of the conversation content
     pub fn content_hash(&self) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
+        let mut hasher = EntryHasher::new();
         for entry in &self.entries {
-            if let Ok(json) = serde_json::to_string(entry) {
-                json.hash(&mut hasher);
-            }
+            hasher.update(entry);
         }
-        format!("{:x}", hasher.finish())
+        hasher.finish()
+    }
+}
+
+impl SessionInfo for ConversationSession {
+    fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    fn content_hash(&self) -> String {
+        ConversationSession::content_hash(self)
+    }
+
+    fn message_count(&self) -> usize {
+        ConversationSession::message_count(self)
+    }
+
+    fn latest_timestamp(&self) -> Option<String> {
+        ConversationSession::latest_timestamp(self)
+    }
+
+    fn project_name(&self) -> Option<&str> {
+        ConversationSession::project_name(self)
+    }
+
+    fn parent_session_id(&self) -> Option<&str> {
+        ConversationSession::parent_session_id(self)
     }
 }
 
@@ -341,6 +783,38 @@ mod tests {
         assert_ne!(session.session_id, "56d02190-2a2d-4a55-9ec1-38e34fb25e84");
     }
 
+    #[test]
+    fn test_subagent_records_parent_session() {
+        use std::fs::File;
+        use std::io::Write;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let subagent_file = temp_dir.path().join("agent-a16263cbf10e1ad0b.jsonl");
+        let mut file = File::create(&subagent_file).unwrap();
+        writeln!(
+            file,
+            r#"{{"type":"user","sessionId":"parent-1","isSidechain":true,"uuid":"1"}}"#
+        )
+        .unwrap();
+        drop(file);
+
+        let session = ConversationSession::from_file(&subagent_file).unwrap();
+        assert_eq!(session.parent_session_id(), Some("parent-1"));
+        let summary = SessionSummary::from_file(&subagent_file).unwrap();
+        assert_eq!(summary.parent_session_id.as_deref(), Some("parent-1"));
+
+        // A regular session whose interior id differs from its stem is not a child
+        let regular_file = temp_dir.path().join("other.jsonl");
+        std::fs::write(
+            &regular_file,
+            "{\"type\":\"user\",\"sessionId\":\"parent-1\"}\n",
+        )
+        .unwrap();
+        let summary = SessionSummary::from_file(&regular_file).unwrap();
+        assert_eq!(summary.parent_session_id, None);
+    }
+
     #[test]
     fn test_mixed_entries_with_and_without_session_id() {
         use std::fs::File;
@@ -361,6 +835,173 @@ mod tests {
         assert_eq!(session.entries.len(), 2);
     }
 
+    #[test]
+    fn test_summary_matches_full_session() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("summary-1.jsonl");
+        let mut file = File::create(&session_path).unwrap();
+        writeln!(file, r#"{{"type":"file-history-snapshot","messageId":"m","timestamp":"2025-01-01T00:00:00Z"}}"#).unwrap();
+        writeln!(file, r#"{{"type":"user","sessionId":"s","uuid":"1","cwd":"/home/u/proj","timestamp":"2025-01-01T00:02:00Z"}}"#).unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, r#"{{"type":"assistant","sessionId":"s","uuid":"2","timestamp":"2025-01-01T00:01:00Z"}}"#).unwrap();
+        drop(file);
+
+        let session = ConversationSession::from_file(&session_path).unwrap();
+        let summary = SessionSummary::from_file(&session_path).unwrap();
+
+        // The streamed pass must agree with the in-memory model on every field
+        // planning and conflict detection compare.
+        assert_eq!(summary.session_id, session.session_id);
+        assert_eq!(summary.message_count, session.message_count());
+        assert_eq!(summary.latest_timestamp, session.latest_timestamp());
+        assert_eq!(summary.content_hash, session.content_hash());
+        assert_eq!(SessionInfo::project_name(&summary), Some("proj"));
+    }
+
+    #[test]
+    fn test_summary_copy_to_matches_write_to_file() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("copy-1.jsonl");
+        let mut file = File::create(&session_path).unwrap();
+        writeln!(file, r#"{{"type":"user", "uuid":"1",  "timestamp":"2025-01-01T00:00:00Z","message":{{"text":"hi"}}}}"#).unwrap();
+        writeln!(
+            file,
+            r#"{{"type":"assistant","uuid":"2","parentUuid":"1"}}"#
+        )
+        .unwrap();
+        drop(file);
+
+        let written = temp_dir.path().join("out/written.jsonl");
+        ConversationSession::from_file(&session_path)
+            .unwrap()
+            .write_to_file(&written)
+            .unwrap();
+
+        let copied = temp_dir.path().join("out/copied.jsonl");
+        SessionSummary::from_file(&session_path)
+            .unwrap()
+            .copy_to(&copied)
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&written).unwrap(),
+            std::fs::read_to_string(&copied).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_copy_to_with_returns_written_hash() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("copy-2.jsonl");
+        let mut file = File::create(&session_path).unwrap();
+        writeln!(file, r#"{{"type":"user","uuid":"1","cwd":"/home/u/proj"}}"#).unwrap();
+        drop(file);
+        let summary = SessionSummary::from_file(&session_path).unwrap();
+
+        let unchanged = temp_dir.path().join("out/unchanged.jsonl");
+        assert_eq!(
+            summary.copy_to_with(&unchanged, |_| true).unwrap(),
+            summary.content_hash
+        );
+
+        let rewritten = temp_dir.path().join("out/rewritten.jsonl");
+        let hash = summary
+            .copy_to_with(&rewritten, |entry| {
+                entry.cwd = Some("~/proj".to_string());
+                true
+            })
+            .unwrap();
+        assert_ne!(hash, summary.content_hash);
+        assert_eq!(
+            hash,
+            SessionSummary::from_file(&rewritten).unwrap().content_hash
+        );
+    }
+
+    #[test]
+    fn test_entry_stream_reports_line_number() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("broken.jsonl");
+        let mut file = File::create(&session_path).unwrap();
+        writeln!(file, r#"{{"type":"user","uuid":"1"}}"#).unwrap();
+        writeln!(file, r#"{{"type":"user","uu"#).unwrap();
+        drop(file);
+
+        let results: Vec<_> = EntryStream::open(&session_path).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().unwrap_err().to_string();
+        assert!(err.contains("line 2"), "unexpected: {err}");
+    }
+
+    #[test]
+    fn test_lenient_parse_recovers_valid_lines() {
+        use std::fs::File;
+        use tempfile::TempDir;
+
+        // Simulates syncing while Claude Code is mid-write: a corrupt line in the
+        // middle and a truncated last line.
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("partial.jsonl");
+        let mut file = File::create(&session_path).unwrap();
+        writeln!(
+            file,
+            r#"{{"type":"user","uuid":"1","timestamp":"2025-01-01T00:00:00Z"}}"#
+        )
+        .unwrap();
+        writeln!(file, "not json at all").unwrap();
+        writeln!(
+            file,
+            r#"{{"type":"assistant","uuid":"2","timestamp":"2025-01-01T00:01:00Z"}}"#
+        )
+        .unwrap();
+        write!(file, r#"{{"type":"user","uuid":"3","times"#).unwrap();
+        drop(file);
+
+        assert!(ConversationSession::from_file(&session_path).is_err());
+
+        let (session, skipped) =
+            ConversationSession::from_file_with_mode(&session_path, ParseMode::Lenient).unwrap();
+        assert_eq!(session.entries.len(), 2);
+        assert_eq!(
+            skipped.iter().map(|s| s.line).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+
+        let summary =
+            SessionSummary::from_file_with_mode(&session_path, ParseMode::Lenient).unwrap();
+        assert_eq!(summary.message_count, 2);
+        assert_eq!(summary.skipped_lines, skipped);
+        assert_eq!(summary.content_hash, session.content_hash());
+        assert_eq!(summary.load().unwrap().entries.len(), 2);
+    }
+
+    #[test]
+    fn test_lenient_parse_with_no_valid_lines_fails() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let session_path = temp_dir.path().join("garbage.jsonl");
+        std::fs::write(&session_path, "garbage\n{{{\n").unwrap();
+
+        let err = SessionSummary::from_file_with_mode(&session_path, ParseMode::Lenient)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("No valid lines"), "unexpected: {err}");
+    }
+
     #[test]
     fn test_project_name_from_cwd() {
         let json = r#"{"type":"user","uuid":"1","cwd":"/Users/abc/my-cool-project"}"#;
@@ -0,0 +1,332 @@
+//! Quarantine for transcripts that cannot be parsed.
+//!
+//! Discovery skips a transcript it cannot parse, which means a corrupted file
+//! is silently never synced. Push and pull hand those failures to
+//! [`Quarantine::add`], which *copies* the file (the original is never
+//! touched) into a per-machine directory under the config dir together with a
+//! small JSON record of where it came from and why it failed. The
+//! `quarantine` command then lists, inspects, retries or discards entries.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigManager;
+use crate::parser::{ParseMode, SessionSummary};
+
+/// Extension of the metadata record stored next to each quarantined copy
+const RECORD_EXTENSION: &str = "json";
+
+/// Extension of the quarantined transcript copy
+const COPY_EXTENSION: &str = "jsonl";
+
+/// One quarantined transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuarantineEntry {
+    /// Stable identifier: `<file stem>-<hash of the source path>`
+    pub id: String,
+
+    /// Where the transcript lives in the Claude Code directory
+    pub source_path: PathBuf,
+
+    /// Parse error that sent the file here
+    pub reason: String,
+
+    /// When the file was first quarantined
+    pub quarantined_at: DateTime<Utc>,
+
+    /// Size of the source when copied, used to detect a changed source
+    pub source_size: u64,
+
+    /// Modification time of the source when copied
+    pub source_modified: Option<DateTime<Utc>>,
+}
+
+/// Result of re-parsing a quarantined entry's source
+#[derive(Debug, PartialEq)]
+pub enum RetryOutcome {
+    /// The source parses now; the entry was released from quarantine
+    Released,
+    /// The source still fails to parse; the entry's reason was updated
+    StillFailing(String),
+    /// The source no longer exists
+    SourceMissing,
+}
+
+/// A per-machine quarantine directory
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+impl Quarantine {
+    /// Quarantine for this machine under the config directory
+    pub fn open() -> Result<Self> {
+        Ok(Self::with_dir(ConfigManager::quarantine_dir()?))
+    }
+
+    /// Quarantine rooted at a custom directory (for testing)
+    pub fn with_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Quarantine { dir: dir.into() }
+    }
+
+    /// Directory holding this quarantine's files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn record_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.{RECORD_EXTENSION}"))
+    }
+
+    /// Path of the quarantined copy for an entry
+    pub fn copy_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.{COPY_EXTENSION}"))
+    }
+
+    /// Copy an unparsable transcript into quarantine.
+    ///
+    /// Re-adding a source that is already quarantined and unchanged (same size
+    /// and modification time) is a no-op returning `Ok(false)`, so repeated
+    /// pushes do not keep recopying the same broken file.
+    ///
+    /// # Returns
+    /// `true` if the file was newly quarantined or its copy refreshed
+    pub fn add(&self, source: &Path, reason: &str) -> Result<bool> {
+        let metadata = fs::metadata(source)
+            .with_context(|| format!("Failed to read metadata for {}", source.display()))?;
+        let source_size = metadata.len();
+        let source_modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let id = entry_id(source);
+
+        let quarantined_at = match self.get(&id) {
+            Ok(existing)
+                if existing.source_size == source_size
+                    && existing.source_modified == source_modified =>
+            {
+                return Ok(false);
+            }
+            Ok(existing) => existing.quarantined_at,
+            Err(_) => Utc::now(),
+        };
+
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "Failed to create quarantine directory: {}",
+                self.dir.display()
+            )
+        })?;
+        fs::copy(source, self.copy_path(&id))
+            .with_context(|| format!("Failed to copy {} into quarantine", source.display()))?;
+
+        let entry = QuarantineEntry {
+            id,
+            source_path: source.to_path_buf(),
+            reason: reason.to_string(),
+            quarantined_at,
+            source_size,
+            source_modified,
+        };
+        self.write_record(&entry)?;
+        log::info!("Quarantined {}: {}", source.display(), reason);
+
+        Ok(true)
+    }
+
+    fn write_record(&self, entry: &QuarantineEntry) -> Result<()> {
+        let path = self.record_path(&entry.id);
+        let json =
+            serde_json::to_string_pretty(entry).context("Failed to serialize quarantine record")?;
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write quarantine record: {}", path.display()))
+    }
+
+    /// All quarantined entries, oldest first
+    pub fn list(&self) -> Result<Vec<QuarantineEntry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+        {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(RECORD_EXTENSION) {
+                continue;
+            }
+            match read_record(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!("Ignoring unreadable quarantine record: {e:#}"),
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            a.quarantined_at
+                .cmp(&b.quarantined_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(entries)
+    }
+
+    /// Look up one entry by id
+    pub fn get(&self, id: &str) -> Result<QuarantineEntry> {
+        let well_formed = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let path = self.record_path(id);
+        if !well_formed || !path.exists() {
+            anyhow::bail!("No quarantined transcript with id '{id}'");
+        }
+        read_record(&path)
+    }
+
+    /// Re-parse an entry's source with the given mode.
+    ///
+    /// A source that parses now is released: its quarantined copy is removed
+    /// and the next push picks the original up as usual.
+    pub fn retry(&self, id: &str, mode: ParseMode) -> Result<RetryOutcome> {
+        let mut entry = self.get(id)?;
+        if !entry.source_path.exists() {
+            return Ok(RetryOutcome::SourceMissing);
+        }
+
+        match SessionSummary::from_file_with_mode(&entry.source_path, mode) {
+            Ok(_) => {
+                self.discard(id)?;
+                Ok(RetryOutcome::Released)
+            }
+            Err(e) => {
+                entry.reason = format!("{e:#}");
+                self.write_record(&entry)?;
+                Ok(RetryOutcome::StillFailing(entry.reason))
+            }
+        }
+    }
+
+    /// Remove an entry and its quarantined copy. The source is not touched.
+    pub fn discard(&self, id: &str) -> Result<()> {
+        // Validate the id before deleting anything derived from it
+        self.get(id)?;
+
+        let copy = self.copy_path(id);
+        if copy.exists() {
+            fs::remove_file(&copy)
+                .with_context(|| format!("Failed to remove {}", copy.display()))?;
+        }
+        let record = self.record_path(id);
+        fs::remove_file(&record).with_context(|| format!("Failed to remove {}", record.display()))
+    }
+}
+
+fn read_record(path: &Path) -> Result<QuarantineEntry> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Derive a stable, filesystem-safe id for a source path.
+///
+/// The stem alone is not unique (resumed sessions and sidechains can share
+/// names across projects), so a short hash of the full path is appended.
+fn entry_id(source: &Path) -> String {
+    let stem: String = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // sha256 rather than the std hasher, whose output may change between
+    // Rust releases and would rename every entry
+    let digest = Sha256::digest(source.as_os_str().as_encoded_bytes());
+    digest[..4].iter().fold(format!("{stem}-"), |mut id, byte| {
+        let _ = write!(id, "{byte:02x}");
+        id
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_copies_without_touching_source() {
+        let claude = tempdir().unwrap();
+        let store = tempdir().unwrap();
+        let quarantine = Quarantine::with_dir(store.path().join("machine"));
+
+        let source = claude.path().join("broken.jsonl");
+        fs::write(&source, "not json\n").unwrap();
+
+        assert!(quarantine.add(&source, "bad line").unwrap());
+        assert!(source.exists());
+
+        let entries = quarantine.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source_path, source);
+        assert_eq!(entries[0].reason, "bad line");
+        assert!(entries[0].id.starts_with("broken-"));
+        assert_eq!(
+            fs::read_to_string(quarantine.copy_path(&entries[0].id)).unwrap(),
+            "not json\n"
+        );
+
+        // Unchanged source is not quarantined twice
+        assert!(!quarantine.add(&source, "bad line").unwrap());
+        assert_eq!(quarantine.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retry_releases_repaired_source() {
+        let claude = tempdir().unwrap();
+        let store = tempdir().unwrap();
+        let quarantine = Quarantine::with_dir(store.path());
+
+        let source = claude.path().join("session.jsonl");
+        fs::write(&source, "garbage\n").unwrap();
+        quarantine.add(&source, "bad").unwrap();
+        let id = quarantine.list().unwrap()[0].id.clone();
+
+        match quarantine.retry(&id, ParseMode::Strict).unwrap() {
+            RetryOutcome::StillFailing(reason) => assert!(reason.contains("line 1")),
+            other => panic!("expected StillFailing, got {other:?}"),
+        }
+
+        fs::write(&source, "{\"type\":\"user\",\"uuid\":\"1\"}\n").unwrap();
+        assert_eq!(
+            quarantine.retry(&id, ParseMode::Strict).unwrap(),
+            RetryOutcome::Released
+        );
+        assert!(quarantine.list().unwrap().is_empty());
+        assert!(!quarantine.copy_path(&id).exists());
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn test_discard_unknown_id_fails() {
+        let store = tempdir().unwrap();
+        let quarantine = Quarantine::with_dir(store.path());
+        assert!(quarantine.discard("../../etc/passwd").is_err());
+        assert!(quarantine.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_entry_id_is_pinned() {
+        // Pinned: ids must survive toolchain upgrades
+        assert_eq!(entry_id(Path::new("/p/abc.jsonl")), "abc-b2a85196");
+        assert!(entry_id(Path::new("/p/a b.jsonl")).starts_with("a_b-"));
+    }
+}
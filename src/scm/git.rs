@@ -4,7 +4,8 @@ use anyhow::{anyhow, Context, Result};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use super::Scm;
+use super::history::walk_patch_log;
+use super::{FileChange, Scm};
 
 /// Git SCM implementation using the git CLI.
 pub struct GitScm {
@@ -215,6 +216,33 @@ impl Scm for GitScm {
     fn reset_soft(&self, commit: &str) -> Result<()> {
         self.run_git_ok(&["reset", "--soft", commit])
     }
+
+    fn walk_history(&self, visit: &mut dyn FnMut(&FileChange)) -> Result<()> {
+        // A repository without commits has no history (and `git log` fails)
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", "HEAD"]) {
+            return Ok(());
+        }
+        walk_patch_log(
+            "git",
+            &[
+                "-c",
+                "core.quotePath=false",
+                "log",
+                "--all",
+                "--reverse",
+                "--no-renames",
+                "--diff-filter=AM",
+                "--no-color",
+                "--no-ext-diff",
+                "--no-textconv",
+                "--unified=0",
+                "--patch",
+                "--format=%x00%H",
+            ],
+            &self.workdir,
+            visit,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -283,4 +311,33 @@ mod tests {
         assert!(scm.has_remote("origin"));
         assert!(!scm.has_remote("upstream"));
     }
+
+    #[test]
+    fn test_git_walk_history() {
+        let temp = TempDir::new().unwrap();
+        let scm = GitScm::init(temp.path()).unwrap();
+
+        // No commits yet
+        let mut changes = Vec::new();
+        scm.walk_history(&mut |c| changes.push(c.clone())).unwrap();
+        assert!(changes.is_empty());
+
+        std::fs::write(temp.path().join(".env"), "TOKEN=one\n").unwrap();
+        scm.stage_all().unwrap();
+        scm.commit("add").unwrap();
+        let first = scm.current_commit_hash().unwrap();
+        std::fs::write(temp.path().join(".env"), "TOKEN=one\nTOKEN=two\n").unwrap();
+        scm.stage_all().unwrap();
+        scm.commit("change").unwrap();
+        std::fs::remove_file(temp.path().join(".env")).unwrap();
+        scm.stage_all().unwrap();
+        scm.commit("remove").unwrap();
+
+        scm.walk_history(&mut |c| changes.push(c.clone())).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].revision, first);
+        assert_eq!(changes[0].path, ".env");
+        assert_eq!(changes[0].added, vec!["TOKEN=one"]);
+        assert_eq!(changes[1].added, vec!["TOKEN=two"]);
+    }
 }
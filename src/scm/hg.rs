@@ -5,7 +5,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use super::Scm;
+use super::history::walk_patch_log;
+use super::{FileChange, Scm};
 
 /// Mercurial SCM implementation using the `hg` CLI.
 pub struct HgScm {
@@ -266,6 +267,25 @@ impl Scm for HgScm {
         self.run_hg(&["update", "-r", commit])?;
         Ok(())
     }
+
+    fn walk_history(&self, visit: &mut dyn FnMut(&FileChange)) -> Result<()> {
+        walk_patch_log(
+            "hg",
+            &[
+                "log",
+                "-r",
+                "all()",
+                "--git",
+                "--patch",
+                "--unified",
+                "0",
+                "--template",
+                "\\x00{node}\\n",
+            ],
+            &self.path,
+            visit,
+        )
+    }
 }
 
 #[cfg(test)]
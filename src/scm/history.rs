@@ -0,0 +1,163 @@
+//! Reading the content history of a repository.
+//!
+//! Both backends print their history as git-style patches, one per file
+//! change, each changeset introduced by a marker line. The parser here turns
+//! that stream into [`FileChange`]s without holding the history in memory.
+
+use anyhow::{anyhow, Context, Result};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// Starts the line naming each changeset in the log output: a NUL byte
+/// cannot begin a patch line
+pub(crate) const REVISION_MARKER: &str = "\0";
+
+/// One file changed by one commit, as visited by
+/// [`Scm::walk_history`](super::Scm::walk_history)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Commit (changeset) that made the change
+    pub revision: String,
+    /// Repository-relative path of the file
+    pub path: String,
+    /// Lines the change added; empty for binary files and deletions
+    pub added: Vec<String>,
+}
+
+/// Run `program` with `args` in `dir` and feed its patch log to `visit`
+pub(crate) fn walk_patch_log(
+    program: &str,
+    args: &[&str],
+    dir: &std::path::Path,
+    visit: &mut dyn FnMut(&FileChange),
+) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{program} {}'", args.join(" ")))?;
+    let stdout = child.stdout.take().context("No output from history log")?;
+    let parsed = parse_patch_log(BufReader::new(stdout), visit);
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run '{program} log'"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} log failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    parsed
+}
+
+/// Parse a patch log (see the module docs) into file changes
+pub(crate) fn parse_patch_log(
+    mut reader: impl BufRead,
+    visit: &mut dyn FnMut(&FileChange),
+) -> Result<()> {
+    let mut current: Option<FileChange> = None;
+    let mut revision = String::new();
+    let mut in_header = false;
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        if reader
+            .read_until(b'\n', &mut buf)
+            .context("Failed to read history log")?
+            == 0
+        {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if let Some(rev) = line.strip_prefix(REVISION_MARKER) {
+            if let Some(change) = current.take() {
+                visit(&change);
+            }
+            revision = rev.trim().to_string();
+        } else if let Some(paths) = line.strip_prefix("diff --git ") {
+            if let Some(change) = current.take() {
+                visit(&change);
+            }
+            current = diff_path(paths).map(|path| FileChange {
+                revision: revision.clone(),
+                path,
+                added: Vec::new(),
+            });
+            in_header = true;
+        } else if let Some(change) = current.as_mut() {
+            if line.starts_with("@@") {
+                in_header = false;
+            } else if !in_header {
+                if let Some(added) = line.strip_prefix('+') {
+                    change.added.push(added.to_string());
+                }
+            }
+        }
+    }
+    if let Some(change) = current.take() {
+        visit(&change);
+    }
+    Ok(())
+}
+
+/// The path of a `diff --git a/<path> b/<path>` header. Renames are not
+/// followed, so both sides name the same path and the split is unambiguous
+/// even when it contains spaces.
+fn diff_path(paths: &str) -> Option<String> {
+    let (a, sep, b) = if paths.starts_with('"') {
+        ("\"a/", "\" \"b/", "\"")
+    } else {
+        ("a/", " b/", "")
+    };
+    let fixed = a.len() + sep.len() + b.len();
+    if paths.len() < fixed || !(paths.len() - fixed).is_multiple_of(2) {
+        return None;
+    }
+    let len = (paths.len() - fixed) / 2;
+    let path = paths.get(a.len()..a.len() + len)?;
+    (paths == format!("{a}{path}{sep}{path}{b}")).then(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_patch_log() {
+        let log = "\0c1\n\
+            diff --git a/dir/my file.txt b/dir/my file.txt\n\
+            new file mode 100644\n\
+            --- /dev/null\n\
+            +++ b/dir/my file.txt\n\
+            @@ -0,0 +1,2 @@\n\
+            +first\n\
+            ++++ not a header\n\
+            diff --git a/img.png b/img.png\n\
+            Binary files /dev/null and b/img.png differ\n\
+            \0c2\n\
+            diff --git \"a/t\\303\\251.txt\" \"b/t\\303\\251.txt\"\n\
+            --- a/x\n\
+            +++ b/x\n\
+            @@ -1 +1 @@\n\
+            -old\n\
+            +new\n";
+        let mut changes = Vec::new();
+        parse_patch_log(log.as_bytes(), &mut |c| changes.push(c.clone())).unwrap();
+
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].revision, "c1");
+        assert_eq!(changes[0].path, "dir/my file.txt");
+        assert_eq!(changes[0].added, vec!["first", "+++ not a header"]);
+        assert_eq!(changes[1].path, "img.png");
+        assert!(changes[1].added.is_empty());
+        assert_eq!(changes[2].revision, "c2");
+        assert_eq!(changes[2].path, "t\\303\\251.txt");
+        assert_eq!(changes[2].added, vec!["new"]);
+    }
+}
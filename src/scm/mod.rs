@@ -5,6 +5,7 @@
 
 mod git;
 mod hg;
+mod history;
 pub mod lfs;
 
 use anyhow::{anyhow, Result};
@@ -12,6 +13,7 @@ use std::path::Path;
 
 pub use git::GitScm;
 pub use hg::HgScm;
+pub use history::FileChange;
 
 /// SCM backend types.
 ///
@@ -92,6 +94,10 @@ pub trait Scm: Send + Sync {
 
     /// Reset to a specific commit (soft reset - keeps working directory).
     fn reset_soft(&self, commit: &str) -> Result<()>;
+
+    /// Visit every file change on every branch, oldest first, with the
+    /// lines it added. Renames are seen as a deletion and an addition.
+    fn walk_history(&self, visit: &mut dyn FnMut(&FileChange)) -> Result<()>;
 }
 
 /// Check if a directory is a repository (Git or Mercurial).
@@ -0,0 +1,615 @@
+//! Persistent full-text index for `search`.
+//!
+//! An inverted index from message tokens to the transcripts containing them,
+//! stored in the config directory. Each indexed transcript is recorded with
+//! the content hash it had when indexed, so updating only re-reads sessions
+//! whose content changed. Push and pull keep the index current with the
+//! sessions they discover anyway; `search` tops up whatever they missed and
+//! `search --reindex` rebuilds it from scratch.
+//!
+//! On disk the index is a directory: a manifest of the indexed transcripts,
+//! and posting lists in segments. An update appends one segment holding only
+//! the transcripts it read, and forgets removed transcripts in the manifest
+//! alone, so push and pull never read the posting lists and write in
+//! proportion to what changed. Once there are [`MAX_SEGMENTS`] segments they
+//! are merged into one, leaving out transcripts no longer indexed.
+//!
+//! The index only narrows a query down to candidate transcripts. Those are
+//! then scanned to apply filters, check phrases and build snippets, so a stale
+//! or damaged index can make search slower but never wrong.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigManager;
+use crate::parser::{EntryStream, SessionSummary};
+use crate::sync::parallel::{parallel_map, resolve_threads};
+
+use super::query::{tokenize, QueryTerm};
+
+/// Bump whenever tokenization or the file layout changes, so stale indexes
+/// are rebuilt rather than trusted.
+const INDEX_VERSION: u32 = 2;
+
+/// Segments kept before they are merged into one
+const MAX_SEGMENTS: usize = 16;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Token -> sorted ids of the documents containing it
+type Postings = BTreeMap<String, Vec<u32>>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    next_doc: u32,
+    next_segment: u32,
+    /// Transcript path -> indexed document
+    docs: BTreeMap<String, IndexedDoc>,
+    /// Numbers of the segment files holding the posting lists, oldest first
+    segments: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedDoc {
+    id: u32,
+    content_hash: String,
+}
+
+/// Inverted index over message text, keyed by transcript path
+pub struct SearchIndex {
+    dir: Option<PathBuf>,
+    manifest: Manifest,
+    /// Postings of the saved segments, read on first use; `None` if a
+    /// segment could not be read
+    saved: OnceCell<Option<Postings>>,
+    /// Postings of the transcripts read since loading, not yet saved
+    pending: Postings,
+    dirty: bool,
+}
+
+impl SearchIndex {
+    /// An index that is never persisted
+    pub fn in_memory() -> Self {
+        SearchIndex {
+            dir: None,
+            manifest: Manifest {
+                version: INDEX_VERSION,
+                ..Default::default()
+            },
+            saved: OnceCell::from(Some(Postings::new())),
+            pending: Postings::new(),
+            dirty: false,
+        }
+    }
+
+    /// Load the index from the config directory
+    pub fn load() -> Result<Self> {
+        // Indexes before segments were a single file next to the directory
+        let legacy = ConfigManager::config_dir()?.join("search-index.json");
+        if legacy.is_file() {
+            if let Err(e) = fs::remove_file(&legacy) {
+                log::debug!("Failed to remove {}: {}", legacy.display(), e);
+            }
+        }
+        Ok(Self::load_from(ConfigManager::search_index_path()?))
+    }
+
+    /// Load the index stored in directory `dir` (for testing).
+    ///
+    /// A missing, unreadable or outdated index is not an error: it starts
+    /// empty and is rebuilt by the next update. Only the manifest is read
+    /// here; posting lists are read by the first query.
+    pub fn load_from<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+        let mut index = Self::in_memory();
+        let manifest_path = dir.join(MANIFEST_FILE);
+
+        if manifest_path.exists() {
+            match fs::read_to_string(&manifest_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| serde_json::from_str::<Manifest>(&json).map_err(Into::into))
+            {
+                Ok(manifest) if manifest.version == INDEX_VERSION => {
+                    index.manifest = manifest;
+                    index.saved = OnceCell::new();
+                }
+                Ok(_) => {
+                    log::info!("Search index format changed; rebuilding");
+                    index.dirty = true;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Ignoring unreadable search index {}: {}",
+                        manifest_path.display(),
+                        e
+                    );
+                    index.dirty = true;
+                }
+            }
+        }
+
+        index.dir = Some(dir);
+        index
+    }
+
+    /// Number of indexed transcripts
+    pub fn len(&self) -> usize {
+        self.manifest.docs.len()
+    }
+
+    /// True if nothing is indexed
+    pub fn is_empty(&self) -> bool {
+        self.manifest.docs.is_empty()
+    }
+
+    /// Drop everything, so the next update re-reads every transcript
+    pub fn clear(&mut self) {
+        self.manifest = Manifest {
+            version: INDEX_VERSION,
+            ..Default::default()
+        };
+        self.saved = OnceCell::from(Some(Postings::new()));
+        self.pending.clear();
+        self.dirty = true;
+    }
+
+    /// Bring the index up to date with the sessions discovered under
+    /// `base_path`.
+    ///
+    /// Sessions that are new or whose content hash changed are (re)read on up
+    /// to `threads` workers (0 = one per CPU); indexed transcripts under
+    /// `base_path` that are no longer among `sessions` are dropped. Sessions
+    /// that cannot be read are left out and retried next time.
+    ///
+    /// Returns the number of transcripts read.
+    pub fn update(
+        &mut self,
+        base_path: &Path,
+        sessions: &[SessionSummary],
+        threads: usize,
+    ) -> usize {
+        let current: HashSet<&str> = sessions.iter().map(|s| s.file_path.as_str()).collect();
+        let stale: Vec<&SessionSummary> = sessions
+            .iter()
+            .filter(|s| !self.is_current(&s.file_path, &s.content_hash))
+            .collect();
+        let stale_paths: HashSet<&str> = stale.iter().map(|s| s.file_path.as_str()).collect();
+
+        // Dropped documents only leave the manifest; their ids linger in the
+        // posting lists, where nothing refers to them, until segments merge
+        let before = self.manifest.docs.len();
+        self.manifest.docs.retain(|path, _| {
+            (!Path::new(path).starts_with(base_path) || current.contains(path.as_str()))
+                && !stale_paths.contains(path.as_str())
+        });
+        let removed = before - self.manifest.docs.len();
+
+        let tokenized = parallel_map(&stale, resolve_threads(threads), |summary| {
+            session_tokens(summary)
+        });
+
+        for (summary, tokens) in stale.iter().zip(tokenized) {
+            let tokens = match tokens {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    log::warn!("Failed to index {}: {:#}", summary.file_path, e);
+                    continue;
+                }
+            };
+            let id = self.manifest.next_doc;
+            self.manifest.next_doc += 1;
+            for token in tokens {
+                // Ids only grow, so pushing keeps every posting list sorted
+                self.pending.entry(token).or_default().push(id);
+            }
+            self.manifest.docs.insert(
+                summary.file_path.clone(),
+                IndexedDoc {
+                    id,
+                    content_hash: summary.content_hash.clone(),
+                },
+            );
+        }
+
+        if !stale.is_empty() || removed > 0 {
+            self.dirty = true;
+        }
+
+        log::debug!(
+            "Search index: {} transcripts read under {}, {} dropped",
+            stale.len(),
+            base_path.display(),
+            removed
+        );
+        stale.len()
+    }
+
+    /// Paths of indexed transcripts that may match every term
+    pub fn candidates(&self, terms: &[QueryTerm]) -> HashSet<String> {
+        let Some(saved) = self.saved_postings() else {
+            // Without the posting lists every indexed transcript may match
+            return self.manifest.docs.keys().cloned().collect();
+        };
+
+        let mut matching: Option<BTreeSet<u32>> = None;
+        for term in terms {
+            let ids = term_docs(saved, term);
+            let ids = ids
+                .union(&term_docs(&self.pending, term))
+                .copied()
+                .collect();
+            matching = Some(match matching {
+                Some(so_far) => so_far.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+
+        let matching = matching.unwrap_or_default();
+        self.manifest
+            .docs
+            .iter()
+            .filter(|(_, doc)| matching.contains(&doc.id))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// True if `path` is indexed at exactly this content
+    pub fn is_current(&self, path: &str, content_hash: &str) -> bool {
+        self.manifest
+            .docs
+            .get(path)
+            .is_some_and(|doc| doc.content_hash == content_hash)
+    }
+
+    /// Postings of the saved segments, merged in segment order
+    fn saved_postings(&self) -> Option<&Postings> {
+        self.saved
+            .get_or_init(|| {
+                let dir = self.dir.as_ref()?;
+                let mut merged = Postings::new();
+                for segment in &self.manifest.segments {
+                    let path = segment_path(dir, *segment);
+                    let postings = fs::read_to_string(&path)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|json| {
+                            serde_json::from_str::<Postings>(&json).map_err(Into::into)
+                        });
+                    match postings {
+                        Ok(postings) => {
+                            for (token, ids) in postings {
+                                merged.entry(token).or_default().extend(ids);
+                            }
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "Ignoring unreadable search index segment {}: {}",
+                                path.display(),
+                                e
+                            );
+                            return None;
+                        }
+                    }
+                }
+                Some(merged)
+            })
+            .as_ref()
+    }
+
+    /// Write the index back if it changed. In-memory indexes are never written.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+        if self.manifest.segments.len() >= MAX_SEGMENTS {
+            self.merge_segments();
+        }
+        if !self.pending.is_empty() {
+            let segment = self.manifest.next_segment;
+            let json = serde_json::to_string(&self.pending)
+                .context("Failed to serialize search index segment")?;
+            write_atomic(&segment_path(&dir, segment), json.as_bytes())?;
+            self.manifest.next_segment += 1;
+            self.manifest.segments.push(segment);
+            if let Some(Some(saved)) = self.saved.get_mut() {
+                for (token, ids) in std::mem::take(&mut self.pending) {
+                    saved.entry(token).or_default().extend(ids);
+                }
+            }
+            self.pending.clear();
+        }
+
+        let json =
+            serde_json::to_string(&self.manifest).context("Failed to serialize search index")?;
+        write_atomic(&dir.join(MANIFEST_FILE), json.as_bytes())?;
+        remove_unused_segments(&dir, &self.manifest.segments);
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Fold every segment and the pending postings into the pending
+    /// postings, keeping only documents still indexed; the next save writes
+    /// them as the only segment. Unreadable segments drop the whole index.
+    fn merge_segments(&mut self) {
+        let live: HashSet<u32> = self.manifest.docs.values().map(|doc| doc.id).collect();
+        let saved = self.saved_postings().cloned();
+        let Some(mut merged) = saved else {
+            self.clear();
+            return;
+        };
+        for (token, ids) in std::mem::take(&mut self.pending) {
+            merged.entry(token).or_default().extend(ids);
+        }
+        merged.retain(|_, ids| {
+            ids.retain(|id| live.contains(id));
+            !ids.is_empty()
+        });
+        self.pending = merged;
+        self.manifest.segments.clear();
+        self.saved = OnceCell::from(Some(Postings::new()));
+    }
+
+    /// Save, logging instead of failing
+    pub fn save_or_warn(&mut self) {
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save search index: {e:#}");
+        }
+    }
+}
+
+fn term_docs(postings: &Postings, term: &QueryTerm) -> BTreeSet<u32> {
+    let posting = |token: &str| -> BTreeSet<u32> {
+        postings
+            .get(token)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    };
+    match term {
+        QueryTerm::Word(word) => posting(word),
+        QueryTerm::Prefix(prefix) => postings
+            .range::<str, _>((
+                std::ops::Bound::Included(prefix.as_str()),
+                std::ops::Bound::Unbounded,
+            ))
+            .take_while(|(token, _)| token.starts_with(prefix.as_str()))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect(),
+        // Every word must be present; adjacency is checked by the scan
+        QueryTerm::Phrase(words) => words
+            .iter()
+            .map(|word| posting(word))
+            .reduce(|a, b| a.intersection(&b).copied().collect())
+            .unwrap_or_default(),
+    }
+}
+
+fn segment_path(dir: &Path, segment: u32) -> PathBuf {
+    dir.join(format!("segment-{segment}.json"))
+}
+
+/// Delete segment files the manifest no longer lists
+fn remove_unused_segments(dir: &Path, segments: &[u32]) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(number) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("segment-"))
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if !segments.contains(&number) {
+            if let Err(e) = fs::remove_file(entry.path()) {
+                log::debug!("Failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+/// Replace `path` with `bytes` through a temp file in the same directory
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .context("Search index path has no parent directory")?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create temp file in {}", dir.display()))?;
+    tmp.write_all(bytes)
+        .context("Failed to write search index")?;
+    tmp.persist(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Distinct tokens of every user and assistant message in a transcript
+fn session_tokens(summary: &SessionSummary) -> Result<BTreeSet<String>> {
+    let mut tokens = BTreeSet::new();
+    for entry in EntryStream::open_with_mode(&summary.file_path, summary.parse_mode)? {
+        let entry = entry?;
+        if entry.entry_type != "user" && entry.entry_type != "assistant" {
+            continue;
+        }
+        if let Some(message) = entry.typed_message() {
+            tokens.extend(tokenize(&message.text()).into_iter().map(|(t, _)| t));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Update the search index with sessions a sync operation just discovered.
+///
+/// Called by push and pull; failures are logged, never returned, because a
+/// sync must not fail over its search index.
+pub(crate) fn update_after_sync(sides: &[(&Path, &[SessionSummary])], threads: usize) {
+    let mut index = match SearchIndex::load() {
+        Ok(index) => index,
+        Err(e) => {
+            log::warn!("Failed to open search index: {e:#}");
+            return;
+        }
+    };
+    for (base_path, sessions) in sides {
+        index.update(base_path, sessions, threads);
+    }
+    index.save_or_warn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::query::parse_query;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, name: &str, text: &str) -> SessionSummary {
+        let path = dir.join(format!("{name}.jsonl"));
+        let line = serde_json::json!({
+            "type": "user",
+            "uuid": "1",
+            "cwd": "/p",
+            "message": {"role": "user", "content": text}
+        });
+        fs::write(&path, format!("{line}\n")).unwrap();
+        SessionSummary::from_file(&path).unwrap()
+    }
+
+    fn candidate_names(index: &SearchIndex, query: &str) -> Vec<String> {
+        let mut names: Vec<String> = index
+            .candidates(&parse_query(query))
+            .into_iter()
+            .map(|p| {
+                Path::new(&p)
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_word_prefix_and_phrase_candidates() {
+        let dir = tempdir().unwrap();
+        let sessions = vec![
+            write_session(dir.path(), "a", "The login redirect loops"),
+            write_session(dir.path(), "b", "Redirecting after logout"),
+            write_session(dir.path(), "c", "Unrelated"),
+        ];
+        let mut index = SearchIndex::in_memory();
+        assert_eq!(index.update(dir.path(), &sessions, 2), 3);
+
+        assert_eq!(candidate_names(&index, "login"), vec!["a"]);
+        assert_eq!(candidate_names(&index, "redirect*"), vec!["a", "b"]);
+        assert_eq!(candidate_names(&index, r#""login redirect""#), vec!["a"]);
+        assert_eq!(
+            candidate_names(&index, "login logout"),
+            Vec::<String>::new()
+        );
+        assert_eq!(candidate_names(&index, "missing*"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_update_is_incremental_and_persistent() {
+        let dir = tempdir().unwrap();
+        let config = tempdir().unwrap();
+        let index_path = config.path().join("search-index");
+        let a = write_session(dir.path(), "a", "alpha");
+        let b = write_session(dir.path(), "b", "beta");
+
+        let mut index = SearchIndex::load_from(&index_path);
+        assert_eq!(index.update(dir.path(), &[a.clone(), b.clone()], 1), 2);
+        index.save().unwrap();
+
+        // Unchanged sessions are not read again
+        let mut reloaded = SearchIndex::load_from(&index_path);
+        assert_eq!(reloaded.update(dir.path(), &[a.clone(), b.clone()], 1), 0);
+
+        // A changed session is re-read and its old tokens forgotten; a session
+        // that is no longer discovered is dropped
+        let a2 = write_session(dir.path(), "a", "gamma");
+        assert_eq!(reloaded.update(dir.path(), &[a2], 1), 1);
+        assert_eq!(reloaded.len(), 1);
+        assert!(candidate_names(&reloaded, "alpha").is_empty());
+        assert!(candidate_names(&reloaded, "beta").is_empty());
+        assert_eq!(candidate_names(&reloaded, "gamma"), vec!["a"]);
+
+        // Sessions under other base paths are left alone
+        let other = tempdir().unwrap();
+        let c = write_session(other.path(), "c", "delta");
+        reloaded.update(other.path(), &[c], 1);
+        reloaded.update(dir.path(), &[], 1);
+        assert_eq!(candidate_names(&reloaded, "delta"), vec!["c"]);
+
+        reloaded.clear();
+        assert!(reloaded.is_empty());
+    }
+
+    #[test]
+    fn test_segments_are_appended_and_merged() {
+        let dir = tempdir().unwrap();
+        let config = tempdir().unwrap();
+        let index_path = config.path().join("search-index");
+        let segments = || {
+            let mut names: Vec<String> = fs::read_dir(&index_path)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .filter(|n| n.starts_with("segment-"))
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Every update that reads a transcript appends one segment, and an
+        // update that reads nothing writes nothing
+        let mut words = Vec::new();
+        for i in 0..MAX_SEGMENTS {
+            words.push(format!("word{i}"));
+            let sessions: Vec<SessionSummary> = words
+                .iter()
+                .map(|w| write_session(dir.path(), w, w))
+                .collect();
+            let mut index = SearchIndex::load_from(&index_path);
+            assert_eq!(index.update(dir.path(), &sessions, 1), 1);
+            index.save().unwrap();
+        }
+        assert_eq!(segments().len(), MAX_SEGMENTS);
+        let unchanged = SearchIndex::load_from(&index_path).dirty;
+        assert!(!unchanged);
+
+        // Dropping a transcript only touches the manifest, until the next
+        // save merges the segments and leaves its postings out
+        let sessions: Vec<SessionSummary> = words[1..]
+            .iter()
+            .map(|w| write_session(dir.path(), w, w))
+            .collect();
+        let mut index = SearchIndex::load_from(&index_path);
+        assert_eq!(index.update(dir.path(), &sessions, 1), 0);
+        assert!(candidate_names(&index, "word0").is_empty());
+        assert_eq!(candidate_names(&index, "word3"), vec!["word3"]);
+        index.save().unwrap();
+        assert_eq!(segments().len(), 1);
+
+        let reloaded = SearchIndex::load_from(&index_path);
+        assert_eq!(reloaded.len(), MAX_SEGMENTS - 1);
+        assert_eq!(candidate_names(&reloaded, "word1*").len(), 7);
+        assert!(!fs::read_to_string(segment_path(&index_path, 16))
+            .unwrap()
+            .contains("word0"));
+    }
+}
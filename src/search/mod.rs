@@ -0,0 +1,543 @@
+//! Search across conversation history.
+//!
+//! Matches the visible text of user and assistant messages (see
+//! [`Message::text`](crate::message::Message::text)), never the surrounding
+//! JSON, so a query for `cwd` finds messages that talk about `cwd` rather than
+//! every entry that has the field. Queries run over sessions already
+//! discovered locally and in the sync repository; a session present on both
+//! sides is reported once, preferring the local copy.
+//!
+//! See [`query`] for the query syntax. The persistent [`index`] narrows a
+//! query to the transcripts that can match before any of them are read.
+
+pub mod index;
+pub mod query;
+pub mod sessions;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{EntryStream, SessionInfo, SessionSummary};
+use crate::sync::discovery::extract_project_name;
+use crate::sync::parallel::{parallel_map, resolve_threads};
+
+use self::index::SearchIndex;
+use self::query::{highlight_ranges, matches_all, parse_query, QueryTerm};
+
+/// Characters of context shown on each side of the first match
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Longest snippet returned, in characters
+const SNIPPET_MAX_CHARS: usize = 200;
+
+/// Message author a search can be restricted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    /// Entry type carrying messages of this role
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            other => anyhow::bail!("Unknown role: '{}'. Use 'user' or 'assistant'.", other),
+        }
+    }
+}
+
+/// Where a matching session was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `~/.claude/projects`
+    Local,
+    /// The sync repository
+    SyncRepo,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Local => write!(f, "local"),
+            Source::SyncRepo => write!(f, "sync repo"),
+        }
+    }
+}
+
+/// A search query and its filters
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Words, `prefix*` terms and `"quoted phrases"` that must all appear in
+    /// a message (case-insensitive)
+    pub text: String,
+    /// Only sessions whose project name contains this (case-insensitive)
+    pub project: Option<String>,
+    /// Only messages on or after this day (UTC)
+    pub since: Option<NaiveDate>,
+    /// Only messages on or before this day (UTC)
+    pub until: Option<NaiveDate>,
+    /// Only messages recorded on this git branch
+    pub branch: Option<String>,
+    /// Only messages by this author
+    pub role: Option<Role>,
+}
+
+impl SearchQuery {
+    /// A query for `text` with no filters
+    pub fn new(text: impl Into<String>) -> Self {
+        SearchQuery {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The parsed query terms
+    pub fn terms(&self) -> Vec<QueryTerm> {
+        parse_query(&self.text)
+    }
+
+    fn matches_project(&self, project: &str) -> bool {
+        self.project
+            .as_ref()
+            .is_none_or(|wanted| project.to_lowercase().contains(&wanted.to_lowercase()))
+    }
+
+    fn matches_date(&self, timestamp: Option<&str>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Some(day) = timestamp
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc).date_naive())
+        else {
+            return false;
+        };
+        self.since.is_none_or(|since| day >= since) && self.until.is_none_or(|until| day <= until)
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date given on the command line
+pub fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{value}' (expected YYYY-MM-DD)"))
+}
+
+/// One matching message
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// Session the message belongs to
+    pub session_id: String,
+    /// Parent session, if the message is in a subagent transcript
+    pub parent_session_id: Option<String>,
+    /// Project name
+    pub project: String,
+    /// Message timestamp
+    pub timestamp: Option<String>,
+    /// "user" or "assistant"
+    pub role: String,
+    /// Git branch recorded with the message
+    pub git_branch: Option<String>,
+    /// Entry uuid, used to report a message only once
+    pub uuid: Option<String>,
+    /// Single-line excerpt around the first match
+    pub snippet: String,
+    /// Transcript the message was found in
+    pub file_path: PathBuf,
+    /// Which side the transcript was found on
+    pub source: Source,
+}
+
+/// Project name of a session: from its `cwd`, else from the encoded project
+/// directory it is stored in
+pub(crate) fn session_project(summary: &SessionSummary, base_path: &Path) -> String {
+    if let Some(name) = summary.project_name() {
+        return name.to_string();
+    }
+    Path::new(&summary.file_path)
+        .strip_prefix(base_path)
+        .ok()
+        .and_then(|relative| relative.components().next())
+        .and_then(|c| c.as_os_str().to_str())
+        .map(|dir| extract_project_name(dir).to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Scan one session's messages for the query
+fn search_session(
+    query: &SearchQuery,
+    terms: &[QueryTerm],
+    summary: &SessionSummary,
+    project: &str,
+    source: Source,
+) -> Result<Vec<SearchHit>> {
+    let mut hits = Vec::new();
+    for entry in EntryStream::open_with_mode(&summary.file_path, summary.parse_mode)? {
+        let entry = entry?;
+        if entry.entry_type != "user" && entry.entry_type != "assistant" {
+            continue;
+        }
+        if query
+            .role
+            .is_some_and(|role| role.as_str() != entry.entry_type)
+        {
+            continue;
+        }
+        if query
+            .branch
+            .as_ref()
+            .is_some_and(|branch| entry.git_branch.as_ref() != Some(branch))
+        {
+            continue;
+        }
+        if !query.matches_date(entry.timestamp.as_deref()) {
+            continue;
+        }
+
+        let Some(message) = entry.typed_message() else {
+            continue;
+        };
+        let text = message.text();
+        if !matches_all(&text, terms) {
+            continue;
+        }
+
+        hits.push(SearchHit {
+            session_id: summary.session_id.clone(),
+            parent_session_id: summary.parent_session_id.clone(),
+            project: project.to_string(),
+            timestamp: entry.timestamp.clone(),
+            role: entry.entry_type.clone(),
+            git_branch: entry.git_branch.clone(),
+            uuid: entry.uuid.clone(),
+            snippet: snippet(&text, terms),
+            file_path: PathBuf::from(&summary.file_path),
+            source,
+        });
+    }
+    Ok(hits)
+}
+
+/// Search the given sessions, newest message first.
+///
+/// `sides` pairs each discovered session list with its source and the
+/// directory it was discovered under. Sessions are scanned on up to `threads`
+/// workers (0 = one per CPU). A message found on more than one side is
+/// reported once, from the first side listed. Transcripts that can no longer
+/// be read are skipped with a warning.
+pub fn search(
+    query: &SearchQuery,
+    sides: &[(Source, &Path, &[SessionSummary])],
+    threads: usize,
+) -> Vec<SearchHit> {
+    search_sessions(query, sides, threads, None)
+}
+
+/// Like [`search`], but only scans sessions the index says can match.
+///
+/// Sessions the index does not hold at their current content are scanned
+/// regardless, so results never depend on how fresh the index is.
+pub fn search_indexed(
+    query: &SearchQuery,
+    sides: &[(Source, &Path, &[SessionSummary])],
+    threads: usize,
+    index: &SearchIndex,
+) -> Vec<SearchHit> {
+    search_sessions(query, sides, threads, Some(index))
+}
+
+fn search_sessions(
+    query: &SearchQuery,
+    sides: &[(Source, &Path, &[SessionSummary])],
+    threads: usize,
+    index: Option<&SearchIndex>,
+) -> Vec<SearchHit> {
+    let terms = query.terms();
+    let indexed_candidates = index.map(|index| (index, index.candidates(&terms)));
+
+    let candidates: Vec<(Source, &SessionSummary, String)> = sides
+        .iter()
+        .flat_map(|(source, base_path, sessions)| {
+            sessions
+                .iter()
+                .map(move |s| (*source, s, session_project(s, base_path)))
+        })
+        .filter(|(_, summary, _)| match &indexed_candidates {
+            Some((index, paths)) => {
+                paths.contains(&summary.file_path)
+                    || !index.is_current(&summary.file_path, &summary.content_hash)
+            }
+            None => true,
+        })
+        .filter(|(_, _, project)| query.matches_project(project))
+        .collect();
+
+    let results = parallel_map(
+        &candidates,
+        resolve_threads(threads),
+        |(source, summary, project)| search_session(query, &terms, summary, project, *source),
+    );
+
+    let mut seen = HashSet::new();
+    let mut hits = Vec::new();
+    for ((_, summary, _), result) in candidates.iter().zip(results) {
+        match result {
+            Ok(session_hits) => hits.extend(session_hits.into_iter().filter(|hit| {
+                // Entries without a uuid cannot be matched across sides
+                hit.uuid.is_none() || seen.insert((hit.session_id.clone(), hit.uuid.clone()))
+            })),
+            Err(e) => log::warn!("Failed to search {}: {:#}", summary.file_path, e),
+        }
+    }
+
+    hits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    hits
+}
+
+/// Single-line excerpt of `text` around the earliest match of any term
+pub fn snippet(text: &str, terms: &[QueryTerm]) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let first_match = highlight_ranges(&flat, terms)
+        .first()
+        .map_or(0, |r| r.start);
+
+    let match_char = flat[..first_match].chars().count();
+    let start_char = match_char.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let mut excerpt: String = flat
+        .chars()
+        .skip(start_char)
+        .take(SNIPPET_MAX_CHARS)
+        .collect();
+
+    if start_char > 0 {
+        excerpt.insert(0, '…');
+    }
+    if start_char + SNIPPET_MAX_CHARS < flat.chars().count() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn entry(kind: &str, uuid: &str, ts: &str, branch: &str, text: &str) -> String {
+        serde_json::json!({
+            "type": kind,
+            "uuid": uuid,
+            "timestamp": ts,
+            "gitBranch": branch,
+            "cwd": "/home/user/webapp",
+            "message": {"role": kind, "content": [{"type": "text", "text": text}]}
+        })
+        .to_string()
+    }
+
+    fn write_session(dir: &Path, name: &str, lines: &[String]) -> SessionSummary {
+        let project = dir.join("-home-user-webapp");
+        fs::create_dir_all(&project).unwrap();
+        let path = project.join(format!("{name}.jsonl"));
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+        SessionSummary::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn test_matches_message_text_not_json() {
+        let dir = tempdir().unwrap();
+        let session = write_session(
+            dir.path(),
+            "s1",
+            &[
+                entry(
+                    "user",
+                    "1",
+                    "2025-01-01T00:00:00Z",
+                    "main",
+                    "How do I fix the Login bug?",
+                ),
+                entry(
+                    "assistant",
+                    "2",
+                    "2025-01-01T00:01:00Z",
+                    "main",
+                    "Check the session cookie.",
+                ),
+            ],
+        );
+        let sessions = [session];
+        let sides = [(Source::Local, dir.path(), &sessions[..])];
+
+        let hits = search(&SearchQuery::new("login BUG"), &sides, 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "s1");
+        assert_eq!(hits[0].project, "webapp");
+        assert_eq!(hits[0].role, "user");
+
+        // Field names and values outside the message text never match
+        assert!(search(&SearchQuery::new("gitBranch"), &sides, 1).is_empty());
+        assert!(search(&SearchQuery::new("webapp"), &sides, 1).is_empty());
+    }
+
+    #[test]
+    fn test_filters() {
+        let dir = tempdir().unwrap();
+        let session = write_session(
+            dir.path(),
+            "s1",
+            &[
+                entry("user", "1", "2025-01-01T10:00:00Z", "main", "deploy please"),
+                entry(
+                    "assistant",
+                    "2",
+                    "2025-02-01T10:00:00Z",
+                    "feature",
+                    "deploy done",
+                ),
+            ],
+        );
+        let sessions = [session];
+        let sides = [(Source::Local, dir.path(), &sessions[..])];
+        let run = |query: SearchQuery| -> Vec<String> {
+            search(&query, &sides, 1)
+                .into_iter()
+                .map(|h| h.uuid.unwrap())
+                .collect()
+        };
+
+        // Newest first
+        assert_eq!(run(SearchQuery::new("deploy")), vec!["2", "1"]);
+        assert_eq!(
+            run(SearchQuery {
+                role: Some(Role::User),
+                ..SearchQuery::new("deploy")
+            }),
+            vec!["1"]
+        );
+        assert_eq!(
+            run(SearchQuery {
+                branch: Some("feature".to_string()),
+                ..SearchQuery::new("deploy")
+            }),
+            vec!["2"]
+        );
+        assert_eq!(
+            run(SearchQuery {
+                since: Some(parse_date("2025-01-15").unwrap()),
+                ..SearchQuery::new("deploy")
+            }),
+            vec!["2"]
+        );
+        assert_eq!(
+            run(SearchQuery {
+                until: Some(parse_date("2025-01-01").unwrap()),
+                ..SearchQuery::new("deploy")
+            }),
+            vec!["1"]
+        );
+        assert!(run(SearchQuery {
+            project: Some("other".to_string()),
+            ..SearchQuery::new("deploy")
+        })
+        .is_empty());
+        assert_eq!(
+            run(SearchQuery {
+                project: Some("WEB".to_string()),
+                ..SearchQuery::new("deploy")
+            }),
+            vec!["2", "1"]
+        );
+    }
+
+    #[test]
+    fn test_indexed_search_with_phrases_and_prefixes() {
+        let dir = tempdir().unwrap();
+        let indexed = write_session(
+            dir.path(),
+            "s1",
+            &[entry(
+                "user",
+                "1",
+                "2025-01-01T00:00:00Z",
+                "main",
+                "the session cookie is stale",
+            )],
+        );
+        let mut index = SearchIndex::in_memory();
+        index.update(dir.path(), std::slice::from_ref(&indexed), 1);
+
+        // Written after indexing, so it must be scanned regardless
+        let unindexed = write_session(
+            dir.path(),
+            "s2",
+            &[entry(
+                "user",
+                "1",
+                "2025-01-02T00:00:00Z",
+                "main",
+                "cookie session mixup",
+            )],
+        );
+        let sessions = [indexed, unindexed];
+        let sides = [(Source::Local, dir.path(), &sessions[..])];
+        let run = |text: &str| -> Vec<String> {
+            search_indexed(&SearchQuery::new(text), &sides, 1, &index)
+                .into_iter()
+                .map(|h| h.session_id)
+                .collect()
+        };
+
+        assert_eq!(run(r#""session cookie""#), vec!["s1"]);
+        assert_eq!(run("cook* sess*"), vec!["s2", "s1"]);
+        assert_eq!(run("mixup"), vec!["s2"]);
+        assert!(run("cook").is_empty());
+    }
+
+    #[test]
+    fn test_same_message_on_both_sides_is_reported_once() {
+        let local = tempdir().unwrap();
+        let repo = tempdir().unwrap();
+        let line = entry("user", "1", "2025-01-01T00:00:00Z", "main", "needle");
+        let local_sessions = [write_session(
+            local.path(),
+            "s1",
+            std::slice::from_ref(&line),
+        )];
+        let repo_sessions = [write_session(repo.path(), "s1", &[line])];
+
+        let hits = search(
+            &SearchQuery::new("needle"),
+            &[
+                (Source::Local, local.path(), &local_sessions[..]),
+                (Source::SyncRepo, repo.path(), &repo_sessions[..]),
+            ],
+            2,
+        );
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, Source::Local);
+    }
+
+    #[test]
+    fn test_snippet_and_highlight_ranges() {
+        let text = format!("{} the Needle\nis here", "x ".repeat(100));
+        let excerpt = snippet(&text, &parse_query("needle"));
+        assert!(excerpt.starts_with('…'));
+        assert!(excerpt.contains("the Needle is here"));
+    }
+}
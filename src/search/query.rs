@@ -0,0 +1,196 @@
+//! Query language and tokenization shared by scanning and the index.
+//!
+//! Text is split into tokens: runs of letters, digits and underscores,
+//! lowercased. A query is a list of terms that must all match a message:
+//!
+//! - `word` matches the token `word`
+//! - `word*` matches any token starting with `word`
+//! - `"two words"` matches those tokens next to each other, in order
+//!
+//! On the command line, a single argument containing spaces is a phrase too,
+//! since the shell has already removed its quotes.
+
+use std::ops::Range;
+
+/// One term of a search query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryTerm {
+    /// A whole token
+    Word(String),
+    /// Any token starting with this
+    Prefix(String),
+    /// Consecutive tokens
+    Phrase(Vec<String>),
+}
+
+/// Lowercased tokens of `text` with their byte ranges in `text`
+pub fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let in_token = c.is_alphanumeric() || c == '_';
+        match (in_token, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push((text[s..i].to_lowercase(), s..i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((text[s..].to_lowercase(), s..text.len()));
+    }
+    tokens
+}
+
+/// Join command-line arguments into query text, quoting arguments that
+/// contain whitespace so they are searched as phrases
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) && !arg.contains('"') {
+                format!("\"{arg}\"")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse query text into terms. Terms without any token (e.g. lone
+/// punctuation) are dropped.
+pub fn parse_query(text: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    for (i, segment) in text.split('"').enumerate() {
+        if i % 2 == 1 {
+            // Inside quotes
+            let words: Vec<String> = tokenize(segment).into_iter().map(|(t, _)| t).collect();
+            match words.len() {
+                0 => {}
+                1 => terms.push(QueryTerm::Word(
+                    words.into_iter().next().unwrap_or_default(),
+                )),
+                _ => terms.push(QueryTerm::Phrase(words)),
+            }
+            continue;
+        }
+
+        for word in segment.split_whitespace() {
+            let prefix = word.ends_with('*');
+            let words: Vec<String> = tokenize(word).into_iter().map(|(t, _)| t).collect();
+            match words.len() {
+                0 => {}
+                // "foo-bar" is two tokens in the text, so search it as a phrase
+                n if n > 1 => terms.push(QueryTerm::Phrase(words)),
+                _ => {
+                    let token = words.into_iter().next().unwrap_or_default();
+                    terms.push(if prefix {
+                        QueryTerm::Prefix(token)
+                    } else {
+                        QueryTerm::Word(token)
+                    });
+                }
+            }
+        }
+    }
+    terms
+}
+
+impl QueryTerm {
+    /// Byte ranges in the tokenized text where this term matches
+    fn find(&self, tokens: &[(String, Range<usize>)]) -> Vec<Range<usize>> {
+        match self {
+            QueryTerm::Word(word) => tokens
+                .iter()
+                .filter(|(t, _)| t == word)
+                .map(|(_, r)| r.clone())
+                .collect(),
+            QueryTerm::Prefix(prefix) => tokens
+                .iter()
+                .filter(|(t, _)| t.starts_with(prefix.as_str()))
+                .map(|(_, r)| r.clone())
+                .collect(),
+            QueryTerm::Phrase(words) => tokens
+                .windows(words.len())
+                .filter(|window| window.iter().zip(words).all(|((t, _), w)| t == w))
+                .map(|window| window[0].1.start..window[window.len() - 1].1.end)
+                .collect(),
+        }
+    }
+}
+
+/// True if every term matches `text`
+pub fn matches_all(text: &str, terms: &[QueryTerm]) -> bool {
+    let tokens = tokenize(text);
+    terms.iter().all(|term| !term.find(&tokens).is_empty())
+}
+
+/// Byte ranges of every match of any term in `text`, sorted and merged, for
+/// highlighting
+pub fn highlight_ranges(text: &str, terms: &[QueryTerm]) -> Vec<Range<usize>> {
+    let tokens = tokenize(text);
+    let mut ranges: Vec<Range<usize>> = terms.iter().flat_map(|term| term.find(&tokens)).collect();
+
+    ranges.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<String> = tokenize("Fix the `parse_mode` bug, ÉCOLE 42!")
+            .into_iter()
+            .map(|(t, _)| t)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec!["fix", "the", "parse_mode", "bug", "école", "42"]
+        );
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query(r#"Login redir* "session cookie" foo-bar ! "x""#),
+            vec![
+                QueryTerm::Word("login".into()),
+                QueryTerm::Prefix("redir".into()),
+                QueryTerm::Phrase(vec!["session".into(), "cookie".into()]),
+                QueryTerm::Phrase(vec!["foo".into(), "bar".into()]),
+                QueryTerm::Word("x".into()),
+            ]
+        );
+
+        let args = vec!["rate limit".to_string(), "retry*".to_string()];
+        assert_eq!(join_args(&args), r#""rate limit" retry*"#);
+    }
+
+    #[test]
+    fn test_matching_and_highlighting() {
+        let text = "The session cookie expired; redirecting to Login.";
+        let terms = parse_query(r#"login redirect* "session cookie""#);
+        assert!(matches_all(text, &terms));
+        assert_eq!(highlight_ranges(text, &terms), vec![4..18, 28..39, 43..48]);
+
+        // Words match whole tokens and phrases need adjacent tokens in order
+        assert!(!matches_all(text, &parse_query("log")));
+        assert!(!matches_all(text, &parse_query(r#""cookie session""#)));
+        assert!(!matches_all(text, &parse_query("login missing")));
+        assert_eq!(
+            highlight_ranges(text, &parse_query("login missing")),
+            vec![43..48]
+        );
+    }
+}
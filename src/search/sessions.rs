@@ -0,0 +1,181 @@
+//! Sessions known to this machine, locally and in the sync repository.
+//!
+//! Commands that read history rather than sync it (`search`, `export`, ...)
+//! look at both sides: the sync repository holds sessions pulled from other
+//! machines that are not in `~/.claude/projects` yet.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::encryption::view::RepoView;
+use crate::filter::FilterConfig;
+use crate::parser::SessionSummary;
+use crate::sync::discovery::claude_projects_dir;
+use crate::sync::index::DiscoveryIndex;
+use crate::sync::SyncState;
+
+use super::{session_project, Source};
+
+/// Sessions discovered locally and in the sync repository
+pub struct KnownSessions {
+    local_dir: PathBuf,
+    local: Vec<SessionSummary>,
+    repo_dir: Option<PathBuf>,
+    repo: Vec<SessionSummary>,
+}
+
+/// A session found by id
+#[derive(Debug)]
+pub struct FoundSession<'a> {
+    pub summary: &'a SessionSummary,
+    pub source: Source,
+    /// Project name, from the session's `cwd` or its project directory
+    pub project: String,
+}
+
+impl KnownSessions {
+    /// Discover sessions on both sides through the discovery index.
+    ///
+    /// Works before `init` too; there is just no repository to include.
+    pub fn discover(filter: &FilterConfig) -> Result<Self> {
+        let local_dir = claude_projects_dir()?;
+        let mut index = DiscoveryIndex::load()?;
+
+        let (local, _) = index.discover(&local_dir, filter)?;
+
+        let repo_dir = SyncState::load()
+            .ok()
+            .and_then(|state| RepoView::plaintext_root(&state.sync_repo_path).ok())
+            .map(|root| root.join(&filter.sync_subdirectory))
+            .filter(|dir| dir.exists());
+        let repo = match &repo_dir {
+            Some(dir) => index.discover(dir, filter)?.0,
+            None => Vec::new(),
+        };
+        index.save_or_warn();
+
+        Ok(KnownSessions {
+            local_dir,
+            local,
+            repo_dir,
+            repo,
+        })
+    }
+
+    /// Each side with the directory it was discovered under, local first
+    pub fn sides(&self) -> Vec<(Source, &Path, &[SessionSummary])> {
+        let mut sides = vec![(Source::Local, self.local_dir.as_path(), &self.local[..])];
+        if let Some(dir) = &self.repo_dir {
+            sides.push((Source::SyncRepo, dir.as_path(), &self.repo[..]));
+        }
+        sides
+    }
+
+    /// Find a session by id or unique id prefix, preferring the local copy
+    pub fn find(&self, id: &str) -> Result<FoundSession<'_>> {
+        find_session(&self.sides(), id)
+    }
+}
+
+/// Find a session by id or unique id prefix across `sides`.
+///
+/// An exact id wins over prefixes. A session present on several sides
+/// counts once and is returned from the first side listed.
+pub fn find_session<'a>(
+    sides: &[(Source, &Path, &'a [SessionSummary])],
+    id: &str,
+) -> Result<FoundSession<'a>> {
+    let id = id.trim();
+    if id.is_empty() {
+        anyhow::bail!("Session id is empty");
+    }
+
+    let found = |source: Source, base_path: &Path, summary: &'a SessionSummary| FoundSession {
+        summary,
+        source,
+        project: session_project(summary, base_path),
+    };
+
+    for (source, base_path, sessions) in sides {
+        if let Some(summary) = sessions.iter().find(|s| s.session_id == id) {
+            return Ok(found(*source, base_path, summary));
+        }
+    }
+
+    let mut matches: Vec<(Source, &Path, &'a SessionSummary)> = Vec::new();
+    for (source, base_path, sessions) in sides {
+        for summary in sessions.iter().filter(|s| s.session_id.starts_with(id)) {
+            if !matches
+                .iter()
+                .any(|(_, _, m)| m.session_id == summary.session_id)
+            {
+                matches.push((*source, base_path, summary));
+            }
+        }
+    }
+
+    match matches.len() {
+        0 => anyhow::bail!("No session found with id '{}'", id),
+        1 => {
+            let (source, base_path, summary) = matches[0];
+            Ok(found(source, base_path, summary))
+        }
+        n => {
+            let mut ids: Vec<&str> = matches
+                .iter()
+                .map(|(_, _, s)| s.session_id.as_str())
+                .collect();
+            ids.sort_unstable();
+            anyhow::bail!(
+                "Session id '{}' is ambiguous ({} matches): {}",
+                id,
+                n,
+                ids.join(", ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, id: &str) -> SessionSummary {
+        let path = dir.join("-home-u-web").join(format!("{id}.jsonl"));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{\"type\":\"user\",\"uuid\":\"1\"}\n").unwrap();
+        SessionSummary::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn test_find_by_id_and_prefix() {
+        let local = tempdir().unwrap();
+        let repo = tempdir().unwrap();
+        let local_sessions = [write_session(local.path(), "abc1")];
+        let repo_sessions = [
+            write_session(repo.path(), "abc1"),
+            write_session(repo.path(), "abc12"),
+            write_session(repo.path(), "def"),
+        ];
+        let sides = [
+            (Source::Local, local.path(), &local_sessions[..]),
+            (Source::SyncRepo, repo.path(), &repo_sessions[..]),
+        ];
+
+        // Exact ids win over longer ids sharing the prefix
+        let found = find_session(&sides, "abc1").unwrap();
+        assert_eq!(found.source, Source::Local);
+        assert_eq!(found.project, "web");
+
+        let found = find_session(&sides, "de").unwrap();
+        assert_eq!(found.summary.session_id, "def");
+        assert_eq!(found.source, Source::SyncRepo);
+
+        let err = find_session(&sides, "ab").unwrap_err().to_string();
+        assert!(err.contains("ambiguous"), "{err}");
+        assert!(err.contains("abc1, abc12"), "{err}");
+        assert!(find_session(&sides, "zzz").is_err());
+    }
+}
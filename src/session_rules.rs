@@ -0,0 +1,476 @@
+//! Content-based exclusion of sessions from sync.
+//!
+//! Path patterns decide by where a transcript lives; exclusion rules decide by
+//! what it holds. Each `[[exclude_rules]]` table combines conditions on the
+//! visible message text (keywords or a regular expression), the git branch,
+//! working directory and model recorded in the transcript, and its message
+//! count. A rule matches a session when all of its conditions do, and a session
+//! matched by any rule is left out, together with the rest of its sync unit
+//! (see [`crate::sync::units`]): push does not copy it into the sync
+//! repository, and pull does not write it from there to this machine.
+//! Sessions already in the sync repository stay there.
+//!
+//! Transcripts are streamed against the rules, and each outcome is cached in
+//! the discovery index until the file or the rules change.
+
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+use crate::parser::{ConversationEntry, ConversationSession, EntryStream, ParseMode, SessionInfo};
+use crate::sync::index::DiscoveryIndex;
+use crate::sync::parallel::{parallel_map, resolve_threads};
+use crate::sync::units::unit_id;
+
+/// A user-defined exclusion rule, as an `[[exclude_rules]]` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcludeRule {
+    /// Name shown in logs and error messages
+    pub name: String,
+    /// Words or phrases, any of which in the message text matches
+    /// (case-insensitive)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Regular expression searched for in the message text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Regular expression searched for in the `gitBranch` of any entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    /// Regular expression searched for in the `cwd` of any entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Regular expression searched for in the model of any assistant message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Matches sessions with at least this many messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_messages: Option<usize>,
+    /// Matches sessions with at most this many messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<usize>,
+}
+
+impl ExcludeRule {
+    /// Whether the rule has no condition, and would match every session
+    fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+            && self.text.is_none()
+            && self.git_branch.is_none()
+            && self.cwd.is_none()
+            && self.model.is_none()
+            && self.min_messages.is_none()
+            && self.max_messages.is_none()
+    }
+}
+
+struct CompiledRule {
+    name: String,
+    keywords: Option<Regex>,
+    text: Option<Regex>,
+    git_branch: Option<Regex>,
+    cwd: Option<Regex>,
+    model: Option<Regex>,
+    min_messages: Option<usize>,
+    max_messages: Option<usize>,
+}
+
+/// Conditions of one rule not yet met while reading a session
+struct Pending<'r> {
+    rule: &'r CompiledRule,
+    keywords: bool,
+    text: bool,
+    git_branch: bool,
+    cwd: bool,
+    model: bool,
+}
+
+/// The configured exclusion rules, compiled
+pub struct SessionRules {
+    rules: Vec<CompiledRule>,
+    /// Fingerprint of the rules, for cached outcomes
+    fingerprint: String,
+}
+
+impl SessionRules {
+    /// Check configured rules for mistakes
+    pub fn validate(rules: &[ExcludeRule]) -> Result<()> {
+        Self::new(rules).map(|_| ())
+    }
+
+    /// Mistakes compiling would not catch: a rule without a condition
+    /// matches every session
+    fn check(rules: &[ExcludeRule]) -> Result<()> {
+        for (i, rule) in rules.iter().enumerate() {
+            let name = rule.name.trim();
+            if name.is_empty() {
+                bail!("exclude rule {} has no name", i + 1);
+            }
+            if rules[..i].iter().any(|r| r.name.trim() == name) {
+                bail!("exclude rule name '{}' is already in use", name);
+            }
+            if rule.is_empty() {
+                bail!(
+                    "exclude rule '{}' has no condition and would exclude every session",
+                    name
+                );
+            }
+            if rule.keywords.iter().any(|k| k.trim().is_empty()) {
+                bail!("exclude rule '{}' has an empty keyword", name);
+            }
+            if let (Some(min), Some(max)) = (rule.min_messages, rule.max_messages) {
+                if min > max {
+                    bail!(
+                        "exclude rule '{}' has min_messages above max_messages",
+                        name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile `rules`
+    pub fn new(rules: &[ExcludeRule]) -> Result<Self> {
+        Self::check(rules)?;
+        let fingerprint = Sha256::digest(serde_json::to_string(rules)?.as_bytes())[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let name = rule.name.trim();
+                let compile = |field: &str, pattern: &Option<String>| {
+                    pattern
+                        .as_deref()
+                        .map(Regex::new)
+                        .transpose()
+                        .with_context(|| {
+                            format!("Invalid {field} pattern for exclude rule '{name}'")
+                        })
+                };
+                let keywords = (!rule.keywords.is_empty())
+                    .then(|| {
+                        let alternatives: Vec<String> =
+                            rule.keywords.iter().map(|k| regex::escape(k)).collect();
+                        RegexBuilder::new(&alternatives.join("|"))
+                            .case_insensitive(true)
+                            .build()
+                    })
+                    .transpose()
+                    .with_context(|| format!("Invalid keywords for exclude rule '{name}'"))?;
+                Ok(CompiledRule {
+                    name: name.to_string(),
+                    keywords,
+                    text: compile("text", &rule.text)?,
+                    git_branch: compile("git_branch", &rule.git_branch)?,
+                    cwd: compile("cwd", &rule.cwd)?,
+                    model: compile("model", &rule.model)?,
+                    min_messages: rule.min_messages,
+                    max_messages: rule.max_messages,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(SessionRules { rules, fingerprint })
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Name of a rule matching `session`, if any
+    pub fn matching(&self, session: &ConversationSession) -> Option<&str> {
+        let mut pending = self.pending(session.message_count());
+        Self::found(&pending).or_else(|| {
+            session
+                .entries
+                .iter()
+                .find_map(|entry| Self::feed(&mut pending, entry))
+        })
+    }
+
+    /// Name of a rule matching the transcript at `path` with `message_count`
+    /// messages, if any. Entries are streamed, and reading stops at the first
+    /// match.
+    fn matching_file(
+        &self,
+        path: &str,
+        message_count: usize,
+        mode: ParseMode,
+    ) -> Result<Option<&str>> {
+        let mut pending = self.pending(message_count);
+        if let Some(rule) = Self::found(&pending) {
+            return Ok(Some(rule));
+        }
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        for entry in EntryStream::open_with_mode(path, mode)? {
+            if let Some(rule) = Self::feed(&mut pending, &entry?) {
+                return Ok(Some(rule));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Rules whose message count bounds admit `message_count`, with every
+    /// other condition still to be met
+    fn pending(&self, message_count: usize) -> Vec<Pending<'_>> {
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.min_messages.is_none_or(|min| message_count >= min)
+                    && rule.max_messages.is_none_or(|max| message_count <= max)
+            })
+            .map(|rule| Pending {
+                rule,
+                keywords: rule.keywords.is_some(),
+                text: rule.text.is_some(),
+                git_branch: rule.git_branch.is_some(),
+                cwd: rule.cwd.is_some(),
+                model: rule.model.is_some(),
+            })
+            .collect()
+    }
+
+    /// Name of a rule with no condition left
+    fn found<'r>(pending: &[Pending<'r>]) -> Option<&'r str> {
+        pending
+            .iter()
+            .find(|p| !(p.keywords || p.text || p.git_branch || p.cwd || p.model))
+            .map(|p| p.rule.name.as_str())
+    }
+
+    /// Mark the conditions `entry` meets; name of a rule it completes, if any
+    fn feed<'r>(pending: &mut [Pending<'r>], entry: &ConversationEntry) -> Option<&'r str> {
+        let hit = |regex: &Option<Regex>, value: Option<&str>| {
+            regex
+                .as_ref()
+                .zip(value)
+                .is_some_and(|(regex, value)| regex.is_match(value))
+        };
+        let mut text: Option<Option<String>> = None;
+        for p in pending.iter_mut() {
+            let rule = p.rule;
+            p.git_branch &= !hit(&rule.git_branch, entry.git_branch.as_deref());
+            p.cwd &= !hit(&rule.cwd, entry.cwd.as_deref());
+            p.model &= !hit(&rule.model, entry_model(entry));
+            if p.keywords || p.text {
+                let text = text.get_or_insert_with(|| message_text(entry)).as_deref();
+                p.keywords &= !hit(&rule.keywords, text);
+                p.text &= !hit(&rule.text, text);
+            }
+        }
+        Self::found(pending)
+    }
+
+    /// Sync units with a transcript matched by a rule. Transcripts are read
+    /// in `mode`, up to `threads` at a time (0: one per CPU), unless `index`
+    /// holds their outcome for these rules. A transcript that cannot be read
+    /// is left out with its unit, with a warning.
+    pub fn excluded_units<'a, S: SessionInfo + Sync>(
+        &self,
+        sessions: &'a [S],
+        mode: ParseMode,
+        threads: usize,
+        index: &mut DiscoveryIndex,
+    ) -> HashSet<&'a str> {
+        if self.is_empty() {
+            return HashSet::new();
+        }
+        let mut matched: Vec<Option<Option<String>>> = sessions
+            .iter()
+            .map(|session| index.rule_match(session, &self.fingerprint))
+            .collect();
+        let misses: Vec<usize> = (0..sessions.len())
+            .filter(|&i| matched[i].is_none())
+            .collect();
+        let read = parallel_map(&misses, resolve_threads(threads), |&i| {
+            let session = &sessions[i];
+            self.matching_file(session.file_path(), session.message_count(), mode)
+                .map(|rule| rule.map(str::to_string))
+        });
+
+        let mut units = HashSet::new();
+        for (i, result) in misses.into_iter().zip(read) {
+            let session = &sessions[i];
+            match result {
+                Ok(rule) => {
+                    index.set_rule_match(session, &self.fingerprint, rule.clone());
+                    matched[i] = Some(rule);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Leaving session {} out: could not check it against exclude rules: {e:#}",
+                        session.session_id()
+                    );
+                    units.insert(unit_id(session));
+                }
+            }
+        }
+        for (session, matched) in sessions.iter().zip(matched) {
+            if let Some(Some(rule)) = matched {
+                log::debug!(
+                    "Session {} matches exclude rule '{}'",
+                    session.session_id(),
+                    rule
+                );
+                units.insert(unit_id(session));
+            }
+        }
+        units
+    }
+}
+
+/// Model of an assistant message
+fn entry_model(entry: &ConversationEntry) -> Option<&str> {
+    entry.message.as_ref()?.get("model")?.as_str()
+}
+
+/// Visible text of a user or assistant message
+fn message_text(entry: &ConversationEntry) -> Option<String> {
+    if entry.entry_type != "user" && entry.entry_type != "assistant" {
+        return None;
+    }
+    Some(entry.typed_message()?.text()).filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(lines: &[serde_json::Value]) -> ConversationSession {
+        ConversationSession {
+            session_id: "s".to_string(),
+            entries: lines
+                .iter()
+                .map(|line| serde_json::from_value(line.clone()).unwrap())
+                .collect(),
+            file_path: "s.jsonl".to_string(),
+        }
+    }
+
+    fn rule(name: &str) -> ExcludeRule {
+        ExcludeRule {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rules_match_all_conditions() {
+        let session = session(&[
+            serde_json::json!({"type": "user", "cwd": "/work/acme", "gitBranch": "secret/launch",
+                "message": {"role": "user", "content": "Draft the ACME Corp contract"}}),
+            serde_json::json!({"type": "assistant", "message": {"role": "assistant",
+                "model": "claude-haiku", "content": [{"type": "text", "text": "Done."}]}}),
+        ]);
+        let matches = |rule: ExcludeRule| {
+            SessionRules::new(&[rule])
+                .unwrap()
+                .matching(&session)
+                .is_some()
+        };
+
+        assert!(matches(ExcludeRule {
+            keywords: vec!["acme corp".to_string()],
+            ..rule("customer")
+        }));
+        assert!(matches(ExcludeRule {
+            git_branch: Some("^secret/".to_string()),
+            model: Some("haiku".to_string()),
+            ..rule("branch")
+        }));
+        assert!(matches(ExcludeRule {
+            max_messages: Some(2),
+            ..rule("short")
+        }));
+        // Every condition of a rule must hold
+        assert!(!matches(ExcludeRule {
+            keywords: vec!["acme".to_string()],
+            cwd: Some("^/home/".to_string()),
+            ..rule("elsewhere")
+        }));
+        assert!(!matches(ExcludeRule {
+            text: Some("acme corp".to_string()),
+            ..rule("case")
+        }));
+        assert!(!matches(ExcludeRule {
+            min_messages: Some(3),
+            ..rule("long")
+        }));
+    }
+
+    #[test]
+    fn test_excluded_units_are_cached_and_unreadable_sessions_left_out() {
+        let claude = tempfile::tempdir().unwrap();
+        let project = claude.path().join("-p");
+        std::fs::create_dir(&project).unwrap();
+        for (id, text) in [("a", "Draft the ACME contract"), ("b", "hello")] {
+            let line = serde_json::json!({"type": "user", "sessionId": id, "uuid": "u1",
+                "message": {"role": "user", "content": text}});
+            std::fs::write(project.join(format!("{id}.jsonl")), format!("{line}\n")).unwrap();
+        }
+        let mut index = DiscoveryIndex::in_memory();
+        let (sessions, _) = index
+            .discover(claude.path(), &crate::filter::FilterConfig::default())
+            .unwrap();
+        let rules = SessionRules::new(&[ExcludeRule {
+            keywords: vec!["acme".to_string()],
+            ..rule("customer")
+        }])
+        .unwrap();
+
+        let excluded = rules.excluded_units(&sessions, ParseMode::Strict, 1, &mut index);
+        assert_eq!(excluded, HashSet::from(["a"]));
+        assert_eq!(
+            index.rule_match(&sessions[0], &rules.fingerprint),
+            Some(Some("customer".to_string()))
+        );
+        assert_eq!(
+            index.rule_match(&sessions[1], &rules.fingerprint),
+            Some(None)
+        );
+
+        // Cached outcomes are not read again, and other rules are; a session
+        // that cannot be read is left out without failing the others
+        std::fs::remove_file(project.join("b.jsonl")).unwrap();
+        let excluded = rules.excluded_units(&sessions, ParseMode::Strict, 1, &mut index);
+        assert_eq!(excluded, HashSet::from(["a"]));
+        std::fs::write(project.join("b.jsonl"), "").unwrap();
+        let other = SessionRules::new(&[ExcludeRule {
+            keywords: vec!["nothing".to_string()],
+            ..rule("other")
+        }])
+        .unwrap();
+        std::fs::remove_file(project.join("a.jsonl")).unwrap();
+        let excluded = other.excluded_units(&sessions, ParseMode::Strict, 1, &mut index);
+        assert_eq!(excluded, HashSet::from(["a"]));
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        assert!(SessionRules::validate(&[ExcludeRule {
+            keywords: vec!["x".to_string()],
+            ..rule("ok")
+        }])
+        .is_ok());
+        assert!(SessionRules::validate(&[rule("empty")]).is_err());
+        assert!(SessionRules::validate(&[ExcludeRule {
+            text: Some("(".to_string()),
+            ..rule("bad")
+        }])
+        .is_err());
+        assert!(SessionRules::validate(&[ExcludeRule {
+            min_messages: Some(5),
+            max_messages: Some(2),
+            ..rule("range")
+        }])
+        .is_err());
+    }
+}
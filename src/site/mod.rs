@@ -0,0 +1,497 @@
+//! Static HTML site of the sync repository.
+//!
+//! `site build` renders the sessions of the sync repository into a directory
+//! that can be opened offline or served as-is by any web server:
+//!
+//! - `index.html`: every project with its session count and last activity
+//! - `projects/<project>.html`: the sessions of a project, newest first, with
+//!   their titles, dates and message counts
+//! - `sessions/<session>.html`: the transcript of a session's active branch
+//! - `search.html` and `search-index.js`: a client-side search over session
+//!   titles and message text
+//!
+//! A manifest in the output directory records the content hash of every
+//! rendered session. Rebuilding after a push only renders sessions that are
+//! new or changed, and removes the pages of sessions that are gone; the
+//! index pages are cheap and always rewritten. Subagent transcripts are not
+//! listed on their own.
+
+pub mod pages;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::parser::SessionSummary;
+use crate::sync::parallel::{parallel_map, resolve_threads};
+use crate::transcript::{html, Item, Part, Thread, TranscriptOptions};
+
+/// Bookkeeping of the last build, inside the output directory
+pub const MANIFEST_FILE: &str = ".site-manifest.json";
+
+/// Directory of the per-project session lists
+pub const PROJECTS_DIR: &str = "projects";
+
+/// Directory of the transcript pages
+pub const SESSIONS_DIR: &str = "sessions";
+
+/// Bump whenever the page layout or manifest format changes, so the next
+/// build renders everything again
+const MANIFEST_VERSION: u32 = 1;
+
+/// Message text kept per session in the search index
+const SEARCH_TEXT_MAX_CHARS: usize = 8_000;
+
+/// Longest session title, in characters
+const TITLE_MAX_CHARS: usize = 100;
+
+/// A rendered session, as listed and searched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SitePage {
+    pub session_id: String,
+    pub project: String,
+    pub content_hash: String,
+    /// Summary Claude Code wrote for the session, else its first prompt
+    pub title: String,
+    pub started: Option<String>,
+    pub last_activity: Option<String>,
+    /// Messages shown on the transcript page
+    pub messages: usize,
+    /// Message text for the search index, truncated
+    pub text: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// Version of claude-code-sync that rendered the pages
+    generator: String,
+    /// Session id -> rendered page
+    sessions: BTreeMap<String, SitePage>,
+    /// Project page files written by the build
+    projects: BTreeSet<String>,
+}
+
+impl Manifest {
+    fn fresh() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            generator: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The manifest of the last build. Pages of another layout or version
+    /// are kept track of (to be cleaned up) but none is reused.
+    fn load(path: &Path) -> (Self, bool) {
+        let manifest = match fs::read_to_string(path) {
+            Ok(json) => match serde_json::from_str::<Manifest>(&json) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    log::warn!(
+                        "Ignoring unreadable site manifest {}: {}",
+                        path.display(),
+                        e
+                    );
+                    return (Self::fresh(), false);
+                }
+            },
+            Err(_) => return (Self::fresh(), false),
+        };
+        let current =
+            manifest.version == MANIFEST_VERSION && manifest.generator == env!("CARGO_PKG_VERSION");
+        (manifest, current)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("Failed to serialize site manifest")?;
+        fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// A session to publish, with its project
+#[derive(Debug, Clone)]
+pub struct SiteSource<'a> {
+    pub summary: &'a SessionSummary,
+    pub project: String,
+}
+
+/// How to build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildOptions {
+    /// Render every session again, even if unchanged
+    pub full: bool,
+    /// Rendering threads (0 = one per CPU)
+    pub threads: usize,
+}
+
+/// What a build did
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub projects: usize,
+    /// Sessions on the site
+    pub sessions: usize,
+    /// Transcript pages (re)rendered
+    pub rendered: usize,
+    /// Transcript pages removed
+    pub removed: usize,
+    /// Sessions that could not be read, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Build or update the site in `out_dir` from `sources`
+pub fn build(
+    sources: &[SiteSource<'_>],
+    out_dir: &Path,
+    options: BuildOptions,
+) -> Result<BuildReport> {
+    let manifest_path = out_dir.join(MANIFEST_FILE);
+    if out_dir.exists() && !manifest_path.exists() {
+        let mut contents = fs::read_dir(out_dir)
+            .with_context(|| format!("Failed to read {}", out_dir.display()))?;
+        if contents.next().is_some() {
+            bail!(
+                "{} is not empty and was not built by 'site build'. Choose a new or empty directory.",
+                out_dir.display()
+            );
+        }
+    }
+    for dir in [PROJECTS_DIR, SESSIONS_DIR] {
+        let path = out_dir.join(dir);
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+    }
+
+    let (previous, reusable) = Manifest::load(&manifest_path);
+    let reusable = reusable && !options.full;
+
+    let sources: Vec<&SiteSource> = sources
+        .iter()
+        .filter(|s| s.summary.parent_session_id.is_none())
+        .collect();
+    let (unchanged, stale): (Vec<&SiteSource>, Vec<&SiteSource>) =
+        sources.iter().partition(|source| {
+            reusable
+                && previous
+                    .sessions
+                    .get(&source.summary.session_id)
+                    .is_some_and(|page| {
+                        page.content_hash == source.summary.content_hash
+                            && page.project == source.project
+                            && out_dir
+                                .join(SESSIONS_DIR)
+                                .join(session_file(&page.session_id))
+                                .exists()
+                    })
+        });
+
+    let results = parallel_map(&stale, resolve_threads(options.threads), |source| {
+        render_session(source, out_dir)
+    });
+
+    let mut manifest = Manifest::fresh();
+    let mut report = BuildReport::default();
+    for source in unchanged {
+        let id = &source.summary.session_id;
+        manifest
+            .sessions
+            .insert(id.clone(), previous.sessions[id].clone());
+    }
+    for (source, result) in stale.iter().zip(results) {
+        match result {
+            Ok(Some(page)) => {
+                report.rendered += 1;
+                manifest.sessions.insert(page.session_id.clone(), page);
+            }
+            // Nothing to show
+            Ok(None) => {}
+            Err(e) => report
+                .failed
+                .push((source.summary.session_id.clone(), format!("{e:#}"))),
+        }
+    }
+
+    for id in previous.sessions.keys() {
+        if !manifest.sessions.contains_key(id) {
+            remove_page(&out_dir.join(SESSIONS_DIR).join(session_file(id)))?;
+            report.removed += 1;
+        }
+    }
+
+    let mut projects: BTreeMap<&str, Vec<&SitePage>> = BTreeMap::new();
+    for page in manifest.sessions.values() {
+        projects.entry(&page.project).or_default().push(page);
+    }
+    for (project, pages) in &mut projects {
+        pages.sort_by(|a, b| {
+            (&b.last_activity, &b.session_id).cmp(&(&a.last_activity, &a.session_id))
+        });
+        let file = project_file(project);
+        write_page(
+            &out_dir.join(PROJECTS_DIR).join(&file),
+            &pages::project_page(project, pages),
+        )?;
+        manifest.projects.insert(file);
+    }
+    for file in previous.projects.difference(&manifest.projects) {
+        remove_page(&out_dir.join(PROJECTS_DIR).join(file))?;
+    }
+
+    write_page(&out_dir.join("index.html"), &pages::index_page(&projects))?;
+    write_page(&out_dir.join("search.html"), &pages::search_page())?;
+    let mut all: Vec<&SitePage> = projects.values().flatten().copied().collect();
+    all.sort_by(|a, b| (&b.last_activity, &b.session_id).cmp(&(&a.last_activity, &a.session_id)));
+    write_page(
+        &out_dir.join("search-index.js"),
+        &pages::search_index_js(&all),
+    )?;
+
+    report.projects = projects.len();
+    report.sessions = manifest.sessions.len();
+    manifest.save(&manifest_path)?;
+    Ok(report)
+}
+
+/// Render the transcript page of a session; `None` if it has no messages
+fn render_session(source: &SiteSource<'_>, out_dir: &Path) -> Result<Option<SitePage>> {
+    let session = source.summary.load()?;
+    let thread = Thread::new(&session, &source.project, TranscriptOptions::default());
+
+    let mut messages = 0;
+    let mut text = String::new();
+    let mut first_prompt = None;
+    for item in &thread.items {
+        let Item::Message(turn) = item else {
+            continue;
+        };
+        let parts = thread.parts(turn);
+        if parts.is_empty() {
+            continue;
+        }
+        messages += 1;
+        for part in &parts {
+            let Part::Text(part) = part else {
+                continue;
+            };
+            if turn.role == "user" && first_prompt.is_none() && is_prompt(turn.entry, part) {
+                first_prompt = Some(part.to_string());
+            }
+            if text.chars().count() < SEARCH_TEXT_MAX_CHARS {
+                text.push_str(part);
+                text.push(' ');
+            }
+        }
+    }
+    if messages == 0 {
+        return Ok(None);
+    }
+
+    let summary = session
+        .entries
+        .iter()
+        .filter(|e| e.entry_type == "summary")
+        .filter_map(|e| e.extra.get("summary").and_then(Value::as_str))
+        .next_back()
+        .map(str::to_string);
+    let title = summary
+        .or(first_prompt)
+        .map(|text| one_line(&text, TITLE_MAX_CHARS))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Untitled session".to_string());
+
+    let project_href = format!("../{PROJECTS_DIR}/{}", project_file(&source.project));
+    let html = html::render_page(
+        &thread,
+        &[
+            ("../index.html", "All projects"),
+            (&project_href, &source.project),
+        ],
+    );
+    write_page(
+        &out_dir
+            .join(SESSIONS_DIR)
+            .join(session_file(&session.session_id)),
+        &html,
+    )?;
+
+    Ok(Some(SitePage {
+        session_id: session.session_id.clone(),
+        project: source.project.clone(),
+        content_hash: source.summary.content_hash.clone(),
+        title,
+        started: thread.header.started.clone(),
+        last_activity: thread.header.last_activity.clone(),
+        messages,
+        text: one_line(&text, SEARCH_TEXT_MAX_CHARS),
+    }))
+}
+
+/// Whether a user text is something the user typed, not a meta message or
+/// the wrapper of a slash command
+fn is_prompt(entry: &crate::parser::ConversationEntry, text: &str) -> bool {
+    entry.extra.get("isMeta").and_then(Value::as_bool) != Some(true)
+        && !text.trim_start().starts_with('<')
+}
+
+/// Whitespace collapsed to single spaces, truncated to `max_chars`
+fn one_line(text: &str, max_chars: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > max_chars {
+        let truncated: String = line.chars().take(max_chars - 1).collect();
+        format!("{truncated}…")
+    } else {
+        line
+    }
+}
+
+/// File name of a session's transcript page
+pub fn session_file(session_id: &str) -> String {
+    format!("{}.html", file_stem(session_id))
+}
+
+/// File name of a project's session list
+pub fn project_file(project: &str) -> String {
+    format!("{}.html", file_stem(project))
+}
+
+/// A name safe to use as a file name and URL. Names that had to be changed
+/// get a short hash of the original, so distinct names stay distinct.
+fn file_stem(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if safe == name && !name.is_empty() && !name.starts_with('.') {
+        return safe;
+    }
+    let hash: String = Sha256::digest(name.as_bytes())[..4]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{}-{hash}", safe.trim_matches(|c| c == '-' || c == '.'))
+}
+
+fn write_page(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn remove_page(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, id: &str, prompt: &str) -> SessionSummary {
+        let lines = [
+            json!({"type": "summary", "summary": format!("About {prompt}")}),
+            json!({"type": "user", "uuid": "1", "parentUuid": null, "cwd": "/home/u/web",
+                   "timestamp": "2025-01-01T10:00:00Z",
+                   "message": {"role": "user", "content": prompt}}),
+            json!({"type": "assistant", "uuid": "2", "parentUuid": "1",
+                   "timestamp": "2025-01-01T10:00:05Z",
+                   "message": {"role": "assistant", "content": [{"type": "text", "text": "Done."}]}}),
+        ];
+        let path = dir.join(format!("{id}.jsonl"));
+        let text: Vec<String> = lines.iter().map(Value::to_string).collect();
+        fs::write(&path, text.join("\n") + "\n").unwrap();
+        SessionSummary::from_file(&path).unwrap()
+    }
+
+    fn sources(summaries: &[SessionSummary]) -> Vec<SiteSource<'_>> {
+        summaries
+            .iter()
+            .map(|summary| SiteSource {
+                summary,
+                project: "web app".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_is_incremental() {
+        let repo = tempdir().unwrap();
+        let out = tempdir().unwrap();
+        let site = out.path().join("site");
+        let mut sessions = vec![
+            write_session(repo.path(), "s1", "fix the login"),
+            write_session(repo.path(), "s2", "add dark mode"),
+        ];
+
+        let report = build(&sources(&sessions), &site, BuildOptions::default()).unwrap();
+        assert_eq!((report.sessions, report.rendered), (2, 2));
+        assert_eq!(report.projects, 1);
+
+        let project =
+            fs::read_to_string(site.join(PROJECTS_DIR).join(project_file("web app"))).unwrap();
+        assert!(project.contains("<a href=\"../sessions/s1.html\">About fix the login</a>"));
+        let transcript = fs::read_to_string(site.join(SESSIONS_DIR).join("s2.html")).unwrap();
+        assert!(transcript.contains("<a href=\"../index.html\">All projects</a>"));
+        let index = fs::read_to_string(site.join("search-index.js")).unwrap();
+        assert!(index.contains("\"text\":\"add dark mode Done.\""));
+
+        // Nothing changed
+        let report = build(&sources(&sessions), &site, BuildOptions::default()).unwrap();
+        assert_eq!(
+            (report.sessions, report.rendered, report.removed),
+            (2, 0, 0)
+        );
+
+        // One session changed, one is gone
+        sessions[0] = write_session(repo.path(), "s1", "fix the logout");
+        sessions.pop();
+        let report = build(&sources(&sessions), &site, BuildOptions::default()).unwrap();
+        assert_eq!(
+            (report.sessions, report.rendered, report.removed),
+            (1, 1, 1)
+        );
+        assert!(!site.join(SESSIONS_DIR).join("s2.html").exists());
+
+        let report = build(
+            &sources(&sessions),
+            &site,
+            BuildOptions {
+                full: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(report.rendered, 1);
+    }
+
+    #[test]
+    fn test_build_refuses_foreign_directory() {
+        let out = tempdir().unwrap();
+        fs::write(out.path().join("notes.txt"), "mine").unwrap();
+        assert!(build(&[], out.path(), BuildOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_file_names_are_safe_and_distinct() {
+        assert_eq!(session_file("3f2a-9c1e"), "3f2a-9c1e.html");
+        let spaced = project_file("web app");
+        let dashed = project_file("web-app");
+        assert!(spaced.starts_with("web-app-") && spaced.ends_with(".html"));
+        assert_eq!(dashed, "web-app.html");
+        assert!(!project_file("../etc").contains('/'));
+        assert!(!project_file("..").starts_with('.'));
+    }
+}
@@ -0,0 +1,165 @@
+//! The index, project and search pages of the site.
+//!
+//! Pages share the transcript style and link to each other relatively, so
+//! the site works from `file://` as well as from a web server. Only the
+//! search page runs a script; its index is a plain script file rather than
+//! JSON, which browsers refuse to fetch from `file://`.
+
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::transcript::html::{escape, STYLE};
+
+use super::{project_file, session_file, SitePage, PROJECTS_DIR, SESSIONS_DIR};
+
+const SITE_STYLE: &str = "\
+table{border-collapse:collapse;width:100%}\
+th,td{text-align:left;padding:.35rem .5rem;border-bottom:1px solid #d0d7de;vertical-align:top}\
+td.num,th.num{text-align:right}td.date{white-space:nowrap;color:#656d76}\
+form.search{margin:1rem 0}form.search input{width:70%;padding:.35rem;font-size:1rem}\
+#results li{margin:.75rem 0}.meta,.snippet{color:#656d76}.snippet{font-size:.9em}";
+
+const SEARCH_SCRIPT: &str = r#"
+const input = document.getElementById("q");
+const results = document.getElementById("results");
+const count = document.getElementById("count");
+const MAX_RESULTS = 200;
+
+function run() {
+  const terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+  results.replaceChildren();
+  count.textContent = "";
+  if (!terms.length) return;
+  let found = 0;
+  for (const s of window.SEARCH_INDEX) {
+    const haystack = (s.title + " " + s.text).toLowerCase();
+    if (!terms.every((t) => haystack.includes(t))) continue;
+    found++;
+    if (found > MAX_RESULTS) continue;
+    const item = document.createElement("li");
+    const link = document.createElement("a");
+    link.href = s.href;
+    link.textContent = s.title;
+    const meta = document.createElement("span");
+    meta.className = "meta";
+    meta.textContent = " — " + s.project + (s.date ? ", " + s.date : "");
+    item.append(link, meta);
+    const at = s.text.toLowerCase().indexOf(terms[0]);
+    if (at >= 0) {
+      const snippet = document.createElement("div");
+      snippet.className = "snippet";
+      const start = Math.max(0, at - 60);
+      snippet.textContent = (start > 0 ? "…" : "") + s.text.slice(start, at + 140) + "…";
+      item.append(snippet);
+    }
+    results.append(item);
+  }
+  count.textContent = found + (found === 1 ? " session" : " sessions") +
+    (found > MAX_RESULTS ? ", showing the newest " + MAX_RESULTS : "");
+}
+
+input.addEventListener("input", run);
+const initial = new URLSearchParams(location.search).get("q");
+if (initial) input.value = initial;
+run();
+"#;
+
+/// The project index
+pub fn index_page(projects: &BTreeMap<&str, Vec<&SitePage>>) -> String {
+    let mut body = String::from("<h1>Conversation history</h1>\n");
+    body.push_str(&search_form("search.html"));
+    body.push_str(
+        "<table>\n<tr><th>Project</th><th class=\"num\">Sessions</th><th>Last activity</th></tr>\n",
+    );
+    for (project, pages) in projects {
+        // Pages are sorted newest first
+        let last = pages.first().and_then(|p| p.last_activity.as_deref());
+        body.push_str(&format!(
+            "<tr><td><a href=\"{PROJECTS_DIR}/{}\">{}</a></td><td class=\"num\">{}</td><td class=\"date\">{}</td></tr>\n",
+            escape(&project_file(project)),
+            escape(project),
+            pages.len(),
+            escape(&display_time(last)),
+        ));
+    }
+    body.push_str("</table>\n");
+    page("Conversation history", &body)
+}
+
+/// The sessions of one project, in the given order
+pub fn project_page(project: &str, pages: &[&SitePage]) -> String {
+    let mut body = String::from("<nav><a href=\"../index.html\">All projects</a></nav>\n");
+    body.push_str(&format!("<h1>{}</h1>\n", escape(project)));
+    body.push_str(&search_form("../search.html"));
+    body.push_str(
+        "<table>\n<tr><th>Session</th><th>Started</th><th>Last activity</th><th class=\"num\">Messages</th></tr>\n",
+    );
+    for page in pages {
+        body.push_str(&format!(
+            "<tr><td><a href=\"../{SESSIONS_DIR}/{}\">{}</a></td><td class=\"date\">{}</td><td class=\"date\">{}</td><td class=\"num\">{}</td></tr>\n",
+            escape(&session_file(&page.session_id)),
+            escape(&page.title),
+            escape(&display_time(page.started.as_deref())),
+            escape(&display_time(page.last_activity.as_deref())),
+            page.messages,
+        ));
+    }
+    body.push_str("</table>\n");
+    page(project, &body)
+}
+
+/// The search page; sessions come from `search-index.js`
+pub fn search_page() -> String {
+    let body = format!(
+        "<nav><a href=\"index.html\">All projects</a></nav>\n<h1>Search</h1>\n\
+         <form class=\"search\" onsubmit=\"return false\"><input id=\"q\" type=\"search\" \
+         placeholder=\"Words to find in titles and messages\" autofocus></form>\n\
+         <p id=\"count\" class=\"meta\"></p>\n<ol id=\"results\"></ol>\n\
+         <script src=\"search-index.js\"></script>\n<script>{SEARCH_SCRIPT}</script>\n"
+    );
+    page("Search", &body)
+}
+
+/// The search index: every session with its title, project, date and
+/// message text, in the given order
+pub fn search_index_js(pages: &[&SitePage]) -> String {
+    let entries: Vec<_> = pages
+        .iter()
+        .map(|page| {
+            json!({
+                "href": format!("{SESSIONS_DIR}/{}", session_file(&page.session_id)),
+                "title": page.title,
+                "project": page.project,
+                "date": display_time(page.last_activity.as_deref()),
+                "text": page.text,
+            })
+        })
+        .collect();
+    format!(
+        "window.SEARCH_INDEX = {};\n",
+        serde_json::to_string(&entries).unwrap_or_else(|_| "[]".to_string())
+    )
+}
+
+fn search_form(action: &str) -> String {
+    format!(
+        "<form class=\"search\" action=\"{action}\"><input name=\"q\" type=\"search\" \
+         placeholder=\"Search conversations\"></form>\n"
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{STYLE}{SITE_STYLE}</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// `2025-01-02T03:04:05.678Z` -> `2025-01-02 03:04`
+fn display_time(timestamp: Option<&str>) -> String {
+    match timestamp {
+        Some(ts) => ts.get(..16).unwrap_or(ts).replace('T', " "),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,509 @@
+//! Token and message statistics over conversation history.
+//!
+//! Totals input, output and cache tokens, messages and sessions, grouped by
+//! any combination of project, day, month, machine and model.
+//!
+//! What counts:
+//! - **Messages** are prompts typed by the user (not tool results handed back
+//!   to the model) and API responses of the assistant. Claude Code writes one
+//!   entry per content block of a response, all repeating the response's
+//!   `message.id` and usage, so a response is counted once, with the usage of
+//!   its last entry.
+//! - **Tokens** come from the `usage` of assistant responses. Responses
+//!   Claude Code synthesizes itself (model `<synthetic>`) cost nothing and
+//!   are skipped.
+//! - **Sessions** are conversations with at least one counted message in a
+//!   group; a subagent transcript counts towards its parent session.
+//! - **Cost** is estimated per response from its tokens and the
+//!   [`pricing`] in effect for its model on its day, when prices are
+//!   configured.
+//!
+//! See [`output`] for the table, CSV and JSON renderings, and [`tools`]
+//! for tool usage analytics over the same sessions.
+
+pub mod output;
+pub mod pricing;
+pub mod tools;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::message::{ContentBlock, MessageContent};
+use crate::parser::{ConversationEntry, EntryStream, SessionSummary};
+use crate::sync::parallel::{parallel_map, resolve_threads};
+
+use self::pricing::PriceTable;
+
+/// Model key of user prompts when grouping by model
+pub const NO_MODEL: &str = "(none)";
+
+/// Key of messages without a timestamp when grouping by day or month
+pub const NO_DATE: &str = "unknown";
+
+/// Something statistics can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Project,
+    Day,
+    Month,
+    Machine,
+    Model,
+}
+
+impl Dimension {
+    /// Column name of this dimension
+    pub fn name(self) -> &'static str {
+        match self {
+            Dimension::Project => "project",
+            Dimension::Day => "day",
+            Dimension::Month => "month",
+            Dimension::Machine => "machine",
+            Dimension::Model => "model",
+        }
+    }
+}
+
+impl std::str::FromStr for Dimension {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "project" => Ok(Dimension::Project),
+            "day" | "date" => Ok(Dimension::Day),
+            "month" => Ok(Dimension::Month),
+            "machine" | "host" => Ok(Dimension::Machine),
+            "model" => Ok(Dimension::Model),
+            other => anyhow::bail!(
+                "Unknown grouping: '{}'. Use project, day, month, machine or model.",
+                other
+            ),
+        }
+    }
+}
+
+/// What to summarize and how to group it
+#[derive(Debug, Clone, Default)]
+pub struct StatsQuery {
+    /// Dimensions to group by, in column order; empty for a single total
+    pub group_by: Vec<Dimension>,
+    /// Only sessions whose project name contains this (case-insensitive)
+    pub project: Option<String>,
+    /// Only messages on or after this day (UTC)
+    pub since: Option<NaiveDate>,
+    /// Only messages on or before this day (UTC)
+    pub until: Option<NaiveDate>,
+    /// Prices to estimate costs with; empty to leave costs out
+    pub prices: PriceTable,
+}
+
+impl StatsQuery {
+    fn matches_project(&self, project: &str) -> bool {
+        self.project
+            .as_ref()
+            .is_none_or(|wanted| project.to_lowercase().contains(&wanted.to_lowercase()))
+    }
+
+    /// Whether a message of `day` is in range; undated messages only count
+    /// when no range is set
+    fn matches_day(&self, day: Option<NaiveDate>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Some(day) = day else {
+            return false;
+        };
+        self.since.is_none_or(|since| day >= since) && self.until.is_none_or(|until| day <= until)
+    }
+}
+
+/// A session to include, with the attributes it is grouped by
+#[derive(Debug, Clone)]
+pub struct SessionSource<'a> {
+    pub summary: &'a SessionSummary,
+    pub project: String,
+    pub machine: String,
+}
+
+/// Counters of one group
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub sessions: u64,
+    pub messages: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    /// Estimated cost of the tokens that have a price
+    pub cost: f64,
+}
+
+impl Totals {
+    fn add(&mut self, other: &Totals) {
+        self.messages += other.messages;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cost += other.cost;
+    }
+
+    fn has_tokens(&self) -> bool {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+            > 0
+    }
+}
+
+/// One group of the report
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsRow {
+    /// Value of each grouped dimension, in [`StatsReport::group_by`] order
+    pub key: Vec<String>,
+    pub totals: Totals,
+}
+
+/// Grouped statistics, rows sorted by key
+#[derive(Debug, Clone)]
+pub struct StatsReport {
+    pub group_by: Vec<Dimension>,
+    pub rows: Vec<StatsRow>,
+    pub total: Totals,
+    /// Whether costs were estimated
+    pub priced: bool,
+    /// Models of responses that used tokens but had no price, with the
+    /// number of such responses; their cost is left out
+    pub unpriced: BTreeMap<String, u64>,
+}
+
+/// A counted message of one session
+struct Counted {
+    key: Vec<String>,
+    totals: Totals,
+    /// Model of a response whose tokens could not be priced
+    unpriced: Option<String>,
+}
+
+/// Summarize `sessions` on up to `threads` workers (0 = one per CPU).
+/// Transcripts that can no longer be read are skipped with a warning.
+pub fn collect(query: &StatsQuery, sessions: &[SessionSource<'_>], threads: usize) -> StatsReport {
+    let sessions: Vec<&SessionSource> = sessions
+        .iter()
+        .filter(|s| query.matches_project(&s.project))
+        .collect();
+
+    let results = parallel_map(&sessions, resolve_threads(threads), |source| {
+        session_messages(query, source)
+    });
+
+    let mut groups: BTreeMap<Vec<String>, (Totals, HashSet<&str>)> = BTreeMap::new();
+    let mut total = Totals::default();
+    let mut all_sessions = HashSet::new();
+    let mut unpriced: BTreeMap<String, u64> = BTreeMap::new();
+    for (source, result) in sessions.iter().zip(results) {
+        let counted = match result {
+            Ok(counted) => counted,
+            Err(e) => {
+                log::warn!(
+                    "Failed to read {} for stats: {:#}",
+                    source.summary.file_path,
+                    e
+                );
+                continue;
+            }
+        };
+        let summary = source.summary;
+        let conversation = summary
+            .parent_session_id
+            .as_deref()
+            .unwrap_or(&summary.session_id);
+        for message in counted {
+            if let Some(model) = message.unpriced {
+                *unpriced.entry(model).or_default() += 1;
+            }
+            let (totals, sessions) = groups.entry(message.key).or_default();
+            totals.add(&message.totals);
+            sessions.insert(conversation);
+            total.add(&message.totals);
+            all_sessions.insert(conversation);
+        }
+    }
+
+    total.sessions = all_sessions.len() as u64;
+    let rows = groups
+        .into_iter()
+        .map(|(key, (mut totals, sessions))| {
+            totals.sessions = sessions.len() as u64;
+            StatsRow { key, totals }
+        })
+        .collect();
+
+    StatsReport {
+        group_by: query.group_by.clone(),
+        rows,
+        total,
+        priced: !query.prices.is_empty(),
+        unpriced,
+    }
+}
+
+/// Count the messages of one session that pass the query's date filter
+fn session_messages(query: &StatsQuery, source: &SessionSource<'_>) -> Result<Vec<Counted>> {
+    let summary = source.summary;
+    let mut counted: Vec<Counted> = Vec::new();
+    // Response id -> position in `counted`
+    let mut responses: HashMap<String, usize> = HashMap::new();
+
+    for entry in EntryStream::open_with_mode(&summary.file_path, summary.parse_mode)? {
+        let entry = entry?;
+        if entry.entry_type != "user" && entry.entry_type != "assistant" {
+            continue;
+        }
+        let Some(message) = entry.typed_message() else {
+            continue;
+        };
+
+        let day = entry_day(&entry);
+        if !query.matches_day(day) {
+            continue;
+        }
+
+        let mut totals = Totals {
+            messages: 1,
+            ..Default::default()
+        };
+        let model = if entry.entry_type == "assistant" {
+            let model = message.model.as_deref().unwrap_or(NO_MODEL);
+            if model == "<synthetic>" {
+                continue;
+            }
+            if let Some(usage) = &message.usage {
+                totals.input_tokens = usage.input_tokens.unwrap_or(0);
+                totals.output_tokens = usage.output_tokens.unwrap_or(0);
+                totals.cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0);
+                totals.cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0);
+            }
+            model
+        } else {
+            if is_tool_result(&message.content) || is_meta(&entry.extra) {
+                continue;
+            }
+            NO_MODEL
+        };
+
+        let mut unpriced = None;
+        if entry.entry_type == "assistant" && !query.prices.is_empty() {
+            match query.prices.lookup(model, day) {
+                Some(price) => totals.cost = price.cost(&totals),
+                None if totals.has_tokens() => unpriced = Some(model.to_string()),
+                None => {}
+            }
+        }
+
+        let key = query
+            .group_by
+            .iter()
+            .map(|dimension| match dimension {
+                Dimension::Project => source.project.clone(),
+                Dimension::Machine => source.machine.clone(),
+                Dimension::Model => model.to_string(),
+                Dimension::Day => day.map_or(NO_DATE.to_string(), |d| d.to_string()),
+                Dimension::Month => {
+                    day.map_or(NO_DATE.to_string(), |d| d.format("%Y-%m").to_string())
+                }
+            })
+            .collect();
+
+        let response_id = (entry.entry_type == "assistant")
+            .then(|| message.extra.get("id").and_then(Value::as_str))
+            .flatten();
+        match response_id.and_then(|id| responses.get(id)) {
+            // Another block of a response already counted: its usage
+            // supersedes the earlier entries'
+            Some(&i) => {
+                if message.usage.is_some() {
+                    counted[i].totals = totals;
+                    counted[i].unpriced = unpriced;
+                }
+            }
+            None => {
+                if let Some(id) = response_id {
+                    responses.insert(id.to_string(), counted.len());
+                }
+                counted.push(Counted {
+                    key,
+                    totals,
+                    unpriced,
+                });
+            }
+        }
+    }
+    Ok(counted)
+}
+
+/// Day (UTC) an entry was written
+fn entry_day(entry: &ConversationEntry) -> Option<NaiveDate> {
+    entry
+        .timestamp
+        .as_deref()
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&Utc).date_naive())
+}
+
+/// True for user messages that only hand tool results back to the model
+fn is_tool_result(content: &Option<MessageContent>) -> bool {
+    match content {
+        Some(MessageContent::Blocks(blocks)) => {
+            !blocks.is_empty()
+                && blocks
+                    .iter()
+                    .all(|b| matches!(b, ContentBlock::ToolResult(_)))
+        }
+        _ => false,
+    }
+}
+
+/// True for user entries Claude Code injects itself (command output, caveats)
+fn is_meta(extra: &Value) -> bool {
+    extra.get("isMeta").and_then(Value::as_bool) == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, id: &str, lines: &[Value]) -> SessionSummary {
+        let path = dir.join(format!("{id}.jsonl"));
+        let content: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        fs::write(&path, content).unwrap();
+        SessionSummary::from_file(&path).unwrap()
+    }
+
+    fn usage(input: u64, output: u64) -> Value {
+        json!({"input_tokens": input, "output_tokens": output,
+               "cache_creation_input_tokens": 100, "cache_read_input_tokens": 1000})
+    }
+
+    #[test]
+    fn test_collect_groups_and_dedupes_responses() {
+        let dir = tempdir().unwrap();
+        let s1 = write_session(
+            dir.path(),
+            "s1",
+            &[
+                json!({"type": "user", "uuid": "1", "timestamp": "2025-03-01T10:00:00Z",
+                       "message": {"role": "user", "content": "hi"}}),
+                // One response written as two entries
+                json!({"type": "assistant", "uuid": "2", "timestamp": "2025-03-01T10:00:01Z",
+                       "message": {"id": "msg_1", "role": "assistant", "model": "opus",
+                                   "content": [{"type": "thinking", "thinking": "..."}], "usage": usage(10, 1)}}),
+                json!({"type": "assistant", "uuid": "3", "timestamp": "2025-03-01T10:00:02Z",
+                       "message": {"id": "msg_1", "role": "assistant", "model": "opus",
+                                   "content": [{"type": "tool_use", "id": "t", "name": "Bash", "input": {}}], "usage": usage(10, 20)}}),
+                // Tool results are not prompts
+                json!({"type": "user", "uuid": "4", "timestamp": "2025-03-01T10:00:03Z",
+                       "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t", "content": "ok"}]}}),
+                json!({"type": "assistant", "uuid": "5", "timestamp": "2025-03-02T09:00:00Z",
+                       "message": {"id": "msg_2", "role": "assistant", "model": "haiku", "content": "done", "usage": usage(5, 5)}}),
+                json!({"type": "assistant", "uuid": "6", "timestamp": "2025-03-02T09:00:01Z",
+                       "message": {"id": "msg_3", "role": "assistant", "model": "<synthetic>", "content": "error"}}),
+            ],
+        );
+        let agent = write_session(
+            dir.path(),
+            "agent-1",
+            &[
+                json!({"type": "assistant", "uuid": "a", "sessionId": "s1", "timestamp": "2025-03-02T09:30:00Z",
+                       "message": {"id": "msg_4", "role": "assistant", "model": "haiku", "content": "sub", "usage": usage(1, 1)}}),
+            ],
+        );
+        let sources = [
+            SessionSource {
+                summary: &s1,
+                project: "web".to_string(),
+                machine: "laptop".to_string(),
+            },
+            SessionSource {
+                summary: &agent,
+                project: "web".to_string(),
+                machine: "laptop".to_string(),
+            },
+        ];
+
+        let query = StatsQuery {
+            group_by: vec![Dimension::Day, Dimension::Model],
+            ..Default::default()
+        };
+        let report = collect(&query, &sources, 2);
+        let rows: Vec<(Vec<&str>, Totals)> = report
+            .rows
+            .iter()
+            .map(|r| (r.key.iter().map(String::as_str).collect(), r.totals))
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].0, vec!["2025-03-01", "(none)"]);
+        assert_eq!(rows[0].1.messages, 1);
+        assert_eq!(rows[1].0, vec!["2025-03-01", "opus"]);
+        assert_eq!(
+            rows[1].1,
+            Totals {
+                sessions: 1,
+                messages: 1,
+                input_tokens: 10,
+                output_tokens: 20,
+                cache_creation_tokens: 100,
+                cache_read_tokens: 1000,
+                cost: 0.0,
+            }
+        );
+        // The subagent's response counts towards its parent session
+        assert_eq!(rows[2].0, vec!["2025-03-02", "haiku"]);
+        assert_eq!(rows[2].1.messages, 2);
+        assert_eq!(rows[2].1.sessions, 1);
+        assert_eq!(rows[2].1.output_tokens, 6);
+
+        assert_eq!(report.total.sessions, 1);
+        assert_eq!(report.total.messages, 4);
+        assert_eq!(report.total.input_tokens, 16);
+
+        let query = StatsQuery {
+            since: Some(NaiveDate::from_ymd_opt(2025, 3, 2).unwrap()),
+            ..Default::default()
+        };
+        let report = collect(&query, &sources, 1);
+        assert_eq!(report.rows.len(), 1);
+        assert!(report.rows[0].key.is_empty());
+        assert_eq!(report.total.messages, 2);
+
+        let query = StatsQuery {
+            project: Some("api".to_string()),
+            ..Default::default()
+        };
+        assert!(collect(&query, &sources, 1).rows.is_empty());
+
+        // Each response is priced on its own day; haiku has no price yet
+        let query = StatsQuery {
+            group_by: vec![Dimension::Model],
+            prices: PriceTable::new(vec![pricing::ModelPrice {
+                model: "opus".to_string(),
+                effective: None,
+                input: 1_000.0,
+                output: 10_000.0,
+                cache_write: 0.0,
+                cache_read: 100.0,
+            }]),
+            ..Default::default()
+        };
+        let report = collect(&query, &sources, 1);
+        assert!(report.priced);
+        assert_eq!(report.rows[2].key, vec!["opus"]);
+        // 10 input, 20 output and 1,000 cache read tokens
+        assert!((report.rows[2].totals.cost - 0.31).abs() < 1e-9);
+        assert_eq!(report.total.cost, report.rows[2].totals.cost);
+        assert_eq!(report.unpriced, BTreeMap::from([("haiku".to_string(), 2)]));
+    }
+}
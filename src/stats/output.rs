@@ -0,0 +1,361 @@
+//! Renderings of a [`StatsReport`]: an aligned table for the terminal, CSV
+//! for spreadsheets and JSON for scripts.
+//!
+//! CSV and JSON use the snake_case metric names and raw numbers; the table
+//! groups digits and closes with a total row. A `cost` column follows the
+//! counters when the report was priced.
+
+use serde_json::{json, Map, Value};
+
+use super::{StatsReport, Totals};
+
+/// Output format of the `stats` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            other => anyhow::bail!("Unknown format: '{}'. Use table, csv or json.", other),
+        }
+    }
+}
+
+/// A counter column: machine name, table heading and value
+struct Metric {
+    name: &'static str,
+    heading: &'static str,
+    value: fn(&Totals) -> u64,
+}
+
+const METRICS: &[Metric] = &[
+    Metric {
+        name: "sessions",
+        heading: "Sessions",
+        value: |t| t.sessions,
+    },
+    Metric {
+        name: "messages",
+        heading: "Messages",
+        value: |t| t.messages,
+    },
+    Metric {
+        name: "input_tokens",
+        heading: "Input",
+        value: |t| t.input_tokens,
+    },
+    Metric {
+        name: "output_tokens",
+        heading: "Output",
+        value: |t| t.output_tokens,
+    },
+    Metric {
+        name: "cache_creation_tokens",
+        heading: "Cache write",
+        value: |t| t.cache_creation_tokens,
+    },
+    Metric {
+        name: "cache_read_tokens",
+        heading: "Cache read",
+        value: |t| t.cache_read_tokens,
+    },
+];
+
+/// Render `report` in `format`
+pub fn render(report: &StatsReport, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => render_table(report),
+        OutputFormat::Csv => render_csv(report),
+        OutputFormat::Json => render_json(report),
+    }
+}
+
+fn render_table(report: &StatsReport) -> String {
+    let mut header: Vec<String> = report
+        .group_by
+        .iter()
+        .map(|d| capitalize(d.name()))
+        .collect();
+    header.extend(METRICS.iter().map(|m| m.heading.to_string()));
+    if report.priced {
+        header.push("Cost".to_string());
+    }
+
+    let row_cells = |key: &[String], totals: &Totals| -> Vec<String> {
+        let mut cells = key.to_vec();
+        cells.extend(METRICS.iter().map(|m| group_digits((m.value)(totals))));
+        if report.priced {
+            let cents = (totals.cost * 100.0).round() as u64;
+            cells.push(format!("{}.{:02}", group_digits(cents / 100), cents % 100));
+        }
+        cells
+    };
+    let rows: Vec<Vec<String>> = report
+        .rows
+        .iter()
+        .map(|row| row_cells(&row.key, &row.totals))
+        .collect();
+
+    // The total row labels itself in the first key column, if there is one
+    let mut total_key = vec![String::new(); report.group_by.len()];
+    if let Some(first) = total_key.first_mut() {
+        *first = "Total".to_string();
+    }
+    let total = row_cells(&total_key, &report.total);
+
+    let keys = report.group_by.len();
+    // A report without groups is just its total
+    let rows = if keys > 0 { &rows[..] } else { &[] };
+    aligned_table(&header, rows, Some(&total), keys)
+}
+
+/// Align `rows` under `header`, the first `text_columns` columns to the
+/// left and the rest (numbers) to the right, with an optional total row
+/// set off by a rule
+pub(super) fn aligned_table(
+    header: &[String],
+    rows: &[Vec<String>],
+    total: Option<&[String]>,
+    text_columns: usize,
+) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for cells in rows.iter().map(Vec::as_slice).chain(total) {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[String]| -> String {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, &width))| {
+                if i < text_columns {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect();
+        padded.join("  ").trim_end().to_string() + "\n"
+    };
+    let rule = widths
+        .iter()
+        .map(|&w| "-".repeat(w))
+        .collect::<Vec<_>>()
+        .join("  ")
+        + "\n";
+
+    let mut out = line(header);
+    out.push_str(&rule);
+    for cells in rows {
+        out.push_str(&line(cells));
+    }
+    if let Some(total) = total {
+        if !rows.is_empty() {
+            out.push_str(&rule);
+        }
+        out.push_str(&line(total));
+    }
+    out
+}
+
+fn render_csv(report: &StatsReport) -> String {
+    let mut header: Vec<&str> = report.group_by.iter().map(|d| d.name()).collect();
+    header.extend(METRICS.iter().map(|m| m.name));
+    if report.priced {
+        header.push("cost");
+    }
+
+    let mut out = header.join(",") + "\n";
+    for row in &report.rows {
+        let mut cells: Vec<String> = row.key.iter().map(|k| csv_field(k)).collect();
+        cells.extend(METRICS.iter().map(|m| (m.value)(&row.totals).to_string()));
+        if report.priced {
+            cells.push(format!("{:.4}", row.totals.cost));
+        }
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_json(report: &StatsReport) -> String {
+    let metrics = |totals: &Totals| -> Map<String, Value> {
+        let mut object: Map<String, Value> = METRICS
+            .iter()
+            .map(|m| (m.name.to_string(), Value::from((m.value)(totals))))
+            .collect();
+        if report.priced {
+            let cost = (totals.cost * 10_000.0).round() / 10_000.0;
+            object.insert("cost".to_string(), Value::from(cost));
+        }
+        object
+    };
+    let rows: Vec<Value> = report
+        .rows
+        .iter()
+        .map(|row| {
+            let mut object: Map<String, Value> = report
+                .group_by
+                .iter()
+                .zip(&row.key)
+                .map(|(d, k)| (d.name().to_string(), Value::from(k.as_str())))
+                .collect();
+            object.extend(metrics(&row.totals));
+            Value::Object(object)
+        })
+        .collect();
+
+    let mut value = json!({
+        "group_by": report.group_by.iter().map(|d| d.name()).collect::<Vec<_>>(),
+        "rows": rows,
+        "total": metrics(&report.total),
+    });
+    if report.priced {
+        value["unpriced_models"] = json!(report.unpriced);
+    }
+    serde_json::to_string_pretty(&value).unwrap_or_default() + "\n"
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+pub(super) fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// `1234567` -> `1,234,567`
+pub(super) fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{Dimension, StatsRow};
+
+    fn report() -> StatsReport {
+        let totals = |sessions, input| Totals {
+            sessions,
+            messages: 4,
+            input_tokens: input,
+            output_tokens: 20,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 1_500,
+            cost: 0.0,
+        };
+        StatsReport {
+            group_by: vec![Dimension::Project],
+            rows: vec![
+                StatsRow {
+                    key: vec!["api, v2".to_string()],
+                    totals: totals(1, 1_234_567),
+                },
+                StatsRow {
+                    key: vec!["web".to_string()],
+                    totals: totals(2, 10),
+                },
+            ],
+            total: Totals {
+                sessions: 3,
+                messages: 8,
+                input_tokens: 1_234_577,
+                output_tokens: 40,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 3_000,
+                cost: 0.0,
+            },
+            priced: false,
+            unpriced: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let report = report();
+
+        let table = render(&report, OutputFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("Project  Sessions  Messages      Input  Output"));
+        assert!(lines[2].starts_with("api, v2         1         4  1,234,567      20"));
+        assert!(lines[5].starts_with("Total           3         8  1,234,577      40"));
+        assert!(lines[5].ends_with("3,000"));
+
+        let csv = render(&report, OutputFormat::Csv);
+        assert_eq!(
+            csv,
+            "project,sessions,messages,input_tokens,output_tokens,cache_creation_tokens,cache_read_tokens\n\
+             \"api, v2\",1,4,1234567,20,0,1500\n\
+             web,2,4,10,20,0,1500\n"
+        );
+
+        let json: Value = serde_json::from_str(&render(&report, OutputFormat::Json)).unwrap();
+        assert_eq!(json["group_by"], json!(["project"]));
+        assert_eq!(json["rows"][1]["project"], "web");
+        assert_eq!(json["rows"][1]["sessions"], 2);
+        assert_eq!(json["total"]["input_tokens"], 1_234_577);
+    }
+
+    #[test]
+    fn test_render_cost_column() {
+        let mut report = report();
+        report.priced = true;
+        report.rows[0].totals.cost = 1234.5678;
+        report.rows[1].totals.cost = 0.004;
+        report.total.cost = 1234.5718;
+        report.unpriced.insert("haiku".to_string(), 3);
+
+        let table = render(&report, OutputFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].ends_with("Cache read      Cost"));
+        assert!(lines[2].ends_with("1,234.57"));
+        assert!(lines[3].ends_with("    0.00"));
+
+        let csv = render(&report, OutputFormat::Csv);
+        assert!(csv.lines().next().unwrap().ends_with(",cost"));
+        assert!(csv.contains(",1500,1234.5678\n"));
+
+        let json: Value = serde_json::from_str(&render(&report, OutputFormat::Json)).unwrap();
+        assert_eq!(json["rows"][0]["cost"], 1234.5678);
+        assert_eq!(json["total"]["cost"], 1234.5718);
+        assert_eq!(json["unpriced_models"]["haiku"], 3);
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1_000), "1,000");
+        assert_eq!(group_digits(12_345_678), "12,345,678");
+    }
+}
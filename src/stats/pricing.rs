@@ -0,0 +1,205 @@
+//! Token prices for cost estimates.
+//!
+//! Prices come from `[[pricing]]` tables in the config file, one per model
+//! and period, in any currency per million tokens of each class:
+//!
+//! ```toml
+//! [[pricing]]
+//! model = "claude-sonnet-4*"
+//! input = 3.0
+//! output = 15.0
+//! cache_write = 3.75
+//! cache_read = 0.30
+//!
+//! [[pricing]]
+//! model = "claude-sonnet-4*"
+//! effective = "2026-01-01"
+//! input = 2.5
+//! output = 12.5
+//! ```
+//!
+//! A response is priced by the entry for its model in effect on the day it
+//! was written. Nothing is built in: published prices change, and an
+//! estimate is only as good as the table finance agreed on.
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::Totals;
+
+/// Prices of one model from a given day, per million tokens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Model name, or a name prefix ending in `*` (e.g. `claude-opus-4*`)
+    pub model: String,
+
+    /// First day (UTC) these prices apply; without one, they apply until a
+    /// dated entry for the same model takes over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective: Option<NaiveDate>,
+
+    /// Uncached input tokens
+    #[serde(default)]
+    pub input: f64,
+
+    /// Output tokens
+    #[serde(default)]
+    pub output: f64,
+
+    /// Tokens written to the prompt cache
+    #[serde(default)]
+    pub cache_write: f64,
+
+    /// Tokens read from the prompt cache
+    #[serde(default)]
+    pub cache_read: f64,
+}
+
+impl ModelPrice {
+    /// Cost of the tokens in `totals`
+    pub fn cost(&self, totals: &Totals) -> f64 {
+        (totals.input_tokens as f64 * self.input
+            + totals.output_tokens as f64 * self.output
+            + totals.cache_creation_tokens as f64 * self.cache_write
+            + totals.cache_read_tokens as f64 * self.cache_read)
+            / 1_000_000.0
+    }
+
+    /// How closely this entry names `model`: exact beats any prefix, a
+    /// longer prefix beats a shorter one. `None` if it does not match.
+    fn specificity(&self, model: &str) -> Option<usize> {
+        match self.model.strip_suffix('*') {
+            Some(prefix) => model.starts_with(prefix).then_some(prefix.len()),
+            None => (self.model == model).then_some(usize::MAX),
+        }
+    }
+}
+
+/// The configured prices, looked up by model and day
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    prices: Vec<ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn new(prices: Vec<ModelPrice>) -> Self {
+        PriceTable { prices }
+    }
+
+    /// True when no prices are configured, so costs are not estimated at all
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    /// Prices of `model` in effect on `day`. Of the entries naming the model
+    /// most specifically, the one with the latest effective date not after
+    /// `day` wins; without a day, the latest one.
+    pub fn lookup(&self, model: &str, day: Option<NaiveDate>) -> Option<&ModelPrice> {
+        let best = self
+            .prices
+            .iter()
+            .filter_map(|p| p.specificity(model))
+            .max()?;
+        self.prices
+            .iter()
+            .filter(|p| p.specificity(model) == Some(best))
+            .filter(|p| match (p.effective, day) {
+                (Some(effective), Some(day)) => effective <= day,
+                _ => true,
+            })
+            .max_by_key(|p| p.effective)
+    }
+
+    /// Check a configured table for mistakes that would skew every estimate
+    pub fn validate(prices: &[ModelPrice]) -> Result<()> {
+        for (i, price) in prices.iter().enumerate() {
+            if price.model.trim().is_empty() {
+                bail!("pricing entry {} has no model", i + 1);
+            }
+            for (class, value) in [
+                ("input", price.input),
+                ("output", price.output),
+                ("cache_write", price.cache_write),
+                ("cache_read", price.cache_read),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    bail!(
+                        "pricing for '{}': {} must be a non-negative number, got {}",
+                        price.model,
+                        class,
+                        value
+                    );
+                }
+            }
+            if prices[..i]
+                .iter()
+                .any(|p| p.model == price.model && p.effective == price.effective)
+            {
+                bail!(
+                    "pricing for '{}' is listed twice for the same effective date",
+                    price.model
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(model: &str, effective: Option<&str>, input: f64) -> ModelPrice {
+        ModelPrice {
+            model: model.to_string(),
+            effective: effective.map(|d| d.parse().unwrap()),
+            input,
+            output: 0.0,
+            cache_write: 0.0,
+            cache_read: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_lookup_by_model_and_effective_date() {
+        let table = PriceTable::new(vec![
+            price("claude-opus-4*", None, 15.0),
+            price("claude-opus-4*", Some("2025-11-01"), 5.0),
+            price("claude-opus-4-1", None, 20.0),
+            price("claude-haiku*", Some("2025-06-01"), 1.0),
+        ]);
+        let day = |d: &str| Some(d.parse::<NaiveDate>().unwrap());
+
+        let input = |model, d| table.lookup(model, d).map(|p| p.input);
+        assert_eq!(input("claude-opus-4-5", day("2025-10-31")), Some(15.0));
+        assert_eq!(input("claude-opus-4-5", day("2025-11-01")), Some(5.0));
+        assert_eq!(input("claude-opus-4-5", None), Some(5.0));
+        // An exact name beats the prefix
+        assert_eq!(input("claude-opus-4-1", day("2025-12-01")), Some(20.0));
+        // Not priced yet on that day, or not at all
+        assert_eq!(input("claude-haiku-3", day("2025-05-31")), None);
+        assert_eq!(input("gpt-4", day("2025-05-31")), None);
+
+        let totals = Totals {
+            input_tokens: 2_000_000,
+            output_tokens: 1_000_000,
+            ..Default::default()
+        };
+        let mut opus = price("claude-opus-4*", None, 15.0);
+        opus.output = 75.0;
+        assert_eq!(opus.cost(&totals), 105.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(PriceTable::validate(&[
+            price("a", None, 1.0),
+            price("a", Some("2025-01-01"), 2.0)
+        ])
+        .is_ok());
+        assert!(PriceTable::validate(&[price("a", None, 1.0), price("a", None, 2.0)]).is_err());
+        assert!(PriceTable::validate(&[price("a", None, -1.0)]).is_err());
+        assert!(PriceTable::validate(&[price(" ", None, 1.0)]).is_err());
+    }
+}
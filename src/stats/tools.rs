@@ -0,0 +1,552 @@
+//! Tool usage analytics.
+//!
+//! Pairs every `tool_use` block of an assistant response with the
+//! `tool_result` that answered it, and reports per project how often each
+//! tool was called, how often it failed and how long it took. Shell commands
+//! run through `Bash` are broken down by program (`git status`, `cargo
+//! test`, `ls`), and the most tool-heavy sessions are listed with how long
+//! they ran. Subagent transcripts count towards their parent session.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parser::EntryStream;
+use crate::sync::parallel::{parallel_map, resolve_threads};
+
+use super::output::{aligned_table, csv_field, group_digits, OutputFormat};
+use super::{entry_day, SessionSource, StatsQuery};
+
+/// Tool whose `command` input is broken down into programs
+const SHELL_TOOL: &str = "Bash";
+
+/// Programs whose first argument names what they do, reported together
+/// with it (`git status` rather than `git`)
+const SUBCOMMAND_PROGRAMS: &[&str] = &[
+    "cargo", "docker", "gh", "git", "go", "kubectl", "npm", "pnpm", "uv", "yarn",
+];
+
+/// Calls of one tool (or one shell program)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolCounts {
+    pub calls: u64,
+    /// Calls whose result was flagged `is_error`
+    pub errors: u64,
+    /// Calls with a timed result, and the time between call and result
+    pub timed: u64,
+    pub total_ms: u64,
+}
+
+impl ToolCounts {
+    fn add_call(&mut self, call: &Call) {
+        self.calls += 1;
+        if call.error {
+            self.errors += 1;
+        }
+        if let Some(ms) = call.ms {
+            self.timed += 1;
+            self.total_ms += ms;
+        }
+    }
+
+    /// Share of calls that failed, in percent
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 * 100.0 / self.calls as f64
+        }
+    }
+
+    /// Mean time from call to result, in milliseconds
+    pub fn average_ms(&self) -> Option<u64> {
+        (self.timed > 0).then(|| self.total_ms / self.timed)
+    }
+}
+
+/// Tool calls of one conversation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionTools {
+    pub session_id: String,
+    pub project: String,
+    pub counts: ToolCounts,
+    /// Time from its first to its last message, in seconds
+    pub duration_secs: Option<i64>,
+}
+
+/// Tool usage over a set of sessions
+#[derive(Debug, Clone, Default)]
+pub struct ToolReport {
+    /// (project, tool) -> calls, sorted
+    pub tools: BTreeMap<(String, String), ToolCounts>,
+    /// (project, program) -> calls of [`SHELL_TOOL`]
+    pub commands: BTreeMap<(String, String), ToolCounts>,
+    /// Conversations with tool calls, most calls first
+    pub sessions: Vec<SessionTools>,
+}
+
+/// One `tool_use` block and what became of it
+#[derive(Debug, Clone)]
+struct Call {
+    tool: String,
+    programs: Vec<String>,
+    error: bool,
+    ms: Option<u64>,
+}
+
+/// Tool calls found in one transcript
+#[derive(Debug, Default)]
+struct FileTools {
+    calls: Vec<Call>,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+/// Collect tool usage from `sessions` on up to `threads` workers (0 = one
+/// per CPU). The query's project and date filters apply; its grouping and
+/// prices do not. Unreadable transcripts are skipped with a warning.
+pub fn collect_tools(
+    query: &StatsQuery,
+    sessions: &[SessionSource<'_>],
+    threads: usize,
+) -> ToolReport {
+    let sessions: Vec<&SessionSource> = sessions
+        .iter()
+        .filter(|s| query.matches_project(&s.project))
+        .collect();
+    let results = parallel_map(&sessions, resolve_threads(threads), |source| {
+        file_tools(query, source)
+    });
+
+    let mut report = ToolReport::default();
+    // Conversation id -> (project, counts, first, last)
+    type Span = Option<DateTime<Utc>>;
+    let mut conversations: HashMap<&str, (&str, ToolCounts, Span, Span)> = HashMap::new();
+    for (source, result) in sessions.iter().zip(results) {
+        let found = match result {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!(
+                    "Failed to read {} for tool stats: {:#}",
+                    source.summary.file_path,
+                    e
+                );
+                continue;
+            }
+        };
+        let summary = source.summary;
+        let conversation = summary
+            .parent_session_id
+            .as_deref()
+            .unwrap_or(&summary.session_id);
+        let (_, counts, first, last) = conversations.entry(conversation).or_insert((
+            &source.project,
+            ToolCounts::default(),
+            None,
+            None,
+        ));
+        *first = (*first).into_iter().chain(found.first).min();
+        *last = (*last).into_iter().chain(found.last).max();
+
+        for call in &found.calls {
+            counts.add_call(call);
+            report
+                .tools
+                .entry((source.project.clone(), call.tool.clone()))
+                .or_default()
+                .add_call(call);
+            for program in &call.programs {
+                report
+                    .commands
+                    .entry((source.project.clone(), program.clone()))
+                    .or_default()
+                    .add_call(call);
+            }
+        }
+    }
+
+    report.sessions = conversations
+        .into_iter()
+        .filter(|(_, (_, counts, _, _))| counts.calls > 0)
+        .map(|(id, (project, counts, first, last))| SessionTools {
+            session_id: id.to_string(),
+            project: project.to_string(),
+            counts,
+            duration_secs: first.zip(last).map(|(f, l)| (l - f).num_seconds()),
+        })
+        .collect();
+    report.sessions.sort_by(|a, b| {
+        b.counts
+            .calls
+            .cmp(&a.counts.calls)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    report
+}
+
+/// Pair the tool calls and results of one transcript
+fn file_tools(query: &StatsQuery, source: &SessionSource<'_>) -> Result<FileTools> {
+    let summary = source.summary;
+    let mut found = FileTools::default();
+    // tool_use id -> (position in `calls`, time of the call)
+    let mut pending: HashMap<String, (usize, Option<DateTime<Utc>>)> = HashMap::new();
+
+    for entry in EntryStream::open_with_mode(&summary.file_path, summary.parse_mode)? {
+        let entry = entry?;
+        if entry.entry_type != "user" && entry.entry_type != "assistant" {
+            continue;
+        }
+        if !query.matches_day(entry_day(&entry)) {
+            continue;
+        }
+        let Some(message) = entry.typed_message() else {
+            continue;
+        };
+        let time = entry
+            .timestamp
+            .as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc));
+        found.first = found.first.into_iter().chain(time).min();
+        found.last = found.last.into_iter().chain(time).max();
+
+        for tool_use in message.tool_uses() {
+            if pending.contains_key(&tool_use.id) {
+                continue;
+            }
+            let programs = if tool_use.name == SHELL_TOOL {
+                tool_use
+                    .input
+                    .get("command")
+                    .and_then(Value::as_str)
+                    .map(command_programs)
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            pending.insert(tool_use.id.clone(), (found.calls.len(), time));
+            found.calls.push(Call {
+                tool: tool_use.name.clone(),
+                programs,
+                error: false,
+                ms: None,
+            });
+        }
+        for result in message.tool_results() {
+            let Some(&(i, called)) = pending.get(&result.tool_use_id) else {
+                continue;
+            };
+            let call = &mut found.calls[i];
+            call.error = result.is_error == Some(true);
+            call.ms = called.zip(time).and_then(|(called, answered)| {
+                (answered - called).num_milliseconds().try_into().ok()
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// Programs run by a shell command line, one per pipeline stage or list
+/// element: `cd src && cargo test | tail` runs `cd`, `cargo test` and
+/// `tail`. Leading `VAR=value` assignments and `sudo` are skipped, and a
+/// program given by path is named by its file name.
+pub fn command_programs(command: &str) -> Vec<String> {
+    split_commands(command)
+        .iter()
+        .filter_map(|segment| {
+            let mut words = segment
+                .split_whitespace()
+                .skip_while(|w| (w.contains('=') && !w.starts_with('=')) || *w == "sudo");
+            let program = words.next()?;
+            let program = program.rsplit('/').next().unwrap_or(program);
+            if program.is_empty()
+                || !program.starts_with(|c: char| c.is_alphanumeric() || c == '.' || c == '_')
+            {
+                return None;
+            }
+            match words.next() {
+                Some(sub)
+                    if SUBCOMMAND_PROGRAMS.contains(&program)
+                        && sub.starts_with(|c: char| c.is_ascii_lowercase()) =>
+                {
+                    Some(format!("{program} {sub}"))
+                }
+                _ => Some(program.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Split a command line at `&&`, `||`, `;`, `|` and newlines outside quotes
+fn split_commands(command: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\\') => {
+                chars.next();
+                continue;
+            }
+            (None, ';' | '\n') => {
+                segments.push(String::new());
+                continue;
+            }
+            (None, '&') if chars.peek() == Some(&'&') => {
+                chars.next();
+                segments.push(String::new());
+                continue;
+            }
+            (None, '|') => {
+                if chars.peek() == Some(&'|') {
+                    chars.next();
+                }
+                segments.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.push(c);
+        }
+    }
+    segments
+}
+
+/// Render a tool report. Tables list every tool, the `limit` most common
+/// commands of each project and the `limit` sessions with the most tool
+/// calls; CSV holds the per-tool rows only; JSON holds everything.
+pub fn render_tools(report: &ToolReport, format: OutputFormat, limit: usize) -> String {
+    match format {
+        OutputFormat::Table => render_tools_table(report, limit),
+        OutputFormat::Csv => {
+            let mut out = "project,tool,calls,errors,timed_calls,total_ms\n".to_string();
+            for ((project, tool), counts) in &report.tools {
+                out.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    csv_field(project),
+                    csv_field(tool),
+                    counts.calls,
+                    counts.errors,
+                    counts.timed,
+                    counts.total_ms
+                ));
+            }
+            out
+        }
+        OutputFormat::Json => {
+            let counts_json = |counts: &ToolCounts| {
+                json!({
+                    "calls": counts.calls,
+                    "errors": counts.errors,
+                    "error_rate": (counts.error_rate() * 10.0).round() / 10.0,
+                    "average_ms": counts.average_ms(),
+                })
+            };
+            let keyed = |map: &BTreeMap<(String, String), ToolCounts>, name: &str| -> Vec<Value> {
+                map.iter()
+                    .map(|((project, key), counts)| {
+                        let mut value = counts_json(counts);
+                        value["project"] = json!(project);
+                        value[name] = json!(key);
+                        value
+                    })
+                    .collect()
+            };
+            let sessions: Vec<Value> = report
+                .sessions
+                .iter()
+                .map(|s| {
+                    let mut value = counts_json(&s.counts);
+                    value["session_id"] = json!(s.session_id);
+                    value["project"] = json!(s.project);
+                    value["duration_secs"] = json!(s.duration_secs);
+                    value
+                })
+                .collect();
+            let value = json!({
+                "tools": keyed(&report.tools, "tool"),
+                "commands": keyed(&report.commands, "command"),
+                "sessions": sessions,
+            });
+            serde_json::to_string_pretty(&value).unwrap_or_default() + "\n"
+        }
+    }
+}
+
+fn render_tools_table(report: &ToolReport, limit: usize) -> String {
+    let counts_cells = |counts: &ToolCounts| {
+        vec![
+            group_digits(counts.calls),
+            group_digits(counts.errors),
+            format!("{:.1}%", counts.error_rate()),
+            counts
+                .average_ms()
+                .map_or("-".to_string(), format_duration_ms),
+        ]
+    };
+    let header = |names: &[&str]| -> Vec<String> {
+        names
+            .iter()
+            .chain(&["Calls", "Errors", "Error rate", "Avg time"])
+            .map(|h| h.to_string())
+            .collect()
+    };
+
+    let mut out = "Tools\n\n".to_string();
+    let rows: Vec<Vec<String>> = report
+        .tools
+        .iter()
+        .map(|((project, tool), counts)| {
+            [vec![project.clone(), tool.clone()], counts_cells(counts)].concat()
+        })
+        .collect();
+    out.push_str(&aligned_table(
+        &header(&["Project", "Tool"]),
+        &rows,
+        None,
+        2,
+    ));
+
+    if !report.commands.is_empty() {
+        out.push_str(&format!("\nTop {SHELL_TOOL} commands\n\n"));
+        let mut per_project: BTreeMap<&str, Vec<(&str, &ToolCounts)>> = BTreeMap::new();
+        for ((project, command), counts) in &report.commands {
+            per_project
+                .entry(project)
+                .or_default()
+                .push((command, counts));
+        }
+        let mut rows = Vec::new();
+        for (project, mut commands) in per_project {
+            commands.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then_with(|| a.0.cmp(b.0)));
+            for (command, counts) in commands.into_iter().take(limit) {
+                rows.push(
+                    [
+                        vec![project.to_string(), command.to_string()],
+                        counts_cells(counts),
+                    ]
+                    .concat(),
+                );
+            }
+        }
+        out.push_str(&aligned_table(
+            &header(&["Project", "Command"]),
+            &rows,
+            None,
+            2,
+        ));
+    }
+
+    out.push_str("\nMost tool-heavy sessions\n\n");
+    let rows: Vec<Vec<String>> = report
+        .sessions
+        .iter()
+        .take(limit)
+        .map(|s| {
+            vec![
+                s.session_id.clone(),
+                s.project.clone(),
+                group_digits(s.counts.calls),
+                group_digits(s.counts.errors),
+                s.duration_secs.map_or("-".to_string(), |secs| {
+                    format_duration_ms(secs.max(0) as u64 * 1000)
+                }),
+            ]
+        })
+        .collect();
+    let header: Vec<String> = ["Session", "Project", "Tool calls", "Errors", "Duration"]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    out.push_str(&aligned_table(&header, &rows, None, 2));
+    out
+}
+
+/// `850ms`, `12.3s`, `4m 05s`, `2h 07m`
+fn format_duration_ms(ms: u64) -> String {
+    let secs = ms / 1000;
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else if secs < 60 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SessionSummary;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_command_programs() {
+        assert_eq!(
+            command_programs("cd src && RUST_LOG=debug cargo test -q 2>&1 | tail -5"),
+            vec!["cd", "cargo test", "tail"]
+        );
+        assert_eq!(
+            command_programs("git -C repo status; /usr/bin/grep \"a|b\" f || true"),
+            vec!["git", "grep", "true"]
+        );
+        assert_eq!(command_programs("sudo ls -la\n(echo hi)"), vec!["ls"]);
+    }
+
+    #[test]
+    fn test_collect_pairs_calls_with_results() {
+        let dir = tempdir().unwrap();
+        let lines = [
+            json!({"type": "assistant", "uuid": "1", "timestamp": "2025-03-01T10:00:00Z",
+                   "message": {"role": "assistant", "content": [
+                       {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "git status && cargo build"}},
+                       {"type": "tool_use", "id": "t2", "name": "Read", "input": {"file_path": "a.rs"}}]}}),
+            json!({"type": "user", "uuid": "2", "timestamp": "2025-03-01T10:00:02Z",
+                   "message": {"role": "user", "content": [
+                       {"type": "tool_result", "tool_use_id": "t1", "content": "error", "is_error": true},
+                       {"type": "tool_result", "tool_use_id": "t2", "content": "fn main"}]}}),
+            json!({"type": "assistant", "uuid": "3", "timestamp": "2025-03-01T10:05:00Z",
+                   "message": {"role": "assistant", "content": [
+                       {"type": "tool_use", "id": "t3", "name": "Bash", "input": {"command": "cargo build"}}]}}),
+        ];
+        let path = dir.path().join("s1.jsonl");
+        let content: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        fs::write(&path, content).unwrap();
+        let summary = SessionSummary::from_file(&path).unwrap();
+        let sources = [SessionSource {
+            summary: &summary,
+            project: "web".to_string(),
+            machine: "laptop".to_string(),
+        }];
+
+        let report = collect_tools(&StatsQuery::default(), &sources, 1);
+        let bash = report.tools[&("web".to_string(), "Bash".to_string())];
+        assert_eq!(bash.calls, 2);
+        assert_eq!(bash.errors, 1);
+        // The last call was never answered, so only the first is timed
+        assert_eq!((bash.timed, bash.total_ms), (1, 2000));
+        assert_eq!(bash.error_rate(), 50.0);
+
+        let cargo = report.commands[&("web".to_string(), "cargo build".to_string())];
+        assert_eq!((cargo.calls, cargo.errors), (2, 1));
+        assert_eq!(report.commands.len(), 2);
+
+        assert_eq!(report.sessions.len(), 1);
+        assert_eq!(report.sessions[0].counts.calls, 3);
+        assert_eq!(report.sessions[0].duration_secs, Some(300));
+
+        let table = render_tools(&report, OutputFormat::Table, 10);
+        assert!(table.contains("\nweb      Bash      2       1       50.0%      2.0s\n"));
+        assert!(table.contains("\nweb      git status       1       1      100.0%      2.0s\n"));
+        assert!(table.contains("\ns1       web               3       1    5m 00s\n"));
+        let csv = render_tools(&report, OutputFormat::Csv, 10);
+        assert!(csv.contains("\nweb,Read,1,0,1,2000\n"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Sync repository copies that deliberately differ from their local source.
+//!
+//! Push may rewrite a session on its way into the repository (redacting
+//! secrets, for one), and pull may rewrite it on its way back (home paths),
+//! so the repository copy no longer hashes like the local transcript.
+//! Comparing hashes alone would then see every such session as modified:
+//! push would rewrite it on every run, and pull would report a conflict. The
+//! ledger, kept in the config directory, remembers which local content each
+//! rewritten copy was made from, so both sides can tell a copy of unchanged
+//! content from a real change.
+//!
+//! Each record also carries a fingerprint of the settings the copy was made
+//! with. Once those settings change, a copy of unchanged content is stale:
+//! push rewrites it under the new settings instead of leaving it alone.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config::ConfigManager;
+use crate::filter::FilterConfig;
+use crate::redact::Findings;
+
+/// Bump whenever the meaning of a record changes
+const LEDGER_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LedgerFile {
+    version: u32,
+    sessions: BTreeMap<String, CopyRecord>,
+}
+
+/// How one session's repository copy was made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopyRecord {
+    /// Content hash of the local transcript that was copied
+    pub local_hash: String,
+    /// Content hash of the copy written to the repository
+    pub repo_hash: String,
+    /// Secrets redacted from the copy, by rule
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub redactions: Findings,
+    /// Fingerprint of the settings the copy was made with (see
+    /// [`settings_fingerprint`]); empty for copies pulled from the repository
+    /// and in records from older versions
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub settings: String,
+    /// When the copy was written (RFC 3339)
+    pub written_at: String,
+}
+
+/// Fingerprint of the settings that shape the repository copy of a session
+pub fn settings_fingerprint(filter: &FilterConfig) -> String {
+    let mut settings = serde_json::json!({
+        "redaction": filter.redaction,
+        "redaction_rules": filter.redaction_rules,
+        "redact_high_entropy": filter.redact_high_entropy,
+        "lite_push": filter.lite_push.enabled.then_some(&filter.lite_push),
+        "anonymize_home_paths": filter
+            .anonymize_home_paths
+            .then_some(crate::home_paths::HOME_TOKEN),
+    });
+    // Copies redacted before redaction scanned more are redacted again
+    if filter.redaction != crate::redact::RedactionMode::Off {
+        settings["redaction_scope"] = crate::redact::SCAN_VERSION.into();
+    }
+    Sha256::digest(settings.to_string().as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Rewritten repository copies, by session id
+pub struct CopyLedger {
+    path: Option<PathBuf>,
+    data: LedgerFile,
+    /// Fingerprint of the current settings, when copies are checked against it
+    settings: Option<String>,
+    dirty: bool,
+}
+
+impl Default for CopyLedger {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl CopyLedger {
+    /// A ledger that is never persisted
+    pub fn in_memory() -> Self {
+        CopyLedger {
+            path: None,
+            data: LedgerFile {
+                version: LEDGER_VERSION,
+                sessions: BTreeMap::new(),
+            },
+            settings: None,
+            dirty: false,
+        }
+    }
+
+    /// Load the ledger from the config directory
+    pub fn load() -> Result<Self> {
+        Ok(Self::load_from(ConfigManager::copy_ledger_path()?))
+    }
+
+    /// Load the ledger from a custom path (for testing).
+    ///
+    /// A missing, unreadable or outdated ledger starts empty: rewritten
+    /// copies then look modified once and are written again.
+    pub fn load_from<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let mut ledger = Self::in_memory();
+
+        if path.exists() {
+            match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| serde_json::from_str::<LedgerFile>(&json).map_err(Into::into))
+            {
+                Ok(data) if data.version == LEDGER_VERSION => ledger.data = data,
+                Ok(_) => log::info!("Copy ledger format changed; starting over"),
+                Err(e) => log::warn!("Ignoring unreadable copy ledger {}: {}", path.display(), e),
+            }
+        }
+
+        ledger.path = Some(path);
+        ledger
+    }
+
+    /// Check copies against the settings in `filter`. Every copy recorded from
+    /// then on carries their fingerprint, including copies identical to their
+    /// source, and [`is_current_copy`](Self::is_current_copy) only accepts
+    /// recorded copies made with the same settings.
+    pub fn with_settings(mut self, filter: &FilterConfig) -> Self {
+        self.settings = Some(settings_fingerprint(filter));
+        self
+    }
+
+    /// Whether the repository copy hashing `repo_hash` is what push would
+    /// write for local content hashing `local_hash`: an identical copy, or a
+    /// recorded copy of that content. With
+    /// [`with_settings`](Self::with_settings), a recorded copy must also have
+    /// been made under the same settings, so a settings change rewrites it.
+    /// An identical copy with no record stays current: it was pushed before
+    /// copies were recorded, and rewriting every such file would churn the
+    /// whole repository for nothing.
+    pub fn is_current_copy(&self, session_id: &str, local_hash: &str, repo_hash: &str) -> bool {
+        match self.data.sessions.get(session_id) {
+            None => local_hash == repo_hash,
+            Some(record) => {
+                record.local_hash == local_hash
+                    && record.repo_hash == repo_hash
+                    && self
+                        .settings
+                        .as_ref()
+                        .is_none_or(|settings| record.settings == *settings)
+            }
+        }
+    }
+
+    /// Whether the repository copy hashing `repo_hash` was made from local
+    /// content hashing `local_hash`
+    pub fn is_copy_of(&self, session_id: &str, local_hash: &str, repo_hash: &str) -> bool {
+        self.data
+            .sessions
+            .get(session_id)
+            .is_some_and(|r| r.local_hash == local_hash && r.repo_hash == repo_hash)
+    }
+
+    /// How the repository copy of a session was made, if it was rewritten
+    pub fn get(&self, session_id: &str) -> Option<&CopyRecord> {
+        self.data.sessions.get(session_id)
+    }
+
+    /// Record a copy of `session_id`. Unless settings are checked, copies
+    /// identical to their source are forgotten instead, as plain hash
+    /// comparison already covers them.
+    pub fn record(
+        &mut self,
+        session_id: &str,
+        local_hash: &str,
+        repo_hash: &str,
+        redactions: Findings,
+    ) {
+        if local_hash == repo_hash && self.settings.is_none() {
+            self.forget(session_id);
+            return;
+        }
+        let record = CopyRecord {
+            local_hash: local_hash.to_string(),
+            repo_hash: repo_hash.to_string(),
+            redactions,
+            settings: self.settings.clone().unwrap_or_default(),
+            written_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.data.sessions.insert(session_id.to_string(), record);
+        self.dirty = true;
+    }
+
+    /// Record a copy pull wrote from the repository. The repository side was
+    /// made elsewhere, under settings unknown here, so the record carries no
+    /// fingerprint: push checks the session again under its own settings.
+    /// Identical copies are recorded too, for the same reason.
+    pub fn record_pulled(&mut self, session_id: &str, local_hash: &str, repo_hash: &str) {
+        let record = CopyRecord {
+            local_hash: local_hash.to_string(),
+            repo_hash: repo_hash.to_string(),
+            redactions: Findings::new(),
+            settings: String::new(),
+            written_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.data.sessions.insert(session_id.to_string(), record);
+        self.dirty = true;
+    }
+
+    /// Drop the record of `session_id`
+    pub fn forget(&mut self, session_id: &str) {
+        if self.data.sessions.remove(session_id).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Write the ledger back if it changed. In-memory ledgers are never written.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let dir = path
+            .parent()
+            .context("Copy ledger path has no parent directory")?;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+        let json =
+            serde_json::to_string_pretty(&self.data).context("Failed to serialize copy ledger")?;
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)
+            .with_context(|| format!("Failed to create temp file in {}", dir.display()))?;
+        tmp.write_all(json.as_bytes())
+            .context("Failed to write copy ledger")?;
+        tmp.persist(path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_records_persist_and_identical_copies_are_forgotten() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("repo-copies.json");
+
+        let mut ledger = CopyLedger::load_from(&path);
+        let findings = Findings::from([("jwt".to_string(), 2)]);
+        ledger.record("s1", "local", "repo", findings.clone());
+        ledger.record("s2", "same", "same", Findings::new());
+        ledger.save().unwrap();
+
+        let mut ledger = CopyLedger::load_from(&path);
+        assert!(ledger.is_copy_of("s1", "local", "repo"));
+        assert!(!ledger.is_copy_of("s1", "changed", "repo"));
+        assert!(!ledger.is_copy_of("s2", "same", "same"));
+        assert_eq!(ledger.get("s1").unwrap().redactions, findings);
+
+        ledger.record("s1", "local-2", "local-2", Findings::new());
+        ledger.save().unwrap();
+        assert!(CopyLedger::load_from(&path).get("s1").is_none());
+    }
+
+    #[test]
+    fn test_copies_made_under_other_settings_are_stale() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("repo-copies.json");
+        let mut filter = FilterConfig::default();
+
+        let mut ledger = CopyLedger::load_from(&path);
+        assert!(ledger.is_current_copy("s1", "same", "same"));
+        ledger = ledger.with_settings(&filter);
+        // Identical copies from before settings were recorded stay current
+        assert!(ledger.is_current_copy("s1", "same", "same"));
+        assert!(!ledger.is_current_copy("s1", "changed", "same"));
+        ledger.record("s1", "same", "same", Findings::new());
+        ledger.record("s2", "local", "repo", Findings::new());
+        ledger.save().unwrap();
+
+        let ledger = CopyLedger::load_from(&path).with_settings(&filter);
+        assert!(ledger.is_current_copy("s1", "same", "same"));
+        assert!(ledger.is_current_copy("s2", "local", "repo"));
+        assert!(!ledger.is_current_copy("s2", "changed", "repo"));
+
+        filter.redaction_rules.push(crate::redact::RedactionRule {
+            name: "internal".to_string(),
+            pattern: "secret".to_string(),
+        });
+        let ledger = CopyLedger::load_from(&path).with_settings(&filter);
+        assert!(!ledger.is_current_copy("s1", "same", "same"));
+        assert!(!ledger.is_current_copy("s2", "local", "repo"));
+
+        // Copies pulled from elsewhere are checked again by the next push
+        let mut ledger = CopyLedger::load_from(&path);
+        ledger.record_pulled("s3", "same", "same");
+        assert!(ledger.is_copy_of("s3", "same", "same"));
+        assert!(!ledger
+            .with_settings(&filter)
+            .is_current_copy("s3", "same", "same"));
+
+        let anonymized = FilterConfig {
+            anonymize_home_paths: true,
+            ..Default::default()
+        };
+        assert_ne!(
+            settings_fingerprint(&anonymized),
+            settings_fingerprint(&FilterConfig::default())
+        );
+    }
+}
@@ -5,7 +5,10 @@ use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 use crate::filter::FilterConfig;
-use crate::parser::ConversationSession;
+use crate::parser::{ConversationSession, SessionSummary};
+
+use super::parallel::{parallel_map, resolve_threads};
+use super::units::{is_sidechain_path, retain_units};
 
 /// Threshold for warning about large conversation files (10 MB)
 pub(crate) const LARGE_FILE_WARNING_THRESHOLD: u64 = 10 * 1024 * 1024;
@@ -29,35 +32,204 @@ pub(crate) fn claude_projects_dir() -> Result<PathBuf> {
     Ok(claude_home_dir()?.join("projects"))
 }
 
+/// Walk `base_path` for `.jsonl` transcripts that pass the filter.
+///
+/// Entries are visited in file-name order so discovery output does not depend
+/// on the filesystem's directory order. Subagent sidechains are always
+/// yielded: whether they sync depends on their parent session, which is only
+/// known once they are parsed (see [`retain_units`]).
+pub(crate) fn session_files<'a>(
+    base_path: &Path,
+    filter: &'a FilterConfig,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    WalkDir::new(base_path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .filter(move |path| is_sidechain_path(path) || filter.should_include(path))
+}
+
 /// Discover all conversation sessions in Claude Code history
 pub fn discover_sessions(
     base_path: &Path,
     filter: &FilterConfig,
 ) -> Result<Vec<ConversationSession>> {
+    let paths: Vec<PathBuf> = session_files(base_path, filter).collect();
+    let parsed = parallel_map(&paths, resolve_threads(filter.discovery_threads), |path| {
+        ConversationSession::from_file(path)
+    });
+
     let mut sessions = Vec::new();
+    for (path, result) in paths.iter().zip(parsed) {
+        match result {
+            Ok(session) => sessions.push(session),
+            Err(e) => {
+                log::warn!("Failed to parse {}: {}", path.display(), e);
+            }
+        }
+    }
+    retain_units(&mut sessions, filter);
 
-    for entry in WalkDir::new(base_path)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
+    Ok(sessions)
+}
 
-        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-            if !filter.should_include(path) {
-                continue;
-            }
+/// A transcript discovery could not parse
+#[derive(Debug, Clone)]
+pub struct ParseFailure {
+    /// Path of the transcript
+    pub path: PathBuf,
+    /// Parse error, including its context chain
+    pub error: String,
+}
 
-            match ConversationSession::from_file(path) {
-                Ok(session) => sessions.push(session),
-                Err(e) => {
-                    log::warn!("Failed to parse {}: {}", path.display(), e);
-                }
-            }
+/// Discover conversation sessions as streamed summaries.
+///
+/// Same walk and filtering as [`discover_sessions`], but each transcript is
+/// reduced to its [`SessionSummary`] in one pass, so peak memory no longer
+/// scales with the largest file. Push, pull and status run on these and only
+/// load full sessions for the ones that need merging.
+pub fn discover_session_summaries(
+    base_path: &Path,
+    filter: &FilterConfig,
+) -> Result<Vec<SessionSummary>> {
+    let (summaries, _) = discover_session_summaries_with_failures(base_path, filter)?;
+    Ok(summaries)
+}
+
+/// Like [`discover_session_summaries`], but also returns the transcripts that
+/// failed to parse so the caller can quarantine or report them.
+///
+/// Reads every file; push, pull and status go through a persistent
+/// [`DiscoveryIndex`](super::index::DiscoveryIndex) instead.
+pub fn discover_session_summaries_with_failures(
+    base_path: &Path,
+    filter: &FilterConfig,
+) -> Result<(Vec<SessionSummary>, Vec<ParseFailure>)> {
+    super::index::DiscoveryIndex::in_memory().discover(base_path, filter)
+}
+
+/// Tell the user which projects of an encrypted sync repository were left
+/// out because this machine's key is not among their recipients.
+pub(crate) fn report_locked_projects(projects: &std::collections::BTreeSet<String>) {
+    for project in projects {
+        println!(
+            "  {} Skipping project {}: it is encrypted to a recipient list this machine's key is not on",
+            "!".yellow(),
+            project.cyan()
+        );
+    }
+}
+
+/// Copy local transcripts that failed to parse into this machine's quarantine
+/// and tell the user about any that are new.
+///
+/// Quarantine problems are logged rather than failing the sync: the broken
+/// transcript is already excluded either way.
+pub(crate) fn quarantine_failures(failures: &[ParseFailure], verbosity: crate::VerbosityLevel) {
+    if failures.is_empty() {
+        return;
+    }
+
+    let quarantine = match crate::quarantine::Quarantine::open() {
+        Ok(q) => q,
+        Err(e) => {
+            log::warn!("Failed to open quarantine: {e:#}");
+            return;
+        }
+    };
+
+    let mut newly_quarantined = 0;
+    for failure in failures {
+        match quarantine.add(&failure.path, &failure.error) {
+            Ok(true) => newly_quarantined += 1,
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to quarantine {}: {e:#}", failure.path.display()),
         }
     }
 
-    Ok(sessions)
+    if verbosity != crate::VerbosityLevel::Quiet {
+        println!(
+            "  {} {} unparsable transcript(s) not synced ({} newly quarantined) — see: claude-code-sync quarantine list",
+            "!".yellow(),
+            failures.len(),
+            newly_quarantined
+        );
+    }
+}
+
+/// Maximum number of partially parsed sessions listed individually
+const MAX_PARTIAL_SESSIONS_TO_DISPLAY: usize = 10;
+
+/// Number of sessions that had malformed lines skipped during discovery
+pub(crate) fn partially_parsed_count(sessions: &[SessionSummary]) -> usize {
+    sessions
+        .iter()
+        .filter(|s| !s.skipped_lines.is_empty())
+        .count()
+}
+
+/// Report sessions whose transcripts had malformed lines skipped.
+///
+/// Prints nothing when every session parsed cleanly. Each affected file is
+/// listed with its skipped line numbers; `detailed` adds the parser's reason
+/// for every line.
+///
+/// # Arguments
+/// * `label` - Which side was discovered (e.g. "local", "sync repo")
+/// * `sessions` - Discovered summaries
+/// * `base_path` - Directory the sessions were discovered under, for display
+/// * `detailed` - Include per-line reasons
+pub(crate) fn report_skipped_lines(
+    label: &str,
+    sessions: &[SessionSummary],
+    base_path: &Path,
+    detailed: bool,
+) {
+    let partial: Vec<&SessionSummary> = sessions
+        .iter()
+        .filter(|s| !s.skipped_lines.is_empty())
+        .collect();
+    if partial.is_empty() {
+        return;
+    }
+
+    let total_lines: usize = partial.iter().map(|s| s.skipped_lines.len()).sum();
+    println!(
+        "  {} Partially parsed ({}): {} session(s), {} malformed line(s) skipped",
+        "!".yellow(),
+        label,
+        partial.len(),
+        total_lines
+    );
+
+    for session in partial.iter().take(MAX_PARTIAL_SESSIONS_TO_DISPLAY) {
+        let path = Path::new(&session.file_path);
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        let lines: Vec<String> = session
+            .skipped_lines
+            .iter()
+            .map(|s| s.line.to_string())
+            .collect();
+        println!("    - {} (line {})", relative.display(), lines.join(", "));
+        if detailed {
+            for skipped in &session.skipped_lines {
+                println!(
+                    "        {} {}",
+                    format!("line {}:", skipped.line).dimmed(),
+                    skipped.reason.dimmed()
+                );
+            }
+        }
+    }
+    if partial.len() > MAX_PARTIAL_SESSIONS_TO_DISPLAY {
+        println!(
+            "    ... and {} more",
+            partial.len() - MAX_PARTIAL_SESSIONS_TO_DISPLAY
+        );
+    }
 }
 
 /// Check for large conversation files and emit warnings
@@ -284,4 +456,30 @@ mod tests {
         assert!(collisions.contains_key("myapp"));
         assert_eq!(collisions.get("myapp").unwrap().len(), 2);
     }
+
+    #[test]
+    fn test_discover_summaries_honors_lenient_parsing() {
+        let temp_dir = tempdir().unwrap();
+        let project_dir = temp_dir.path().join("-root-project");
+        fs::create_dir(&project_dir).unwrap();
+        fs::write(
+            project_dir.join("abc.jsonl"),
+            "{\"type\":\"user\",\"uuid\":\"1\",\"cwd\":\"/root/project\"}\n{\"type\":\"assis",
+        )
+        .unwrap();
+
+        let lenient = FilterConfig::default();
+        let summaries = discover_session_summaries(temp_dir.path(), &lenient).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].skipped_lines.len(), 1);
+        assert_eq!(summaries[0].skipped_lines[0].line, 2);
+        assert_eq!(partially_parsed_count(&summaries), 1);
+
+        let strict = FilterConfig {
+            lenient_parsing: false,
+            ..Default::default()
+        };
+        let summaries = discover_session_summaries(temp_dir.path(), &strict).unwrap();
+        assert!(summaries.is_empty());
+    }
 }
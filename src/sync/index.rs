@@ -0,0 +1,487 @@
+//! Persistent discovery index.
+//!
+//! Summarizing a transcript means reading all of it, and push, pull and status
+//! each summarize every transcript on both the local and the sync repo side.
+//! The index caches each file's [`SessionSummary`] in the config directory,
+//! keyed by path and validated by size and modification time, so only files
+//! that changed since the last run are read again.
+//!
+//! Each entry can also hold the outcome of the exclusion rules
+//! ([`crate::session_rules`]) for that file, tied to a fingerprint of the
+//! rules, so an unchanged session is only read against the rules once.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ConfigManager;
+use crate::filter::FilterConfig;
+use crate::parser::{SessionInfo, SessionSummary};
+
+use super::discovery::{session_files, ParseFailure};
+use super::parallel::{parallel_map, resolve_threads};
+use super::units::retain_units;
+
+/// Bump whenever the meaning of a cached summary changes (e.g. the content
+/// hash algorithm), so stale indexes are discarded rather than trusted.
+const INDEX_VERSION: u32 = 2;
+
+/// Files modified this close to (or after) the moment they were indexed are
+/// not trusted: a write landing in the same timestamp tick as the read would
+/// otherwise leave a stale summary that size and mtime cannot reveal.
+const RACY_WINDOW_SECS: u64 = 2;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+    indexed_at_secs: u64,
+    summary: SessionSummary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule_match: Option<RuleMatch>,
+}
+
+/// Outcome of the exclusion rules for one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RuleMatch {
+    /// Fingerprint of the rules evaluated
+    rules: String,
+    /// Name of the rule that matched, if any
+    matched: Option<String>,
+}
+
+/// Size and modification time of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(FileStamp {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+impl IndexEntry {
+    fn is_fresh_for(&self, stamp: FileStamp) -> bool {
+        self.size == stamp.size
+            && self.mtime_secs == stamp.mtime_secs
+            && self.mtime_nanos == stamp.mtime_nanos
+            && self.mtime_secs + RACY_WINDOW_SECS < self.indexed_at_secs
+    }
+}
+
+/// Cache of session summaries keyed by file path
+pub struct DiscoveryIndex {
+    path: Option<PathBuf>,
+    data: IndexFile,
+    dirty: bool,
+}
+
+impl DiscoveryIndex {
+    /// An index that is never persisted; every discovery reads every file
+    pub fn in_memory() -> Self {
+        DiscoveryIndex {
+            path: None,
+            data: IndexFile {
+                version: INDEX_VERSION,
+                entries: BTreeMap::new(),
+            },
+            dirty: false,
+        }
+    }
+
+    /// Load the index from the config directory
+    pub fn load() -> Result<Self> {
+        Ok(Self::load_from(ConfigManager::discovery_index_path()?))
+    }
+
+    /// Load the index from a custom path (for testing).
+    ///
+    /// A missing, unreadable or outdated index is not an error: it simply
+    /// starts empty and is rebuilt by the next discovery.
+    pub fn load_from<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let mut index = Self::in_memory();
+
+        if path.exists() {
+            match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| serde_json::from_str::<IndexFile>(&json).map_err(Into::into))
+            {
+                Ok(data) if data.version == INDEX_VERSION => index.data = data,
+                Ok(_) => log::info!("Discovery index format changed; rebuilding"),
+                Err(e) => log::warn!(
+                    "Ignoring unreadable discovery index {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+
+        index.path = Some(path);
+        index
+    }
+
+    /// Number of cached files
+    pub fn len(&self) -> usize {
+        self.data.entries.len()
+    }
+
+    /// True if nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.data.entries.is_empty()
+    }
+
+    /// Discover session summaries under `base_path`, reading only files that
+    /// are new or changed since they were last indexed.
+    ///
+    /// Files that must be read are parsed and hashed on a worker pool sized by
+    /// `discovery_threads`; results are always in path order.
+    ///
+    /// The filter applies per sync unit: subagent sidechains follow their parent
+    /// session in or out.
+    ///
+    /// Cached entries under `base_path` whose files are gone (or no longer pass
+    /// the filter) are pruned. Files that fail to parse are never cached, so
+    /// they are retried, and reported, on every run.
+    pub fn discover(
+        &mut self,
+        base_path: &Path,
+        filter: &FilterConfig,
+    ) -> Result<(Vec<SessionSummary>, Vec<ParseFailure>)> {
+        let mode = filter.parse_mode();
+        let now_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let paths: Vec<PathBuf> = session_files(base_path, filter).collect();
+        let stamps: Vec<Option<FileStamp>> = paths.iter().map(|p| FileStamp::of(p)).collect();
+
+        // Serve what the index can, then parse and hash the rest in parallel
+        let misses: Vec<usize> = (0..paths.len())
+            .filter(|&i| {
+                let key = paths[i].to_string_lossy();
+                match (stamps[i], self.data.entries.get(key.as_ref())) {
+                    (Some(stamp), Some(entry)) => {
+                        !(entry.is_fresh_for(stamp) && entry.summary.parse_mode == mode)
+                    }
+                    _ => true,
+                }
+            })
+            .collect();
+        let parsed = parallel_map(&misses, resolve_threads(filter.discovery_threads), |&i| {
+            SessionSummary::from_file_with_mode(&paths[i], mode)
+        });
+        let mut parsed: BTreeMap<usize, Result<SessionSummary>> =
+            misses.into_iter().zip(parsed).collect();
+        let hits = paths.len() - parsed.len();
+
+        let mut summaries = Vec::new();
+        let mut failures = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (i, path) in paths.into_iter().enumerate() {
+            let key = path.to_string_lossy().to_string();
+
+            let Some(result) = parsed.remove(&i) else {
+                summaries.push(self.data.entries[&key].summary.clone());
+                seen.insert(key);
+                continue;
+            };
+
+            match result {
+                Ok(summary) => {
+                    if let Some(stamp) = stamps[i] {
+                        self.data.entries.insert(
+                            key.clone(),
+                            IndexEntry {
+                                size: stamp.size,
+                                mtime_secs: stamp.mtime_secs,
+                                mtime_nanos: stamp.mtime_nanos,
+                                indexed_at_secs: now_secs,
+                                summary: summary.clone(),
+                                rule_match: None,
+                            },
+                        );
+                        self.dirty = true;
+                        seen.insert(key);
+                    }
+                    summaries.push(summary);
+                }
+                Err(e) => {
+                    log::warn!("Failed to parse {}: {}", path.display(), e);
+                    if self.data.entries.remove(&key).is_some() {
+                        self.dirty = true;
+                    }
+                    failures.push(ParseFailure {
+                        path,
+                        error: format!("{e:#}"),
+                    });
+                }
+            }
+        }
+
+        let before = self.data.entries.len();
+        self.data
+            .entries
+            .retain(|key, _| !Path::new(key).starts_with(base_path) || seen.contains(key));
+        if self.data.entries.len() != before {
+            self.dirty = true;
+        }
+
+        retain_units(&mut summaries, filter);
+
+        log::debug!(
+            "Discovered {} sessions under {} ({} from index)",
+            summaries.len(),
+            base_path.display(),
+            hits
+        );
+
+        Ok((summaries, failures))
+    }
+
+    /// Cached outcome of the exclusion rules fingerprinted `rules` for
+    /// `session`: `Some(matched)` with the name of the matching rule, if any,
+    /// or None when the rules or the file changed since
+    pub(crate) fn rule_match<S: SessionInfo>(
+        &self,
+        session: &S,
+        rules: &str,
+    ) -> Option<Option<String>> {
+        let entry = self.data.entries.get(session.file_path())?;
+        let cached = entry.rule_match.as_ref()?;
+        (cached.rules == rules && entry.summary.content_hash == session.content_hash())
+            .then(|| cached.matched.clone())
+    }
+
+    /// Cache the outcome of the exclusion rules fingerprinted `rules` for
+    /// `session`. Files that are not indexed are left out.
+    pub(crate) fn set_rule_match<S: SessionInfo>(
+        &mut self,
+        session: &S,
+        rules: &str,
+        matched: Option<String>,
+    ) {
+        let Some(entry) = self.data.entries.get_mut(session.file_path()) else {
+            return;
+        };
+        if entry.summary.content_hash != session.content_hash() {
+            return;
+        }
+        entry.rule_match = Some(RuleMatch {
+            rules: rules.to_string(),
+            matched,
+        });
+        self.dirty = true;
+    }
+
+    /// Write the index back if discovery changed it. In-memory indexes are
+    /// never written.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let dir = path
+            .parent()
+            .context("Discovery index path has no parent directory")?;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+        let json =
+            serde_json::to_string(&self.data).context("Failed to serialize discovery index")?;
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)
+            .with_context(|| format!("Failed to create temp file in {}", dir.display()))?;
+        tmp.write_all(json.as_bytes())
+            .context("Failed to write discovery index")?;
+        tmp.persist(path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Save, logging instead of failing: a sync must never fail because its
+    /// cache could not be written.
+    pub fn save_or_warn(&mut self) {
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save discovery index: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    const LINE_A: &str = "{\"type\":\"user\",\"uuid\":\"a\",\"cwd\":\"/p\"}\n";
+    const LINE_B: &str = "{\"type\":\"user\",\"uuid\":\"b\",\"cwd\":\"/p\"}\n";
+
+    /// Write `content` and backdate the mtime past the racy window
+    fn write_aged(path: &Path, content: &str, age_secs: u64) {
+        fs::write(path, content).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_files_are_served_from_index() {
+        let claude = tempdir().unwrap();
+        let config = tempdir().unwrap();
+        let index_path = config.path().join("discovery-index.json");
+        let project = claude.path().join("-p");
+        fs::create_dir(&project).unwrap();
+        let session = project.join("s.jsonl");
+        write_aged(&session, LINE_A, 60);
+        let filter = FilterConfig::default();
+
+        let mut index = DiscoveryIndex::load_from(&index_path);
+        let (first, _) = index.discover(claude.path(), &filter).unwrap();
+        index.save().unwrap();
+        assert_eq!(index.len(), 1);
+
+        // Same size, same mtime: a reload serves the cached summary without
+        // reading the file, so the rewrite below goes unnoticed by design.
+        let mtime = fs::metadata(&session).unwrap().modified().unwrap();
+        fs::write(&session, LINE_B).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&session)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let mut reloaded = DiscoveryIndex::load_from(&index_path);
+        let (cached, _) = reloaded.discover(claude.path(), &filter).unwrap();
+        assert_eq!(cached[0].content_hash, first[0].content_hash);
+
+        // A changed mtime invalidates the entry
+        write_aged(&session, LINE_B, 30);
+        let (fresh, _) = reloaded.discover(claude.path(), &filter).unwrap();
+        assert_ne!(fresh[0].content_hash, first[0].content_hash);
+    }
+
+    #[test]
+    fn test_recently_modified_files_are_always_reparsed() {
+        let claude = tempdir().unwrap();
+        let session = claude.path().join("s.jsonl");
+        fs::write(&session, LINE_A).unwrap();
+        let filter = FilterConfig::default();
+
+        let mut index = DiscoveryIndex::in_memory();
+        index.discover(claude.path(), &filter).unwrap();
+
+        // Rewritten within the same tick: size and mtime may not change, but the
+        // entry was indexed inside the racy window and is not trusted.
+        let mtime = fs::metadata(&session).unwrap().modified().unwrap();
+        fs::write(&session, LINE_B).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&session)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let (summaries, _) = index.discover(claude.path(), &filter).unwrap();
+        let expected = SessionSummary::from_file(&session).unwrap();
+        assert_eq!(summaries[0].content_hash, expected.content_hash);
+    }
+
+    #[test]
+    fn test_deleted_files_are_pruned_and_failures_not_cached() {
+        let claude = tempdir().unwrap();
+        let keep = claude.path().join("keep.jsonl");
+        let gone = claude.path().join("gone.jsonl");
+        let broken = claude.path().join("broken.jsonl");
+        write_aged(&keep, LINE_A, 60);
+        write_aged(&gone, LINE_B, 60);
+        write_aged(&broken, "not json\n", 60);
+        let filter = FilterConfig::default();
+
+        let mut index = DiscoveryIndex::in_memory();
+        let (summaries, failures) = index.discover(claude.path(), &filter).unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(index.len(), 2);
+
+        fs::remove_file(&gone).unwrap();
+        let (summaries, failures) = index.discover(claude.path(), &filter).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_outdated_or_corrupt_index_starts_empty() {
+        let config = tempdir().unwrap();
+        let index_path = config.path().join("discovery-index.json");
+
+        fs::write(&index_path, "{ not json").unwrap();
+        assert!(DiscoveryIndex::load_from(&index_path).is_empty());
+
+        fs::write(&index_path, r#"{"version":0,"entries":{}}"#).unwrap();
+        assert!(DiscoveryIndex::load_from(&index_path).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_discovery_matches_sequential() {
+        let claude = tempdir().unwrap();
+        for project in ["-b", "-a", "-c"] {
+            let dir = claude.path().join(project);
+            fs::create_dir(&dir).unwrap();
+            for n in (0..10).rev() {
+                let line = format!("{{\"type\":\"user\",\"uuid\":\"{project}{n}\"}}\n");
+                fs::write(dir.join(format!("s{n}.jsonl")), line).unwrap();
+            }
+        }
+
+        let discover = |threads| {
+            let filter = FilterConfig {
+                discovery_threads: threads,
+                ..Default::default()
+            };
+            let (summaries, _) = DiscoveryIndex::in_memory()
+                .discover(claude.path(), &filter)
+                .unwrap();
+            summaries
+                .into_iter()
+                .map(|s| (s.file_path, s.content_hash))
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = discover(1);
+        assert_eq!(sequential.len(), 30);
+        let mut sorted = sequential.clone();
+        sorted.sort();
+        assert_eq!(sequential, sorted, "output is in path order");
+        assert_eq!(discover(8), sequential);
+    }
+}
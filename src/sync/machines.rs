@@ -0,0 +1,168 @@
+//! Which machine wrote each session in the sync repository.
+//!
+//! Every push records the sessions it added or modified in
+//! `machines/<machine>.json` at the root of the sync repository, mapping
+//! session id to the content hash it pushed. One file per machine means two
+//! machines pushing at once never touch the same file. Sessions a machine
+//! merely pushes back unchanged after pulling them are not claimed, so a
+//! session belongs to the machine that last changed it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the sync repository holding one manifest per machine
+pub const MACHINES_SUBDIR: &str = "machines";
+
+/// Sessions one machine pushed, as written to `machines/<machine>.json`
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MachineManifest {
+    /// Session id -> content hash last pushed from this machine
+    #[serde(default)]
+    pub sessions: BTreeMap<String, String>,
+}
+
+impl MachineManifest {
+    /// Manifest path of `machine` in the sync repository
+    pub fn path(repo_root: &Path, machine: &str) -> PathBuf {
+        repo_root
+            .join(MACHINES_SUBDIR)
+            .join(format!("{machine}.json"))
+    }
+
+    /// Load the manifest of `machine`; empty if it has none yet
+    pub fn load(repo_root: &Path, machine: &str) -> Result<Self> {
+        let path = Self::path(repo_root, machine);
+        if !path.exists() {
+            return Ok(MachineManifest::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read machine manifest: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse machine manifest: {}", path.display()))
+    }
+
+    /// Record `sessions` (id, content hash) as pushed from `machine`.
+    ///
+    /// Earlier entries are kept, so sessions since deleted locally stay
+    /// attributed. The file is only rewritten when an entry changed, so a push
+    /// with nothing new leaves the repository clean. Returns whether it was.
+    pub fn record<'a>(
+        repo_root: &Path,
+        machine: &str,
+        sessions: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<bool> {
+        // A manifest that no longer parses is rebuilt rather than blocking the push
+        let mut manifest = Self::load(repo_root, machine).unwrap_or_else(|e| {
+            log::warn!("{e:#}; starting a new one");
+            MachineManifest::default()
+        });
+
+        let mut changed = false;
+        for (id, hash) in sessions {
+            if manifest.sessions.get(id).map(String::as_str) != Some(hash) {
+                manifest.sessions.insert(id.to_string(), hash.to_string());
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(false);
+        }
+
+        let path = Self::path(repo_root, machine);
+        fs::create_dir_all(repo_root.join(MACHINES_SUBDIR))
+            .with_context(|| format!("Failed to create {}", MACHINES_SUBDIR))?;
+        let json = serde_json::to_string_pretty(&manifest)?;
+        fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed to write machine manifest: {}", path.display()))?;
+        Ok(true)
+    }
+}
+
+/// Every machine manifest in a sync repository, indexed by session
+#[derive(Debug, Default)]
+pub struct MachineAttribution {
+    /// Session id -> (machine, content hash) for each machine listing it,
+    /// sorted by machine name
+    sessions: HashMap<String, Vec<(String, String)>>,
+}
+
+impl MachineAttribution {
+    /// Read all manifests under `repo_root`. Unreadable ones are skipped
+    /// with a warning.
+    pub fn load(repo_root: &Path) -> Self {
+        let mut attribution = MachineAttribution::default();
+        let Ok(dir) = fs::read_dir(repo_root.join(MACHINES_SUBDIR)) else {
+            return attribution;
+        };
+
+        let mut paths: Vec<PathBuf> = dir
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(machine) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            match MachineManifest::load(repo_root, machine) {
+                Ok(manifest) => {
+                    for (id, hash) in manifest.sessions {
+                        attribution
+                            .sessions
+                            .entry(id)
+                            .or_default()
+                            .push((machine.to_string(), hash));
+                    }
+                }
+                Err(e) => log::warn!("{e:#}"),
+            }
+        }
+        attribution
+    }
+
+    /// Machine that wrote the session as it is now: the one that pushed
+    /// `content_hash`, else the first (by name) that pushed it at all, else
+    /// none (pushed before manifests were recorded)
+    pub fn machine_of(&self, session_id: &str, content_hash: &str) -> Option<&str> {
+        let machines = self.sessions.get(session_id)?;
+        machines
+            .iter()
+            .find(|(_, hash)| hash == content_hash)
+            .or_else(|| machines.first())
+            .map(|(machine, _)| machine.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_and_attribute() {
+        let repo = tempdir().unwrap();
+
+        assert!(
+            MachineManifest::record(repo.path(), "laptop", [("s1", "h1"), ("s2", "h2")]).unwrap()
+        );
+        // Nothing new: the manifest is left alone
+        assert!(!MachineManifest::record(repo.path(), "laptop", [("s1", "h1")]).unwrap());
+        // The desktop continued s2
+        assert!(MachineManifest::record(repo.path(), "desktop", [("s2", "h3")]).unwrap());
+
+        let attribution = MachineAttribution::load(repo.path());
+        assert_eq!(attribution.machine_of("s1", "h1"), Some("laptop"));
+        assert_eq!(attribution.machine_of("s2", "h3"), Some("desktop"));
+        assert_eq!(attribution.machine_of("s2", "h2"), Some("laptop"));
+        assert_eq!(attribution.machine_of("s3", "h4"), None);
+
+        // Entries of earlier pushes are kept
+        MachineManifest::record(repo.path(), "laptop", [("s4", "h5")]).unwrap();
+        let manifest = MachineManifest::load(repo.path(), "laptop").unwrap();
+        assert_eq!(manifest.sessions.len(), 3);
+    }
+}
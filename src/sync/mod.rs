@@ -1,11 +1,16 @@
 // Module declarations
+pub mod copies;
 pub mod discovery;
+pub mod index;
 mod init;
+pub mod machines;
+pub(crate) mod parallel;
 mod pull;
 pub mod push;
 mod remote;
 mod state;
 mod status;
+pub mod units;
 
 // Re-export public types and functions
 pub use init::{init_from_onboarding, init_sync_repo};
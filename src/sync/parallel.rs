@@ -0,0 +1,87 @@
+//! Minimal worker pool for discovery.
+//!
+//! Parsing and hashing transcripts is independent per file, so discovery fans
+//! the work out over scoped threads. Results always come back in input order,
+//! whatever order the workers finish in, so everything built on discovery
+//! (push plans, conflict reports) is identical between runs.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Resolve a configured thread count: 0 means one per available CPU
+pub(crate) fn resolve_threads(configured: usize) -> usize {
+    if configured > 0 {
+        configured
+    } else {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+}
+
+/// Apply `f` to every item using up to `threads` workers, returning the
+/// results in the same order as `items`.
+pub(crate) fn parallel_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.max(1).min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|r| r.expect("every item is processed exactly once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map_preserves_order() {
+        let items: Vec<u64> = (0..200).collect();
+        let results = parallel_map(&items, 8, |n| {
+            // Uneven work so workers finish out of order
+            std::thread::sleep(std::time::Duration::from_micros((200 - n) * 10));
+            n * 2
+        });
+        assert_eq!(results, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parallel_map_handles_trivial_inputs() {
+        let empty: Vec<u32> = Vec::new();
+        assert!(parallel_map(&empty, 4, |n| *n).is_empty());
+        assert_eq!(parallel_map(&[7], 4, |n| n + 1), vec![8]);
+        assert_eq!(parallel_map(&[1, 2, 3], 0, |n| n * 10), vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn test_resolve_threads() {
+        assert_eq!(resolve_threads(3), 3);
+        assert!(resolve_threads(0) >= 1);
+    }
+}
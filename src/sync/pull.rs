@@ -1,25 +1,30 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use inquire::Confirm;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-use crate::conflict::ConflictDetector;
+use crate::conflict::{group_by_unit, ConflictDetector};
+use crate::encryption::view::RepoView;
 use crate::filter::FilterConfig;
 use crate::history::{
     ConversationSummary, OperationHistory, OperationRecord, OperationType, SyncOperation,
 };
+use crate::home_paths::HomePaths;
 use crate::interactive_conflict;
 use crate::parser::ConversationSession;
 use crate::report::{save_conflict_report, ConflictReport};
 use crate::scm;
 use crate::undo::Snapshot;
 
+use super::copies::CopyLedger;
 use super::discovery::{
-    claude_home_dir, claude_projects_dir, discover_sessions, find_local_project_by_name,
-    warn_large_files,
+    claude_home_dir, claude_projects_dir, find_local_project_by_name, quarantine_failures,
+    report_locked_projects, report_skipped_lines, warn_large_files,
 };
+use super::index::DiscoveryIndex;
 use super::state::SyncState;
+use super::units::{report_orphans, unit_members, Refusal, SessionGate};
 use super::MAX_CONVERSATIONS_TO_DISPLAY;
 
 /// Pull and merge history from sync repository
@@ -37,7 +42,7 @@ pub fn pull_history(
 
     let state = SyncState::load()?;
     let repo = scm::open(&state.sync_repo_path)?;
-    let filter = FilterConfig::load()?;
+    let mut filter = FilterConfig::load()?;
     let claude_dir = claude_projects_dir()?;
 
     // Get the current branch name for operation record
@@ -59,19 +64,58 @@ pub fn pull_history(
         }
     }
 
+    // The fetched policy applies to this pull already
+    filter.apply_repo_policy(&state.sync_repo_path)?;
+
+    // Everything below reads plaintext: for an encrypted repository, the
+    // view decrypted from it
+    let view = RepoView::open(&state.sync_repo_path, &filter)?;
+    report_locked_projects(view.locked_projects());
+
     // Discover local sessions
     println!("  {} local sessions...", "Discovering".cyan());
-    let local_sessions = discover_sessions(&claude_dir, &filter)?;
+    let mut index = DiscoveryIndex::load()?;
+    let (local_sessions, parse_failures) = index.discover(&claude_dir, &filter)?;
     println!(
         "  {} {} local sessions",
         "Found".green(),
         local_sessions.len()
     );
+    quarantine_failures(&parse_failures, verbosity);
 
     // Discover remote sessions
-    let remote_projects_dir = state.sync_repo_path.join(&filter.sync_subdirectory);
+    let remote_projects_dir = view.root().join(&filter.sync_subdirectory);
     println!("  {} remote sessions...", "Discovering".cyan());
-    let remote_sessions = discover_sessions(&remote_projects_dir, &filter)?;
+    let (mut remote_sessions, _) = index.discover(&remote_projects_dir, &filter)?;
+    // Denied and excluded sessions are never written here, whoever pushed
+    // them. Deny patterns are written against ~/.claude, so each session is
+    // matched where this pull would write it.
+    let gate = SessionGate::new(&remote_sessions, &filter, &mut index, |s| {
+        let path = Path::new(&s.file_path);
+        let remote_relative = path.strip_prefix(&remote_projects_dir).unwrap_or(path);
+        local_relative_path(remote_relative, &claude_dir, &filter)
+            .unwrap_or_else(|| remote_relative.to_path_buf())
+    })?;
+    let (mut denied_remote, mut excluded_remote) = (0, 0);
+    remote_sessions.retain(|s| match gate.refusal(s) {
+        Some(Refusal::Denied) => {
+            denied_remote += 1;
+            false
+        }
+        Some(Refusal::Excluded) => {
+            excluded_remote += 1;
+            false
+        }
+        None => true,
+    });
+    index.save_or_warn();
+    crate::search::index::update_after_sync(
+        &[
+            (claude_dir.as_path(), &local_sessions[..]),
+            (remote_projects_dir.as_path(), &remote_sessions[..]),
+        ],
+        filter.discovery_threads,
+    );
     println!(
         "  {} {} remote sessions",
         "Found".green(),
@@ -85,14 +129,24 @@ pub fn pull_history(
     if verbosity != VerbosityLevel::Quiet {
         println!("  {} conflicts...", "Detecting".cyan());
     }
+    // Copies push rewrote (redacted) from unchanged local content are not changes
+    let mut ledger = CopyLedger::load()?;
+    // Home paths push wrote as `${HOME}` are written back as this machine's home
+    let home_paths = HomePaths::from_config(&filter);
+    let localize = |e: &mut crate::parser::ConversationEntry| {
+        if let Some(home_paths) = &home_paths {
+            home_paths.expand(e);
+        }
+        true
+    };
     let mut detector = ConflictDetector::new();
-    detector.detect(&local_sessions, &remote_sessions);
+    detector.detect_with_ledger(&local_sessions, &remote_sessions, &ledger);
 
     // ============================================================================
     // ARTIFACT PULL PLAN (read-only, so the snapshot below can cover it)
     // ============================================================================
     let artifact_plan =
-        crate::artifacts::engine::plan_pull(&claude_home_dir()?, &state.sync_repo_path, &filter)?;
+        crate::artifacts::engine::plan_pull(&claude_home_dir()?, view.root(), &filter)?;
 
     // ============================================================================
     // SNAPSHOT CREATION: Only backup files that will actually change
@@ -103,11 +157,22 @@ pub fn pull_history(
     // deleted_files so undo removes them again.
     // This reduces snapshot size from potentially gigabytes to typically <1MB.
     let snapshot_path = if detector.has_conflicts() || !artifact_plan.is_empty() {
+        // Conflicts are resolved per sync unit, so back up every local transcript
+        // of a conflicting session's unit: undo then restores the parent and its
+        // subagent transcripts together.
+        let conflicting_units: HashSet<&str> =
+            detector.conflicts().iter().map(|c| c.unit_id()).collect();
         let mut files_to_snapshot: Vec<PathBuf> = detector
             .conflicts()
             .iter()
             .map(|c| c.local_file.clone())
             .collect();
+        for member in unit_members(&local_sessions, &conflicting_units) {
+            let path = PathBuf::from(&member.file_path);
+            if !files_to_snapshot.contains(&path) {
+                files_to_snapshot.push(path);
+            }
+        }
         files_to_snapshot.extend(artifact_plan.paths_to_snapshot());
 
         println!(
@@ -164,6 +229,25 @@ pub fn pull_history(
             "•".cyan(),
             remote_sessions.len()
         );
+        if denied_remote > 0 {
+            println!(
+                "  {} Skipped (deny patterns): {}",
+                "•".dimmed(),
+                denied_remote
+            );
+        }
+        if excluded_remote > 0 {
+            println!("  {} Excluded by rule: {}", "•".dimmed(), excluded_remote);
+        }
+        let detailed = verbosity == VerbosityLevel::Verbose;
+        report_skipped_lines("local", &local_sessions, &claude_dir, detailed);
+        report_skipped_lines(
+            "sync repo",
+            &remote_sessions,
+            &remote_projects_dir,
+            detailed,
+        );
+        report_orphans("sync repo", &remote_sessions, &remote_projects_dir);
         println!();
     }
 
@@ -179,7 +263,7 @@ pub fn pull_history(
                 "  {}. {} ({} messages)",
                 idx + 1,
                 relative_path.display(),
-                session.message_count()
+                session.message_count
             );
         }
         if remote_sessions.len() > 20 {
@@ -236,54 +320,80 @@ pub fn pull_history(
         let mut smart_merge_success_count = 0;
         let mut smart_merge_failed_conflicts = Vec::new();
 
-        for conflict in detector.conflicts_mut() {
-            // Find local and remote sessions
-            if let (Some(local_session), Some(remote_session)) = (
-                local_map.get(&conflict.session_id),
-                remote_map.get(&conflict.session_id),
-            ) {
-                // Try smart merge
-                match conflict.try_smart_merge(local_session, remote_session) {
-                    Ok(()) => {
-                        smart_merge_success_count += 1;
-                        // Write merged result to local file
-                        if let crate::conflict::ConflictResolution::SmartMerge {
-                            ref merged_entries,
-                            ref stats,
-                        } = conflict.resolution
-                        {
-                            // Create a new session with merged entries
-                            let merged_session = ConversationSession {
-                                session_id: conflict.session_id.clone(),
-                                entries: merged_entries.clone(),
-                                file_path: conflict.local_file.to_string_lossy().to_string(),
-                            };
-
-                            // Write merged session to local path
-                            if let Err(e) = merged_session.write_to_file(&conflict.local_file) {
-                                log::warn!(
-                                    "Failed to write merged session {}: {}",
-                                    conflict.session_id,
-                                    e
-                                );
-                                smart_merge_failed_conflicts.push(conflict.clone());
-                            } else {
-                                println!(
-                                    "  {} Smart merged {} ({} local + {} remote = {} total, {} branches)",
-                                    "✓".green(),
-                                    conflict.session_id,
-                                    stats.local_messages,
-                                    stats.remote_messages,
-                                    stats.merged_messages,
-                                    stats.branches_detected
-                                );
-                            }
-                        }
+        // A session and its subagent sidechains are merged as one unit: if any
+        // of them cannot be merged, the whole unit goes to manual resolution so
+        // its transcripts are never resolved in different ways.
+        let units = group_by_unit(detector.conflicts());
+        let conflicts = detector.conflicts_mut();
+
+        for unit in units {
+            let mut unit_merged = true;
+            for &i in &unit {
+                let conflict = &mut conflicts[i];
+                let merge_result = match (
+                    local_map.get(&conflict.session_id),
+                    remote_map.get(&conflict.session_id),
+                ) {
+                    // Only conflicting sessions are materialized in full for merging
+                    (Some(local_summary), Some(remote_summary)) => {
+                        local_summary.load().and_then(|local_session| {
+                            let mut remote_session = remote_summary.load()?;
+                            remote_session.entries.iter_mut().for_each(|e| {
+                                localize(e);
+                            });
+                            conflict.try_smart_merge(&local_session, &remote_session)
+                        })
                     }
-                    Err(e) => {
-                        log::warn!("Smart merge failed for {}: {}", conflict.session_id, e);
-                        log::info!("Falling back to manual resolution...");
+                    _ => Err(anyhow::anyhow!("session not found on both sides")),
+                };
+
+                if let Err(e) = merge_result {
+                    log::warn!("Smart merge failed for {}: {}", conflict.session_id, e);
+                    log::info!("Falling back to manual resolution...");
+                    unit_merged = false;
+                    break;
+                }
+            }
+
+            if !unit_merged {
+                smart_merge_failed_conflicts.extend(unit.iter().map(|&i| conflicts[i].clone()));
+                continue;
+            }
+
+            for &i in &unit {
+                let conflict = &conflicts[i];
+                // Write merged result to local file
+                if let crate::conflict::ConflictResolution::SmartMerge {
+                    ref merged_entries,
+                    ref stats,
+                } = conflict.resolution
+                {
+                    // Create a new session with merged entries
+                    let merged_session = ConversationSession {
+                        session_id: conflict.session_id.clone(),
+                        entries: merged_entries.clone(),
+                        file_path: conflict.local_file.to_string_lossy().to_string(),
+                    };
+
+                    // Write merged session to local path
+                    if let Err(e) = merged_session.write_to_file(&conflict.local_file) {
+                        log::warn!(
+                            "Failed to write merged session {}: {}",
+                            conflict.session_id,
+                            e
+                        );
                         smart_merge_failed_conflicts.push(conflict.clone());
+                    } else {
+                        smart_merge_success_count += 1;
+                        println!(
+                            "  {} Smart merged {} ({} local + {} remote = {} total, {} branches)",
+                            "✓".green(),
+                            conflict.session_id,
+                            stats.local_messages,
+                            stats.remote_messages,
+                            stats.merged_messages,
+                            stats.branches_detected
+                        );
                     }
                 }
             }
@@ -324,6 +434,7 @@ pub fn pull_history(
                     &remote_sessions,
                     &claude_dir,
                     &remote_projects_dir,
+                    home_paths.as_ref(),
                 )?;
 
                 // Save conflict report
@@ -360,7 +471,7 @@ pub fn pull_history(
                             .iter()
                             .find(|s| s.session_id == conflict.session_id)
                         {
-                            session.write_to_file(&renamed_path)?;
+                            gate.copy(session, &renamed_path, localize)?;
                         }
 
                         renames.push((conflict.remote_file.clone(), renamed_path));
@@ -401,8 +512,8 @@ pub fn pull_history(
                 match ConversationSummary::new(
                     session.session_id.clone(),
                     relative_path.clone(),
-                    session.latest_timestamp(),
-                    session.message_count(),
+                    session.latest_timestamp.clone(),
+                    session.message_count,
                     SyncOperation::Conflict,
                 ) {
                     Ok(summary) => affected_conversations.push(summary),
@@ -448,59 +559,35 @@ pub fn pull_history(
             continue;
         }
 
-        let (dest_path, relative_path_for_tracking) = if filter.use_project_name_only {
-            // Extract project name and session filename from remote path
-            let remote_relative = Path::new(&remote_session.file_path)
-                .strip_prefix(&remote_projects_dir)
-                .ok()
-                .unwrap_or_else(|| Path::new(&remote_session.file_path));
-
-            // Get the project name from the remote path structure
-            let project_name = remote_relative
-                .components()
-                .next()
-                .and_then(|c| c.as_os_str().to_str())
-                .unwrap_or("unknown");
-
-            // Find matching local Claude project directory
-            if let Some(local_project_dir) = find_local_project_by_name(&claude_dir, project_name) {
-                // Get just the session filename
-                if let Some(filename) = remote_relative.file_name() {
-                    let dest = local_project_dir.join(filename);
-                    // Compute relative path for tracking from the destination
-                    let tracking_path = dest
-                        .strip_prefix(&claude_dir)
-                        .map(|p| p.to_path_buf())
-                        .unwrap_or_else(|_| remote_relative.to_path_buf());
-                    (dest, tracking_path)
-                } else {
-                    log::warn!(
-                        "Could not extract filename from remote path: {:?}",
-                        remote_relative
-                    );
-                    skipped_no_local_match += 1;
-                    continue; // Skip this session
-                }
-            } else {
-                log::warn!(
-                    "No matching local project found for '{}'. \
-                     Open the project with Claude Code locally first, or disable use_project_name_only.",
-                    project_name
-                );
-                skipped_no_local_match += 1;
-                continue; // Skip this session - no local match
-            }
-        } else {
-            let relative_path = Path::new(&remote_session.file_path)
-                .strip_prefix(&remote_projects_dir)
-                .ok()
-                .unwrap_or_else(|| Path::new(&remote_session.file_path));
-            (claude_dir.join(relative_path), relative_path.to_path_buf())
+        let remote_relative = Path::new(&remote_session.file_path)
+            .strip_prefix(&remote_projects_dir)
+            .unwrap_or_else(|_| Path::new(&remote_session.file_path));
+        let Some(relative_path_for_tracking) =
+            local_relative_path(remote_relative, &claude_dir, &filter)
+        else {
+            log::warn!(
+                "No matching local project found for '{}'. \
+                 Open the project with Claude Code locally first, or disable use_project_name_only.",
+                remote_relative
+                    .components()
+                    .next()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .unwrap_or_default()
+            );
+            skipped_no_local_match += 1;
+            continue; // Skip this session - no local match
         };
+        let dest_path = claude_dir.join(&relative_path_for_tracking);
 
         // Determine operation type based on local state
         let operation = if let Some(local) = local_map.get(&remote_session.session_id) {
-            if local.content_hash() == remote_session.content_hash() {
+            if local.content_hash == remote_session.content_hash
+                || ledger.is_copy_of(
+                    &remote_session.session_id,
+                    &local.content_hash,
+                    &remote_session.content_hash,
+                )
+            {
                 unchanged_count += 1;
                 SyncOperation::Unchanged
             } else {
@@ -514,7 +601,12 @@ pub fn pull_history(
 
         // Copy file if it's not unchanged
         if operation != SyncOperation::Unchanged {
-            remote_session.write_to_file(&dest_path)?;
+            let local_hash = gate.copy(remote_session, &dest_path, localize)?;
+            ledger.record_pulled(
+                &remote_session.session_id,
+                &local_hash,
+                &remote_session.content_hash,
+            );
             merged_count += 1;
         }
 
@@ -523,8 +615,8 @@ pub fn pull_history(
         match ConversationSummary::new(
             remote_session.session_id.clone(),
             relative_path_str.clone(),
-            remote_session.latest_timestamp(),
-            remote_session.message_count(),
+            remote_session.latest_timestamp.clone(),
+            remote_session.message_count,
             operation,
         ) {
             Ok(summary) => affected_conversations.push(summary),
@@ -533,6 +625,9 @@ pub fn pull_history(
     }
 
     println!("  {} Merged {} sessions", "✓".green(), merged_count);
+    if let Err(e) = ledger.save() {
+        log::warn!("Failed to save copy ledger: {e:#}");
+    }
 
     // ============================================================================
     // APPLY ARTIFACT PULL PLAN (remote wins; snapshot already covers changes)
@@ -684,3 +779,29 @@ pub fn pull_history(
 
     Ok(())
 }
+
+/// Where a session at `remote_relative` in the repository's projects
+/// directory is written locally, relative to `claude_dir`. In name-only mode
+/// the repository names projects by name only, which stands for the one local
+/// project of that name; None when there is no single one.
+fn local_relative_path(
+    remote_relative: &Path,
+    claude_dir: &Path,
+    filter: &FilterConfig,
+) -> Option<PathBuf> {
+    if !filter.use_project_name_only {
+        return Some(remote_relative.to_path_buf());
+    }
+    let project_name = remote_relative.components().next()?.as_os_str().to_str()?;
+    let local_project_dir = find_local_project_by_name(claude_dir, project_name)?;
+    // Keep the path below the project directory, so subagent transcripts
+    // stay next to (or under) their parent session
+    let within_project: PathBuf = remote_relative.components().skip(1).collect();
+    within_project.file_name()?;
+    Some(
+        local_project_dir
+            .strip_prefix(claude_dir)
+            .ok()?
+            .join(within_project),
+    )
+}
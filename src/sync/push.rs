@@ -1,21 +1,31 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use inquire::Confirm;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::encryption::view::RepoView;
 use crate::filter::FilterConfig;
 use crate::history::{
     ConversationSummary, OperationHistory, OperationRecord, OperationType, SyncOperation,
 };
+use crate::home_paths::HomePaths;
 use crate::interactive_conflict;
+use crate::lite::LiteStats;
+use crate::parser::{EntryStream, SessionInfo, SessionSummary};
+use crate::redact::{Findings, RedactionMode, Redactor};
 use crate::scm;
 
+use super::copies::CopyLedger;
 use super::discovery::{
-    claude_home_dir, claude_projects_dir, discover_sessions, find_colliding_projects,
+    claude_home_dir, claude_projects_dir, find_colliding_projects, partially_parsed_count,
+    quarantine_failures, report_locked_projects, report_skipped_lines,
 };
+use super::index::DiscoveryIndex;
+use super::parallel::{parallel_map, resolve_threads};
 use super::state::SyncState;
+use super::units::{report_orphans, Refusal, SessionGate};
 use super::MAX_CONVERSATIONS_TO_DISPLAY;
 
 /// One session's planned copy into the sync repository.
@@ -38,6 +48,38 @@ pub struct PushPlan {
     pub modified: usize,
     pub unchanged: usize,
     pub skipped_no_cwd: usize,
+    /// Sessions left out because they match a deny pattern
+    pub skipped_denied: usize,
+    /// Sessions left out because they match an exclude rule
+    pub excluded_by_rule: usize,
+    /// The check sessions are copied through
+    pub(crate) gate: SessionGate,
+}
+
+impl PushPlan {
+    /// Leave out sessions bound for the given project directories, returning
+    /// how many were dropped
+    pub fn drop_projects(&mut self, projects: &BTreeSet<String>) -> usize {
+        let before = self.entries.len();
+        let (dropped, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|entry| {
+                entry
+                    .relative_path
+                    .components()
+                    .next()
+                    .is_some_and(|c| projects.contains(&*c.as_os_str().to_string_lossy()))
+            });
+        for entry in &dropped {
+            match entry.operation {
+                SyncOperation::Added => self.added -= 1,
+                SyncOperation::Modified => self.modified -= 1,
+                _ => self.unchanged -= 1,
+            }
+        }
+        self.entries = kept;
+        before - self.entries.len()
+    }
 }
 
 /// Outcome counts of a completed push, returned to callers and tests.
@@ -48,6 +90,18 @@ pub struct PushReport {
     pub modified: usize,
     pub unchanged: usize,
     pub skipped_no_cwd: usize,
+    /// Local sessions left out because they match a deny pattern.
+    pub skipped_denied: usize,
+    /// Local sessions left out because they match an exclude rule.
+    pub excluded_by_rule: usize,
+    /// Local sessions pushed with malformed lines skipped (lenient parsing).
+    pub partially_parsed: usize,
+    /// Sessions written with secrets redacted.
+    pub redacted_sessions: usize,
+    /// Secrets redacted across those sessions.
+    pub redacted_secrets: usize,
+    /// What lite push left out of the sessions written.
+    pub trimmed: LiteStats,
     /// Per-category artifact outcomes (empty when no category is enabled).
     pub artifacts: crate::artifacts::engine::ArtifactReport,
 }
@@ -56,23 +110,25 @@ pub struct PushReport {
 /// respecting `use_project_name_only`. Returns None when the session lacks the
 /// `cwd` needed for project-name mapping.
 fn compute_relative_path(
-    session: &crate::parser::ConversationSession,
+    session: &impl SessionInfo,
     claude_dir: &Path,
     filter: &FilterConfig,
 ) -> Option<PathBuf> {
+    let file_path = Path::new(session.file_path());
     if filter.use_project_name_only {
-        let full_relative = Path::new(&session.file_path)
-            .strip_prefix(claude_dir)
-            .unwrap_or(Path::new(&session.file_path));
+        let full_relative = file_path.strip_prefix(claude_dir).unwrap_or(file_path);
 
-        let filename = full_relative.file_name()?;
+        // Replace only the encoded project directory: subagent transcripts
+        // nested under their parent session must stay there
+        let within_project: PathBuf = full_relative.components().skip(1).collect();
+        within_project.file_name()?;
         let project_name = session.project_name()?;
-        Some(PathBuf::from(project_name).join(filename))
+        Some(PathBuf::from(project_name).join(within_project))
     } else {
         Some(
-            Path::new(&session.file_path)
+            file_path
                 .strip_prefix(claude_dir)
-                .unwrap_or(Path::new(&session.file_path))
+                .unwrap_or(file_path)
                 .to_path_buf(),
         )
     }
@@ -82,36 +138,79 @@ fn compute_relative_path(
 /// contents without writing anything. Sessions are keyed by their identity
 /// (filename stem), so sibling files sharing an interior sessionId — subagent
 /// sidechains, resumed sessions — classify independently (issue #68).
-pub fn plan_push(
-    sessions: &[crate::parser::ConversationSession],
+///
+/// The sync repository side is read as streamed summaries, so planning never
+/// holds the repo's transcripts in memory.
+pub fn plan_push<S: SessionInfo + Sync>(
+    sessions: &[S],
+    claude_dir: &Path,
+    projects_dir: &Path,
+    filter: &FilterConfig,
+) -> Result<PushPlan> {
+    plan_push_with_index(
+        sessions,
+        claude_dir,
+        projects_dir,
+        filter,
+        &mut DiscoveryIndex::in_memory(),
+        &CopyLedger::in_memory(),
+    )
+}
+
+/// [`plan_push`], reading the sync repository side through a discovery index
+/// so unchanged repo files are not re-parsed. A repo copy that an earlier
+/// push rewrote from the same local content (see [`CopyLedger`]) counts as
+/// unchanged, unless it was made under other settings.
+pub fn plan_push_with_index<S: SessionInfo + Sync>(
+    sessions: &[S],
     claude_dir: &Path,
     projects_dir: &Path,
     filter: &FilterConfig,
+    index: &mut DiscoveryIndex,
+    ledger: &CopyLedger,
 ) -> Result<PushPlan> {
     let existing_sessions = if projects_dir.exists() {
-        discover_sessions(projects_dir, filter)?
+        index.discover(projects_dir, filter)?.0
     } else {
         Vec::new()
     };
     let existing_map: HashMap<_, _> = existing_sessions
         .iter()
-        .map(|s| (s.session_id.clone(), s))
+        .map(|s| (s.session_id.as_str(), s))
         .collect();
 
+    let gate = SessionGate::new(sessions, filter, index, |s| {
+        let path = Path::new(s.file_path());
+        path.strip_prefix(claude_dir).unwrap_or(path).to_path_buf()
+    })?;
     let mut plan = PushPlan::default();
 
     for (session_index, session) in sessions.iter().enumerate() {
+        match gate.refusal(session) {
+            Some(Refusal::Denied) => {
+                plan.skipped_denied += 1;
+                log::debug!("Skipping session {} (denied)", session.session_id());
+                continue;
+            }
+            Some(Refusal::Excluded) => {
+                plan.excluded_by_rule += 1;
+                log::debug!("Skipping session {} (exclude rule)", session.session_id());
+                continue;
+            }
+            None => {}
+        }
         let relative_path = match compute_relative_path(session, claude_dir, filter) {
             Some(path) => path,
             None => {
                 plan.skipped_no_cwd += 1;
-                log::debug!("Skipping session {} (no cwd)", session.session_id);
+                log::debug!("Skipping session {} (no cwd)", session.session_id());
                 continue;
             }
         };
 
-        let operation = if let Some(existing) = existing_map.get(&session.session_id) {
-            if existing.content_hash() == session.content_hash() {
+        let operation = if let Some(existing) = existing_map.get(session.session_id()) {
+            let local_hash = session.content_hash();
+            if ledger.is_current_copy(session.session_id(), &local_hash, &existing.content_hash) {
                 plan.unchanged += 1;
                 SyncOperation::Unchanged
             } else {
@@ -130,6 +229,7 @@ pub fn plan_push(
         });
     }
 
+    plan.gate = gate;
     Ok(plan)
 }
 
@@ -151,6 +251,7 @@ pub fn push_history(
     let state = SyncState::load()?;
     let repo = scm::open(&state.sync_repo_path)?;
     let mut filter = FilterConfig::load()?;
+    filter.apply_repo_policy(&state.sync_repo_path)?;
 
     // Override exclude_attachments if specified in command
     if exclude_attachments {
@@ -168,6 +269,10 @@ pub fn push_history(
 
     let claude_dir = claude_projects_dir()?;
 
+    // Sessions and artifacts are written as plaintext: into the repository,
+    // or for an encrypted one into its view, sealed into the repo below
+    let mut view = RepoView::open(&state.sync_repo_path, &filter)?;
+
     // Get the current branch name for operation record
     let branch_name = branch
         .map(|s| s.to_string())
@@ -176,8 +281,10 @@ pub fn push_history(
 
     // Discover all sessions
     println!("  {} conversation sessions...", "Discovering".cyan());
-    let sessions = discover_sessions(&claude_dir, &filter)?;
+    let mut index = DiscoveryIndex::load()?;
+    let (sessions, parse_failures) = index.discover(&claude_dir, &filter)?;
     println!("  {} {} sessions", "Found".green(), sessions.len());
+    quarantine_failures(&parse_failures, verbosity);
 
     // Check for project name collisions when using project-name-only mode
     if filter.use_project_name_only {
@@ -215,34 +322,117 @@ pub fn push_history(
     // ============================================================================
     // COPY SESSIONS AND TRACK CHANGES
     // ============================================================================
-    let projects_dir = state.sync_repo_path.join(&filter.sync_subdirectory);
+    let projects_dir = view.root().join(&filter.sync_subdirectory);
     fs::create_dir_all(&projects_dir)?;
 
     // Classify every session against the sync repo, then apply the plan
     println!("  {} sessions to sync repository...", "Copying".cyan());
-    let plan = plan_push(&sessions, &claude_dir, &projects_dir, &filter)?;
+    let mut ledger = CopyLedger::load()?.with_settings(&filter);
+    let mut plan = plan_push_with_index(
+        &sessions,
+        &claude_dir,
+        &projects_dir,
+        &filter,
+        &mut index,
+        &ledger,
+    )?;
+    // Projects encrypted to others cannot be read here, so their repository
+    // copies can be neither compared nor merged: leave them alone
+    let locked_sessions = plan.drop_projects(view.locked_projects());
+    report_locked_projects(view.locked_projects());
+    index.save_or_warn();
+    crate::search::index::update_after_sync(
+        &[(claude_dir.as_path(), &sessions[..])],
+        filter.discovery_threads,
+    );
     let added_count = plan.added;
     let modified_count = plan.modified;
     let unchanged_count = plan.unchanged;
     let skipped_no_cwd = plan.skipped_no_cwd;
+    let skipped_denied = plan.skipped_denied;
+    let excluded_by_rule = plan.excluded_by_rule;
+
+    // Secrets must not reach the repository: strict mode refuses the whole
+    // push before anything is written, redact mode rewrites them on copy
+    let redactor = match filter.redaction {
+        RedactionMode::Off => None,
+        RedactionMode::Redact | RedactionMode::Strict => Some(
+            Redactor::new(&filter.redaction_rules)?.with_high_entropy(filter.redact_high_entropy),
+        ),
+    };
+    if let (RedactionMode::Strict, Some(redactor)) = (filter.redaction, &redactor) {
+        let found = scan_for_secrets(&sessions, &plan, redactor, filter.discovery_threads)?;
+        if !found.is_empty() {
+            println!();
+            println!("{}", "Secrets found in sessions to push:".red().bold());
+            for (session, findings) in &found {
+                println!(
+                    "  {} {}",
+                    session.session_id.cyan(),
+                    describe_findings(findings)
+                );
+            }
+            println!();
+            anyhow::bail!(
+                "Push blocked: {} session(s) contain secrets (redaction = \"strict\"). \
+                 Remove them from the transcripts, or set redaction = \"redact\" to push \
+                 redacted copies.",
+                found.len()
+            );
+        }
+    }
 
     // Track pushed conversations for operation record
     let mut pushed_conversations: Vec<ConversationSummary> = Vec::new();
+    let mut written: Vec<(&str, String)> = Vec::new();
+    let mut redacted: Vec<(&str, Findings)> = Vec::new();
+    let mut lite_stats = LiteStats::default();
+    let home_paths = HomePaths::from_config(&filter);
 
     for entry in &plan.entries {
         let session = &sessions[entry.session_index];
         let dest_path = projects_dir.join(&entry.relative_path);
 
-        // Write the session file
-        session.write_to_file(&dest_path)?;
+        // Only write sessions whose content changed. An unchanged session can
+        // still differ byte-for-byte from the repo copy (written by another
+        // version, key order, number formatting); leaving the repo bytes alone
+        // is what keeps a hashing or serialization change from rewriting every
+        // file in the repository.
+        if entry.operation != SyncOperation::Unchanged {
+            let mut findings = Findings::new();
+            let mut trimmed = LiteStats::default();
+            let repo_hash = plan.gate.copy(session, &dest_path, |e| {
+                if !filter.lite_push.apply(e, &mut trimmed) {
+                    return false;
+                }
+                if let Some(home_paths) = &home_paths {
+                    home_paths.collapse(e);
+                }
+                if let (RedactionMode::Redact, Some(redactor)) = (filter.redaction, &redactor) {
+                    redactor.redact_entry(e, &mut findings);
+                }
+                true
+            })?;
+            lite_stats.add(&trimmed);
+            ledger.record(
+                &session.session_id,
+                &session.content_hash,
+                &repo_hash,
+                findings.clone(),
+            );
+            if !findings.is_empty() {
+                redacted.push((&session.session_id, findings));
+            }
+            written.push((&session.session_id, repo_hash));
+        }
 
         // Track this session in pushed conversations
         let relative_path_str = entry.relative_path.to_string_lossy().to_string();
         match ConversationSummary::new(
             session.session_id.clone(),
             relative_path_str.clone(),
-            session.latest_timestamp(),
-            session.message_count(),
+            session.latest_timestamp.clone(),
+            session.message_count,
             entry.operation,
         ) {
             Ok(summary) => pushed_conversations.push(summary),
@@ -250,14 +440,42 @@ pub fn push_history(
         }
     }
 
+    if let Err(e) = ledger.save() {
+        log::warn!("Failed to save copy ledger: {e:#}");
+    }
+    let redacted_secrets: usize = redacted
+        .iter()
+        .flat_map(|(_, findings)| findings.values())
+        .sum();
+
+    // Claim the sessions this push wrote, for per-machine statistics
+    crate::sync::machines::MachineManifest::record(
+        &state.sync_repo_path,
+        &crate::config::ConfigManager::machine_name(),
+        written.iter().map(|(id, hash)| (*id, hash.as_str())),
+    )?;
+
     // ============================================================================
     // COPY ARTIFACTS (settings, skills, agents, ...) AND WRITE IGNORE GUARD
     // ============================================================================
-    let artifact_report = crate::artifacts::engine::push_artifacts(
-        &claude_home_dir()?,
-        &state.sync_repo_path,
-        &filter,
-    )?;
+    let artifact_report =
+        crate::artifacts::engine::push_artifacts(&claude_home_dir()?, view.root(), &filter)?;
+    let sealed = view.seal()?;
+    // Who can read what changed is shown whatever the verbosity
+    for (project, keys) in view.rekeyed_lists() {
+        let target = match project {
+            Some(name) => format!("project {name}"),
+            None => "the repository".to_string(),
+        };
+        println!(
+            "  {} Re-encrypted {} to its new recipients:",
+            "!".yellow(),
+            target.cyan()
+        );
+        for key in keys {
+            println!("      {key}");
+        }
+    }
     crate::artifacts::engine::ensure_ignore_files(&state.sync_repo_path, filter.backend()?)?;
 
     // ============================================================================
@@ -271,11 +489,64 @@ pub fn push_history(
         println!("  {} Unchanged: {}", "•".dimmed(), unchanged_count);
         let total_with_cwd = sessions.len().saturating_sub(skipped_no_cwd);
         println!("  {} Skipped (no cwd): {}", "•".dimmed(), skipped_no_cwd);
+        if skipped_denied > 0 {
+            println!(
+                "  {} Skipped (deny patterns): {}",
+                "•".dimmed(),
+                skipped_denied
+            );
+        }
+        if excluded_by_rule > 0 {
+            println!("  {} Excluded by rule: {}", "•".dimmed(), excluded_by_rule);
+        }
+        if locked_sessions > 0 {
+            println!(
+                "  {} Skipped (locked projects): {}",
+                "•".dimmed(),
+                locked_sessions
+            );
+        }
         println!(
             "  {} Sessions (with project context): {}",
             "•".cyan(),
             total_with_cwd
         );
+        report_skipped_lines(
+            "local",
+            &sessions,
+            &claude_dir,
+            verbosity == VerbosityLevel::Verbose,
+        );
+        report_orphans("local", &sessions, &claude_dir);
+        if !redacted.is_empty() {
+            println!(
+                "  {} Redacted: {} secret(s) in {} session(s)",
+                "•".yellow(),
+                redacted_secrets,
+                redacted.len()
+            );
+            if verbosity == VerbosityLevel::Verbose {
+                for (session_id, findings) in &redacted {
+                    println!("      {} {}", session_id, describe_findings(findings));
+                }
+            }
+        }
+        if !lite_stats.is_empty() {
+            println!(
+                "  {} Lite: {} tool result(s) trimmed ({} KiB), {} snapshot(s) dropped",
+                "•".cyan(),
+                lite_stats.trimmed_results,
+                lite_stats.trimmed_bytes / 1024,
+                lite_stats.dropped_snapshots
+            );
+        }
+        if view.is_encrypted() {
+            println!(
+                "  {} Encrypted: {} file(s) written",
+                "•".cyan(),
+                sealed.written
+            );
+        }
         if !artifact_report.counts.is_empty() {
             println!(
                 "  {} Artifacts: {} added, {} modified, {} unchanged",
@@ -498,6 +769,53 @@ pub fn push_history(
         modified: modified_count,
         unchanged: unchanged_count,
         skipped_no_cwd,
+        skipped_denied,
+        excluded_by_rule,
+        partially_parsed: partially_parsed_count(&sessions),
+        redacted_sessions: redacted.len(),
+        redacted_secrets,
+        trimmed: lite_stats,
         artifacts: artifact_report,
     })
 }
+
+/// Sessions the plan covers that contain secrets, with what was found. Copies
+/// already in the repository are scanned too: they may predate the current
+/// rules.
+fn scan_for_secrets<'a>(
+    sessions: &'a [SessionSummary],
+    plan: &PushPlan,
+    redactor: &Redactor,
+    threads: usize,
+) -> Result<Vec<(&'a SessionSummary, Findings)>> {
+    let covered: Vec<&SessionSummary> = plan
+        .entries
+        .iter()
+        .map(|entry| &sessions[entry.session_index])
+        .collect();
+    let scanned = parallel_map(&covered, resolve_threads(threads), |session| {
+        let mut findings = Findings::new();
+        for entry in EntryStream::open_with_mode(&session.file_path, session.parse_mode)? {
+            redactor.redact_entry(&mut entry?, &mut findings);
+        }
+        Ok::<_, anyhow::Error>(findings)
+    });
+    let mut found = Vec::new();
+    for (session, findings) in covered.into_iter().zip(scanned) {
+        let findings =
+            findings.with_context(|| format!("Failed to scan session {}", session.session_id))?;
+        if !findings.is_empty() {
+            found.push((session, findings));
+        }
+    }
+    Ok(found)
+}
+
+/// `2 aws-access-key, 1 jwt`
+fn describe_findings(findings: &Findings) -> String {
+    findings
+        .iter()
+        .map(|(rule, count)| format!("{count} {rule}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -5,14 +5,17 @@ use std::path::Path;
 use crate::filter::FilterConfig;
 use crate::scm;
 
-use super::discovery::{claude_projects_dir, discover_sessions};
+use super::discovery::{claude_projects_dir, report_skipped_lines};
+use super::index::DiscoveryIndex;
 use super::state::SyncState;
+use super::units::report_orphans;
 
 /// Show sync status
 pub fn show_status(show_conflicts: bool, show_files: bool) -> Result<()> {
     let state = SyncState::load()?;
     let repo = scm::open(&state.sync_repo_path)?;
-    let filter = FilterConfig::load()?;
+    let mut filter = FilterConfig::load()?;
+    filter.apply_repo_policy(&state.sync_repo_path)?;
     let claude_dir = claude_projects_dir()?;
 
     println!("{}", "=== Claude Code Sync Status ===".bold().cyan());
@@ -52,22 +55,47 @@ pub fn show_status(show_conflicts: bool, show_files: bool) -> Result<()> {
     // Session counts
     println!();
     println!("{}", "Sessions:".bold());
-    let local_sessions = discover_sessions(&claude_dir, &filter)?;
+    let mut index = DiscoveryIndex::load()?;
+    let (local_sessions, _) = index.discover(&claude_dir, &filter)?;
     println!("  Local: {}", local_sessions.len().to_string().cyan());
 
-    let remote_projects_dir = state.sync_repo_path.join(&filter.sync_subdirectory);
-    if remote_projects_dir.exists() {
-        let remote_sessions = discover_sessions(&remote_projects_dir, &filter)?;
+    // An encrypted repository is read through its plaintext view
+    let repo_root = crate::encryption::view::RepoView::plaintext_root(&state.sync_repo_path)?;
+    let remote_projects_dir = repo_root.join(&filter.sync_subdirectory);
+    let remote_sessions = if remote_projects_dir.exists() {
+        let (remote_sessions, _) = index.discover(&remote_projects_dir, &filter)?;
         println!("  Sync repo: {}", remote_sessions.len().to_string().cyan());
+        remote_sessions
+    } else {
+        Vec::new()
+    };
+    index.save_or_warn();
+    let quarantined = crate::quarantine::Quarantine::open()?.list()?.len();
+    if quarantined > 0 {
+        println!(
+            "  Quarantined: {} {}",
+            quarantined.to_string().yellow(),
+            "(see: claude-code-sync quarantine list)".dimmed()
+        );
+    } else {
+        println!("  Quarantined: {}", "0".green());
     }
+    report_skipped_lines("local", &local_sessions, &claude_dir, show_files);
+    report_skipped_lines(
+        "sync repo",
+        &remote_sessions,
+        &remote_projects_dir,
+        show_files,
+    );
+    report_orphans("local", &local_sessions, &claude_dir);
+    report_orphans("sync repo", &remote_sessions, &remote_projects_dir);
 
     // Artifact categories: enabled state and local-vs-repo drift
     println!();
     println!("{}", "Artifacts:".bold());
     if filter.sync_artifacts.any_enabled() || !filter.exclude_attachments {
         let claude_home = super::discovery::claude_home_dir()?;
-        let plan =
-            crate::artifacts::engine::plan_pull(&claude_home, &state.sync_repo_path, &filter)?;
+        let plan = crate::artifacts::engine::plan_pull(&claude_home, &repo_root, &filter)?;
         for desc in crate::artifacts::registry::REGISTRY {
             if !crate::artifacts::engine::is_category_enabled(desc, &filter) {
                 println!("  {}: {}", desc.name, "disabled".dimmed());
@@ -109,7 +137,7 @@ pub fn show_status(show_conflicts: bool, show_files: bool) -> Result<()> {
             println!(
                 "  {} ({} messages)",
                 relative.display(),
-                session.message_count()
+                session.message_count
             );
         }
         if local_sessions.len() > 20 {
@@ -0,0 +1,368 @@
+//! Sync units: a session together with its subagent sidechains.
+//!
+//! Subagent transcripts (`agent-<hash>.jsonl`) have their own identity, but
+//! they only make sense next to the session that spawned them. Claude Code
+//! stores them either beside the parent (`<project>/agent-<hash>.jsonl`) or
+//! under it (`<project>/<parent>/subagents/agent-<hash>.jsonl`), and every
+//! entry carries the parent's `sessionId`. Discovery uses that link so filters
+//! include or exclude a whole unit at once, and pull snapshots and resolves
+//! conflicts per unit.
+//!
+//! A sidechain whose parent transcript is nowhere to be found is an orphan. It
+//! is still synced on its own merits, but reported so the user can clean up.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::filter::FilterConfig;
+use crate::parser::{is_sidechain_id, ConversationEntry, SessionInfo, SessionSummary};
+use crate::session_rules::SessionRules;
+
+use super::index::DiscoveryIndex;
+
+/// Maximum number of orphaned sidechains listed individually
+const MAX_ORPHANS_TO_DISPLAY: usize = 10;
+
+/// True if `path` names a subagent sidechain transcript
+pub(crate) fn is_sidechain_path(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(is_sidechain_id)
+}
+
+/// The session id of the unit a transcript belongs to: its parent's for a
+/// sidechain, its own otherwise.
+pub fn unit_id<S: SessionInfo + ?Sized>(session: &S) -> &str {
+    session
+        .parent_session_id()
+        .unwrap_or_else(|| session.session_id())
+}
+
+/// Where the parent transcript of a sidechain at `path` would live
+fn parent_file_candidates(path: &Path, parent_id: &str) -> Vec<PathBuf> {
+    let file_name = format!("{parent_id}.jsonl");
+    let mut candidates = Vec::new();
+    if let Some(dir) = path.parent() {
+        candidates.push(dir.join(&file_name));
+        // <project>/<parent>/subagents/agent-x.jsonl
+        if dir.file_name().and_then(|n| n.to_str()) == Some("subagents") {
+            if let Some(project_dir) = dir.parent().and_then(|d| d.parent()) {
+                candidates.push(project_dir.join(&file_name));
+            }
+        }
+    }
+    candidates
+}
+
+/// Apply the filter per sync unit rather than per file.
+///
+/// `sessions` must come from a walk that filtered regular sessions only (see
+/// [`session_files`](super::discovery::session_files)). A sidechain is kept if
+/// its parent was kept and dropped if its parent exists but was filtered out
+/// (or failed to parse), whatever the sidechain's own size, age or path. An
+/// orphaned sidechain is judged by the filter like any other file.
+pub(crate) fn retain_units<S: SessionInfo>(sessions: &mut Vec<S>, filter: &FilterConfig) {
+    let included: HashSet<String> = sessions
+        .iter()
+        .filter(|s| s.parent_session_id().is_none())
+        .map(|s| s.session_id().to_string())
+        .collect();
+
+    sessions.retain(|session| {
+        let path = Path::new(session.file_path());
+        match session.parent_session_id() {
+            Some(parent) if included.contains(parent) => true,
+            Some(parent) => {
+                let parent_exists = parent_file_candidates(path, parent)
+                    .iter()
+                    .any(|candidate| candidate.exists());
+                !parent_exists && filter.should_include(path)
+            }
+            // Named like a sidechain but not linked to any parent
+            None => !is_sidechain_path(path) || filter.should_include(path),
+        }
+    });
+}
+
+/// Why [`SessionGate`] refuses to copy a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Refusal {
+    /// A transcript of its unit matches a deny pattern
+    Denied,
+    /// A transcript of its unit matches an exclusion rule
+    Excluded,
+}
+
+/// The one check for copying sessions between `~/.claude` and the sync
+/// repository, in either direction: a unit with a transcript a deny pattern
+/// covers, or one an exclusion rule matches, is never copied, so neither
+/// half of it syncs. Push and pull build it from the sessions they copy, and
+/// copy through [`SessionGate::copy`].
+#[derive(Debug, Default)]
+pub(crate) struct SessionGate {
+    denied: HashSet<String>,
+    excluded: HashSet<String>,
+}
+
+impl SessionGate {
+    /// Check `sessions` against `filter`.
+    ///
+    /// # Arguments
+    /// * `sessions` - Discovered summaries, local or from the sync repository
+    /// * `filter` - Configuration holding the deny patterns and exclusion rules
+    /// * `index` - Discovery index caching exclusion rule outcomes
+    /// * `local_path` - Where a transcript lives under `~/.claude/projects`
+    ///   on this machine, which deny patterns are written against
+    pub(crate) fn new<S: SessionInfo + Sync>(
+        sessions: &[S],
+        filter: &FilterConfig,
+        index: &mut DiscoveryIndex,
+        local_path: impl Fn(&S) -> PathBuf,
+    ) -> Result<Self> {
+        let denied = if filter.deny_patterns.is_empty() {
+            HashSet::new()
+        } else {
+            sessions
+                .iter()
+                .filter(|s| filter.is_session_denied(&local_path(s)))
+                .map(|s| unit_id(s).to_string())
+                .collect()
+        };
+        let excluded = SessionRules::new(&filter.exclude_rules)?
+            .excluded_units(
+                sessions,
+                filter.parse_mode(),
+                filter.discovery_threads,
+                index,
+            )
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        Ok(SessionGate { denied, excluded })
+    }
+
+    /// Why `session` must not be copied, if it must not
+    pub(crate) fn refusal<S: SessionInfo + ?Sized>(&self, session: &S) -> Option<Refusal> {
+        let unit = unit_id(session);
+        if self.denied.contains(unit) {
+            Some(Refusal::Denied)
+        } else if self.excluded.contains(unit) {
+            Some(Refusal::Excluded)
+        } else {
+            None
+        }
+    }
+
+    /// [`SessionSummary::copy_to_with`], for a session the gate lets through
+    pub(crate) fn copy<F>(
+        &self,
+        session: &SessionSummary,
+        dest: &Path,
+        transform: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&mut ConversationEntry) -> bool,
+    {
+        if let Some(refusal) = self.refusal(session) {
+            let rule = match refusal {
+                Refusal::Denied => "a deny pattern",
+                Refusal::Excluded => "an exclusion rule",
+            };
+            bail!(
+                "Refusing to copy session {}: it matches {rule}",
+                session.session_id
+            );
+        }
+        session.copy_to_with(dest, transform)
+    }
+}
+
+/// Sidechains among `sessions` whose parent session is not among them
+pub fn orphaned_sidechains<S: SessionInfo>(sessions: &[S]) -> Vec<&S> {
+    let ids: HashSet<&str> = sessions.iter().map(|s| s.session_id()).collect();
+    sessions
+        .iter()
+        .filter(|s| s.parent_session_id().is_some_and(|p| !ids.contains(p)))
+        .collect()
+}
+
+/// Every session belonging to one of the given units, parents and sidechains
+pub fn unit_members<'a, S: SessionInfo>(
+    sessions: &'a [S],
+    unit_ids: &'a HashSet<&str>,
+) -> impl Iterator<Item = &'a S> + 'a {
+    sessions
+        .iter()
+        .filter(move |s| unit_ids.contains(unit_id(*s)))
+}
+
+/// Report sidechains whose parent transcript is missing.
+///
+/// Prints nothing when every sidechain has its parent.
+///
+/// # Arguments
+/// * `label` - Which side was discovered (e.g. "local", "sync repo")
+/// * `sessions` - Discovered summaries
+/// * `base_path` - Directory the sessions were discovered under, for display
+pub(crate) fn report_orphans<S: SessionInfo>(label: &str, sessions: &[S], base_path: &Path) {
+    let orphans = orphaned_sidechains(sessions);
+    if orphans.is_empty() {
+        return;
+    }
+
+    println!(
+        "  {} Orphaned subagent transcripts ({}): {} with no parent session",
+        "!".yellow(),
+        label,
+        orphans.len()
+    );
+    for orphan in orphans.iter().take(MAX_ORPHANS_TO_DISPLAY) {
+        let path = Path::new(orphan.file_path());
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        println!(
+            "    - {} (parent {})",
+            relative.display(),
+            orphan.parent_session_id().unwrap_or("unknown")
+        );
+    }
+    if orphans.len() > MAX_ORPHANS_TO_DISPLAY {
+        println!(
+            "    ... and {} more",
+            orphans.len() - MAX_ORPHANS_TO_DISPLAY
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SessionSummary;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, session_id: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            path,
+            format!("{{\"type\":\"user\",\"uuid\":\"1\",\"sessionId\":\"{session_id}\"}}\n"),
+        )
+        .unwrap();
+    }
+
+    fn summaries(paths: &[PathBuf]) -> Vec<SessionSummary> {
+        paths
+            .iter()
+            .map(|p| SessionSummary::from_file(p).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sidechains_follow_their_parent() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("-p");
+        let kept = project.join("keep.jsonl");
+        let nested = project.join("keep/subagents/agent-1.jsonl");
+        let flat = project.join("agent-2.jsonl");
+        let excluded_child = project.join("drop/subagents/agent-3.jsonl");
+        write(&kept, "keep");
+        write(&nested, "keep");
+        write(&flat, "keep");
+        write(&project.join("drop.jsonl"), "drop");
+        write(&excluded_child, "drop");
+
+        // The walk already dropped drop.jsonl; its sidechain must go too, and
+        // the exclude pattern matching "agent-2" must not split keep's unit.
+        let filter = FilterConfig {
+            exclude_patterns: vec!["*agent-2*".to_string()],
+            ..Default::default()
+        };
+        let mut sessions = summaries(&[kept, nested, flat, excluded_child]);
+        retain_units(&mut sessions, &filter);
+
+        let ids: Vec<&str> = sessions.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["keep", "agent-1", "agent-2"]);
+        assert!(orphaned_sidechains(&sessions).is_empty());
+    }
+
+    #[test]
+    fn test_orphans_are_kept_and_reported() {
+        let dir = tempdir().unwrap();
+        let orphan = dir.path().join("-p/gone/subagents/agent-9.jsonl");
+        write(&orphan, "gone");
+
+        let mut sessions = summaries(&[orphan]);
+        retain_units(&mut sessions, &FilterConfig::default());
+        assert_eq!(sessions.len(), 1);
+
+        let orphans = orphaned_sidechains(&sessions);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].parent_session_id.as_deref(), Some("gone"));
+
+        // Orphans answer to the filter themselves
+        let filter = FilterConfig {
+            exclude_patterns: vec!["*agent-9*".to_string()],
+            ..Default::default()
+        };
+        retain_units(&mut sessions, &filter);
+        assert!(sessions.is_empty());
+    }
+
+    #[test]
+    fn test_unit_members() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("-p");
+        let paths = [
+            project.join("a.jsonl"),
+            project.join("a/subagents/agent-1.jsonl"),
+            project.join("b.jsonl"),
+        ];
+        write(&paths[0], "a");
+        write(&paths[1], "a");
+        write(&paths[2], "b");
+        let sessions = summaries(&paths);
+
+        let units: HashSet<&str> = ["a"].into_iter().collect();
+        let members: Vec<&str> = unit_members(&sessions, &units)
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(members, vec!["a", "agent-1"]);
+        assert_eq!(unit_id(&sessions[1]), "a");
+    }
+
+    #[test]
+    fn test_gate_refuses_whole_units() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("-home-me-secret");
+        let paths = [
+            project.join("a.jsonl"),
+            project.join("a/subagents/agent-1.jsonl"),
+            dir.path().join("-home-me-open/b.jsonl"),
+        ];
+        write(&paths[0], "a");
+        write(&paths[1], "a");
+        write(&paths[2], "b");
+        let sessions = summaries(&paths);
+        let filter = FilterConfig {
+            deny_patterns: vec!["projects/-home-me-secret/a.jsonl".to_string()],
+            ..Default::default()
+        };
+        let gate = SessionGate::new(&sessions, &filter, &mut DiscoveryIndex::in_memory(), |s| {
+            Path::new(&s.file_path)
+                .strip_prefix(dir.path())
+                .unwrap()
+                .to_path_buf()
+        })
+        .unwrap();
+
+        // Denying the parent denies its sidechain, and neither can be copied
+        assert_eq!(gate.refusal(&sessions[0]), Some(Refusal::Denied));
+        assert_eq!(gate.refusal(&sessions[1]), Some(Refusal::Denied));
+        assert_eq!(gate.refusal(&sessions[2]), None);
+        let dest = dir.path().join("out.jsonl");
+        assert!(gate.copy(&sessions[1], &dest, |_| true).is_err());
+        assert!(!dest.exists());
+        assert!(gate.copy(&sessions[2], &dest, |_| true).is_ok());
+    }
+}
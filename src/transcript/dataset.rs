@@ -0,0 +1,460 @@
+//! Conversations as fine-tuning and evaluation records.
+//!
+//! Each session becomes one JSON object, written as a line of JSONL, in one
+//! of two widely supported chat formats:
+//!
+//! - ShareGPT: `{"id": ..., "conversations": [{"from": ..., "value": ...}]}`
+//!   with `human`, `gpt` and `system` turns, plus `function_call` and
+//!   `observation` turns for tool calls and their results
+//! - OpenAI chat: `{"messages": [{"role": ..., "content": ...}]}` with
+//!   `system`, `user`, `assistant` (carrying `tool_calls`) and `tool` messages
+//!
+//! Only the active branch is followed, like a transcript. Reasoning is kept
+//! inline as `<think>...</think>` before the answer. Entries Claude Code
+//! writes for itself (`system` events, `file-history-snapshot`s, compaction
+//! summaries, meta prompts and synthetic responses) become system turns
+//! unless dropped.
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::message::{ContentBlock, MessageContent, ToolUseBlock};
+use crate::parser::{ConversationEntry, ConversationSession};
+
+use super::tool_result_text;
+use super::tree::{is_message_entry, parent_of, MessageTree};
+
+/// Record format of a dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetFormat {
+    ShareGpt,
+    OpenAi,
+}
+
+impl std::str::FromStr for DatasetFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "sharegpt" => Ok(DatasetFormat::ShareGpt),
+            "openai" => Ok(DatasetFormat::OpenAi),
+            other => anyhow::bail!("Unknown format: '{}'. Use 'sharegpt' or 'openai'.", other),
+        }
+    }
+}
+
+/// What to leave out of a record
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DatasetOptions {
+    /// Model reasoning
+    pub drop_thinking: bool,
+    /// Tool output. The OpenAI format requires every tool call to be
+    /// answered, so there the calls go too.
+    pub drop_tool_results: bool,
+    /// Entries Claude Code writes for itself rather than the conversation
+    pub drop_system: bool,
+}
+
+/// One turn of a conversation, independent of the record format
+#[derive(Debug, Clone, PartialEq)]
+enum Turn {
+    System(String),
+    User(String),
+    Assistant {
+        text: String,
+        calls: Vec<ToolUseBlock>,
+    },
+    ToolResult {
+        call_id: String,
+        content: String,
+    },
+}
+
+/// The record of `session` in `format`, or `None` if its active branch has
+/// no assistant turn left to learn from
+pub fn record(
+    session: &ConversationSession,
+    format: DatasetFormat,
+    options: DatasetOptions,
+) -> Option<Value> {
+    let turns = turns(session, options);
+    if !turns.iter().any(|t| matches!(t, Turn::Assistant { .. })) {
+        return None;
+    }
+    Some(match format {
+        DatasetFormat::ShareGpt => sharegpt(&session.session_id, &turns),
+        DatasetFormat::OpenAi => openai(&turns)?,
+    })
+}
+
+/// The turns of the active branch, in file order. Entries that are not
+/// messages belong to it if their nearest message ancestor does, or if they
+/// hang off no message at all (snapshots, summaries).
+fn turns(session: &ConversationSession, options: DatasetOptions) -> Vec<Turn> {
+    let tree = MessageTree::new(session);
+    let branch: HashSet<*const ConversationEntry> = tree
+        .path(&HashMap::new())
+        .iter()
+        .map(|step| tree.entry(step.node) as *const _)
+        .collect();
+    let by_uuid: HashMap<&str, &ConversationEntry> = session
+        .entries
+        .iter()
+        .filter_map(|e| e.uuid.as_deref().map(|uuid| (uuid, e)))
+        .collect();
+    let on_branch = |entry: &ConversationEntry| -> bool {
+        if is_message_entry(entry) {
+            return branch.contains(&(entry as *const _));
+        }
+        let mut seen = HashSet::new();
+        let mut parent = parent_of(entry);
+        while let Some(uuid) = parent {
+            match by_uuid.get(uuid) {
+                Some(e) if is_message_entry(e) => return branch.contains(&(*e as *const _)),
+                Some(e) if seen.insert(uuid) => parent = parent_of(e),
+                _ => break,
+            }
+        }
+        true
+    };
+
+    let mut turns = Vec::new();
+    for entry in session.entries.iter().filter(|e| on_branch(e)) {
+        for turn in entry_turns(entry, options) {
+            push(&mut turns, turn);
+        }
+    }
+    turns
+}
+
+fn entry_turns(entry: &ConversationEntry, options: DatasetOptions) -> Vec<Turn> {
+    let Some(message) = entry.typed_message().filter(|_| is_message_entry(entry)) else {
+        if options.drop_system {
+            return Vec::new();
+        }
+        return vec![Turn::System(system_text(entry))];
+    };
+
+    let meta = entry.extra.get("isMeta").and_then(Value::as_bool) == Some(true);
+    let synthetic = message.model.as_deref() == Some("<synthetic>");
+    if (meta || synthetic) && options.drop_system {
+        return Vec::new();
+    }
+
+    let blocks = match message.content {
+        Some(MessageContent::Text(text)) => vec![ContentBlock::Text(crate::message::TextBlock {
+            text,
+            extra: Default::default(),
+        })],
+        Some(MessageContent::Blocks(blocks)) => blocks,
+        None => Vec::new(),
+    };
+
+    let assistant = entry.entry_type == "assistant";
+    let mut turns = Vec::new();
+    for block in blocks {
+        let turn = match block {
+            ContentBlock::Text(text) if text.text.trim().is_empty() => continue,
+            ContentBlock::Text(text) if assistant => Turn::Assistant {
+                text: text.text,
+                calls: Vec::new(),
+            },
+            ContentBlock::Text(text) => Turn::User(text.text),
+            ContentBlock::Thinking(thinking)
+                if options.drop_thinking || thinking.thinking.trim().is_empty() =>
+            {
+                continue
+            }
+            ContentBlock::Thinking(thinking) => Turn::Assistant {
+                text: format!("<think>\n{}\n</think>", thinking.thinking.trim()),
+                calls: Vec::new(),
+            },
+            ContentBlock::ToolUse(call) => Turn::Assistant {
+                text: String::new(),
+                calls: vec![call],
+            },
+            ContentBlock::ToolResult(_) if options.drop_tool_results => continue,
+            ContentBlock::ToolResult(result) => Turn::ToolResult {
+                content: tool_result_text(&result),
+                call_id: result.tool_use_id,
+            },
+            // Attachments carry no text to train on
+            ContentBlock::Image(_) | ContentBlock::Other(_) => continue,
+        };
+        turns.push(turn);
+    }
+    turns
+}
+
+/// Text of an entry that is not a message: its content or summary, else the
+/// entry itself
+fn system_text(entry: &ConversationEntry) -> String {
+    ["content", "summary"]
+        .iter()
+        .filter_map(|key| entry.extra.get(key).and_then(Value::as_str))
+        .find(|text| !text.trim().is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}: {}", entry.entry_type, entry.extra))
+}
+
+/// Append `turn`, merging it into the previous one if both are from the
+/// user or both from the assistant (Claude Code writes one entry per block)
+fn push(turns: &mut Vec<Turn>, turn: Turn) {
+    match (turns.last_mut(), turn) {
+        (Some(Turn::User(previous)), Turn::User(text)) => {
+            previous.push_str("\n\n");
+            previous.push_str(&text);
+        }
+        (
+            Some(Turn::Assistant {
+                text: previous,
+                calls: previous_calls,
+            }),
+            Turn::Assistant { text, calls },
+        ) => {
+            if !text.is_empty() {
+                if !previous.is_empty() {
+                    previous.push_str("\n\n");
+                }
+                previous.push_str(&text);
+            }
+            previous_calls.extend(calls);
+        }
+        (_, turn) => turns.push(turn),
+    }
+}
+
+fn sharegpt(session_id: &str, turns: &[Turn]) -> Value {
+    let mut conversations = Vec::new();
+    let mut add = |from: &str, value: String| {
+        conversations.push(json!({"from": from, "value": value}));
+    };
+    for turn in turns {
+        match turn {
+            Turn::System(text) => add("system", text.clone()),
+            Turn::User(text) => add("human", text.clone()),
+            Turn::Assistant { text, calls } => {
+                if !text.is_empty() {
+                    add("gpt", text.clone());
+                }
+                for call in calls {
+                    add(
+                        "function_call",
+                        json!({"name": call.name, "arguments": call.input}).to_string(),
+                    );
+                }
+            }
+            Turn::ToolResult { content, .. } => add("observation", content.clone()),
+        }
+    }
+    json!({"id": session_id, "conversations": conversations})
+}
+
+/// OpenAI chat messages. Calls without a result on the branch (interrupted,
+/// or results dropped) and results without their call are left out, since
+/// the format only allows answered calls.
+fn openai(turns: &[Turn]) -> Option<Value> {
+    let answered: HashSet<&str> = turns
+        .iter()
+        .filter_map(|t| match t {
+            Turn::ToolResult { call_id, .. } => Some(call_id.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut called = HashSet::new();
+
+    let mut messages = Vec::new();
+    for turn in turns {
+        match turn {
+            Turn::System(text) => messages.push(json!({"role": "system", "content": text})),
+            Turn::User(text) => messages.push(json!({"role": "user", "content": text})),
+            Turn::Assistant { text, calls } => {
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .filter(|call| answered.contains(call.id.as_str()))
+                    .map(|call| {
+                        called.insert(call.id.as_str());
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": {"name": call.name, "arguments": call.input.to_string()},
+                        })
+                    })
+                    .collect();
+                let mut message = json!({"role": "assistant"});
+                match (text.is_empty(), calls.is_empty()) {
+                    (true, true) => continue,
+                    (true, false) => message["content"] = Value::Null,
+                    (false, _) => message["content"] = json!(text),
+                }
+                if !calls.is_empty() {
+                    message["tool_calls"] = json!(calls);
+                }
+                messages.push(message);
+            }
+            Turn::ToolResult { call_id, content } if called.contains(call_id.as_str()) => {
+                messages.push(json!({"role": "tool", "tool_call_id": call_id, "content": content}))
+            }
+            Turn::ToolResult { .. } => {}
+        }
+    }
+    messages
+        .iter()
+        .any(|m| m["role"] == "assistant")
+        .then(|| json!({ "messages": messages }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        uuid: &str,
+        parent: Option<&str>,
+        entry_type: &str,
+        content: Value,
+    ) -> ConversationEntry {
+        serde_json::from_value(json!({
+            "type": entry_type,
+            "uuid": uuid,
+            "parentUuid": parent,
+            "message": {"role": entry_type, "content": content},
+        }))
+        .unwrap()
+    }
+
+    fn raw(value: Value) -> ConversationEntry {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A prompt that was edited, a tool call, and Claude Code's own entries
+    fn session() -> ConversationSession {
+        ConversationSession {
+            session_id: "s1".to_string(),
+            entries: vec![
+                raw(json!({"type": "file-history-snapshot", "messageId": "1", "snapshot": {}})),
+                entry("1", None, "user", json!("old prompt")),
+                entry("2", Some("1"), "assistant", json!("old answer")),
+                entry("3", None, "user", json!("list files")),
+                entry(
+                    "4",
+                    Some("3"),
+                    "assistant",
+                    json!([{"type": "thinking", "thinking": "use ls"}]),
+                ),
+                entry(
+                    "5",
+                    Some("4"),
+                    "assistant",
+                    json!([{"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}]),
+                ),
+                raw(
+                    json!({"type": "system", "uuid": "s", "parentUuid": "5", "content": "hook ran"}),
+                ),
+                entry(
+                    "6",
+                    Some("s"),
+                    "user",
+                    json!([{"type": "tool_result", "tool_use_id": "t1", "content": "a.txt"}]),
+                ),
+                entry(
+                    "7",
+                    Some("6"),
+                    "assistant",
+                    json!([{"type": "text", "text": "One file."}]),
+                ),
+            ],
+            file_path: "s1.jsonl".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sharegpt_follows_active_branch() {
+        let record = record(
+            &session(),
+            DatasetFormat::ShareGpt,
+            DatasetOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(record["id"], "s1");
+        let turns: Vec<(String, String)> = record["conversations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                (
+                    t["from"].as_str().unwrap().into(),
+                    t["value"].as_str().unwrap().into(),
+                )
+            })
+            .collect();
+        let from: Vec<&str> = turns.iter().map(|(from, _)| from.as_str()).collect();
+        assert_eq!(
+            from,
+            vec![
+                "system",
+                "human",
+                "gpt",
+                "function_call",
+                "system",
+                "observation",
+                "gpt"
+            ]
+        );
+        assert!(turns[0].1.starts_with("file-history-snapshot: "));
+        assert_eq!(turns[1].1, "list files");
+        assert_eq!(turns[2].1, "<think>\nuse ls\n</think>");
+        assert_eq!(
+            turns[3].1,
+            r#"{"arguments":{"command":"ls"},"name":"Bash"}"#
+        );
+        assert_eq!(turns[4].1, "hook ran");
+        assert_eq!(turns[5].1, "a.txt");
+    }
+
+    #[test]
+    fn test_openai_with_dropped_parts() {
+        let options = DatasetOptions {
+            drop_thinking: true,
+            drop_system: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            record(&session(), DatasetFormat::OpenAi, options).unwrap(),
+            json!({"messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "t1",
+                    "type": "function",
+                    "function": {"name": "Bash", "arguments": r#"{"command":"ls"}"#},
+                }]},
+                {"role": "tool", "tool_call_id": "t1", "content": "a.txt"},
+                {"role": "assistant", "content": "One file."},
+            ]})
+        );
+
+        // Without results the calls go too, and so does the assistant
+        // message that only made them
+        let options = DatasetOptions {
+            drop_tool_results: true,
+            ..options
+        };
+        assert_eq!(
+            record(&session(), DatasetFormat::OpenAi, options).unwrap(),
+            json!({"messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": "One file."},
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_sessions_without_answers_have_no_record() {
+        let session = ConversationSession {
+            session_id: "s2".to_string(),
+            entries: vec![entry("1", None, "user", json!("hello?"))],
+            file_path: "s2.jsonl".to_string(),
+        };
+        assert!(record(&session, DatasetFormat::ShareGpt, DatasetOptions::default()).is_none());
+    }
+}
@@ -0,0 +1,246 @@
+//! Self-contained HTML transcripts.
+//!
+//! One file with inline styles and no scripts or external resources, so it
+//! can be attached to a doc or opened offline. Message text is rendered from
+//! Markdown; raw HTML inside messages is shown as text, never interpreted.
+
+use pulldown_cmark::{html, Event, Options, Parser};
+
+use super::{tool_input, tool_result_text, tool_summary, Item, Part, Thread};
+
+pub(crate) const STYLE: &str = "\
+body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Helvetica,Arial,sans-serif;\
+max-width:860px;margin:2rem auto;padding:0 1rem;line-height:1.5;color:#1f2328;background:#fff}\
+header{border-bottom:1px solid #d0d7de;margin-bottom:1.5rem}\
+header dl{display:grid;grid-template-columns:max-content 1fr;gap:.25rem 1rem}\
+header dt{font-weight:600}header dd{margin:0}\
+.turn{border:1px solid #d0d7de;border-radius:6px;margin:1rem 0;padding:.5rem 1rem}\
+.turn.user{background:#f6f8fa}\
+.role{font-weight:600;margin:.25rem 0}.role time{font-weight:normal;color:#656d76;margin-left:.5rem}\
+details{border:1px solid #d0d7de;border-radius:6px;margin:.5rem 0;padding:.25rem .75rem;background:#fff}\
+summary{cursor:pointer}summary code{font-size:.9em}\
+pre{background:#f6f8fa;padding:.75rem;overflow-x:auto;border-radius:6px}\
+code{font-family:ui-monospace,SFMono-Regular,Menlo,Consolas,monospace}\
+.error summary{color:#cf222e}.attachment{color:#656d76;font-style:italic}\
+.branch{margin:1.5rem 0 .5rem;padding:.25rem .75rem;border-left:4px solid #bf8700;font-weight:600}\
+nav{margin-bottom:1rem}nav a{margin-right:1rem}";
+
+/// Render a thread as a standalone HTML document
+pub fn render(thread: &Thread<'_>) -> String {
+    render_page(thread, &[])
+}
+
+/// Render a thread as a page of a larger site, with `(href, label)` links
+/// above it
+pub fn render_page(thread: &Thread<'_>, links: &[(&str, &str)]) -> String {
+    let header = &thread.header;
+    let title = format!("Session {}", header.session_id);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    out.push_str(&format!("<style>{STYLE}</style>\n</head>\n<body>\n"));
+    if !links.is_empty() {
+        out.push_str("<nav>");
+        for (href, label) in links {
+            out.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(href),
+                escape(label)
+            ));
+        }
+        out.push_str("</nav>\n");
+    }
+
+    out.push_str(&format!("<header>\n<h1>{}</h1>\n<dl>\n", escape(&title)));
+    let mut field = |name: &str, value: &str| {
+        out.push_str(&format!("<dt>{name}</dt><dd>{}</dd>\n", escape(value)));
+    };
+    field("Project", &header.project);
+    if let Some(branch) = &header.git_branch {
+        field("Git branch", branch);
+    }
+    if let Some(started) = &header.started {
+        field("Started", started);
+    }
+    if let Some(last) = &header.last_activity {
+        field("Last activity", last);
+    }
+    if !header.models.is_empty() {
+        field("Models", &header.models.join(", "));
+    }
+    out.push_str("</dl>\n</header>\n<main>\n");
+
+    // Consecutive entries of one author (Claude Code writes one per content
+    // block) share a box
+    let mut open_role = None;
+    for item in &thread.items {
+        let turn = match item {
+            Item::Branch { index, count } => {
+                close_turn(&mut out, &mut open_role);
+                out.push_str(&format!(
+                    "<div class=\"branch\">Branch {index} of {count}</div>\n"
+                ));
+                continue;
+            }
+            Item::Message(turn) => turn,
+        };
+
+        let parts = thread.parts(turn);
+        if parts.is_empty() {
+            continue;
+        }
+        if open_role != Some(turn.role) {
+            close_turn(&mut out, &mut open_role);
+            let title = if turn.role == "user" {
+                "User"
+            } else {
+                "Assistant"
+            };
+            out.push_str(&format!(
+                "<section class=\"turn {}\">\n<div class=\"role\">{title}",
+                turn.role
+            ));
+            if let Some(ts) = &turn.entry.timestamp {
+                out.push_str(&format!("<time>{}</time>", escape(ts)));
+            }
+            out.push_str("</div>\n");
+            open_role = Some(turn.role);
+        }
+
+        for part in parts {
+            render_part(&mut out, &part);
+        }
+    }
+    close_turn(&mut out, &mut open_role);
+
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+fn close_turn(out: &mut String, open_role: &mut Option<&str>) {
+    if open_role.take().is_some() {
+        out.push_str("</section>\n");
+    }
+}
+
+fn render_part(out: &mut String, part: &Part<'_>) {
+    match part {
+        Part::Text(text) => out.push_str(&markdown_to_html(text)),
+        Part::Thinking(thinking) => {
+            out.push_str("<details>\n<summary>Thinking</summary>\n");
+            out.push_str(&markdown_to_html(thinking));
+            out.push_str("</details>\n");
+        }
+        Part::ToolCall { call, result } => {
+            let failed = result.is_some_and(|r| r.is_error == Some(true));
+            out.push_str(if failed {
+                "<details class=\"error\">\n"
+            } else {
+                "<details>\n"
+            });
+            out.push_str(&format!("<summary>Tool: {}", escape(&call.name)));
+            if let Some(hint) = tool_summary(call) {
+                out.push_str(&format!(" — <code>{}</code>", escape(&hint)));
+            }
+            if failed {
+                out.push_str(" (error)");
+            }
+            out.push_str("</summary>\n<p><strong>Input</strong></p>\n");
+            out.push_str(&pre(&tool_input(call)));
+            if let Some(result) = result {
+                out.push_str("<p><strong>Result</strong></p>\n");
+                out.push_str(&pre(&tool_result_text(result)));
+            }
+            out.push_str("</details>\n");
+        }
+        Part::ToolResult(result) => {
+            out.push_str("<details>\n<summary>Tool result</summary>\n");
+            out.push_str(&pre(&tool_result_text(result)));
+            out.push_str("</details>\n");
+        }
+        Part::Attachment(reference) => {
+            out.push_str(&format!(
+                "<p class=\"attachment\">📎 Attachment: {}</p>\n",
+                escape(reference)
+            ));
+        }
+    }
+}
+
+/// Render Markdown, showing any raw HTML it contains as text
+fn markdown_to_html(text: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+fn pre(text: &str) -> String {
+    format!("<pre><code>{}</code></pre>\n", escape(text.trim_end()))
+}
+
+/// Escape text for HTML element content and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ConversationEntry, ConversationSession};
+    use crate::transcript::TranscriptOptions;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_html_escapes_and_collapses_tools() {
+        let lines = [
+            json!({"type": "user", "uuid": "1", "parentUuid": null,
+                   "message": {"role": "user", "content": "Why does <script>alert(1)</script> run? **bold**"}}),
+            json!({"type": "assistant", "uuid": "2", "parentUuid": "1",
+                   "message": {"role": "assistant", "content": [
+                       {"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "a<b>.rs"}}]}}),
+            json!({"type": "user", "uuid": "3", "parentUuid": "2",
+                   "message": {"role": "user", "content": [
+                       {"type": "tool_result", "tool_use_id": "t1", "content": "no such file", "is_error": true}]}}),
+        ];
+        let session = ConversationSession {
+            session_id: "s1".to_string(),
+            entries: lines
+                .into_iter()
+                .map(|l| serde_json::from_value::<ConversationEntry>(l).unwrap())
+                .collect(),
+            file_path: "s1.jsonl".to_string(),
+        };
+
+        let thread = Thread::new(&session, "web", TranscriptOptions::default());
+        let html = render(&thread);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains(
+            "<details class=\"error\">\n<summary>Tool: Read — <code>a&lt;b&gt;.rs</code> (error)</summary>"
+        ));
+        assert!(html.contains("<pre><code>no such file</code></pre>"));
+        assert_eq!(html.matches("<section").count(), 2);
+        assert_eq!(html.matches("</section>").count(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! Markdown transcripts.
+//!
+//! Message text is Markdown already and is written as-is. Tool calls and
+//! thinking are wrapped in `<details>` blocks, which GitHub, GitLab and most
+//! editors render collapsed.
+
+use super::{tool_input, tool_result_text, tool_summary, Item, Part, Thread};
+
+/// Render a thread as a Markdown document
+pub fn render(thread: &Thread<'_>) -> String {
+    let header = &thread.header;
+    let mut out = format!("# Session {}\n\n", header.session_id);
+
+    out.push_str(&format!("- **Project:** {}\n", header.project));
+    if let Some(branch) = &header.git_branch {
+        out.push_str(&format!("- **Git branch:** {branch}\n"));
+    }
+    if let Some(started) = &header.started {
+        out.push_str(&format!("- **Started:** {started}\n"));
+    }
+    if let Some(last) = &header.last_activity {
+        out.push_str(&format!("- **Last activity:** {last}\n"));
+    }
+    if !header.models.is_empty() {
+        out.push_str(&format!("- **Models:** {}\n", header.models.join(", ")));
+    }
+
+    // Consecutive entries of one author (Claude Code writes one per content
+    // block) read as a single message
+    let mut last_role = None;
+    for item in &thread.items {
+        let turn = match item {
+            Item::Branch { index, count } => {
+                out.push_str(&format!("\n---\n\n> **Branch {index} of {count}**\n"));
+                last_role = None;
+                continue;
+            }
+            Item::Message(turn) => turn,
+        };
+
+        let parts = thread.parts(turn);
+        if parts.is_empty() {
+            continue;
+        }
+        if last_role != Some(turn.role) {
+            let title = if turn.role == "user" {
+                "User"
+            } else {
+                "Assistant"
+            };
+            match &turn.entry.timestamp {
+                Some(ts) => out.push_str(&format!("\n## {title} · {ts}\n")),
+                None => out.push_str(&format!("\n## {title}\n")),
+            }
+            last_role = Some(turn.role);
+        }
+
+        for part in parts {
+            out.push('\n');
+            render_part(&mut out, &part);
+        }
+    }
+
+    out
+}
+
+fn render_part(out: &mut String, part: &Part<'_>) {
+    match part {
+        Part::Text(text) => {
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+        Part::Thinking(thinking) => {
+            out.push_str("<details>\n<summary>Thinking</summary>\n\n");
+            out.push_str(thinking.trim_end());
+            out.push_str("\n\n</details>\n");
+        }
+        Part::ToolCall { call, result } => {
+            let mut summary = format!("Tool: {}", call.name);
+            if let Some(hint) = tool_summary(call) {
+                summary.push_str(&format!(" — {}", inline_code(&hint)));
+            }
+            if result.is_some_and(|r| r.is_error == Some(true)) {
+                summary.push_str(" (error)");
+            }
+            out.push_str(&format!("<details>\n<summary>{summary}</summary>\n\n"));
+            out.push_str("**Input**\n\n");
+            out.push_str(&code_block(&tool_input(call), "json"));
+            if let Some(result) = result {
+                out.push_str("\n**Result**\n\n");
+                out.push_str(&code_block(&tool_result_text(result), ""));
+            }
+            out.push_str("\n</details>\n");
+        }
+        Part::ToolResult(result) => {
+            out.push_str("<details>\n<summary>Tool result</summary>\n\n");
+            out.push_str(&code_block(&tool_result_text(result), ""));
+            out.push_str("\n</details>\n");
+        }
+        Part::Attachment(reference) => {
+            out.push_str(&format!("📎 *Attachment: {reference}*\n"));
+        }
+    }
+}
+
+/// Fenced code block whose fence is longer than any backtick run in `text`
+fn code_block(text: &str, lang: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(text).max(2) + 1);
+    format!("{fence}{lang}\n{}\n{fence}\n", text.trim_end())
+}
+
+/// Inline code span that survives backticks in `text`
+fn inline_code(text: &str) -> String {
+    let ticks = "`".repeat(longest_backtick_run(text) + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{ticks} {text} {ticks}")
+    } else {
+        format!("{ticks}{text}{ticks}")
+    }
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ConversationEntry, ConversationSession};
+    use crate::transcript::TranscriptOptions;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_markdown() {
+        let lines = [
+            json!({"type": "user", "uuid": "1", "parentUuid": null, "timestamp": "2025-01-01T10:00:00Z",
+                   "message": {"role": "user", "content": "List the files"}}),
+            json!({"type": "assistant", "uuid": "2", "parentUuid": "1", "timestamp": "2025-01-01T10:00:01Z",
+                   "message": {"role": "assistant", "model": "claude-sonnet-4", "content": [
+                       {"type": "text", "text": "Sure."},
+                       {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}]}}),
+            json!({"type": "user", "uuid": "3", "parentUuid": "2", "timestamp": "2025-01-01T10:00:02Z",
+                   "message": {"role": "user", "content": [
+                       {"type": "tool_result", "tool_use_id": "t1", "content": "```\nREADME.md"}]}}),
+            json!({"type": "assistant", "uuid": "4", "parentUuid": "3", "timestamp": "2025-01-01T10:00:03Z",
+                   "message": {"role": "assistant", "model": "claude-sonnet-4", "content": [
+                       {"type": "text", "text": "One file."}]}}),
+        ];
+        let session = ConversationSession {
+            session_id: "s1".to_string(),
+            entries: lines
+                .into_iter()
+                .map(|l| serde_json::from_value::<ConversationEntry>(l).unwrap())
+                .collect(),
+            file_path: "s1.jsonl".to_string(),
+        };
+
+        let thread = Thread::new(&session, "web", TranscriptOptions::default());
+        let markdown = render(&thread);
+
+        assert!(markdown.starts_with("# Session s1\n\n- **Project:** web\n"));
+        assert!(markdown.contains("- **Models:** claude-sonnet-4\n"));
+        assert!(markdown.contains("\n## User · 2025-01-01T10:00:00Z\n\nList the files\n"));
+        assert!(markdown.contains("<summary>Tool: Bash — `ls`</summary>"));
+        // A result containing a fence gets a longer one
+        assert!(markdown.contains("````\n```\nREADME.md\n````\n"));
+        // The tool result turn adds no heading, so the answer continues the
+        // assistant message
+        assert_eq!(markdown.matches("## Assistant").count(), 1);
+        assert_eq!(markdown.matches("## User").count(), 1);
+        assert!(markdown.trim_end().ends_with("One file."));
+    }
+}
@@ -0,0 +1,504 @@
+//! Human-readable transcripts of a conversation.
+//!
+//! A session file is a tree: every entry points at its `parentUuid`, and
+//! editing a prompt or regenerating a response starts a sibling branch. The
+//! [`Thread`] of a session is what a reader wants to see of that tree:
+//!
+//! - the active branch (default): the path from the root to the most recent
+//!   message, i.e. the conversation as it stands in Claude Code
+//! - all branches: every message, depth first, with each alternative branch
+//!   marked where the conversation forked
+//!
+//! Only user and assistant messages are shown. Tool results, which Claude
+//! Code stores as user messages, are shown together with the tool call they
+//! answer. The thread is then rendered by [`markdown`] or [`html`], or paged
+//! in the terminal by the [`viewer`]. [`dataset`] turns the same branch into
+//! chat records for fine-tuning and evaluation.
+
+pub mod dataset;
+pub mod html;
+pub mod markdown;
+pub mod tree;
+pub mod viewer;
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::message::{ContentBlock, ImageBlock, Message, ToolResultBlock, ToolUseBlock};
+use crate::parser::{ConversationEntry, ConversationSession};
+
+use self::tree::{MessageTree, Step};
+
+/// Longest one-line tool call summary, in characters
+const TOOL_SUMMARY_MAX_CHARS: usize = 80;
+
+/// Output format of a transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" | "htm" => Ok(Format::Html),
+            other => anyhow::bail!("Unknown format: '{}'. Use 'markdown' or 'html'.", other),
+        }
+    }
+}
+
+/// What to put in a transcript
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranscriptOptions {
+    /// Every branch of the conversation instead of the active one only
+    pub all_branches: bool,
+}
+
+/// Session details shown above the messages
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptHeader {
+    pub session_id: String,
+    pub project: String,
+    pub git_branch: Option<String>,
+    pub started: Option<String>,
+    pub last_activity: Option<String>,
+    /// Models that answered, in order of first use
+    pub models: Vec<String>,
+}
+
+/// One rendered step of a thread
+#[derive(Debug, Clone)]
+pub enum Item<'a> {
+    /// A message
+    Message(Turn<'a>),
+    /// Start of alternative `index` (1-based) of `count` at a fork
+    Branch { index: usize, count: usize },
+}
+
+/// A user or assistant message in a thread
+#[derive(Debug, Clone)]
+pub struct Turn<'a> {
+    pub entry: &'a ConversationEntry,
+    /// "user" or "assistant"
+    pub role: &'a str,
+    pub message: Message,
+}
+
+/// The messages of a session to render, in reading order
+#[derive(Debug, Clone)]
+pub struct Thread<'a> {
+    pub header: TranscriptHeader,
+    pub items: Vec<Item<'a>>,
+    /// Tool results by the id of the call they answer
+    results: HashMap<String, ToolResultBlock>,
+}
+
+/// A piece of a message, ready to render
+#[derive(Debug, Clone)]
+pub enum Part<'a> {
+    /// Markdown text
+    Text(&'a str),
+    /// Model reasoning
+    Thinking(&'a str),
+    /// A tool call with its result, if the thread has it
+    ToolCall {
+        call: &'a ToolUseBlock,
+        result: Option<&'a ToolResultBlock>,
+    },
+    /// A tool result whose call is not in the thread
+    ToolResult(&'a ToolResultBlock),
+    /// Reference to an attached image or document
+    Attachment(String),
+}
+
+impl<'a> Thread<'a> {
+    /// Select the messages of `session` to render
+    pub fn new(
+        session: &'a ConversationSession,
+        project: &str,
+        options: TranscriptOptions,
+    ) -> Self {
+        let tree = MessageTree::new(session);
+        let steps = if options.all_branches {
+            tree.depth_first()
+        } else {
+            // Only the active branch: its forks are not worth marking
+            tree.path(&HashMap::new())
+                .into_iter()
+                .map(|step| Step {
+                    branch: None,
+                    ..step
+                })
+                .collect()
+        };
+        Self::from_steps(session, project, &tree, &steps)
+    }
+
+    /// A thread of the given walk through `tree`, marking each step that is
+    /// one of several alternatives
+    pub fn from_steps(
+        session: &ConversationSession,
+        project: &str,
+        tree: &MessageTree<'a>,
+        steps: &[Step],
+    ) -> Self {
+        let mut items = Vec::new();
+        for step in steps {
+            if let Some((index, count)) = step.branch {
+                items.push(Item::Branch { index, count });
+            }
+            if let Some(turn) = turn(tree.entry(step.node)) {
+                items.push(Item::Message(turn));
+            }
+        }
+
+        let mut results = HashMap::new();
+        for item in &items {
+            if let Item::Message(turn) = item {
+                for result in turn.message.tool_results() {
+                    results.insert(result.tool_use_id.clone(), result.clone());
+                }
+            }
+        }
+
+        let header = header(session, project, &items);
+        Thread {
+            header,
+            items,
+            results,
+        }
+    }
+
+    /// The parts of a message to render. Tool results answering a call in
+    /// this thread are attached to the call instead of shown on their own.
+    pub fn parts<'t>(&'t self, turn: &'t Turn<'a>) -> Vec<Part<'t>> {
+        let Some(content) = &turn.message.content else {
+            return Vec::new();
+        };
+        let blocks = match content {
+            crate::message::MessageContent::Text(text) => return vec![Part::Text(text)],
+            crate::message::MessageContent::Blocks(blocks) => blocks,
+        };
+
+        let calls: HashSet<&str> = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Message(turn) => Some(turn),
+                _ => None,
+            })
+            .flat_map(|turn| turn.message.tool_uses().map(|call| call.id.as_str()))
+            .collect();
+
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) if !text.text.trim().is_empty() => {
+                    Some(Part::Text(&text.text))
+                }
+                ContentBlock::Text(_) => None,
+                ContentBlock::Thinking(thinking) if !thinking.thinking.trim().is_empty() => {
+                    Some(Part::Thinking(&thinking.thinking))
+                }
+                ContentBlock::Thinking(_) => None,
+                ContentBlock::ToolUse(call) => Some(Part::ToolCall {
+                    call,
+                    result: self.results.get(&call.id),
+                }),
+                ContentBlock::ToolResult(result) if calls.contains(result.tool_use_id.as_str()) => {
+                    None
+                }
+                ContentBlock::ToolResult(result) => Some(Part::ToolResult(result)),
+                ContentBlock::Image(image) => Some(Part::Attachment(image_reference(image))),
+                ContentBlock::Other(value) => other_reference(value).map(Part::Attachment),
+            })
+            .collect()
+    }
+}
+
+/// Render `session` in the given format
+pub fn render(
+    session: &ConversationSession,
+    project: &str,
+    format: Format,
+    options: TranscriptOptions,
+) -> String {
+    let thread = Thread::new(session, project, options);
+    match format {
+        Format::Markdown => markdown::render(&thread),
+        Format::Html => html::render(&thread),
+    }
+}
+
+fn turn(entry: &ConversationEntry) -> Option<Turn<'_>> {
+    Some(Turn {
+        entry,
+        role: entry.entry_type.as_str(),
+        message: entry.typed_message()?,
+    })
+}
+
+fn header(session: &ConversationSession, project: &str, items: &[Item<'_>]) -> TranscriptHeader {
+    let turns = || {
+        items.iter().filter_map(|item| match item {
+            Item::Message(turn) => Some(turn),
+            _ => None,
+        })
+    };
+
+    let mut models: Vec<String> = Vec::new();
+    for turn in turns() {
+        if let Some(model) = &turn.message.model {
+            // Placeholder used for messages Claude Code synthesizes itself
+            if model != "<synthetic>" && !models.contains(model) {
+                models.push(model.clone());
+            }
+        }
+    }
+
+    let timestamps: Vec<&str> = turns()
+        .filter_map(|turn| turn.entry.timestamp.as_deref())
+        .collect();
+
+    TranscriptHeader {
+        session_id: session.session_id.clone(),
+        project: project.to_string(),
+        git_branch: turns()
+            .filter_map(|turn| turn.entry.git_branch.clone())
+            .next_back(),
+        started: timestamps.iter().min().map(|s| s.to_string()),
+        last_activity: timestamps.iter().max().map(|s| s.to_string()),
+        models,
+    }
+}
+
+/// One-line hint of what a tool call did: its command, path, pattern, ...
+pub fn tool_summary(call: &ToolUseBlock) -> Option<String> {
+    const KEYS: [&str; 8] = [
+        "command",
+        "file_path",
+        "path",
+        "pattern",
+        "url",
+        "query",
+        "description",
+        "prompt",
+    ];
+    let value = KEYS
+        .iter()
+        .find_map(|key| call.input.get(key).and_then(Value::as_str))?;
+    let line = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.is_empty() {
+        return None;
+    }
+    if line.chars().count() > TOOL_SUMMARY_MAX_CHARS {
+        let truncated: String = line.chars().take(TOOL_SUMMARY_MAX_CHARS - 1).collect();
+        Some(format!("{truncated}…"))
+    } else {
+        Some(line)
+    }
+}
+
+/// Tool input pretty-printed as JSON
+pub fn tool_input(call: &ToolUseBlock) -> String {
+    serde_json::to_string_pretty(&call.input).unwrap_or_else(|_| call.input.to_string())
+}
+
+/// Text of a tool result, with attachments it carries referenced
+pub fn tool_result_text(result: &ToolResultBlock) -> String {
+    let mut parts = vec![result.text()];
+    if let Some(crate::message::MessageContent::Blocks(blocks)) = &result.content {
+        for block in blocks {
+            match block {
+                ContentBlock::Image(image) => parts.push(image_reference(image)),
+                ContentBlock::Other(value) => parts.extend(other_reference(value)),
+                _ => {}
+            }
+        }
+    }
+    parts.retain(|p| !p.is_empty());
+    parts.join("\n\n")
+}
+
+/// Reference to an image block, e.g. "Image (image/png, 12.3 KB)"
+fn image_reference(image: &ImageBlock) -> String {
+    attachment_reference("Image", &image.source)
+}
+
+/// Reference to a document or other attachment block, if it is one
+fn other_reference(value: &Value) -> Option<String> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("document") => Some(attachment_reference(
+            "Document",
+            value.get("source").unwrap_or(&Value::Null),
+        )),
+        _ => None,
+    }
+}
+
+fn attachment_reference(kind: &str, source: &Value) -> String {
+    let media_type = source.get("media_type").and_then(Value::as_str);
+    let mut details: Vec<String> = media_type.map(str::to_string).into_iter().collect();
+    match source.get("type").and_then(Value::as_str) {
+        Some("base64") => {
+            if let Some(data) = source.get("data").and_then(Value::as_str) {
+                details.push(format_size(data.len() as u64 * 3 / 4));
+            }
+        }
+        Some("url") => {
+            if let Some(url) = source.get("url").and_then(Value::as_str) {
+                details.push(url.to_string());
+            }
+        }
+        _ => {}
+    }
+    if details.is_empty() {
+        kind.to_string()
+    } else {
+        format!("{kind} ({})", details.join(", "))
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{b} B"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(
+        uuid: &str,
+        parent: Option<&str>,
+        entry_type: &str,
+        content: Value,
+    ) -> ConversationEntry {
+        serde_json::from_value(json!({
+            "type": entry_type,
+            "uuid": uuid,
+            "parentUuid": parent,
+            "sessionId": "s1",
+            "timestamp": format!("2025-01-01T00:00:0{uuid}Z"),
+            "cwd": "/home/u/web",
+            "message": {"role": entry_type, "content": content},
+        }))
+        .unwrap()
+    }
+
+    fn session(entries: Vec<ConversationEntry>) -> ConversationSession {
+        ConversationSession {
+            session_id: "s1".to_string(),
+            entries,
+            file_path: "s1.jsonl".to_string(),
+        }
+    }
+
+    fn texts(thread: &Thread<'_>) -> Vec<String> {
+        thread
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Message(turn) => turn.message.text(),
+                Item::Branch { index, count } => format!("[{index}/{count}]"),
+            })
+            .collect()
+    }
+
+    /// 1 -> 2 -> 3, then the prompt was edited: 1 -> 4 -> 5
+    fn forked() -> ConversationSession {
+        session(vec![
+            entry("1", None, "user", json!("hi")),
+            entry("2", Some("1"), "assistant", json!("first answer")),
+            entry("3", Some("2"), "user", json!("old prompt")),
+            entry("4", Some("2"), "user", json!("new prompt")),
+            entry("5", Some("4"), "assistant", json!("new answer")),
+        ])
+    }
+
+    #[test]
+    fn test_active_branch_follows_latest_message() {
+        let session = forked();
+        let thread = Thread::new(&session, "web", TranscriptOptions::default());
+        assert_eq!(
+            texts(&thread),
+            vec!["hi", "first answer", "new prompt", "new answer"]
+        );
+        assert_eq!(thread.header.project, "web");
+        assert_eq!(
+            thread.header.started.as_deref(),
+            Some("2025-01-01T00:00:01Z")
+        );
+    }
+
+    #[test]
+    fn test_all_branches_marks_forks() {
+        let session = forked();
+        let thread = Thread::new(&session, "web", TranscriptOptions { all_branches: true });
+        assert_eq!(
+            texts(&thread),
+            vec![
+                "hi",
+                "first answer",
+                "[1/2]",
+                "old prompt",
+                "[2/2]",
+                "new prompt",
+                "new answer"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tool_results_attach_to_their_call() {
+        let session = session(vec![
+            entry(
+                "1",
+                None,
+                "assistant",
+                json!([{"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls -la"}}]),
+            ),
+            entry(
+                "2",
+                Some("1"),
+                "user",
+                json!([
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "a.txt"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+                ]),
+            ),
+        ]);
+        let thread = Thread::new(&session, "web", TranscriptOptions::default());
+        let Item::Message(call_turn) = &thread.items[0] else {
+            panic!("expected a message");
+        };
+        match thread.parts(call_turn).as_slice() {
+            [Part::ToolCall {
+                call,
+                result: Some(result),
+            }] => {
+                assert_eq!(tool_summary(call).as_deref(), Some("ls -la"));
+                assert_eq!(result.text(), "a.txt");
+            }
+            other => panic!("unexpected parts: {other:?}"),
+        }
+
+        // The result is not repeated with the user message; the image is
+        // referenced, not embedded
+        let Item::Message(result_turn) = &thread.items[1] else {
+            panic!("expected a message");
+        };
+        match thread.parts(result_turn).as_slice() {
+            [Part::Attachment(reference)] => assert_eq!(reference, "Image (image/png, 3 B)"),
+            other => panic!("unexpected parts: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! The message tree of a session.
+//!
+//! The same `parentUuid` tree that [`merge::MessageNode`](crate::merge::MessageNode)
+//! builds for merging, restricted to user and assistant messages and indexed
+//! for walking one branch at a time. A message's parent is its nearest
+//! ancestor that is itself a message, so system entries in between never
+//! split the tree; a message without a uuid continues the one before it.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::{ConversationEntry, ConversationSession};
+
+/// Where a branch choice is made: under a message, or among the roots
+pub type ForkKey = Option<usize>;
+
+/// One message on a walk through the tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// Message index in file order
+    pub node: usize,
+    /// `(index, count)` (1-based) if this message is one of several
+    /// alternatives under its parent
+    pub branch: Option<(usize, usize)>,
+}
+
+/// User and assistant messages of a session, linked by parent
+#[derive(Debug, Clone)]
+pub struct MessageTree<'a> {
+    messages: Vec<&'a ConversationEntry>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>,
+    /// Latest message (highest index) in each subtree
+    latest: Vec<usize>,
+}
+
+impl<'a> MessageTree<'a> {
+    /// Build the tree of `session`
+    pub fn new(session: &'a ConversationSession) -> Self {
+        let messages: Vec<&ConversationEntry> = session
+            .entries
+            .iter()
+            .filter(|e| is_message_entry(e))
+            .collect();
+
+        let positions: HashMap<&str, usize> = messages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.uuid.as_deref().map(|uuid| (uuid, i)))
+            .collect();
+        let by_uuid: HashMap<&str, &ConversationEntry> = session
+            .entries
+            .iter()
+            .filter_map(|e| e.uuid.as_deref().map(|uuid| (uuid, e)))
+            .collect();
+
+        let mut parents: Vec<Option<usize>> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                if entry.uuid.is_none() {
+                    return i.checked_sub(1);
+                }
+                // Walk up through non-message entries to the nearest message
+                let mut seen = HashSet::new();
+                let mut parent = parent_of(entry);
+                while let Some(uuid) = parent {
+                    if let Some(&p) = positions.get(uuid) {
+                        return (p != i).then_some(p);
+                    }
+                    if !seen.insert(uuid) {
+                        break;
+                    }
+                    parent = by_uuid.get(uuid).and_then(|e| parent_of(e));
+                }
+                None
+            })
+            .collect();
+
+        // Break parent cycles, which would leave messages unreachable
+        let mut reachable = vec![false; messages.len()];
+        loop {
+            let mut children = vec![Vec::new(); messages.len()];
+            let mut roots = Vec::new();
+            for (i, parent) in parents.iter().enumerate() {
+                match parent {
+                    Some(p) => children[*p].push(i),
+                    None => roots.push(i),
+                }
+            }
+
+            let mut stack = roots.clone();
+            while let Some(i) = stack.pop() {
+                reachable[i] = true;
+                stack.extend(&children[i]);
+            }
+
+            match reachable.iter().position(|r| !r) {
+                Some(stuck) => parents[stuck] = None,
+                None => {
+                    let latest = subtree_latest(&roots, &children);
+                    return MessageTree {
+                        messages,
+                        parents,
+                        children,
+                        roots,
+                        latest,
+                    };
+                }
+            }
+        }
+    }
+
+    /// The message at `node`
+    pub fn entry(&self, node: usize) -> &'a ConversationEntry {
+        self.messages[node]
+    }
+
+    /// Parent of `node`, the key of the fork it was chosen at
+    pub fn fork_of(&self, node: usize) -> ForkKey {
+        self.parents[node]
+    }
+
+    /// Walk from a root to a leaf. At each fork, take the alternative given
+    /// in `choices` (0-based), else the one leading to the most recent
+    /// message. With no choices this is the active branch.
+    pub fn path(&self, choices: &HashMap<ForkKey, usize>) -> Vec<Step> {
+        let mut steps = Vec::new();
+        let mut fork: ForkKey = None;
+        let mut options = &self.roots;
+        while !options.is_empty() {
+            let index = match choices.get(&fork) {
+                Some(&choice) if choice < options.len() => choice,
+                _ => self.latest_alternative(options),
+            };
+            let node = options[index];
+            steps.push(Step {
+                node,
+                branch: (options.len() > 1).then_some((index + 1, options.len())),
+            });
+            fork = Some(node);
+            options = &self.children[node];
+        }
+        steps
+    }
+
+    /// Every message, depth first with alternatives in file order
+    pub fn depth_first(&self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        let push_options = |stack: &mut Vec<Step>, options: &[usize]| {
+            for (index, &node) in options.iter().enumerate().rev() {
+                stack.push(Step {
+                    node,
+                    branch: (options.len() > 1).then_some((index + 1, options.len())),
+                });
+            }
+        };
+
+        let mut stack = Vec::new();
+        push_options(&mut stack, &self.roots);
+        while let Some(step) = stack.pop() {
+            steps.push(step);
+            push_options(&mut stack, &self.children[step.node]);
+        }
+        steps
+    }
+
+    fn latest_alternative(&self, options: &[usize]) -> usize {
+        options
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, &node)| self.latest[node])
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+/// True for entries carrying a user or assistant message
+pub(super) fn is_message_entry(entry: &ConversationEntry) -> bool {
+    (entry.entry_type == "user" || entry.entry_type == "assistant") && entry.message.is_some()
+}
+
+/// Parent of an entry, following compaction boundaries (which start a new
+/// root but record the message they continue as `logicalParentUuid`)
+pub(super) fn parent_of(entry: &ConversationEntry) -> Option<&str> {
+    entry
+        .parent_uuid
+        .as_deref()
+        .or_else(|| entry.extra.get("logicalParentUuid").and_then(Value::as_str))
+}
+
+/// Highest message index in each subtree
+fn subtree_latest(roots: &[usize], children: &[Vec<usize>]) -> Vec<usize> {
+    let mut latest: Vec<usize> = (0..children.len()).collect();
+    // Preorder, then fold children into parents in reverse
+    let mut order = Vec::with_capacity(children.len());
+    let mut stack = roots.to_vec();
+    while let Some(node) = stack.pop() {
+        order.push(node);
+        stack.extend(&children[node]);
+    }
+    for &node in order.iter().rev() {
+        for &child in &children[node] {
+            latest[node] = latest[node].max(latest[child]);
+        }
+    }
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(uuid: Option<&str>, parent: Option<&str>, entry_type: &str) -> ConversationEntry {
+        serde_json::from_value(json!({
+            "type": entry_type,
+            "uuid": uuid,
+            "parentUuid": parent,
+            "message": {"role": entry_type, "content": "x"},
+        }))
+        .unwrap()
+    }
+
+    fn tree_of(entries: Vec<ConversationEntry>) -> ConversationSession {
+        ConversationSession {
+            session_id: "s1".to_string(),
+            entries,
+            file_path: "s1.jsonl".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_paths_and_choices() {
+        // a -> b -> {c, d -> e}; the system entry between d and e is skipped
+        let mut system = entry(Some("sys"), Some("d"), "system");
+        system.message = None;
+        let session = tree_of(vec![
+            entry(Some("a"), None, "user"),
+            entry(Some("b"), Some("a"), "assistant"),
+            entry(Some("c"), Some("b"), "user"),
+            entry(Some("d"), Some("b"), "user"),
+            system,
+            entry(Some("e"), Some("sys"), "assistant"),
+        ]);
+        let tree = MessageTree::new(&session);
+
+        let nodes = |steps: Vec<Step>| steps.iter().map(|s| s.node).collect::<Vec<_>>();
+        let active = tree.path(&HashMap::new());
+        assert_eq!(nodes(active.clone()), vec![0, 1, 3, 4]);
+        assert_eq!(active[2].branch, Some((2, 2)));
+        assert_eq!(tree.fork_of(3), Some(1));
+
+        let choices = HashMap::from([(Some(1), 0)]);
+        assert_eq!(nodes(tree.path(&choices)), vec![0, 1, 2]);
+        assert_eq!(nodes(tree.depth_first()), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_messages_without_uuid_and_cycles() {
+        let session = tree_of(vec![
+            entry(None, None, "user"),
+            entry(None, None, "assistant"),
+            entry(Some("x"), Some("y"), "user"),
+            entry(Some("y"), Some("x"), "assistant"),
+        ]);
+        let tree = MessageTree::new(&session);
+
+        // The uuid-less messages chain in file order; the cycle is broken so
+        // both of its messages are still reachable
+        let all: Vec<usize> = tree.depth_first().iter().map(|s| s.node).collect();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+        assert_eq!(tree.fork_of(1), Some(0));
+    }
+}
@@ -0,0 +1,545 @@
+//! Terminal transcript viewer behind the `show` command.
+//!
+//! Lays a thread out as wrapped, styled lines and pages through them. Tool
+//! input and output (and thinking) are folded to one line until expanded.
+//! Forks in the message tree are marked where they occur, and the viewer can
+//! switch between their alternatives, re-laying the rest of the conversation
+//! along the chosen branch.
+
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::parser::ConversationSession;
+
+use super::tree::{ForkKey, MessageTree, Step};
+use super::{tool_input, tool_result_text, tool_summary, Item, Part, Thread};
+
+/// Width used when the terminal size is unknown
+const DEFAULT_WIDTH: usize = 100;
+
+/// Indentation of message content under its role line
+const INDENT: &str = "  ";
+
+/// How a line is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Title,
+    Meta,
+    User,
+    Assistant,
+    Text,
+    Tool,
+    ToolError,
+    Folded,
+    Fork,
+}
+
+/// One laid-out line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub style: Style,
+    pub text: String,
+}
+
+/// A fork marker in the laid-out lines
+#[derive(Debug, Clone, Copy)]
+struct ForkLine {
+    line: usize,
+    key: ForkKey,
+    /// Chosen alternative, 0-based
+    index: usize,
+    count: usize,
+}
+
+/// Viewer state: the branch being shown and its layout
+pub struct Viewer<'a> {
+    session: &'a ConversationSession,
+    project: String,
+    tree: MessageTree<'a>,
+    choices: HashMap<ForkKey, usize>,
+    expanded: bool,
+    width: usize,
+    lines: Vec<Line>,
+    forks: Vec<ForkLine>,
+}
+
+impl<'a> Viewer<'a> {
+    /// Lay out the active branch of `session`
+    pub fn new(
+        session: &'a ConversationSession,
+        project: &str,
+        expanded: bool,
+        width: usize,
+    ) -> Self {
+        let mut viewer = Viewer {
+            session,
+            project: project.to_string(),
+            tree: MessageTree::new(session),
+            choices: HashMap::new(),
+            expanded,
+            width: width.max(20),
+            lines: Vec::new(),
+            forks: Vec::new(),
+        };
+        viewer.layout();
+        viewer
+    }
+
+    /// The laid-out lines
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Number of forks on the branch being shown
+    pub fn fork_count(&self) -> usize {
+        self.forks.len()
+    }
+
+    /// Fold or unfold tool output and thinking
+    pub fn toggle_expanded(&mut self) {
+        self.expanded = !self.expanded;
+        self.layout();
+    }
+
+    /// Re-lay out for a new terminal width
+    pub fn set_width(&mut self, width: usize) {
+        self.width = width.max(20);
+        self.layout();
+    }
+
+    /// Switch fork `fork` (0-based, in line order) to its next (`delta` 1)
+    /// or previous (`delta` -1) alternative. Returns the fork's new line.
+    pub fn switch_branch(&mut self, fork: usize, delta: isize) -> Option<usize> {
+        let current = *self.forks.get(fork)?;
+        let next = (current.index as isize + delta).rem_euclid(current.count as isize) as usize;
+        self.choices.insert(current.key, next);
+        // Forks further down follow the most recent message again
+        for later in &self.forks[fork + 1..] {
+            self.choices.remove(&later.key);
+        }
+        self.layout();
+        self.forks
+            .iter()
+            .find(|f| f.key == current.key)
+            .map(|f| f.line)
+    }
+
+    fn layout(&mut self) {
+        let steps: Vec<Step> = self.tree.path(&self.choices);
+        let thread = Thread::from_steps(self.session, &self.project, &self.tree, &steps);
+        let mut fork_keys = steps
+            .iter()
+            .filter(|s| s.branch.is_some())
+            .map(|s| self.tree.fork_of(s.node));
+
+        let mut out = Layout {
+            lines: Vec::new(),
+            width: self.width,
+        };
+        let mut forks = Vec::new();
+
+        let header = &thread.header;
+        out.push(Style::Title, &format!("Session {}", header.session_id));
+        let mut meta = vec![header.project.clone()];
+        meta.extend(header.git_branch.as_ref().map(|b| format!("branch {b}")));
+        meta.extend(header.started.clone());
+        meta.extend((!header.models.is_empty()).then(|| header.models.join(", ")));
+        out.push(Style::Meta, &meta.join(" · "));
+
+        let mut last_role = None;
+        for item in &thread.items {
+            let turn = match item {
+                Item::Branch { index, count } => {
+                    out.blank();
+                    forks.push(ForkLine {
+                        line: out.lines.len(),
+                        key: fork_keys.next().flatten(),
+                        index: index - 1,
+                        count: *count,
+                    });
+                    out.push(
+                        Style::Fork,
+                        &format!("── Branch {index} of {count} ── (←/→ to switch)"),
+                    );
+                    last_role = None;
+                    continue;
+                }
+                Item::Message(turn) => turn,
+            };
+
+            let parts = thread.parts(turn);
+            if parts.is_empty() {
+                continue;
+            }
+            if last_role != Some(turn.role) {
+                out.blank();
+                let (style, title) = if turn.role == "user" {
+                    (Style::User, "User")
+                } else {
+                    (Style::Assistant, "Assistant")
+                };
+                let mut role_line = format!("● {title}");
+                if let Some(ts) = &turn.entry.timestamp {
+                    role_line.push_str(&format!(" · {ts}"));
+                }
+                out.push(style, &role_line);
+                last_role = Some(turn.role);
+            }
+
+            for part in &parts {
+                self.layout_part(&mut out, part);
+            }
+        }
+
+        self.lines = out.lines;
+        self.forks = forks;
+    }
+
+    fn layout_part(&self, out: &mut Layout, part: &Part<'_>) {
+        match part {
+            Part::Text(text) => out.wrapped(Style::Text, text, INDENT),
+            Part::Thinking(thinking) => {
+                if self.expanded {
+                    out.push(Style::Folded, &format!("{INDENT}✻ Thinking"));
+                    out.wrapped(Style::Folded, thinking, "    ");
+                } else {
+                    out.push(
+                        Style::Folded,
+                        &format!("{INDENT}✻ Thinking ({})", count_lines(thinking)),
+                    );
+                }
+            }
+            Part::ToolCall { call, result } => {
+                let failed = result.is_some_and(|r| r.is_error == Some(true));
+                let style = if failed {
+                    Style::ToolError
+                } else {
+                    Style::Tool
+                };
+                let mut title = format!("{INDENT}⏺ {}", call.name);
+                if let Some(hint) = tool_summary(call) {
+                    title.push_str(&format!("({hint})"));
+                }
+                if failed {
+                    title.push_str(" — error");
+                }
+                out.push(style, &title);
+
+                let output = result.map(tool_result_text);
+                if self.expanded {
+                    out.push(Style::Folded, &format!("{INDENT}  ⎿ Input"));
+                    out.wrapped(Style::Folded, &tool_input(call), "      ");
+                    if let Some(output) = &output {
+                        out.push(Style::Folded, &format!("{INDENT}  ⎿ Output"));
+                        out.wrapped(Style::Folded, output, "      ");
+                    }
+                } else {
+                    let folded = match &output {
+                        Some(output) => format!("{INDENT}  ⎿ {} of output", count_lines(output)),
+                        None => format!("{INDENT}  ⎿ no output"),
+                    };
+                    out.push(Style::Folded, &folded);
+                }
+            }
+            Part::ToolResult(result) => {
+                let output = tool_result_text(result);
+                if self.expanded {
+                    out.push(Style::Tool, &format!("{INDENT}⎿ Tool output"));
+                    out.wrapped(Style::Folded, &output, "    ");
+                } else {
+                    out.push(
+                        Style::Folded,
+                        &format!("{INDENT}⎿ Tool output ({})", count_lines(&output)),
+                    );
+                }
+            }
+            Part::Attachment(reference) => {
+                out.push(Style::Meta, &format!("{INDENT}📎 {reference}"));
+            }
+        }
+    }
+}
+
+/// Lines being laid out at a fixed width
+struct Layout {
+    lines: Vec<Line>,
+    width: usize,
+}
+
+impl Layout {
+    fn push(&mut self, style: Style, text: &str) {
+        self.wrapped(style, text, "");
+    }
+
+    fn blank(&mut self) {
+        self.lines.push(Line {
+            style: Style::Text,
+            text: String::new(),
+        });
+    }
+
+    /// Add `text` word-wrapped to the width, every line prefixed by `indent`
+    fn wrapped(&mut self, style: Style, text: &str, indent: &str) {
+        let available = self.width.saturating_sub(indent.chars().count()).max(10);
+        for source in text.trim_end().lines() {
+            for piece in wrap(source.trim_end(), available) {
+                self.lines.push(Line {
+                    style,
+                    text: format!("{indent}{piece}"),
+                });
+            }
+        }
+    }
+}
+
+/// Break `line` into pieces of at most `width` characters, at spaces where
+/// possible
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+    while rest.len() > width {
+        let cut = rest[..=width]
+            .iter()
+            .rposition(|c| *c == ' ')
+            .filter(|&i| i > 0)
+            .unwrap_or(width);
+        pieces.push(
+            rest[..cut]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        let skip = if rest.get(cut) == Some(&' ') {
+            cut + 1
+        } else {
+            cut
+        };
+        rest.drain(..skip);
+    }
+    pieces.push(rest.into_iter().collect());
+    pieces
+}
+
+fn count_lines(text: &str) -> String {
+    match text.trim_end().lines().count() {
+        1 => "1 line".to_string(),
+        n => format!("{n} lines"),
+    }
+}
+
+/// A line with its style applied
+fn styled(line: &Line) -> String {
+    let text = line.text.as_str();
+    match line.style {
+        Style::Title => text.bold().to_string(),
+        Style::Meta => text.dimmed().to_string(),
+        Style::User => text.cyan().bold().to_string(),
+        Style::Assistant => text.green().bold().to_string(),
+        Style::Text => text.to_string(),
+        Style::Tool => text.yellow().to_string(),
+        Style::ToolError => text.red().to_string(),
+        Style::Folded => text.dimmed().to_string(),
+        Style::Fork => text.magenta().bold().to_string(),
+    }
+}
+
+/// Print every line, for output that is not a terminal
+pub fn print(viewer: &Viewer<'_>) {
+    for line in viewer.lines() {
+        println!("{}", styled(line));
+    }
+}
+
+/// Page through the transcript until the user quits
+pub fn page(viewer: &mut Viewer<'_>) -> Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let result = event_loop(viewer, &mut stdout);
+
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// Current terminal width, or a default when unknown
+pub fn terminal_width() -> usize {
+    terminal::size()
+        .map(|(cols, _)| cols as usize)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+fn event_loop(viewer: &mut Viewer<'_>, stdout: &mut io::Stdout) -> Result<()> {
+    let mut top = 0usize;
+    loop {
+        let (_, rows) = terminal::size()?;
+        let height = (rows as usize).saturating_sub(1).max(1);
+        let max_top = viewer.lines().len().saturating_sub(height);
+        top = top.min(max_top);
+        let current_fork = current_fork(viewer, top, height);
+        draw(viewer, stdout, top, height, current_fork)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::Resize(cols, _) => {
+                viewer.set_width(cols as usize);
+                continue;
+            }
+            _ => continue,
+        };
+
+        match key {
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => return Ok(()),
+            KeyEvent { code, .. } => match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => top += 1,
+                KeyCode::Up | KeyCode::Char('k') => top = top.saturating_sub(1),
+                KeyCode::PageDown | KeyCode::Char(' ') | KeyCode::Char('f') => top += height,
+                KeyCode::PageUp | KeyCode::Char('b') => top = top.saturating_sub(height),
+                KeyCode::Home | KeyCode::Char('g') => top = 0,
+                KeyCode::End | KeyCode::Char('G') => top = max_top,
+                KeyCode::Char('t') => viewer.toggle_expanded(),
+                KeyCode::Char(']') | KeyCode::Char('n') => {
+                    if let Some(fork) = viewer.forks.iter().find(|f| f.line > top) {
+                        top = fork.line;
+                    }
+                }
+                KeyCode::Char('[') | KeyCode::Char('p') => {
+                    if let Some(fork) = viewer.forks.iter().rev().find(|f| f.line < top) {
+                        top = fork.line;
+                    }
+                }
+                KeyCode::Right | KeyCode::Left | KeyCode::Char('l') | KeyCode::Char('h') => {
+                    if let Some(fork) = current_fork {
+                        let delta = if matches!(code, KeyCode::Right | KeyCode::Char('l')) {
+                            1
+                        } else {
+                            -1
+                        };
+                        let row = viewer.forks[fork].line.saturating_sub(top);
+                        if let Some(line) = viewer.switch_branch(fork, delta) {
+                            // Keep the fork where it was on screen
+                            top = line.saturating_sub(row);
+                        }
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+/// The fork arrow keys act on: the first one on screen, else the nearest
+/// one above it
+fn current_fork(viewer: &Viewer<'_>, top: usize, height: usize) -> Option<usize> {
+    viewer
+        .forks
+        .iter()
+        .position(|f| f.line >= top && f.line < top + height)
+        .or_else(|| viewer.forks.iter().rposition(|f| f.line < top))
+}
+
+fn draw(
+    viewer: &Viewer<'_>,
+    stdout: &mut io::Stdout,
+    top: usize,
+    height: usize,
+    current_fork: Option<usize>,
+) -> Result<()> {
+    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    for (row, line) in viewer.lines().iter().skip(top).take(height).enumerate() {
+        queue!(stdout, cursor::MoveTo(0, row as u16))?;
+        write!(stdout, "{}", styled(line))?;
+    }
+
+    let total = viewer.lines().len();
+    let mut status = format!(" {}/{} ", (top + height).min(total), total);
+    if let Some(fork) = current_fork {
+        let f = viewer.forks[fork];
+        status.push_str(&format!(
+            "· fork {}/{}: branch {} of {} ",
+            fork + 1,
+            viewer.fork_count(),
+            f.index + 1,
+            f.count
+        ));
+    }
+    status.push_str("· t tools · [ ] forks · ←/→ branch · q quit ");
+    queue!(stdout, cursor::MoveTo(0, height as u16))?;
+    write!(stdout, "{}", status.reversed())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ConversationEntry;
+    use serde_json::json;
+
+    fn text(viewer: &Viewer<'_>) -> Vec<String> {
+        viewer.lines().iter().map(|l| l.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_switch_branches_and_fold_tools() {
+        let lines = [
+            json!({"type": "user", "uuid": "1", "parentUuid": null,
+                   "message": {"role": "user", "content": "start"}}),
+            json!({"type": "assistant", "uuid": "2", "parentUuid": "1",
+                   "message": {"role": "assistant", "content": [
+                       {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}]}}),
+            json!({"type": "user", "uuid": "3", "parentUuid": "2",
+                   "message": {"role": "user", "content": [
+                       {"type": "tool_result", "tool_use_id": "t1", "content": "a\nb"}]}}),
+            json!({"type": "user", "uuid": "4", "parentUuid": "3",
+                   "message": {"role": "user", "content": "old question"}}),
+            json!({"type": "user", "uuid": "5", "parentUuid": "3",
+                   "message": {"role": "user", "content": "new question"}}),
+        ];
+        let session = ConversationSession {
+            session_id: "s1".to_string(),
+            entries: lines
+                .into_iter()
+                .map(|l| serde_json::from_value::<ConversationEntry>(l).unwrap())
+                .collect(),
+            file_path: "s1.jsonl".to_string(),
+        };
+
+        let mut viewer = Viewer::new(&session, "web", false, 80);
+        let shown = text(&viewer);
+        assert!(shown.contains(&"  ⏺ Bash(ls)".to_string()));
+        assert!(shown.contains(&"    ⎿ 2 lines of output".to_string()));
+        assert!(shown.contains(&"── Branch 2 of 2 ── (←/→ to switch)".to_string()));
+        assert!(shown.contains(&"  new question".to_string()));
+        assert_eq!(viewer.fork_count(), 1);
+
+        viewer.switch_branch(0, 1);
+        let shown = text(&viewer);
+        assert!(shown.contains(&"── Branch 1 of 2 ── (←/→ to switch)".to_string()));
+        assert!(shown.contains(&"  old question".to_string()));
+        assert!(!shown.contains(&"  new question".to_string()));
+
+        viewer.toggle_expanded();
+        let shown = text(&viewer);
+        assert!(shown.contains(&"      a".to_string()));
+        assert!(shown.contains(&"      b".to_string()));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("aaa bbb ccc", 7), vec!["aaa bbb", "ccc"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), vec![""]);
+    }
+}
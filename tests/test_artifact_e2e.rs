@@ -12,6 +12,7 @@ use std::process::Command;
 use claude_code_sync::artifacts::registry::ArtifactToggles;
 use claude_code_sync::filter::FilterConfig;
 use claude_code_sync::history::OperationHistory;
+use claude_code_sync::session_rules::ExcludeRule;
 use claude_code_sync::sync::{pull_history, push_history, sync_bidirectional, SyncState};
 use claude_code_sync::VerbosityLevel;
 use serial_test::serial;
@@ -44,21 +45,24 @@ impl Machine {
         )
         .unwrap();
 
-        let filter = FilterConfig {
+        let machine = Machine {
+            _root: root,
+            home,
+            config,
+        };
+        machine.configure(&FilterConfig {
             sync_artifacts: ArtifactToggles::all_enabled(),
             ..Default::default()
-        };
+        });
+        machine
+    }
+
+    fn configure(&self, filter: &FilterConfig) {
         fs::write(
-            config.join("claude-code-sync/config.toml"),
-            toml::to_string_pretty(&filter).unwrap(),
+            self.config.join("claude-code-sync/config.toml"),
+            toml::to_string_pretty(filter).unwrap(),
         )
         .unwrap();
-
-        Machine {
-            _root: root,
-            home,
-            config,
-        }
     }
 
     fn activate(&self) {
@@ -304,3 +308,102 @@ fn test_full_pipeline_sync_converges_prompt_history() {
         .collect();
     assert_eq!(ts, vec![1000, 2000], "chronological order");
 }
+
+#[test]
+#[serial]
+fn test_pull_matches_deny_patterns_against_local_paths_in_name_only_mode() {
+    let _restore = EnvRestore::capture();
+    let repo = TempDir::new().unwrap();
+    init_git_repo(repo.path());
+    let name_only = FilterConfig {
+        use_project_name_only: true,
+        ..Default::default()
+    };
+
+    // Machine A pushes one session in each of two projects; the repository
+    // names them `webapp` and `api`
+    let machine_a = Machine::new(repo.path());
+    machine_a.configure(&name_only);
+    machine_a.activate();
+    for (project, session) in [("webapp", "aaaa-1111"), ("api", "bbbb-2222")] {
+        let dir = machine_a
+            .claude()
+            .join(format!("projects/-home-user-{project}"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{session}.jsonl")),
+            format!(
+                "{{\"type\":\"user\",\"sessionId\":\"{session}\",\"uuid\":\"u1\",\"timestamp\":\"2025-01-01T00:00:00Z\",\"cwd\":\"/home/user/{project}\"}}\n"
+            ),
+        )
+        .unwrap();
+    }
+    let report = push_history(Some("A"), false, None, false, false, VerbosityLevel::Quiet).unwrap();
+    assert_eq!(report.added, 2);
+    assert!(repo
+        .path()
+        .join("projects/webapp/aaaa-1111.jsonl")
+        .is_file());
+
+    // Machine B denies its local webapp project by its ~/.claude path
+    let machine_b = Machine::new(repo.path());
+    machine_b.configure(&FilterConfig {
+        deny_patterns: vec!["projects/-home-me-webapp*".to_string()],
+        ..name_only
+    });
+    machine_b.activate();
+    let b_projects = machine_b.claude().join("projects");
+    fs::create_dir_all(b_projects.join("-home-me-webapp")).unwrap();
+    fs::create_dir_all(b_projects.join("-home-me-api")).unwrap();
+
+    pull_history(false, None, false, VerbosityLevel::Quiet).unwrap();
+    assert!(b_projects.join("-home-me-api/bbbb-2222.jsonl").is_file());
+    assert!(!b_projects.join("-home-me-webapp/aaaa-1111.jsonl").exists());
+}
+
+#[test]
+#[serial]
+fn test_pull_leaves_out_sessions_matching_exclude_rules() {
+    let _restore = EnvRestore::capture();
+    let repo = TempDir::new().unwrap();
+    init_git_repo(repo.path());
+
+    // Machine A, without rules, pushes a session in each of two projects
+    let machine_a = Machine::new(repo.path());
+    machine_a.configure(&FilterConfig::default());
+    machine_a.activate();
+    for (project, session) in [("webapp", "aaaa-1111"), ("api", "bbbb-2222")] {
+        let dir = machine_a
+            .claude()
+            .join(format!("projects/-home-user-{project}"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{session}.jsonl")),
+            format!(
+                "{{\"type\":\"user\",\"sessionId\":\"{session}\",\"uuid\":\"u1\",\"timestamp\":\"2025-01-01T00:00:00Z\",\"cwd\":\"/home/user/{project}\"}}\n"
+            ),
+        )
+        .unwrap();
+    }
+    let report = push_history(Some("A"), false, None, false, false, VerbosityLevel::Quiet).unwrap();
+    assert_eq!(report.added, 2);
+
+    // Machine B's rule keeps webapp sessions off it, whoever pushed them
+    let machine_b = Machine::new(repo.path());
+    machine_b.configure(&FilterConfig {
+        exclude_rules: vec![ExcludeRule {
+            name: "webapp".to_string(),
+            cwd: Some("/webapp$".to_string()),
+            ..Default::default()
+        }],
+        ..Default::default()
+    });
+    machine_b.activate();
+
+    pull_history(false, None, false, VerbosityLevel::Quiet).unwrap();
+    let b_projects = machine_b.claude().join("projects");
+    assert!(b_projects.join("-home-user-api/bbbb-2222.jsonl").is_file());
+    assert!(!b_projects
+        .join("-home-user-webapp/aaaa-1111.jsonl")
+        .exists());
+}
@@ -743,3 +743,34 @@ fn test_pull_refuses_unlisted_files_in_allowlist_categories() {
         "unlisted allowlist files count as skipped"
     );
 }
+
+#[test]
+fn test_deny_patterns_apply_in_both_directions() {
+    let claude = TempDir::new().unwrap();
+    let repo = TempDir::new().unwrap();
+    seed_claude_dir(claude.path());
+    let mut filter = all_on_filter();
+    filter.deny_patterns = vec![
+        "skills/my-skill/references".to_string(),
+        "*.private.md".to_string(),
+    ];
+
+    push_artifacts(claude.path(), repo.path(), &filter).unwrap();
+    assert!(repo
+        .path()
+        .join("artifacts/skills/my-skill/SKILL.md")
+        .is_file());
+    assert!(!repo
+        .path()
+        .join("artifacts/skills/my-skill/references")
+        .exists());
+
+    // Files another machine pushed before the patterns existed stay put
+    fs::create_dir_all(repo.path().join("artifacts/agents")).unwrap();
+    fs::write(repo.path().join("artifacts/agents/ops.private.md"), b"x").unwrap();
+    let machine_b = TempDir::new().unwrap();
+    let plan = plan_pull(machine_b.path(), repo.path(), &filter).unwrap();
+    apply_pull(&plan, false).unwrap();
+    assert!(machine_b.path().join("skills/my-skill/SKILL.md").is_file());
+    assert!(!machine_b.path().join("agents/ops.private.md").exists());
+}
@@ -0,0 +1,145 @@
+//! A session pushed with its home directory written as `${HOME}` is pulled onto
+//! another machine under that machine's home, and neither side then sees the
+//! rewritten copy as a change. Project directory names are not rewritten.
+
+use std::fs;
+
+use claude_code_sync::filter::FilterConfig;
+use claude_code_sync::home_paths::HomePaths;
+use claude_code_sync::parser::SessionSummary;
+use claude_code_sync::redact::Findings;
+use claude_code_sync::sync::copies::CopyLedger;
+use claude_code_sync::sync::index::DiscoveryIndex;
+use claude_code_sync::sync::push::{plan_push, plan_push_with_index};
+use tempfile::TempDir;
+
+const SESSION_ID: &str = "5c1d8e3f-2a4b-4c6d-9e0f-1a2b3c4d5e6f";
+const REL_PATH: &str = "-home-alice-proj/5c1d8e3f-2a4b-4c6d-9e0f-1a2b3c4d5e6f.jsonl";
+
+fn plan_unchanged(claude: &TempDir, repo: &TempDir, ledger: &mut CopyLedger) -> usize {
+    let local = vec![SessionSummary::from_file(claude.path().join(REL_PATH)).unwrap()];
+    plan_push_with_index(
+        &local,
+        claude.path(),
+        repo.path(),
+        &FilterConfig::default(),
+        &mut DiscoveryIndex::in_memory(),
+        ledger,
+    )
+    .unwrap()
+    .unchanged
+}
+
+#[test]
+fn test_home_paths_round_trip_between_machines() {
+    let alice = TempDir::new().unwrap();
+    let bob = TempDir::new().unwrap();
+    let repo = TempDir::new().unwrap();
+    let alice_path = alice.path().join(REL_PATH);
+    fs::create_dir_all(alice_path.parent().unwrap()).unwrap();
+    fs::write(
+        &alice_path,
+        format!(
+            "{}\n",
+            serde_json::json!({
+                "type": "assistant", "sessionId": SESSION_ID, "uuid": "a1",
+                "timestamp": "2025-01-01T00:00:00Z", "cwd": "/home/alice/proj",
+                "message": {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "Read",
+                     "input": {"file_path": "/home/alice/proj/src/main.rs"}}
+                ]}
+            })
+        ),
+    )
+    .unwrap();
+    let alice_bytes = fs::read(&alice_path).unwrap();
+
+    // Push from alice's machine
+    let alice_home = HomePaths::new("/home/alice").unwrap();
+    let mut alice_ledger = CopyLedger::in_memory();
+    let session = SessionSummary::from_file(&alice_path).unwrap();
+    let repo_hash = session
+        .copy_to_with(repo.path().join(REL_PATH), |e| {
+            alice_home.collapse(e);
+            true
+        })
+        .unwrap();
+    alice_ledger.record(
+        SESSION_ID,
+        &session.content_hash,
+        &repo_hash,
+        Findings::new(),
+    );
+    let copied = fs::read_to_string(repo.path().join(REL_PATH)).unwrap();
+    assert!(!copied.contains("alice/"));
+    assert!(copied.contains(r#""cwd":"${HOME}/proj""#));
+    assert_eq!(fs::read(&alice_path).unwrap(), alice_bytes);
+    assert_eq!(plan_unchanged(&alice, &repo, &mut alice_ledger), 1);
+
+    // Pull onto bob's machine
+    let bob_home = HomePaths::new("/Users/bob").unwrap();
+    let mut bob_ledger = CopyLedger::in_memory();
+    let remote = SessionSummary::from_file(repo.path().join(REL_PATH)).unwrap();
+    let local_hash = remote
+        .copy_to_with(bob.path().join(REL_PATH), |e| {
+            bob_home.expand(e);
+            true
+        })
+        .unwrap();
+    bob_ledger.record(
+        SESSION_ID,
+        &local_hash,
+        &remote.content_hash,
+        Findings::new(),
+    );
+    let pulled = fs::read_to_string(bob.path().join(REL_PATH)).unwrap();
+    assert!(pulled.contains("/Users/bob/proj/src/main.rs"));
+    assert!(!pulled.contains("${HOME}"));
+    assert_eq!(plan_unchanged(&bob, &repo, &mut bob_ledger), 1);
+}
+
+#[test]
+fn test_project_directory_keeps_the_home_path() {
+    let claude = TempDir::new().unwrap();
+    let repo = TempDir::new().unwrap();
+    let path = claude.path().join(REL_PATH);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        format!(
+            "{}\n",
+            serde_json::json!({
+                "type": "user", "sessionId": SESSION_ID, "uuid": "u1",
+                "timestamp": "2025-01-01T00:00:00Z", "cwd": "/home/alice/proj",
+                "message": {"role": "user", "content": "hi"}
+            })
+        ),
+    )
+    .unwrap();
+    let local = vec![SessionSummary::from_file(&path).unwrap()];
+    let destination = |filter: FilterConfig| {
+        plan_push(&local, claude.path(), repo.path(), &filter)
+            .unwrap()
+            .entries[0]
+            .relative_path
+            .clone()
+    };
+
+    // Anonymization rewrites content only: the encoded project directory
+    // still names the user
+    let anonymized = destination(FilterConfig {
+        anonymize_home_paths: true,
+        ..Default::default()
+    });
+    assert_eq!(anonymized, std::path::Path::new(REL_PATH));
+    assert!(anonymized.starts_with("-home-alice-proj"));
+
+    // Storing projects by name alone keeps it out
+    let by_name = destination(FilterConfig {
+        anonymize_home_paths: true,
+        use_project_name_only: true,
+        ..Default::default()
+    });
+    assert!(by_name.starts_with("proj"));
+    assert!(!by_name.to_string_lossy().contains("alice"));
+}
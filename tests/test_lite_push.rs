@@ -0,0 +1,132 @@
+//! A lite push copy leaves bulky tool output and file history snapshots out
+//! of the repository, yet must read as unchanged to later pushes while the
+//! local transcript stays whole.
+
+use std::fs;
+use std::io::Write;
+
+use claude_code_sync::filter::FilterConfig;
+use claude_code_sync::lite::{LitePush, LiteStats};
+use claude_code_sync::parser::SessionSummary;
+use claude_code_sync::redact::Findings;
+use claude_code_sync::sync::copies::CopyLedger;
+use claude_code_sync::sync::index::DiscoveryIndex;
+use claude_code_sync::sync::push::plan_push_with_index;
+use tempfile::TempDir;
+
+const SESSION_ID: &str = "0b7e4c2a-9f1d-4e3b-8a6c-5d2f1e0a9b8c";
+
+#[test]
+fn test_lite_copy_is_smaller_and_unchanged_on_repush() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    let local_path = claude
+        .path()
+        .join("-home-user-myproj")
+        .join(format!("{SESSION_ID}.jsonl"));
+    fs::create_dir_all(local_path.parent().unwrap()).unwrap();
+    let log = "compiling crate\n".repeat(2_000);
+    let mut file = fs::File::create(&local_path).unwrap();
+    writeln!(
+        file,
+        r#"{{"type":"file-history-snapshot","messageId":"u1","snapshot":{{}}}}"#
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "{}",
+        serde_json::json!({
+            "type": "user", "sessionId": SESSION_ID, "uuid": "u1",
+            "timestamp": "2025-01-01T00:00:00Z", "cwd": "/home/user/myproj",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": log}
+            ]},
+            "toolUseResult": {"stdout": log}
+        })
+    )
+    .unwrap();
+    drop(file);
+    let local_bytes = fs::read(&local_path).unwrap();
+
+    let filter = FilterConfig {
+        lite_push: LitePush {
+            enabled: true,
+            max_tool_result_bytes: 1024,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut ledger = CopyLedger::in_memory().with_settings(&filter);
+    let push = |ledger: &mut CopyLedger, stats: &mut LiteStats| {
+        let local = vec![SessionSummary::from_file(&local_path).unwrap()];
+        let plan = plan_push_with_index(
+            &local,
+            claude.path(),
+            repo_projects.path(),
+            &filter,
+            &mut DiscoveryIndex::in_memory(),
+            ledger,
+        )
+        .unwrap();
+        for entry in &plan.entries {
+            let session = &local[entry.session_index];
+            let repo_hash = session
+                .copy_to_with(repo_projects.path().join(&entry.relative_path), |e| {
+                    filter.lite_push.apply(e, stats)
+                })
+                .unwrap();
+            ledger.record(
+                &session.session_id,
+                &session.content_hash,
+                &repo_hash,
+                Findings::new(),
+            );
+        }
+        plan
+    };
+
+    let mut stats = LiteStats::default();
+    let first = push(&mut ledger, &mut stats);
+    assert_eq!(first.added, 1);
+    assert_eq!((stats.trimmed_results, stats.dropped_snapshots), (1, 1));
+    let copied = fs::read_to_string(
+        repo_projects
+            .path()
+            .join(first.entries[0].relative_path.as_path()),
+    )
+    .unwrap();
+    assert!(copied.len() < 4 * 1024);
+    assert!(!copied.contains("file-history-snapshot"));
+    assert!(copied.contains("truncated by lite push]"));
+    assert_eq!(fs::read(&local_path).unwrap(), local_bytes);
+
+    // The smaller copy is not a perpetual modification
+    let second = push(&mut ledger, &mut LiteStats::default());
+    assert_eq!((second.unchanged, second.modified), (1, 0));
+
+    // Changing the threshold or turning lite push off rewrites the copy
+    let local = vec![SessionSummary::from_file(&local_path).unwrap()];
+    for lite_push in [
+        LitePush {
+            max_tool_result_bytes: 2048,
+            ..filter.lite_push.clone()
+        },
+        LitePush::default(),
+    ] {
+        let changed = FilterConfig {
+            lite_push,
+            ..filter.clone()
+        };
+        ledger = ledger.with_settings(&changed);
+        let plan = plan_push_with_index(
+            &local,
+            claude.path(),
+            repo_projects.path(),
+            &changed,
+            &mut DiscoveryIndex::in_memory(),
+            &ledger,
+        )
+        .unwrap();
+        assert_eq!((plan.unchanged, plan.modified), (0, 1));
+    }
+}
@@ -7,8 +7,10 @@ use std::io::Write;
 use std::path::Path;
 
 use claude_code_sync::filter::FilterConfig;
+use claude_code_sync::sync::copies::CopyLedger;
 use claude_code_sync::sync::discovery::discover_sessions;
-use claude_code_sync::sync::push::plan_push;
+use claude_code_sync::sync::index::DiscoveryIndex;
+use claude_code_sync::sync::push::{plan_push, plan_push_with_index};
 use tempfile::TempDir;
 
 const PARENT_SESSION_ID: &str = "56d02190-2a2d-4a55-9ec1-38e34fb25e84";
@@ -125,3 +127,213 @@ fn test_push_plan_detects_real_modification() {
     );
     assert_eq!(plan2.unchanged, 2);
 }
+
+#[test]
+fn test_push_plan_ignores_formatting_only_differences() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    let filter = FilterConfig::default();
+
+    let project = claude.path().join("-home-user-myproj");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join("formatted.jsonl"),
+        r#"{"type":"assistant","uuid":"1","cwd":"/home/user/myproj","message":{"role":"assistant","usage":{"input_tokens":10,"output_tokens":2}},"costUSD":1.0}
+"#,
+    )
+    .unwrap();
+
+    // Same data as written by another tool: different key order, spacing and
+    // number formatting.
+    let repo_project = repo_projects.path().join("-home-user-myproj");
+    fs::create_dir_all(&repo_project).unwrap();
+    fs::write(
+        repo_project.join("formatted.jsonl"),
+        r#"{ "costUSD": 1, "message": { "usage": { "output_tokens": 2, "input_tokens": 1e1 }, "role": "assistant" }, "cwd": "/home/user/myproj", "uuid": "1", "type": "assistant" }
+"#,
+    )
+    .unwrap();
+
+    let sessions = discover_sessions(claude.path(), &filter).unwrap();
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert_eq!(plan.modified, 0, "formatting alone is not a modification");
+    assert_eq!(plan.unchanged, 1);
+}
+
+#[test]
+fn test_filter_moves_parent_and_subagents_together() {
+    let claude = TempDir::new().unwrap();
+    seed_claude_projects(claude.path());
+
+    // Excluding the parent by path also excludes its subagents, which live
+    // under a directory the pattern does not name
+    let exclude_parent = FilterConfig {
+        exclude_patterns: vec![format!("*{PARENT_SESSION_ID}.jsonl")],
+        ..Default::default()
+    };
+    let sessions = discover_sessions(claude.path(), &exclude_parent).unwrap();
+    assert!(
+        sessions.is_empty(),
+        "subagents follow their excluded parent"
+    );
+
+    // Excluding one subagent by path does not split the unit
+    let exclude_subagent = FilterConfig {
+        exclude_patterns: vec!["*agent-a16263cbf10e1ad0b*".to_string()],
+        ..Default::default()
+    };
+    let sessions = discover_sessions(claude.path(), &exclude_subagent).unwrap();
+    assert_eq!(sessions.len(), 3);
+}
+
+#[test]
+fn test_project_name_only_keeps_subagents_under_parent() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    seed_claude_projects(claude.path());
+    let filter = FilterConfig {
+        use_project_name_only: true,
+        ..Default::default()
+    };
+
+    let sessions = discover_sessions(claude.path(), &filter).unwrap();
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    let mut paths: Vec<String> = plan
+        .entries
+        .iter()
+        .map(|e| e.relative_path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            format!("myproj/{PARENT_SESSION_ID}.jsonl"),
+            format!("myproj/{PARENT_SESSION_ID}/subagents/agent-a16263cbf10e1ad0b.jsonl"),
+            format!("myproj/{PARENT_SESSION_ID}/subagents/agent-b7f2915d40c88e221.jsonl"),
+        ]
+    );
+}
+
+#[test]
+fn test_deny_pattern_leaves_out_whole_unit() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    seed_claude_projects(claude.path());
+
+    // Denying one subagent transcript denies the session it belongs to
+    let filter = FilterConfig {
+        deny_patterns: vec!["agent-a16263cbf10e1ad0b.jsonl".to_string()],
+        ..Default::default()
+    };
+    let sessions = discover_sessions(claude.path(), &filter).unwrap();
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert!(plan.entries.is_empty());
+    assert_eq!(plan.skipped_denied, 3);
+
+    // Anchored patterns match below ~/.claude, where sessions live in projects/
+    let filter = FilterConfig {
+        deny_patterns: vec!["projects/-home-user-other".to_string()],
+        ..Default::default()
+    };
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert_eq!((plan.entries.len(), plan.skipped_denied), (3, 0));
+    let filter = FilterConfig {
+        deny_patterns: vec!["projects/-home-user-my*".to_string()],
+        ..Default::default()
+    };
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert_eq!((plan.entries.len(), plan.skipped_denied), (0, 3));
+}
+
+#[test]
+fn test_exclude_rule_is_counted_separately_and_leaves_out_whole_unit() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    seed_claude_projects(claude.path());
+
+    // Only one subagent mentions the keyword; its whole session stays home
+    let subagent = claude
+        .path()
+        .join("-home-user-myproj")
+        .join(PARENT_SESSION_ID)
+        .join("subagents/agent-a16263cbf10e1ad0b.jsonl");
+    fs::write(
+        &subagent,
+        format!(
+            r#"{{"type":"user","sessionId":"{PARENT_SESSION_ID}","uuid":"sub-a-1","timestamp":"2025-01-01T00:00:00Z","cwd":"/home/user/myproj","message":{{"role":"user","content":"Ask Acme Corp for the keys"}}}}"#
+        ) + "\n",
+    )
+    .unwrap();
+    let filter: FilterConfig = toml::from_str(
+        r#"
+        [[exclude_rules]]
+        name = "customer"
+        keywords = ["acme corp"]
+        "#,
+    )
+    .unwrap();
+    let sessions = discover_sessions(claude.path(), &filter).unwrap();
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert!(plan.entries.is_empty());
+    assert_eq!(plan.excluded_by_rule, 3);
+    assert_eq!(plan.skipped_denied, 0);
+
+    let filter: FilterConfig = toml::from_str(
+        r#"
+        [[exclude_rules]]
+        name = "release branches"
+        git_branch = "^release/"
+        "#,
+    )
+    .unwrap();
+    let plan = plan_push(&sessions, claude.path(), repo_projects.path(), &filter).unwrap();
+    assert_eq!((plan.added, plan.excluded_by_rule), (3, 0));
+}
+
+#[test]
+fn test_existing_copies_stay_unchanged_under_a_settings_ledger() {
+    let claude = TempDir::new().unwrap();
+    let repo_projects = TempDir::new().unwrap();
+    seed_claude_projects(claude.path());
+    let filter = FilterConfig::default();
+
+    // A repository populated before copies were recorded, by a writer that
+    // spaced and ordered keys differently: the same content, other bytes
+    for entry in walkdir::WalkDir::new(claude.path()) {
+        let entry = entry.unwrap();
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let dest = repo_projects
+            .path()
+            .join(entry.path().strip_prefix(claude.path()).unwrap());
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        let reformatted: String = fs::read_to_string(entry.path())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&value)
+                        .unwrap()
+                        .replace('\n', "")
+                )
+            })
+            .collect();
+        fs::write(dest, reformatted).unwrap();
+    }
+
+    let sessions = discover_sessions(claude.path(), &filter).unwrap();
+    let plan = plan_push_with_index(
+        &sessions,
+        claude.path(),
+        repo_projects.path(),
+        &filter,
+        &mut DiscoveryIndex::in_memory(),
+        &CopyLedger::in_memory().with_settings(&filter),
+    )
+    .unwrap();
+    assert_eq!(plan.unchanged, 3, "{:?}", plan.entries);
+    assert_eq!((plan.added, plan.modified), (0, 0));
+}